@@ -0,0 +1,19 @@
+pub mod skills;
+pub mod unused_strings;
+
+#[derive(clap::Subcommand)]
+pub enum AuditCommands {
+    /// Cross-check the skill, expertise, command and skill mod tables against each other
+    Skills(skills::SkillsArgs),
+    /// List string table keys that nothing references
+    UnusedStrings(unused_strings::UnusedStringsArgs),
+}
+
+impl AuditCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            AuditCommands::Skills(skills) => skills.handle(),
+            AuditCommands::UnusedStrings(unused_strings) => unused_strings.handle(),
+        }
+    }
+}
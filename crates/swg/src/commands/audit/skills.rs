@@ -0,0 +1,336 @@
+use clap::Args;
+use miette::{miette, Context, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use swg_assets::Asset;
+use swg_iff::datatable::{CellData, DataTable, Row};
+use tracing::{info, info_span, warn};
+
+use crate::commands::sources::{OpenSources, Sources};
+
+/// The CRC the game looks commands up by, computed over the lowercased name
+static COMMAND_CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_BZIP2);
+
+/// Skills grant certifications through the same column as commands, but they aren't commands
+const CERTIFICATION_PREFIX: &str = "cert_";
+
+#[derive(Args)]
+pub struct SkillsArgs {
+    #[command(flatten)]
+    sources: Sources,
+
+    /// The datatable defining every skill
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "datatables/skill/skills.iff"
+    )]
+    skills: String,
+
+    /// The datatable defining every expertise, each of which must also be a skill
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "datatables/expertise/expertise.iff"
+    )]
+    expertise: String,
+
+    /// The datatable defining every command
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "datatables/command/command_table.iff"
+    )]
+    commands: String,
+
+    /// The datatable listing every skill mod by name
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "datatables/skill/skill_mods.iff"
+    )]
+    skill_mods: String,
+}
+
+/// A row of the skills datatable
+struct Skill<'a> {
+    name: &'a str,
+    parent: Option<&'a str>,
+    required: Vec<&'a str>,
+    commands: Vec<&'a str>,
+    skill_mods: Vec<&'a str>,
+    hidden: bool,
+}
+
+impl<'a> Skill<'a> {
+    fn new(row: &'a Row) -> Option<Self> {
+        Some(Skill {
+            name: string(row, "NAME").filter(|name| !name.is_empty())?,
+            parent: string(row, "PARENT").filter(|parent| !parent.is_empty()),
+            required: list(row, "SKILLS_REQUIRED").collect(),
+            commands: list(row, "COMMANDS").collect(),
+            // Skill mods are listed with the amount they grant, e.g. `pistol_accuracy=5`
+            skill_mods: list(row, "SKILL_MODS")
+                .map(|skill_mod| skill_mod.split('=').next().unwrap_or_default())
+                .collect(),
+            hidden: flag(row, "IS_HIDDEN"),
+        })
+    }
+}
+
+impl SkillsArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("skills").entered();
+
+        let sources = self.sources.open()?;
+
+        let skills_table = datatable(&sources, &self.skills)?
+            .ok_or_else(|| miette!("unable to find {}", self.skills))?;
+        let skills = skills_table
+            .rows
+            .iter()
+            .filter_map(Skill::new)
+            .collect::<Vec<_>>();
+
+        let mut problems = Vec::new();
+
+        let mut names = BTreeSet::new();
+        for skill in &skills {
+            if !names.insert(skill.name) {
+                problems.push(format!("{}: defined more than once", skill.name));
+            }
+        }
+
+        let commands_table = optional(&sources, &self.commands)?;
+        let commands = commands_table.as_ref().map(|table| {
+            table
+                .rows
+                .iter()
+                .filter_map(|row| string(row, "commandName"))
+                .map(str::to_ascii_lowercase)
+                .collect::<BTreeSet<_>>()
+        });
+
+        let skill_mods_table = optional(&sources, &self.skill_mods)?;
+        let skill_mods = skill_mods_table.as_ref().map(|table| {
+            table
+                .rows
+                .iter()
+                .filter_map(|row| string(row, "NAME"))
+                .collect::<BTreeSet<_>>()
+        });
+
+        let skill_names = string_keys(&sources, "skl_n")?;
+        let skill_mod_names = string_keys(&sources, "stat_n")?;
+
+        for skill in &skills {
+            if let Some(parent) = skill.parent.filter(|parent| !names.contains(parent)) {
+                problems.push(format!("{}: parent {} is not a skill", skill.name, parent));
+            }
+
+            for required in skill.required.iter().filter(|r| !names.contains(*r)) {
+                problems.push(format!(
+                    "{}: requires unknown skill {}",
+                    skill.name, required
+                ));
+            }
+
+            if let Some(commands) = &commands {
+                for command in skill.commands.iter().filter(|command| {
+                    !command.starts_with(CERTIFICATION_PREFIX)
+                        && !commands.contains(&command.to_ascii_lowercase())
+                }) {
+                    problems.push(format!(
+                        "{}: grants unknown command {}",
+                        skill.name, command
+                    ));
+                }
+            }
+
+            for skill_mod in &skill.skill_mods {
+                if skill_mods
+                    .as_ref()
+                    .is_some_and(|skill_mods| !skill_mods.contains(skill_mod))
+                {
+                    problems.push(format!(
+                        "{}: grants unknown skill mod {}",
+                        skill.name, skill_mod
+                    ));
+                }
+                if skill_mod_names
+                    .as_ref()
+                    .is_some_and(|strings| !strings.contains(*skill_mod))
+                {
+                    problems.push(format!(
+                        "{}: skill mod {} has no name in stat_n",
+                        skill.name, skill_mod
+                    ));
+                }
+            }
+
+            if !skill.hidden
+                && skill_names
+                    .as_ref()
+                    .is_some_and(|strings| !strings.contains(skill.name))
+            {
+                problems.push(format!("{}: has no name in skl_n", skill.name));
+            }
+        }
+
+        if let Some(expertise) = optional(&sources, &self.expertise)? {
+            for name in expertise.rows.iter().filter_map(|row| string(row, "NAME")) {
+                if !names.contains(name) {
+                    problems.push(format!("{}: expertise is not a skill", name));
+                }
+            }
+        }
+
+        if let Some(commands) = &commands {
+            let mut crcs = HashMap::new();
+            for command in commands {
+                let crc = COMMAND_CRC.checksum(command.as_bytes());
+                if let Some(other) = crcs.insert(crc, command) {
+                    problems.push(format!(
+                        "{}: shares the CRC {:#010x} with {}",
+                        command, crc, other
+                    ));
+                }
+            }
+        }
+
+        let prerequisites = skills
+            .iter()
+            .map(|skill| {
+                let edges = skill.parent.iter().chain(&skill.required).copied();
+                (skill.name, edges.collect())
+            })
+            .collect();
+        for cycle in cycles(&prerequisites) {
+            problems.push(format!(
+                "{}: prerequisites form a cycle {}",
+                cycle[0],
+                cycle.join(" -> ")
+            ));
+        }
+
+        for problem in &problems {
+            println!("{}", problem);
+        }
+
+        info!(
+            "found {} problems across {} skills",
+            problems.len(),
+            skills.len()
+        );
+
+        if !problems.is_empty() {
+            return Err(miette!("found {} problems", problems.len()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Read a datatable, if any source has it
+fn datatable(sources: &OpenSources, path: &str) -> Result<Option<DataTable>> {
+    let Some(data) = sources.read(path)? else {
+        return Ok(None);
+    };
+
+    match Asset::parse(&data).context(format!("parsing {}", path))? {
+        Asset::DataTable(table) => Ok(Some(table)),
+        _ => Err(miette!("{} is not a datatable", path)),
+    }
+}
+
+/// Read a datatable which only some checks need, warning that they are skipped without it
+fn optional(sources: &OpenSources, path: &str) -> Result<Option<DataTable>> {
+    let table = datatable(sources, path)?;
+    if table.is_none() {
+        warn!("unable to find {}, skipping the checks which need it", path);
+    }
+    Ok(table)
+}
+
+/// The keys of a string table, if any source has it
+fn string_keys(sources: &OpenSources, table: &str) -> Result<Option<BTreeSet<String>>> {
+    let keys = sources
+        .string_table(table)?
+        .map(|stf| stf.keys().cloned().collect());
+    if keys.is_none() {
+        warn!(
+            "unable to find string table {}, skipping the checks which need it",
+            table
+        );
+    }
+    Ok(keys)
+}
+
+/// The value of a string column, whose name is matched ignoring case
+fn string<'a>(row: &'a Row, column: &str) -> Option<&'a str> {
+    let cell = row
+        .cells
+        .iter()
+        .find(|cell| cell.name.0.eq_ignore_ascii_case(column.as_bytes()))?;
+    match &cell.data {
+        CellData::String(value) => std::str::from_utf8(&value.0).ok(),
+        _ => None,
+    }
+}
+
+/// The values of a comma separated string column
+fn list<'a>(row: &'a Row, column: &str) -> impl Iterator<Item = &'a str> {
+    string(row, column)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// The value of a boolean column, false when it is missing
+fn flag(row: &Row, column: &str) -> bool {
+    row.cells
+        .iter()
+        .find(|cell| cell.name.0.eq_ignore_ascii_case(column.as_bytes()))
+        .is_some_and(|cell| matches!(cell.data, CellData::Boolean(true)))
+}
+
+/// Find every cycle among the prerequisites of skills, each listed from and back to its first skill
+fn cycles<'a>(prerequisites: &BTreeMap<&'a str, Vec<&'a str>>) -> Vec<Vec<&'a str>> {
+    fn visit<'a>(
+        skill: &'a str,
+        prerequisites: &BTreeMap<&'a str, Vec<&'a str>>,
+        done: &mut BTreeSet<&'a str>,
+        path: &mut Vec<&'a str>,
+        cycles: &mut Vec<Vec<&'a str>>,
+    ) {
+        if done.contains(skill) {
+            return;
+        }
+        if let Some(start) = path.iter().position(|s| *s == skill) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(skill);
+            cycles.push(cycle);
+            return;
+        }
+
+        path.push(skill);
+        for prerequisite in prerequisites.get(skill).into_iter().flatten() {
+            visit(prerequisite, prerequisites, done, path, cycles);
+        }
+        path.pop();
+        done.insert(skill);
+    }
+
+    let mut done = BTreeSet::new();
+    let mut cycles = Vec::new();
+    for skill in prerequisites.keys() {
+        visit(
+            skill,
+            prerequisites,
+            &mut done,
+            &mut Vec::new(),
+            &mut cycles,
+        );
+    }
+    cycles
+}
@@ -0,0 +1,171 @@
+use clap::Args;
+use miette::{Context, IntoDiagnostic, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs::File,
+    io::Cursor,
+    path::PathBuf,
+};
+use swg_stf::read::StringTableReader;
+use swg_tre::TreArchive;
+use tracing::{info, info_span, warn};
+use walkdir::WalkDir;
+
+/// Extensions of files which may reference strings, IFF covers both datatables and templates
+const REFERENCING_EXTENSIONS: [&str; 3] = [".iff", ".inc", ".ui"];
+
+#[derive(Args)]
+pub struct UnusedStringsArgs {
+    /// A game directory containing TRE files and loose assets
+    #[arg(short, long, value_name = "DIR")]
+    game_dir: PathBuf,
+
+    /// The language whose string tables are audited
+    #[arg(long, default_value = "en")]
+    language: String,
+}
+
+#[derive(Default)]
+struct Audit {
+    tables: BTreeMap<String, BTreeSet<String>>,
+    references: HashSet<(String, String)>,
+}
+
+impl UnusedStringsArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("unused_strings", game_dir = %self.game_dir.display()).entered();
+
+        let mut audit = Audit::default();
+
+        let files = WalkDir::new(&self.game_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file());
+
+        for file in files {
+            let path = file.path();
+            if path.extension().is_some_and(|ext| ext == "tre") {
+                self.visit_archive(&mut audit, path)?;
+                continue;
+            }
+
+            let name = path
+                .strip_prefix(&self.game_dir)
+                .into_diagnostic()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            if self.is_interesting(&name) {
+                let data = std::fs::read(path)
+                    .into_diagnostic()
+                    .context(format!("path: {}", path.display()))?;
+                self.visit(&mut audit, &name, &data);
+            }
+        }
+
+        let mut total = 0;
+        let mut unused = 0;
+        for (table, keys) in &audit.tables {
+            for key in keys {
+                total += 1;
+                if !audit.references.contains(&(table.clone(), key.clone())) {
+                    unused += 1;
+                    println!("{}:{}", table, key);
+                }
+            }
+        }
+
+        info!("{} of {} string keys are unreferenced", unused, total);
+
+        Ok(())
+    }
+
+    fn visit_archive(&self, audit: &mut Audit, path: &std::path::Path) -> Result<()> {
+        let _span = info_span!("archive", archive = %path.display()).entered();
+
+        let f = File::open(path)
+            .into_diagnostic()
+            .context(format!("path: {}", path.display()))?;
+        let tre = TreArchive::new(&f)?;
+
+        let mut data = Vec::new();
+        for (index, name) in tre.file_names().enumerate() {
+            if !self.is_interesting(name) {
+                continue;
+            }
+            tre.read_into(index, &mut data)
+                .context(format!("reading {}", name))?;
+            self.visit(audit, name, &data);
+        }
+
+        Ok(())
+    }
+
+    fn string_table<'a>(&self, name: &'a str) -> Option<&'a str> {
+        name.strip_prefix("string/")?
+            .strip_prefix(self.language.as_str())?
+            .strip_prefix('/')?
+            .strip_suffix(".stf")
+    }
+
+    fn is_interesting(&self, name: &str) -> bool {
+        self.string_table(name).is_some()
+            || REFERENCING_EXTENSIONS
+                .iter()
+                .any(|extension| name.ends_with(extension))
+    }
+
+    fn visit(&self, audit: &mut Audit, name: &str, data: &[u8]) {
+        if let Some(table) = self.string_table(name) {
+            match StringTableReader::decode(Cursor::new(data)) {
+                Ok(stf) => audit
+                    .tables
+                    .entry(table.to_owned())
+                    .or_default()
+                    .extend(stf.keys().cloned()),
+                Err(e) => warn!("unable to read {}: {}", name, e),
+            }
+            return;
+        }
+
+        audit.references.extend(at_references(data));
+
+        // Templates and datatables store string ids as a table name followed by a key
+        if name.ends_with(".iff") {
+            let strings = data
+                .split(|b| *b == 0)
+                .filter_map(|s| std::str::from_utf8(s).ok())
+                .collect::<Vec<_>>();
+            audit.references.extend(
+                strings
+                    .windows(2)
+                    .map(|pair| (pair[0].to_owned(), pair[1].to_owned())),
+            );
+        }
+    }
+}
+
+/// Find every `@table:key` reference in the data
+pub(crate) fn at_references(data: &[u8]) -> impl Iterator<Item = (String, String)> + '_ {
+    let is_table = |b: &u8| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'/');
+    let is_key = |b: &u8| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-');
+
+    data.iter()
+        .enumerate()
+        .filter(|(_, b)| **b == b'@')
+        .filter_map(move |(i, _)| {
+            let rest = &data[i + 1..];
+            let table_len = rest.iter().take_while(|b| is_table(b)).count();
+            let rest = rest[table_len..].strip_prefix(b":")?;
+            let key_len = rest.iter().take_while(|b| is_key(b)).count();
+
+            if table_len == 0 || key_len == 0 {
+                return None;
+            }
+
+            Some((
+                String::from_utf8_lossy(&data[i + 1..i + 1 + table_len]).into_owned(),
+                String::from_utf8_lossy(&rest[..key_len]).into_owned(),
+            ))
+        })
+}
@@ -0,0 +1,362 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Args;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    ops::Deref,
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+};
+use swg_tre::{
+    diff::{self, DiffOptions},
+    read::TreArchiveOptions,
+    vfs::{TreVfs, VfsLayer},
+    watch::{VfsEvent, WatchedVfs},
+    TreArchive,
+};
+use tracing::{info, info_span, warn};
+
+/// The version of JSON-RPC spoken over the socket
+const JSONRPC: &str = "2.0";
+
+/// Error codes defined by JSON-RPC
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// The Unix socket to listen on
+    #[arg(short, long, value_name = "PATH")]
+    socket: PathBuf,
+
+    /// TRE files or directories of loose files to serve, later ones override earlier ones
+    #[arg(short, long, value_name = "FILE", required = true)]
+    archive: Vec<PathBuf>,
+
+    /// Bytes of decompressed entries to keep in memory for each archive
+    #[arg(long, value_name = "BYTES", default_value_t = 64 * 1024 * 1024)]
+    cache_size: u64,
+
+    /// Reload the archives and directories whenever they change on disk
+    ///
+    /// Archives are reopened without the entry cache, so `--cache-size` has no effect.
+    #[arg(long, default_value_t = false)]
+    watch: bool,
+}
+
+/// A JSON-RPC request, sent as a single line
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// A JSON-RPC response, sent as a single line
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    /// Only list files whose names start with this
+    #[serde(default)]
+    prefix: String,
+}
+
+#[derive(Deserialize)]
+struct ReadParams {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct DiffParams {
+    left: PathBuf,
+    right: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct VerifyParams {
+    /// Only verify these files, every file is verified when empty
+    #[serde(default)]
+    names: Vec<String>,
+}
+
+/// The archives and directories being served, kept open between requests
+struct Daemon {
+    paths: Vec<PathBuf>,
+    vfs: Served,
+}
+
+/// The VFS requests are answered from
+enum Served {
+    /// Opened once when the daemon starts
+    Fixed(TreVfs<File>),
+    /// Rebuilt whenever its archives or directories change
+    Watched(WatchedVfs),
+}
+
+impl DaemonArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("daemon", socket = %self.socket.display()).entered();
+
+        let daemon = Daemon {
+            paths: self.archive.clone(),
+            vfs: match self.watch {
+                true => Served::Watched(self.watch_layers()?),
+                false => Served::Fixed(self.open_layers()?),
+            },
+        };
+        info!(
+            "serving {} files from {} layers",
+            daemon.vfs().len(),
+            daemon.paths.len()
+        );
+
+        if self.socket.exists() {
+            if UnixStream::connect(&self.socket).is_ok() {
+                return Err(miette!(
+                    "another daemon is listening on {}",
+                    self.socket.display()
+                ));
+            }
+            std::fs::remove_file(&self.socket)
+                .into_diagnostic()
+                .context(format!("removing stale socket {}", self.socket.display()))?;
+        }
+        let listener = UnixListener::bind(&self.socket)
+            .into_diagnostic()
+            .context(format!("path: {}", self.socket.display()))?;
+
+        std::thread::scope(|scope| {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let daemon = &daemon;
+                        scope.spawn(move || {
+                            if let Err(e) = daemon.serve(stream) {
+                                warn!("connection closed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("unable to accept a connection: {}", e),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn open_layers(&self) -> Result<TreVfs<File>> {
+        let options = TreArchiveOptions::builder()
+            .cache_size(self.cache_size)
+            .build();
+        let layers = self
+            .archive
+            .iter()
+            .map(|path| -> Result<_> {
+                if path.is_dir() {
+                    return Ok(VfsLayer::Directory(path.clone()));
+                }
+
+                let f = File::open(path)
+                    .into_diagnostic()
+                    .context(format!("path: {}", path.display()))?;
+                let tre = TreArchive::with_options(f, options.clone())
+                    .context(format!("reading {}", path.display()))?;
+                Ok(VfsLayer::Archive(tre))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(TreVfs::with_layers(layers)?)
+    }
+
+    /// Open the layers to be rebuilt on changes, logging each rebuild
+    fn watch_layers(&self) -> Result<WatchedVfs> {
+        let watched = TreVfs::watch(&self.archive).context("watching the layers")?;
+
+        let events = watched.subscribe();
+        std::thread::spawn(move || {
+            for event in events {
+                match event {
+                    VfsEvent::Reloaded { paths } => {
+                        info!("reloaded after {} paths changed", paths.len())
+                    }
+                    VfsEvent::Failed { error, .. } => {
+                        warn!("unable to reload, still serving the old files: {}", error)
+                    }
+                }
+            }
+        });
+
+        Ok(watched)
+    }
+}
+
+impl Daemon {
+    /// The VFS as it is now, which shouldn't be held onto while a watched VFS may be rebuilt
+    fn vfs(&self) -> Box<dyn Deref<Target = TreVfs<File>> + '_> {
+        match &self.vfs {
+            Served::Fixed(vfs) => Box::new(vfs),
+            Served::Watched(watched) => Box::new(watched.vfs()),
+        }
+    }
+
+    /// Answer each request on a connection until it is closed
+    fn serve(&self, stream: UnixStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => {
+                    let result = self.dispatch(&request.method, request.params);
+                    Response {
+                        jsonrpc: JSONRPC,
+                        id: request.id,
+                        result: result.as_ref().ok().cloned(),
+                        error: result.err(),
+                    }
+                }
+                Err(e) => Response {
+                    jsonrpc: JSONRPC,
+                    id: Value::Null,
+                    result: None,
+                    error: Some(RpcError::new(PARSE_ERROR, e)),
+                },
+            };
+
+            serde_json::to_writer(&mut writer, &response)?;
+            writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    fn dispatch(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let _span = info_span!("request", method).entered();
+
+        match method {
+            "list" => self.list(parse(params)?),
+            "read" => self.read(parse(params)?),
+            "diff" => self.diff(parse(params)?),
+            "verify" => self.verify(parse(params)?),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {}", method),
+            )),
+        }
+    }
+
+    fn list(&self, params: ListParams) -> Result<Value, RpcError> {
+        let vfs = self.vfs();
+        let names = vfs
+            .file_names()
+            .filter(|name| name.starts_with(&params.prefix))
+            .collect::<Vec<_>>();
+        Ok(json!(names))
+    }
+
+    fn read(&self, params: ReadParams) -> Result<Value, RpcError> {
+        let vfs = self.vfs();
+        let location = vfs.locate(&params.name).ok_or_else(|| {
+            RpcError::new(SERVER_ERROR, format!("unable to find {}", params.name))
+        })?;
+        let data = vfs
+            .contents_by_name(&params.name)
+            .map_err(|e| RpcError::new(SERVER_ERROR, e))?;
+
+        Ok(json!({
+            "name": params.name,
+            "layer": self.paths[location.layer],
+            "size": data.len(),
+            "data": STANDARD.encode(data),
+        }))
+    }
+
+    fn diff(&self, params: DiffParams) -> Result<Value, RpcError> {
+        let open = |path: &PathBuf| {
+            File::open(path)
+                .map_err(swg_tre::error::Error::from)
+                .and_then(TreArchive::new)
+                .map_err(|e| RpcError::new(SERVER_ERROR, format!("{}: {}", path.display(), e)))
+        };
+        let (left, right) = (open(&params.left)?, open(&params.right)?);
+
+        let report = diff::compare(&left, &right, DiffOptions::default())
+            .map_err(|e| RpcError::new(SERVER_ERROR, e))?;
+
+        Ok(json!({
+            "added": report.added,
+            "removed": report.removed,
+            "modified": report
+                .modified
+                .iter()
+                .map(|entry| json!({
+                    "name": entry.name,
+                    "left_size": entry.left_size,
+                    "right_size": entry.right_size,
+                }))
+                .collect::<Vec<_>>(),
+        }))
+    }
+
+    fn verify(&self, params: VerifyParams) -> Result<Value, RpcError> {
+        let vfs = self.vfs();
+        let names = match params.names.is_empty() {
+            true => vfs.file_names().map(str::to_owned).collect(),
+            false => params.names,
+        };
+
+        let mut failed = Vec::new();
+        for name in &names {
+            let result = vfs
+                .by_name(name)
+                .and_then(|mut file| Ok(io::copy(&mut file, &mut io::sink())?));
+            if let Err(e) = result {
+                failed.push(json!({ "name": name, "error": e.to_string() }));
+            }
+        }
+
+        Ok(json!({ "checked": names.len(), "failed": failed }))
+    }
+}
+
+/// Decode the parameters of a request, treating missing parameters as an empty object
+fn parse<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Null => json!({}),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e))
+}
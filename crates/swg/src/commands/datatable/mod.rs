@@ -0,0 +1,19 @@
+pub mod strings;
+pub mod to_sql;
+
+#[derive(clap::Subcommand)]
+pub enum DatatableCommands {
+    /// Export datatables as SQL statements
+    ToSql(to_sql::ToSqlArgs),
+    /// List the localized strings a datatable refers to, with their text
+    Strings(strings::StringsArgs),
+}
+
+impl DatatableCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            DatatableCommands::ToSql(to_sql) => to_sql.handle(),
+            DatatableCommands::Strings(strings) => strings.handle(),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use clap::Args;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use std::{fs::File, io::Write, path::PathBuf};
+use swg_assets::{
+    strings::{string_cells, StringResolver},
+    Asset,
+};
+use tracing::{info, info_span, warn};
+
+use crate::commands::sources::Sources;
+
+#[derive(Args)]
+pub struct StringsArgs {
+    /// Datatables to read, e.g. datatables/skill/skills.iff
+    #[arg(required = true)]
+    tables: Vec<String>,
+
+    #[command(flatten)]
+    sources: Sources,
+
+    /// Write the review sheet to a file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+impl StringsArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("datatable_strings", language = %self.sources.language()).entered();
+
+        let overlay = self.sources.overlay()?;
+        let mut strings = StringResolver::new(&overlay, self.sources.language());
+
+        let output: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(
+                File::create(path)
+                    .into_diagnostic()
+                    .context(format!("path: {}", path.display()))?,
+            ),
+            None => Box::new(std::io::stdout()),
+        };
+        let mut writer = csv::Writer::from_writer(output);
+        writer
+            .write_record(["datatable", "row", "column", "string", "text"])
+            .into_diagnostic()?;
+
+        let (mut found, mut missing) = (0, 0);
+        for path in &self.tables {
+            let Asset::DataTable(table) =
+                swg_assets::load(&overlay, path).context(format!("reading {}", path))?
+            else {
+                return Err(miette!("{} is not a datatable", path));
+            };
+
+            for cell in string_cells(&table) {
+                let text = strings
+                    .resolve(&cell.id)
+                    .context(format!("resolving {}", cell.id))?;
+                match text {
+                    Some(_) => found += 1,
+                    None => {
+                        warn!(
+                            "{} row {} refers to missing string {}",
+                            path, cell.row, cell.id
+                        );
+                        missing += 1;
+                    }
+                }
+
+                writer
+                    .write_record([
+                        path.as_str(),
+                        &cell.row.to_string(),
+                        &cell.column,
+                        &cell.id.to_string(),
+                        text.as_deref().unwrap_or_default(),
+                    ])
+                    .into_diagnostic()?;
+            }
+        }
+        writer.flush().into_diagnostic()?;
+
+        info!("resolved {} strings, {} are missing", found, missing);
+
+        Ok(())
+    }
+}
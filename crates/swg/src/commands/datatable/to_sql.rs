@@ -0,0 +1,242 @@
+use binrw::BinRead;
+use clap::{Args, ValueEnum};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::File,
+    io::{Cursor, Read, Write},
+    path::{Path, PathBuf},
+};
+use swg_iff::{
+    datatable::{CellData, CellType, DataTable},
+    iff::IFFFile,
+};
+use swg_tre::TreArchive;
+use tracing::{info, info_span};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Dialect {
+    #[default]
+    Sqlite,
+    Mysql,
+}
+
+#[derive(Args)]
+pub struct ToSqlArgs {
+    /// Datatable IFF files to export, named after their file stem
+    #[arg(short, long, value_name = "FILE")]
+    file: Vec<PathBuf>,
+
+    /// TRE files to export every datatable from, later archives override entries in earlier ones
+    #[arg(short, long, value_name = "FILE")]
+    archive: Vec<PathBuf>,
+
+    /// Only export this entry from the archives, e.g. datatables/skill/skills.iff
+    #[arg(long, value_name = "NAME", requires = "archive")]
+    entry: Option<String>,
+
+    /// The SQL dialect to generate
+    #[arg(long, value_enum, default_value_t)]
+    dialect: Dialect,
+
+    /// Write the statements to a file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Write the tables directly into a SQLite database
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["output", "dialect"])]
+    database: Option<PathBuf>,
+}
+
+impl ToSqlArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("to_sql", dialect = ?self.dialect).entered();
+
+        let tables = self.collect_tables()?;
+        if tables.is_empty() {
+            return Err(miette!("no datatables found"));
+        }
+
+        #[cfg(feature = "sqlite")]
+        if let Some(database) = &self.database {
+            let sql = to_sql(&tables, Dialect::Sqlite)?;
+            info!("writing {}", database.display());
+            return rusqlite::Connection::open(database)
+                .and_then(|connection| connection.execute_batch(&sql))
+                .into_diagnostic()
+                .context(format!("writing {}", database.display()));
+        }
+
+        let sql = to_sql(&tables, self.dialect)?;
+        match &self.output {
+            Some(path) => File::create(path)
+                .into_diagnostic()
+                .context(format!("creating {}", path.display()))?
+                .write_all(sql.as_bytes())
+                .into_diagnostic(),
+            None => std::io::stdout()
+                .write_all(sql.as_bytes())
+                .into_diagnostic(),
+        }
+    }
+
+    /// Read every requested datatable, keyed by the name of the SQL table it becomes
+    fn collect_tables(&self) -> Result<BTreeMap<String, DataTable>> {
+        let mut sources = BTreeMap::new();
+
+        for path in &self.archive {
+            let f = File::open(path)
+                .into_diagnostic()
+                .context(format!("path: {}", path.display()))?;
+            let tre = TreArchive::new(&f)?;
+
+            let names = tre
+                .file_names()
+                .filter(|name| match &self.entry {
+                    Some(entry) => name == entry,
+                    None => name.starts_with("datatables/") && name.ends_with(".iff"),
+                })
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+
+            for name in names {
+                let mut data = Vec::new();
+                tre.by_name(&name)?
+                    .read_to_end(&mut data)
+                    .into_diagnostic()?;
+                sources.insert(table_name(&name), (name, data));
+            }
+        }
+
+        for path in &self.file {
+            let data = std::fs::read(path)
+                .into_diagnostic()
+                .context(format!("path: {}", path.display()))?;
+            let name = path.display().to_string();
+            sources.insert(table_name(&file_stem(path)?), (name, data));
+        }
+
+        sources
+            .into_iter()
+            .map(|(table, (name, data))| {
+                info!("reading {}", name);
+                IFFFile::read_be(&mut Cursor::new(data))
+                    .map_err(swg_iff::error::Error::from)
+                    .and_then(|iff| Ok(DataTable::try_from(iff)?))
+                    .context(format!("parsing {}", name))
+                    .map(|datatable| (table, datatable))
+            })
+            .collect()
+    }
+}
+
+fn file_stem(path: &Path) -> Result<String> {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .ok_or(miette!("unable to create file stem for {}", path.display()))
+}
+
+/// Turn an entry name like `datatables/skill/skills.iff` into `skill_skills`
+fn table_name(name: &str) -> String {
+    name.trim_start_matches("datatables/")
+        .trim_end_matches(".iff")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn to_sql(tables: &BTreeMap<String, DataTable>, dialect: Dialect) -> Result<String> {
+    let mut sql = String::new();
+    write_sql(&mut sql, tables, dialect).into_diagnostic()?;
+    Ok(sql)
+}
+
+fn write_sql(
+    sql: &mut String,
+    tables: &BTreeMap<String, DataTable>,
+    dialect: Dialect,
+) -> std::fmt::Result {
+    writeln!(
+        sql,
+        "{};",
+        match dialect {
+            Dialect::Sqlite => "BEGIN TRANSACTION",
+            Dialect::Mysql => "START TRANSACTION",
+        }
+    )?;
+
+    for (name, table) in tables {
+        let table_name = quote_identifier(name, dialect);
+
+        writeln!(sql)?;
+        writeln!(sql, "DROP TABLE IF EXISTS {};", table_name)?;
+        writeln!(sql, "CREATE TABLE {} (", table_name)?;
+        for (i, (column, cell_type)) in table.columns.iter().zip(&table.types).enumerate() {
+            writeln!(
+                sql,
+                "    {} {}{}",
+                quote_identifier(&column.to_string(), dialect),
+                column_type(cell_type, dialect),
+                if i + 1 < table.columns.len() { "," } else { "" }
+            )?;
+        }
+        writeln!(sql, ");")?;
+
+        let columns = table
+            .columns
+            .iter()
+            .map(|column| quote_identifier(&column.to_string(), dialect))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        for row in &table.rows {
+            let values = row
+                .cells
+                .iter()
+                .map(|cell| value(&cell.data, dialect))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(
+                sql,
+                "INSERT INTO {} ({}) VALUES ({});",
+                table_name, columns, values
+            )?;
+        }
+    }
+
+    writeln!(sql)?;
+    writeln!(sql, "COMMIT;")
+}
+
+fn quote_identifier(identifier: &str, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::Sqlite => format!("\"{}\"", identifier.replace('"', "\"\"")),
+        Dialect::Mysql => format!("`{}`", identifier.replace('`', "``")),
+    }
+}
+
+fn column_type(cell_type: &CellType, dialect: Dialect) -> &'static str {
+    match (cell_type, dialect) {
+        (CellType::String(_), _) => "TEXT",
+        (CellType::Boolean(_), Dialect::Sqlite) => "INTEGER",
+        (CellType::Boolean(_), Dialect::Mysql) => "BOOLEAN",
+        (CellType::Integer(_) | CellType::Enum(_, _), Dialect::Sqlite) => "INTEGER",
+        (CellType::Integer(_) | CellType::Enum(_, _), Dialect::Mysql) => "INT UNSIGNED",
+    }
+}
+
+fn value(data: &CellData, dialect: Dialect) -> String {
+    match data {
+        CellData::String(value) => {
+            let value = value.to_string().replace('\'', "''");
+            match dialect {
+                Dialect::Sqlite => format!("'{}'", value),
+                Dialect::Mysql => format!("'{}'", value.replace('\\', "\\\\")),
+            }
+        }
+        CellData::Boolean(value) => (*value as u8).to_string(),
+        CellData::Integer(value) | CellData::Enum(value) => value.to_string(),
+    }
+}
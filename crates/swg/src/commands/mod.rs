@@ -1,18 +1,104 @@
+pub mod audit;
+#[cfg(unix)]
+pub mod daemon;
+pub mod datatable;
+mod output;
+pub mod quest;
+pub mod refactor;
+mod sources;
+pub mod stf;
+pub mod template;
+pub mod terrain;
+pub mod toc;
 pub mod tre;
+pub mod ui;
+pub mod vfs;
+pub mod ws;
 
 #[derive(clap::Subcommand)]
 pub enum Commands {
+    /// Audit game data for problems
+    Audit {
+        #[command(subcommand)]
+        command: audit::AuditCommands,
+    },
+    /// Keep archives open and answer JSON-RPC requests on a Unix socket
+    #[cfg(unix)]
+    Daemon(daemon::DaemonArgs),
+    /// Handle datatable IFF files
+    Datatable {
+        #[command(subcommand)]
+        command: datatable::DatatableCommands,
+    },
+    /// Work with the text of quests
+    Quest {
+        #[command(subcommand)]
+        command: quest::QuestCommands,
+    },
+    /// Make changes which span many assets
+    Refactor {
+        #[command(subcommand)]
+        command: refactor::RefactorCommands,
+    },
+    /// Handle string table files
+    Stf {
+        #[command(subcommand)]
+        command: stf::StfCommands,
+    },
+    /// Handle object templates
+    Template {
+        #[command(subcommand)]
+        command: template::TemplateCommands,
+    },
+    /// Render maps from terrain files
+    Terrain {
+        #[command(subcommand)]
+        command: terrain::TerrainCommands,
+    },
+    /// Build and inspect TOC files, which index many TRE files at once
+    Toc {
+        #[command(subcommand)]
+        command: toc::TocCommands,
+    },
     /// Handle TRE files
     Tre {
         #[command(subcommand)]
         command: tre::TreCommands,
     },
+    /// Handle the layouts of the client UI
+    Ui {
+        #[command(subcommand)]
+        command: ui::UiCommands,
+    },
+    /// Inspect how the game's archives combine into the files it loads
+    Vfs {
+        #[command(subcommand)]
+        command: vfs::VfsCommands,
+    },
+    /// Handle world snapshots, which place the static objects of a world
+    Ws {
+        #[command(subcommand)]
+        command: ws::WsCommands,
+    },
 }
 
 impl Commands {
     pub fn handle(&self) -> miette::Result<()> {
         match self {
+            Commands::Audit { command } => command.handle(),
+            #[cfg(unix)]
+            Commands::Daemon(daemon) => daemon.handle(),
+            Commands::Datatable { command } => command.handle(),
+            Commands::Quest { command } => command.handle(),
+            Commands::Refactor { command } => command.handle(),
+            Commands::Stf { command } => command.handle(),
+            Commands::Template { command } => command.handle(),
+            Commands::Terrain { command } => command.handle(),
+            Commands::Toc { command } => command.handle(),
             Commands::Tre { command } => command.handle(),
+            Commands::Ui { command } => command.handle(),
+            Commands::Vfs { command } => command.handle(),
+            Commands::Ws { command } => command.handle(),
         }
     }
 }
@@ -0,0 +1,53 @@
+//! Where commands write files named by archive entries and documents
+
+use std::path::{Component, Path, PathBuf};
+use tracing::warn;
+
+/// Whether every component of a relative name is a normal one, rather than `..` or a root which
+/// could place it outside of a directory it's joined onto
+fn is_normal(name: &str) -> bool {
+    Path::new(name)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Join a relative name onto `root`, or `None` if it could place the path outside of `root`
+pub(crate) fn safe_join(root: &Path, name: &str) -> Option<PathBuf> {
+    is_normal(name).then(|| root.join(name))
+}
+
+/// Whether an archive entry can be written below a directory by its name, with a warning if not
+pub(crate) fn is_safe_entry(name: &str) -> bool {
+    let safe = is_normal(name);
+    if !safe {
+        warn!("skipping unsafe entry name {}", name);
+    }
+    safe
+}
+
+/// The path to write an archive entry to below `root`, or `None`, with a warning, if its name
+/// would place it outside of `root`
+pub(crate) fn output_path(root: &Path, name: &str) -> Option<PathBuf> {
+    is_safe_entry(name).then(|| root.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{output_path, safe_join};
+
+    #[test]
+    fn reject_traversal() {
+        let root = Path::new("out");
+        assert_eq!(
+            output_path(root, "string/en/obj_n.stf"),
+            Some(root.join("string/en/obj_n.stf"))
+        );
+
+        for name in ["string/en/../../../x.stf", "/etc/passwd", "./a.stf"] {
+            assert_eq!(output_path(root, name), None, "{}", name);
+        }
+        assert_eq!(safe_join(root, "../x.stf"), None);
+    }
+}
@@ -0,0 +1,18 @@
+pub mod strings;
+
+#[derive(clap::Subcommand)]
+pub enum QuestCommands {
+    /// Gather every string a quest uses into a single document for review
+    Strings(strings::StringsArgs),
+    /// Write the edits made to a quest strings document back into string tables
+    ImportStrings(strings::ImportStringsArgs),
+}
+
+impl QuestCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            QuestCommands::Strings(strings) => strings.handle(),
+            QuestCommands::ImportStrings(import) => import.handle(),
+        }
+    }
+}
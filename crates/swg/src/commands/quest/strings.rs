@@ -0,0 +1,218 @@
+use clap::Args;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+use swg_assets::Asset;
+use swg_iff::datatable::CellData;
+use swg_stf::StringTableWriter;
+use tracing::{info, info_span, warn};
+use widestring::U16String;
+
+use crate::commands::{audit::unused_strings::at_references, output::safe_join, sources::Sources};
+
+#[derive(Args)]
+pub struct StringsArgs {
+    /// The name of the quest, e.g. `legacy_head_to_bestine`
+    quest: String,
+
+    #[command(flatten)]
+    sources: Sources,
+
+    /// Also include every string of a conversation, e.g. `c_newbie_mentor`
+    #[arg(long, value_name = "NAME")]
+    conversation: Vec<String>,
+
+    /// Write the document to a file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ImportStringsArgs {
+    /// A document created by `quest strings`
+    document: PathBuf,
+
+    #[command(flatten)]
+    sources: Sources,
+
+    /// A directory to write the changed string tables to
+    #[arg(short, long, value_name = "DIR")]
+    output: PathBuf,
+}
+
+/// Every string used by a quest, grouped by string table and key
+#[derive(Serialize, Deserialize)]
+struct QuestStrings {
+    quest: String,
+    language: String,
+    strings: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl StringsArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("quest_strings", quest = %self.quest).entered();
+
+        let sources = self.sources.open()?;
+
+        let mut whole_tables = BTreeSet::from([format!("quest/ground/{}", self.quest)]);
+        whole_tables.extend(
+            self.conversation
+                .iter()
+                .map(|name| format!("conversation/{}", name)),
+        );
+
+        let mut references = BTreeMap::<String, BTreeSet<String>>::new();
+        let mut found = false;
+        for path in [
+            format!("datatables/questlist/quest/{}.iff", self.quest),
+            format!("datatables/questtask/quest/{}.iff", self.quest),
+        ] {
+            let Some(data) = sources.read(&path)? else {
+                warn!("unable to find {}", path);
+                continue;
+            };
+            found = true;
+
+            let Asset::DataTable(table) =
+                Asset::parse(&data).context(format!("parsing {}", path))?
+            else {
+                warn!("{} is not a datatable", path);
+                continue;
+            };
+
+            let cells = table.rows.iter().flat_map(|row| &row.cells);
+            for cell in cells {
+                if let CellData::String(value) = &cell.data {
+                    for (table, key) in at_references(&value.0) {
+                        references.entry(table).or_default().insert(key);
+                    }
+                }
+            }
+        }
+
+        if !found {
+            return Err(miette!("unable to find quest {}", self.quest));
+        }
+
+        let mut strings = BTreeMap::<String, BTreeMap<String, String>>::new();
+        for table in &whole_tables {
+            match sources.string_table(table)? {
+                Some(stf) => strings.entry(table.clone()).or_default().extend(
+                    stf.iter()
+                        .map(|(key, value)| (key.clone(), value.to_string_lossy())),
+                ),
+                None => warn!("unable to find string table {}", table),
+            }
+        }
+
+        for (table, keys) in &references {
+            if whole_tables.contains(table) {
+                continue;
+            }
+
+            let Some(stf) = sources.string_table(table)? else {
+                warn!("unable to find string table {}", table);
+                continue;
+            };
+
+            for key in keys {
+                match stf.get(key) {
+                    Some(value) => {
+                        strings
+                            .entry(table.clone())
+                            .or_default()
+                            .insert(key.clone(), value.to_string_lossy());
+                    }
+                    None => warn!("{}:{} is referenced but does not exist", table, key),
+                }
+            }
+        }
+
+        info!(
+            "gathered {} strings from {} tables",
+            strings.values().map(BTreeMap::len).sum::<usize>(),
+            strings.len()
+        );
+
+        let document = serde_yaml::to_string(&QuestStrings {
+            quest: self.quest.clone(),
+            language: sources.language().to_owned(),
+            strings,
+        })
+        .into_diagnostic()?;
+
+        match &self.output {
+            Some(path) => std::fs::write(path, document)
+                .into_diagnostic()
+                .context(format!("writing {}", path.display())),
+            None => std::io::stdout()
+                .write_all(document.as_bytes())
+                .into_diagnostic(),
+        }
+    }
+}
+
+impl ImportStringsArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span =
+            info_span!("quest_import_strings", document = %self.document.display()).entered();
+
+        let f = File::open(&self.document)
+            .into_diagnostic()
+            .context(format!("path: {}", self.document.display()))?;
+        let document: QuestStrings = serde_yaml::from_reader(f)
+            .into_diagnostic()
+            .context(format!("parsing {}", self.document.display()))?;
+
+        let sources = self.sources.open()?;
+        if sources.language() != document.language {
+            return Err(miette!(
+                "document is for language {} but {} was requested",
+                document.language,
+                sources.language()
+            ));
+        }
+
+        for (table, edits) in &document.strings {
+            let name = format!("string/{}/{}.stf", document.language, table);
+            let path = safe_join(&self.output, &name)
+                .ok_or_else(|| miette!("{} is not a valid string table name", table))?;
+
+            let mut stf = sources.string_table(table)?.unwrap_or_default();
+
+            let mut changed = 0;
+            for (key, value) in edits {
+                let value = U16String::from_str(value);
+                if stf.get(key) != Some(&value) {
+                    stf.insert(key.clone(), value)
+                        .context(format!("adding {}", key))?;
+                    changed += 1;
+                }
+            }
+
+            if changed == 0 {
+                continue;
+            }
+
+            info!("writing {} changed strings to {}", changed, path.display());
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .into_diagnostic()
+                    .context(format!("creating {}", parent.display()))?;
+            }
+            let f = File::create(&path)
+                .into_diagnostic()
+                .context(format!("path: {}", path.display()))?;
+            StringTableWriter::encode(&stf, BufWriter::new(f))
+                .context(format!("writing {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,19 @@
+pub mod move_asset;
+pub mod rename_string;
+
+#[derive(clap::Subcommand)]
+pub enum RefactorCommands {
+    /// Move an asset to a new path and update every reference to it
+    Move(move_asset::MoveArgs),
+    /// Rename the key of a string and update every datatable and template referencing it
+    RenameString(rename_string::RenameStringArgs),
+}
+
+impl RefactorCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            RefactorCommands::Move(move_asset) => move_asset.handle(),
+            RefactorCommands::RenameString(rename_string) => rename_string.handle(),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use clap::Args;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use swg_iff::rewrite::replace_string;
+use swg_tre::{write::TreWriterOptions, CompressionMethod, TreArchive, TreWriter};
+use tracing::{info, info_span, warn};
+use walkdir::WalkDir;
+
+use crate::commands::output::{output_path, safe_join};
+
+#[derive(Args)]
+pub struct MoveArgs {
+    /// The current path of the asset, e.g. `object/tangible/item/shared_thing.iff`
+    old: String,
+
+    /// The path to move the asset to
+    new: String,
+
+    /// A game directory containing TRE files and loose assets, later files override earlier ones
+    #[arg(short, long, value_name = "DIR")]
+    game_dir: PathBuf,
+
+    /// The directory or TRE file to write the moved asset and every updated file to, a `.tre`
+    /// extension selects a TRE
+    #[arg(short, long, value_name = "PATH")]
+    out: PathBuf,
+
+    /// Allow overwriting an existing output TRE file
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+}
+
+#[derive(Default)]
+struct Refactor {
+    /// The contents of the moved asset, with any references to itself updated
+    moved: Option<Vec<u8>>,
+    /// Every file which references the asset, rewritten, alongside the number of references
+    updated: BTreeMap<String, (Vec<u8>, usize)>,
+    /// Whether an asset already exists at the new path
+    exists: bool,
+}
+
+impl MoveArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("move", old = %self.old, new = %self.new).entered();
+
+        for path in [&self.old, &self.new] {
+            if safe_join(&self.out, path).is_none() {
+                return Err(miette!("{} is not a valid asset path", path));
+            }
+        }
+
+        let mut refactor = Refactor::default();
+
+        let files = WalkDir::new(&self.game_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file());
+
+        for file in files {
+            let path = file.path();
+            if path.extension().is_some_and(|ext| ext == "tre") {
+                self.visit_archive(&mut refactor, path)?;
+                continue;
+            }
+
+            let name = path
+                .strip_prefix(&self.game_dir)
+                .into_diagnostic()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let data = std::fs::read(path)
+                .into_diagnostic()
+                .context(format!("path: {}", path.display()))?;
+            self.visit(&mut refactor, &name, data);
+        }
+
+        let Some(moved) = refactor.moved else {
+            return Err(miette!("unable to find {}", self.old));
+        };
+        if refactor.exists {
+            warn!("{} already exists and will be replaced", self.new);
+        }
+
+        for (name, (_, references)) in &refactor.updated {
+            println!("{}: {} references", name, references);
+        }
+        info!(
+            "updated {} references in {} files",
+            refactor.updated.values().map(|(_, n)| n).sum::<usize>(),
+            refactor.updated.len()
+        );
+
+        let files = std::iter::once((self.new.as_str(), moved.as_slice())).chain(
+            refactor
+                .updated
+                .iter()
+                .map(|(name, (data, _))| (name.as_str(), data.as_slice())),
+        );
+
+        if self.out.extension().is_some_and(|ext| ext == "tre") {
+            self.write_archive(files)
+        } else {
+            self.write_directory(files)
+        }
+    }
+
+    fn visit_archive(&self, refactor: &mut Refactor, path: &Path) -> Result<()> {
+        let _span = info_span!("archive", archive = %path.display()).entered();
+
+        let f = File::open(path)
+            .into_diagnostic()
+            .context(format!("path: {}", path.display()))?;
+        let tre = TreArchive::new(&f)?;
+
+        let names = tre.file_names().map(str::to_owned).collect::<Vec<_>>();
+        for name in names {
+            let mut data = Vec::new();
+            tre.by_name(&name)?
+                .read_to_end(&mut data)
+                .into_diagnostic()
+                .context(format!("reading {}", name))?;
+            self.visit(refactor, &name, data);
+        }
+
+        Ok(())
+    }
+
+    fn visit(&self, refactor: &mut Refactor, name: &str, data: Vec<u8>) {
+        // A later copy of a file replaces whatever an earlier one needed
+        refactor.updated.remove(name);
+
+        let data = match self.rewrite(name, data) {
+            Ok(Some((data, references))) if name != self.old => {
+                refactor.updated.insert(name.to_owned(), (data, references));
+                return;
+            }
+            Ok(Some((data, _))) => data,
+            Ok(None) => return,
+            Err(data) => data,
+        };
+
+        if name == self.old {
+            refactor.moved = Some(data);
+        } else if name == self.new {
+            refactor.exists = true;
+        }
+    }
+
+    /// Replace references to the asset, handing the data back when there are none
+    fn rewrite(&self, name: &str, data: Vec<u8>) -> Result<Option<(Vec<u8>, usize)>, Vec<u8>> {
+        let old = self.old.as_bytes();
+        if !data.starts_with(b"FORM") || !data.windows(old.len()).any(|w| w == old) {
+            return Err(data);
+        }
+
+        match replace_string(&data, &self.old, &self.new) {
+            Ok((_, 0)) => Err(data),
+            Ok(rewritten) => Ok(Some(rewritten)),
+            Err(e) => {
+                warn!("unable to update {}: {}", name, e);
+                Err(data)
+            }
+        }
+    }
+
+    fn write_directory<'a>(&self, files: impl Iterator<Item = (&'a str, &'a [u8])>) -> Result<()> {
+        for (name, data) in files {
+            let Some(path) = output_path(&self.out, name) else {
+                continue;
+            };
+            info!("writing {}", path.display());
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .into_diagnostic()
+                    .context(format!("creating {}", parent.display()))?;
+            }
+            std::fs::write(&path, data)
+                .into_diagnostic()
+                .context(format!("writing {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_archive<'a>(&self, files: impl Iterator<Item = (&'a str, &'a [u8])>) -> Result<()> {
+        info!("creating {}", self.out.display());
+
+        let mut out = if !self.overwrite {
+            File::create_new(&self.out)
+        } else {
+            File::create(&self.out)
+        }
+        .into_diagnostic()
+        .context(format!("creating {}", self.out.display()))?;
+
+        let mut tre = TreWriter::new(&mut out, TreWriterOptions::builder().build());
+        for (name, data) in files {
+            tre.start_file(name, CompressionMethod::Auto)
+                .context(format!("starting entry for {}", name))?;
+            tre.write_all(data)
+                .into_diagnostic()
+                .context(format!("writing {}", name))?;
+        }
+
+        tre.finish().context("finalizing tre file")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use clap::Args;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use std::path::PathBuf;
+use swg_assets::{
+    strings::{rename_key, StringId},
+    AssetSource, Overlay,
+};
+use swg_stf::StringTableWriter;
+use tracing::{info, info_span};
+
+use crate::commands::{output::output_path, sources::open_sources};
+
+#[derive(Args)]
+pub struct RenameStringArgs {
+    /// The string to rename, e.g. `@obj_n:old_key`
+    id: String,
+
+    /// The new key of the string
+    key: String,
+
+    /// A directory or TRE file to read from, later sources override earlier ones
+    #[arg(short, long = "source", value_name = "PATH", required = true)]
+    sources: Vec<PathBuf>,
+
+    /// The directory to write every changed string table and referencing file to, without it
+    /// the references are only reported
+    #[arg(short, long, value_name = "DIR")]
+    out: Option<PathBuf>,
+}
+
+impl RenameStringArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("rename_string", id = %self.id, key = %self.key).entered();
+
+        let id = StringId::parse(&self.id)
+            .ok_or_else(|| miette!("{} is not a string id like @table:key", self.id))?;
+        let source: Overlay = open_sources(&self.sources)?.into();
+
+        // Datatables and object templates are both IFF files
+        let files = source
+            .list("")?
+            .into_iter()
+            .filter(|path| path.ends_with(".iff"));
+        let rename = rename_key(&source, &id, &self.key, files)?;
+
+        for (name, (_, references)) in &rename.references {
+            println!("{}: {} references", name, references);
+        }
+        info!(
+            "renamed the key in {} tables and {} references in {} files",
+            rename.tables.len(),
+            rename.references.values().map(|(_, n)| n).sum::<usize>(),
+            rename.references.len()
+        );
+
+        let Some(out) = &self.out else {
+            return Ok(());
+        };
+
+        let mut files = Vec::new();
+        for (name, table) in &rename.tables {
+            let mut data = Vec::new();
+            StringTableWriter::encode(table, &mut data).context(format!("encoding {}", name))?;
+            files.push((name.as_str(), data));
+        }
+        files.extend(
+            rename
+                .references
+                .iter()
+                .map(|(name, (data, _))| (name.as_str(), data.clone())),
+        );
+
+        for (name, data) in files {
+            let Some(path) = output_path(out, name) else {
+                continue;
+            };
+            info!("writing {}", path.display());
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .into_diagnostic()
+                    .context(format!("creating {}", parent.display()))?;
+            }
+            std::fs::write(&path, data)
+                .into_diagnostic()
+                .context(format!("writing {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! Game data read from a list of directories and TRE files, shared by the commands which look up
+//! assets and strings across them
+
+use clap::Args;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use std::{
+    fs::File,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use swg_assets::{error::Error as AssetError, AssetSource, Directory, Overlay};
+use swg_stf::{read::StringTableReader, types::StringTable};
+use swg_tre::{vfs::TreVfs, TreArchive};
+
+/// Game data to read from
+#[derive(Args)]
+pub struct Sources {
+    /// A directory or TRE file to read from, later sources override earlier ones
+    #[arg(short, long = "source", value_name = "PATH", required = true)]
+    sources: Vec<PathBuf>,
+
+    /// The language of the string tables
+    #[arg(long, default_value = "en")]
+    language: String,
+}
+
+/// Every source of [`Sources`], opened
+pub(crate) struct OpenSources {
+    sources: Vec<Box<dyn AssetSource>>,
+    language: String,
+}
+
+/// Open every directory or TRE file as a source of assets
+pub(crate) fn open_sources(paths: &[PathBuf]) -> Result<Vec<Box<dyn AssetSource>>> {
+    paths
+        .iter()
+        .map(|path| -> Result<Box<dyn AssetSource>> {
+            if path.is_dir() {
+                return Ok(Box::new(Directory::new(path)));
+            }
+
+            let f = File::open(path)
+                .into_diagnostic()
+                .context(format!("path: {}", path.display()))?;
+            Ok(Box::new(TreArchive::new(f)?))
+        })
+        .collect()
+}
+
+/// Open the archives and directories a client directory loads, in the search tree order of its
+/// `.cfg` files
+pub(crate) fn open_game_dir(dir: &Path) -> Result<TreVfs<File>> {
+    let vfs = TreVfs::from_client_dir(dir).context(format!("opening {}", dir.display()))?;
+    if vfs.layer_count() == 0 {
+        return Err(miette!(
+            "{} has no .cfg files listing archives to load",
+            dir.display()
+        ));
+    }
+    Ok(vfs)
+}
+
+/// Read an asset from the last source which has it
+pub(crate) fn read_asset(
+    sources: &[Box<dyn AssetSource>],
+    path: &str,
+) -> Result<Option<Arc<[u8]>>> {
+    for source in sources.iter().rev() {
+        match source.read(path) {
+            Ok(data) => return Ok(Some(data)),
+            Err(AssetError::NotFound(_)) => continue,
+            Err(e) => return Err(e).context(format!("reading {}", path)),
+        }
+    }
+
+    Ok(None)
+}
+
+impl Sources {
+    pub(crate) fn open(&self) -> Result<OpenSources> {
+        Ok(OpenSources {
+            sources: open_sources(&self.sources)?,
+            language: self.language.clone(),
+        })
+    }
+
+    /// Open every source as the layers of a single overlay
+    pub(crate) fn overlay(&self) -> Result<Overlay> {
+        Ok(open_sources(&self.sources)?.into())
+    }
+
+    /// The language of the string tables
+    pub(crate) fn language(&self) -> &str {
+        &self.language
+    }
+}
+
+impl OpenSources {
+    /// The language of the string tables
+    pub(crate) fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Read an asset from the last source which has it
+    pub(crate) fn read(&self, path: &str) -> Result<Option<Arc<[u8]>>> {
+        read_asset(&self.sources, path)
+    }
+
+    /// Read a string table by its name, e.g. `quest/ground/legacy_head_to_bestine`
+    pub(crate) fn string_table(&self, table: &str) -> Result<Option<StringTable>> {
+        let path = format!("string/{}/{}.stf", self.language, table);
+        self.read(&path)?
+            .map(|data| StringTableReader::decode(Cursor::new(&data[..])))
+            .transpose()
+            .context(format!("parsing {}", path))
+    }
+}
@@ -0,0 +1,140 @@
+use clap::{Args, ValueEnum};
+use miette::{Context, IntoDiagnostic, Result};
+use rayon::prelude::*;
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+use swg_stf::{
+    read::StringTableReader, surrogates::SurrogatePolicy, text::TextTable, types::StringTable,
+};
+use tracing::{info, info_span, warn};
+
+use crate::commands::{output::output_path, sources::open_game_dir};
+
+/// The format string tables are exported as
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// An object with the table's header fields and a list of its entries
+    #[default]
+    Json,
+    /// An `id,key,value` row for every string
+    Csv,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ExportAllArgs {
+    /// A client directory, whose .cfg files list the archives and directories to load
+    #[arg(short, long, value_name = "DIR")]
+    game_dir: PathBuf,
+
+    /// The directory to write the exported tables to, mirroring their paths in the game
+    #[arg(short, long, value_name = "DIR")]
+    out: PathBuf,
+
+    /// The format to export tables as
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+
+    /// What to do with values which aren't valid UTF-16
+    #[arg(long, value_enum, default_value_t)]
+    surrogates: Surrogates,
+}
+
+/// What happens to values with an unpaired surrogate, which can't be written as text
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Surrogates {
+    /// Write them as a `\uD800` style escape, which is read back as the original value
+    #[default]
+    Preserve,
+    /// Replace them with U+FFFD
+    Replace,
+    /// Fail the export
+    Error,
+}
+
+impl From<Surrogates> for SurrogatePolicy {
+    fn from(value: Surrogates) -> Self {
+        match value {
+            Surrogates::Preserve => SurrogatePolicy::Preserve,
+            Surrogates::Replace => SurrogatePolicy::Replace,
+            Surrogates::Error => SurrogatePolicy::Error,
+        }
+    }
+}
+
+impl ExportAllArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("export_all", game_dir = %self.game_dir.display()).entered();
+
+        let vfs = open_game_dir(&self.game_dir)?;
+        let tables = vfs
+            .file_names()
+            .filter(|name| name.ends_with(".stf"))
+            .collect::<Vec<_>>();
+
+        info!("exporting {} string tables", tables.len());
+
+        let exported = tables
+            .par_iter()
+            .map(|name| -> Result<usize> {
+                let Some(path) = output_path(&self.out, name) else {
+                    return Ok(0);
+                };
+
+                let data = vfs
+                    .contents_by_name(name)
+                    .context(format!("reading {}", name))?;
+                match StringTableReader::decode(Cursor::new(data)) {
+                    Ok(table) => {
+                        self.write(name, &path, &table)?;
+                        Ok(1)
+                    }
+                    Err(e) => {
+                        warn!("unable to read {}: {}", name, e);
+                        Ok(0)
+                    }
+                }
+            })
+            .try_reduce(|| 0, |a, b| Ok(a + b))?;
+
+        info!(
+            "exported {} of {} string tables to {}",
+            exported,
+            tables.len(),
+            self.out.display()
+        );
+
+        Ok(())
+    }
+
+    fn write(&self, name: &str, path: &Path, table: &StringTable) -> Result<()> {
+        let path = path.with_extension(self.format.extension());
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .into_diagnostic()
+                .context(format!("creating {}", parent.display()))?;
+        }
+
+        let data = TextTable::with_policy(table, self.surrogates.into())
+            .and_then(|table| match self.format {
+                Format::Json => table.to_json(),
+                Format::Csv => table.to_csv(),
+            })
+            .context(format!("encoding {}", name))?;
+
+        std::fs::write(&path, data)
+            .into_diagnostic()
+            .context(format!("writing {}", path.display()))
+    }
+}
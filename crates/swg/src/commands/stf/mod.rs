@@ -0,0 +1,27 @@
+pub mod export_all;
+pub mod references;
+pub mod report;
+pub mod transcode;
+
+#[derive(clap::Subcommand)]
+pub enum StfCommands {
+    /// Export every string table in a game directory as JSON or CSV
+    ExportAll(export_all::ExportAllArgs),
+    /// List the datatables and object templates which refer to strings, or report them unused
+    References(references::ReferencesArgs),
+    /// Write an HTML page comparing two STF files, or every string table of two game versions
+    Report(report::ReportArgs),
+    /// Rewrite a locale's string tables into another locale using find/replace and casing rules
+    Transcode(transcode::TranscodeArgs),
+}
+
+impl StfCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            StfCommands::ExportAll(export_all) => export_all.handle(),
+            StfCommands::References(references) => references.handle(),
+            StfCommands::Report(report) => report.handle(),
+            StfCommands::Transcode(transcode) => transcode.handle(),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use clap::Args;
+use miette::{miette, Result};
+use std::path::PathBuf;
+use swg_assets::{references::ReferenceIndex, strings::StringId, AssetSource, Overlay};
+use tracing::{info, info_span};
+
+use crate::commands::sources::open_sources;
+
+#[derive(Args)]
+pub struct ReferencesArgs {
+    /// The strings to look up, e.g. `@obj_n:thing`
+    #[arg(required = true)]
+    ids: Vec<String>,
+
+    /// A directory or TRE file to read from, later sources override earlier ones
+    #[arg(short, long = "source", value_name = "PATH", required = true)]
+    sources: Vec<PathBuf>,
+}
+
+impl ReferencesArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("stf_references").entered();
+
+        let ids = self
+            .ids
+            .iter()
+            .map(|id| {
+                StringId::parse(id)
+                    .ok_or_else(|| miette!("{} is not a string id like @table:key", id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let source: Overlay = open_sources(&self.sources)?.into();
+
+        let index = ReferenceIndex::build(&source, source.list("")?)?;
+        info!("indexed references to {} strings", index.len());
+
+        for id in &ids {
+            let references = index.references(id);
+            if references.is_empty() {
+                println!("{}: unused", id);
+                continue;
+            }
+
+            println!("{}: {} references", id, references.len());
+            for reference in references {
+                println!("  {} {:?}", reference.path, reference.location);
+            }
+        }
+
+        Ok(())
+    }
+}
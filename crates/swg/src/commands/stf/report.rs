@@ -0,0 +1,84 @@
+use clap::Args;
+use miette::{Context, IntoDiagnostic, Result};
+use std::{fs::File, io::Write, path::PathBuf};
+use swg_assets::{catalog::Catalog, Overlay};
+use swg_stf::{read::StringTableReader, report::DiffReport, types::StringTable};
+use tracing::{info, info_span};
+
+use crate::commands::sources::open_sources;
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// The old STF file, or a directory or TRE file to compare every string table of
+    #[arg(short, long, value_name = "PATH")]
+    left: PathBuf,
+
+    /// The new STF file, or a directory or TRE file to compare every string table of
+    #[arg(short, long, value_name = "PATH")]
+    right: PathBuf,
+
+    /// The language of the string tables, when comparing game data
+    #[arg(long, default_value = "en")]
+    language: String,
+
+    /// The heading of the report, defaulting to the compared paths
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Write the report to a file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+impl ReportArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!(
+            "stf_report",
+            left = %self.left.display(),
+            right = %self.right.display()
+        )
+        .entered();
+
+        let title = self
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("{} → {}", self.left.display(), self.right.display()));
+        let mut report = DiffReport::new(title);
+
+        let is_stf = |path: &PathBuf| path.extension().is_some_and(|ext| ext == "stf");
+        if is_stf(&self.left) && is_stf(&self.right) {
+            let left = read_table(&self.left)?;
+            let right = read_table(&self.right)?;
+            let name = self.right.file_stem().unwrap_or_default().to_string_lossy();
+            report.add(name, left.diff(&right));
+        } else {
+            let left = self.catalog(&self.left)?;
+            let right = self.catalog(&self.right)?;
+            report.tables = left.diff(&right);
+        }
+        info!(tables = report.tables.len(), "compared string tables");
+
+        let html = report.to_html();
+        match &self.output {
+            Some(path) => std::fs::write(path, html)
+                .into_diagnostic()
+                .context(format!("writing {}", path.display())),
+            None => std::io::stdout()
+                .write_all(html.as_bytes())
+                .into_diagnostic(),
+        }
+    }
+
+    /// Load every string table of the language from a directory or TRE file
+    fn catalog(&self, path: &PathBuf) -> Result<Catalog> {
+        let source: Overlay = open_sources(std::slice::from_ref(path))?.into();
+        Catalog::load(&source, &self.language).context(format!("reading {}", path.display()))
+    }
+}
+
+fn read_table(path: &PathBuf) -> Result<StringTable> {
+    let f = File::open(path)
+        .into_diagnostic()
+        .context(format!("path: {}", path.display()))?;
+    StringTableReader::decode(f).context(format!("reading {}", path.display()))
+}
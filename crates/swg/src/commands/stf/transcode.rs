@@ -0,0 +1,389 @@
+use clap::Args;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Cursor, Read},
+    path::PathBuf,
+};
+use swg_stf::{read::StringTableReader, types::StringTable, StringTableWriter};
+use swg_tre::{write::TreWriterOptions, CompressionMethod, TreArchive, TreWriter};
+use tracing::{info, info_span, warn};
+use walkdir::WalkDir;
+use widestring::U16String;
+
+use crate::commands::output::is_safe_entry;
+
+#[derive(Args)]
+pub struct TranscodeArgs {
+    /// A directory or TRE file containing `string/<locale>/` tables
+    #[arg(short, long, value_name = "PATH")]
+    source: PathBuf,
+
+    /// The locale to read string tables from
+    #[arg(long, value_name = "LOCALE")]
+    from_locale: String,
+
+    /// The locale to write string tables to
+    #[arg(long, value_name = "LOCALE")]
+    to_locale: String,
+
+    /// A YAML file with the rules to apply, in order, to every string
+    #[arg(short, long, value_name = "FILE")]
+    rules: PathBuf,
+
+    /// The directory or TRE file to write the new locale to, a `.tre` extension selects a TRE
+    #[arg(short, long, value_name = "PATH")]
+    output: PathBuf,
+
+    /// Allow overwriting an existing output TRE file
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+}
+
+/// A rule as it appears in the rules file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    /// Literal text to find
+    find: Option<String>,
+    /// A regular expression to find, the replacement may refer to its groups
+    pattern: Option<String>,
+    /// The replacement for `find` or `pattern`
+    replace: Option<String>,
+    /// A casing to apply to the whole string
+    case: Option<Case>,
+    /// Only apply to tables whose path within the locale matches this expression
+    files: Option<String>,
+    /// Only apply to keys matching this expression
+    keys: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Case {
+    Upper,
+    Lower,
+    Title,
+    Sentence,
+}
+
+enum Action {
+    Literal { find: String, replace: String },
+    Pattern { regex: Regex, replace: String },
+    Case(Case),
+}
+
+struct Rule {
+    action: Action,
+    files: Option<Regex>,
+    keys: Option<Regex>,
+}
+
+impl TryFrom<RuleSpec> for Rule {
+    type Error = miette::Report;
+
+    fn try_from(spec: RuleSpec) -> Result<Self> {
+        let compile = |expression: Option<String>| {
+            expression
+                .map(|expression| {
+                    Regex::new(&expression)
+                        .into_diagnostic()
+                        .context(format!("compiling {}", expression))
+                })
+                .transpose()
+        };
+
+        let action = match (spec.find, spec.pattern, spec.replace, spec.case) {
+            (Some(find), None, Some(replace), None) => Action::Literal { find, replace },
+            (None, Some(pattern), Some(replace), None) => Action::Pattern {
+                regex: compile(Some(pattern))?.expect("pattern is present"),
+                replace,
+            },
+            (None, None, None, Some(case)) => Action::Case(case),
+            _ => {
+                return Err(miette!(
+                    "a rule needs exactly one of `find` and `replace`, `pattern` and `replace`, or `case`"
+                ))
+            }
+        };
+
+        Ok(Rule {
+            action,
+            files: compile(spec.files)?,
+            keys: compile(spec.keys)?,
+        })
+    }
+}
+
+impl Rule {
+    fn applies(&self, file: &str, key: &str) -> bool {
+        self.files
+            .as_ref()
+            .map_or(true, |files| files.is_match(file))
+            && self.keys.as_ref().map_or(true, |keys| keys.is_match(key))
+    }
+
+    fn apply(&self, value: &str) -> String {
+        match &self.action {
+            Action::Literal { find, replace } => value.replace(find.as_str(), replace),
+            Action::Pattern { regex, replace } => {
+                regex.replace_all(value, replace.as_str()).into_owned()
+            }
+            Action::Case(Case::Upper) => value.to_uppercase(),
+            Action::Case(Case::Lower) => value.to_lowercase(),
+            Action::Case(Case::Title) => value
+                .split_inclusive(char::is_whitespace)
+                .map(capitalize)
+                .collect(),
+            Action::Case(Case::Sentence) => capitalize(value),
+        }
+    }
+}
+
+/// Uppercase the first character and lowercase the rest
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+impl TranscodeArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!(
+            "transcode",
+            from = %self.from_locale,
+            to = %self.to_locale,
+            source = %self.source.display()
+        )
+        .entered();
+
+        let rules = self.read_rules()?;
+        let tables = self.read_tables()?;
+        if tables.is_empty() {
+            return Err(miette!(
+                "no string tables found for locale {}",
+                self.from_locale
+            ));
+        }
+
+        let mut changed = 0;
+        let tables = tables
+            .into_iter()
+            .map(|(file, mut table)| -> Result<_> {
+                let rewrites = table
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let original = value.to_string_lossy();
+                        let rewritten = rules
+                            .iter()
+                            .filter(|rule| rule.applies(&file, key))
+                            .fold(original.clone(), |value, rule| rule.apply(&value));
+
+                        (rewritten != original).then(|| (key.clone(), rewritten))
+                    })
+                    .collect::<Vec<_>>();
+
+                changed += rewrites.len();
+                for (key, rewritten) in rewrites {
+                    table.update(&key, U16String::from_str(&rewritten))?;
+                }
+                Ok((file, table))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        info!("rewrote {} strings in {} tables", changed, tables.len());
+
+        if self.output.extension().is_some_and(|ext| ext == "tre") {
+            self.write_archive(&tables)
+        } else {
+            self.write_directory(&tables)
+        }
+    }
+
+    fn read_rules(&self) -> Result<Vec<Rule>> {
+        let f = File::open(&self.rules)
+            .into_diagnostic()
+            .context(format!("path: {}", self.rules.display()))?;
+        let specs: Vec<RuleSpec> = serde_yaml::from_reader(f)
+            .into_diagnostic()
+            .context(format!("parsing {}", self.rules.display()))?;
+
+        specs.into_iter().map(Rule::try_from).collect()
+    }
+
+    /// Read every table of the source locale, keyed by its path within the locale
+    fn read_tables(&self) -> Result<BTreeMap<String, StringTable>> {
+        let prefix = format!("string/{}/", self.from_locale);
+        let mut tables = BTreeMap::new();
+
+        let mut decode =
+            |file: &str, data: &[u8]| match StringTableReader::decode(Cursor::new(data)) {
+                Ok(table) => {
+                    tables.insert(file.to_owned(), table);
+                }
+                Err(e) => warn!("unable to read {}{}: {}", prefix, file, e),
+            };
+
+        if self.source.is_dir() {
+            let root = self.source.join(&prefix);
+            let files = WalkDir::new(&root)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "stf"));
+
+            for file in files {
+                let name = file
+                    .path()
+                    .strip_prefix(&root)
+                    .into_diagnostic()?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let data = std::fs::read(file.path())
+                    .into_diagnostic()
+                    .context(format!("path: {}", file.path().display()))?;
+                decode(&name, &data);
+            }
+        } else {
+            let f = File::open(&self.source)
+                .into_diagnostic()
+                .context(format!("path: {}", self.source.display()))?;
+            let tre = TreArchive::new(&f)?;
+
+            let names = tre
+                .file_names()
+                .filter(|name| name.starts_with(&prefix) && name.ends_with(".stf"))
+                .filter(|name| is_safe_entry(name))
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+
+            let mut data = Vec::new();
+            for name in names {
+                data.clear();
+                tre.by_name(&name)?
+                    .read_to_end(&mut data)
+                    .into_diagnostic()
+                    .context(format!("reading {}", name))?;
+                decode(&name[prefix.len()..], &data);
+            }
+        }
+
+        Ok(tables)
+    }
+
+    fn write_directory(&self, tables: &BTreeMap<String, StringTable>) -> Result<()> {
+        let root = self.output.join("string").join(&self.to_locale);
+
+        for (file, table) in tables {
+            let path = root.join(file);
+            info!("writing {}", path.display());
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .into_diagnostic()
+                    .context(format!("creating {}", parent.display()))?;
+            }
+            let f = File::create(&path)
+                .into_diagnostic()
+                .context(format!("path: {}", path.display()))?;
+            StringTableWriter::encode(table, BufWriter::new(f))
+                .context(format!("writing {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_archive(&self, tables: &BTreeMap<String, StringTable>) -> Result<()> {
+        info!("creating {}", self.output.display());
+
+        let mut out = if !self.overwrite {
+            File::create_new(&self.output)
+        } else {
+            File::create(&self.output)
+        }
+        .into_diagnostic()
+        .context(format!("creating {}", self.output.display()))?;
+
+        let mut tre = TreWriter::new(&mut out, TreWriterOptions::builder().build());
+
+        for (file, table) in tables {
+            let name = format!("string/{}/{}", self.to_locale, file);
+            tre.start_file(&name, CompressionMethod::Auto)
+                .context(format!("starting entry for {}", name))?;
+            StringTableWriter::encode(table, &mut tre).context(format!("writing {}", name))?;
+        }
+
+        tre.finish().context("finalizing tre file")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use swg_stf::{types::StringTable, StringTableWriter};
+    use swg_tre::{
+        write::{SeparatorPolicy, TreNamePolicy, TreWriterOptions},
+        CompressionMethod, TreWriter,
+    };
+
+    use super::TranscodeArgs;
+
+    #[test]
+    fn skip_traversal_names() -> miette::Result<()> {
+        let root = std::env::temp_dir().join(format!("swg_transcode_{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+
+        let mut table = Vec::new();
+        StringTableWriter::encode(&StringTable::from_iter([("key", "value")]), &mut table)?;
+
+        let source = root.join("source.tre");
+        let mut tre = TreWriter::new(
+            std::fs::File::create(&source).unwrap(),
+            TreWriterOptions::builder()
+                .name_policy(
+                    TreNamePolicy::builder()
+                        .separators(SeparatorPolicy::Allow)
+                        .build(),
+                )
+                .build(),
+        );
+        for name in ["string/en/obj_n.stf", "string/en/../../../x.stf"] {
+            tre.start_file(name, CompressionMethod::None)?;
+            tre.write_all(&table).unwrap();
+        }
+        tre.finish()?;
+
+        let rules = root.join("rules.yaml");
+        std::fs::write(&rules, "[]").unwrap();
+
+        let args = TranscodeArgs {
+            source,
+            from_locale: "en".to_owned(),
+            to_locale: "fr".to_owned(),
+            rules,
+            output: root.join("out/nested"),
+            overwrite: false,
+        };
+        let tables = args.read_tables()?;
+        assert_eq!(tables.keys().collect::<Vec<_>>(), ["obj_n.stf"]);
+
+        args.handle()?;
+        assert!(root.join("out/nested/string/fr/obj_n.stf").exists());
+        assert!(!root.join("out/x.stf").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        Ok(())
+    }
+}
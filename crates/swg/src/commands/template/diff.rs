@@ -0,0 +1,145 @@
+use clap::Args;
+use itertools::{EitherOrBoth, Itertools};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+use swg_assets::AssetSource;
+use swg_iff::template::ObjectTemplate;
+use tracing::{info, info_span};
+
+use crate::commands::sources::{open_sources, read_asset};
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// The original template
+    left: PathBuf,
+
+    /// The changed template
+    right: PathBuf,
+
+    /// Include the values each template inherits from the templates it derives from
+    #[arg(long, requires = "sources")]
+    resolve: bool,
+
+    /// A directory or TRE file to find base templates in, later sources override earlier ones
+    #[arg(short, long = "source", value_name = "PATH")]
+    sources: Vec<PathBuf>,
+}
+
+impl DiffArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!(
+            "template_diff",
+            left = %self.left.display(),
+            right = %self.right.display()
+        )
+        .entered();
+
+        let sources = open_sources(&self.sources)?;
+        let left = self.read(&self.left, &sources)?;
+        let right = self.read(&self.right, &sources)?;
+
+        let mut changes = 0;
+        if left.tag != right.tag {
+            changes += 1;
+            println!("~ tag: {} → {}", left.tag.red(), right.tag.green());
+        }
+        if left.base != right.base {
+            changes += 1;
+            let base = |base: &Option<String>| base.clone().unwrap_or_else(|| "none".into());
+            println!(
+                "~ base: {} → {}",
+                base(&left.base).red(),
+                base(&right.base).green()
+            );
+        }
+
+        let parameters = left
+            .parameters
+            .iter()
+            .merge_join_by(&right.parameters, |(l, _), (r, _)| l.cmp(r));
+        for parameter in parameters {
+            match parameter {
+                EitherOrBoth::Both((name, old), (_, new)) if old != new => {
+                    println!(
+                        "~ {}: {} → {}",
+                        name,
+                        describe(old).red(),
+                        describe(new).green()
+                    );
+                }
+                EitherOrBoth::Both(_, _) => continue,
+                EitherOrBoth::Left((name, old)) => {
+                    println!("{}", format!("- {}: {}", name, describe(old)).red());
+                }
+                EitherOrBoth::Right((name, new)) => {
+                    println!("{}", format!("+ {}: {}", name, describe(new)).green());
+                }
+            }
+            changes += 1;
+        }
+
+        info!("found {} differences", changes);
+
+        Ok(())
+    }
+
+    fn read(&self, path: &Path, sources: &[Box<dyn AssetSource>]) -> Result<ObjectTemplate> {
+        let data = std::fs::read(path)
+            .into_diagnostic()
+            .context(format!("path: {}", path.display()))?;
+        let mut template =
+            ObjectTemplate::parse(&data).context(format!("parsing {}", path.display()))?;
+
+        if self.resolve {
+            resolve(&mut template, sources)?;
+        }
+
+        Ok(template)
+    }
+}
+
+/// Apply the values a template inherits along its whole chain of base templates
+fn resolve(template: &mut ObjectTemplate, sources: &[Box<dyn AssetSource>]) -> Result<()> {
+    let mut seen = BTreeSet::new();
+    let mut next = template.base.clone();
+
+    while let Some(path) = next {
+        if !seen.insert(path.clone()) {
+            return Err(miette!("{} derives from itself", path));
+        }
+
+        let data = read_asset(sources, &path)?
+            .ok_or_else(|| miette!("unable to find base template {}", path))?;
+        let base = ObjectTemplate::parse(&data).context(format!("parsing {}", path))?;
+        template.inherit(&base);
+        next = base.base;
+    }
+
+    Ok(())
+}
+
+/// Render an encoded value as the strings it holds, or as hex when it holds anything else
+fn describe(value: &[u8]) -> String {
+    text(value).unwrap_or_else(|| value.iter().map(|b| format!("{:02x}", b)).join(" "))
+}
+
+/// The strings of a value, which follow the byte saying how the value is set
+fn text(value: &[u8]) -> Option<String> {
+    let (_, strings) = value.split_first()?;
+    let strings = strings.strip_suffix(&[0])?;
+
+    strings
+        .split(|b| *b == 0)
+        .map(|s| {
+            std::str::from_utf8(s)
+                .ok()
+                .filter(|s| !s.is_empty() && !s.chars().any(char::is_control))
+                .map(|s| format!("{:?}", s))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|strings| strings.join(" "))
+}
@@ -0,0 +1,15 @@
+pub mod diff;
+
+#[derive(clap::Subcommand)]
+pub enum TemplateCommands {
+    /// Compare the parameters of two object templates
+    Diff(diff::DiffArgs),
+}
+
+impl TemplateCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            TemplateCommands::Diff(diff) => diff.handle(),
+        }
+    }
+}
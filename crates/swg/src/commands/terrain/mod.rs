@@ -0,0 +1,15 @@
+pub mod tiles;
+
+#[derive(clap::Subcommand)]
+pub enum TerrainCommands {
+    /// Render a pyramid of map tiles for a planet, marking the objects its snapshots place
+    Tiles(tiles::TilesArgs),
+}
+
+impl TerrainCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            TerrainCommands::Tiles(tiles) => tiles.handle(),
+        }
+    }
+}
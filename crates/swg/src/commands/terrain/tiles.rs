@@ -0,0 +1,232 @@
+use clap::Args;
+use miette::{Context, IntoDiagnostic, Result};
+use rayon::prelude::*;
+use std::{
+    fs::File,
+    io::BufWriter,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+use swg_iff::{snapshot::WorldSnapshot, terrain::TerrainHeader};
+use tracing::{info, info_span};
+
+/// The width and height of a tile in pixels
+const TILE_SIZE: u32 = 256;
+
+/// The deepest zoom level tiles can be rendered at
+const MAX_ZOOM: u8 = 10;
+
+/// The distance between grid lines in metres
+const GRID_SPACING: f32 = 1024.0;
+
+const BACKGROUND: [u8; 3] = [0x3a, 0x40, 0x3b];
+const GRID: [u8; 3] = [0x4a, 0x52, 0x4c];
+const BUILDING: [u8; 3] = [0xf0, 0xa0, 0x3c];
+const OBJECT: [u8; 3] = [0x8c, 0xc8, 0xf0];
+
+#[derive(Args)]
+pub struct TilesArgs {
+    /// The planet's terrain file, which gives the size of the map
+    #[arg(short, long, value_name = "FILE")]
+    terrain: PathBuf,
+
+    /// World snapshots whose objects are marked on the map
+    #[arg(short, long, value_name = "FILE")]
+    snapshot: Vec<PathBuf>,
+
+    /// The zoom levels to render, from the first to the last inclusive, e.g. `0..5`
+    #[arg(short, long, value_name = "RANGE", default_value = "0..5", value_parser = parse_zoom)]
+    zoom: RangeInclusive<u8>,
+
+    /// The directory to write tiles to, as `<zoom>/<x>/<y>.png`
+    #[arg(short, long, value_name = "DIR")]
+    out: PathBuf,
+}
+
+/// An object drawn on the map, positioned in world metres
+struct Marker {
+    x: f32,
+    z: f32,
+    radius: f32,
+    color: [u8; 3],
+}
+
+impl TilesArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("tiles", terrain = %self.terrain.display()).entered();
+
+        let header = TerrainHeader::parse(&read(&self.terrain)?)
+            .context(format!("reading {}", self.terrain.display()))?;
+        info!("{} is {}m wide", header.name, header.map_width);
+
+        let mut markers = Vec::new();
+        for path in &self.snapshot {
+            let snapshot = WorldSnapshot::parse(&read(path)?)
+                .context(format!("reading {}", path.display()))?;
+
+            // Contained objects are positioned relative to their container, so only the objects in
+            // the world itself are drawn
+            markers.extend(snapshot.nodes.iter().map(|node| Marker {
+                x: node.position[0],
+                z: node.position[2],
+                radius: node.radius,
+                color: match node.portal_layout_crc {
+                    0 => OBJECT,
+                    _ => BUILDING,
+                },
+            }));
+        }
+        info!("marking {} objects", markers.len());
+
+        let mut written = 0;
+        for zoom in self.zoom.clone() {
+            let count = 1u32 << zoom;
+            (0..count * count).into_par_iter().try_for_each(|i| {
+                let (x, y) = (i % count, i / count);
+                let pixels = render(header.map_width, &markers, zoom, x, y);
+                self.write_tile(zoom, x, y, &pixels)
+            })?;
+            written += count * count;
+        }
+        info!("wrote {} tiles to {}", written, self.out.display());
+
+        Ok(())
+    }
+
+    fn write_tile(&self, zoom: u8, x: u32, y: u32, pixels: &[u8]) -> Result<()> {
+        let dir = self.out.join(zoom.to_string()).join(x.to_string());
+        std::fs::create_dir_all(&dir)
+            .into_diagnostic()
+            .context(format!("creating {}", dir.display()))?;
+
+        let path = dir.join(format!("{}.png", y));
+        let f = File::create(&path)
+            .into_diagnostic()
+            .context(format!("path: {}", path.display()))?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(f), TILE_SIZE, TILE_SIZE);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(pixels))
+            .into_diagnostic()
+            .context(format!("writing {}", path.display()))
+    }
+}
+
+/// Render the RGB pixels of a tile, with the map centred on the origin and north at the top
+fn render(map_width: f32, markers: &[Marker], zoom: u8, x: u32, y: u32) -> Vec<u8> {
+    let scale = (TILE_SIZE << zoom) as f32 / map_width;
+    let half = map_width / 2.0;
+    let (left, top) = ((x * TILE_SIZE) as f32, (y * TILE_SIZE) as f32);
+
+    // A grid line is drawn in each column and row of pixels a multiple of the spacing falls in
+    let is_grid = |pixel: f32| {
+        (pixel / scale / GRID_SPACING).floor() != ((pixel + 1.0) / scale / GRID_SPACING).floor()
+    };
+    let grid_columns = (0..TILE_SIZE)
+        .map(|px| is_grid(left + px as f32))
+        .collect::<Vec<_>>();
+    let grid_rows = (0..TILE_SIZE)
+        .map(|py| is_grid(top + py as f32))
+        .collect::<Vec<_>>();
+
+    let mut pixels = Vec::with_capacity((TILE_SIZE * TILE_SIZE * 3) as usize);
+    for row in &grid_rows {
+        for column in &grid_columns {
+            let color = match *row || *column {
+                true => GRID,
+                false => BACKGROUND,
+            };
+            pixels.extend_from_slice(&color);
+        }
+    }
+
+    for marker in markers {
+        let cx = (marker.x + half) * scale - left;
+        let cy = (half - marker.z) * scale - top;
+        let radius = (marker.radius * scale).clamp(1.0, 32.0);
+
+        let span = |centre: f32| {
+            let start = (centre - radius).floor().max(0.0) as u32;
+            let end = (centre + radius).ceil().min(TILE_SIZE as f32) as u32;
+            start..end
+        };
+        for py in span(cy) {
+            for px in span(cx) {
+                let (dx, dy) = (px as f32 + 0.5 - cx, py as f32 + 0.5 - cy);
+                if dx * dx + dy * dy <= radius * radius {
+                    let at = ((py * TILE_SIZE + px) * 3) as usize;
+                    pixels[at..at + 3].copy_from_slice(&marker.color);
+                }
+            }
+        }
+    }
+
+    pixels
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path)
+        .into_diagnostic()
+        .context(format!("path: {}", path.display()))
+}
+
+/// Parse a range of zoom levels such as `0..5`, or a single level
+fn parse_zoom(value: &str) -> Result<RangeInclusive<u8>, String> {
+    let (start, end) = match value.split_once("..") {
+        Some((start, end)) => (start, end.trim_start_matches('=')),
+        None => (value, value),
+    };
+    let level = |level: &str| {
+        level
+            .trim()
+            .parse::<u8>()
+            .map_err(|e| format!("{}: {}", level, e))
+    };
+
+    let (start, end) = (level(start)?, level(end)?);
+    if start > end || end > MAX_ZOOM {
+        return Err(format!(
+            "zoom levels must run from low to high, up to {}",
+            MAX_ZOOM
+        ));
+    }
+    Ok(start..=end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_zoom, render, Marker, BACKGROUND, BUILDING, TILE_SIZE};
+
+    #[test]
+    fn zoom_ranges() {
+        assert_eq!(parse_zoom("0..5"), Ok(0..=5));
+        assert_eq!(parse_zoom("2..=3"), Ok(2..=3));
+        assert_eq!(parse_zoom("4"), Ok(4..=4));
+        assert!(parse_zoom("5..0").is_err());
+        assert!(parse_zoom("0..30").is_err());
+    }
+
+    #[test]
+    fn place_markers() {
+        let markers = [Marker {
+            x: 1000.0,
+            z: 1000.0,
+            radius: 10.0,
+            color: BUILDING,
+        }];
+        let pixel = |pixels: &[u8], px: u32, py: u32| {
+            let at = ((py * TILE_SIZE + px) * 3) as usize;
+            [pixels[at], pixels[at + 1], pixels[at + 2]]
+        };
+
+        // North east of the origin is the top right tile at zoom 1, and the centre at zoom 0
+        let tile = render(8192.0, &markers, 1, 1, 0);
+        assert_eq!(pixel(&tile, 62, 193), BUILDING);
+        assert_eq!(pixel(&tile, 100, 100), BACKGROUND);
+        let tile = render(8192.0, &markers, 0, 0, 0);
+        assert_eq!(pixel(&tile, 159, 96), BUILDING);
+    }
+}
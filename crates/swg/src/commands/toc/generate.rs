@@ -0,0 +1,94 @@
+use clap::Args;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Component, Path, PathBuf},
+};
+use swg_tre::{
+    config::{ClientConfig, SearchKind},
+    toc::{TocWriter, TocWriterOptions},
+    TreArchive,
+};
+use tracing::{info, info_span, warn};
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// A client directory, whose .cfg files list the TRE files to index
+    #[arg(short, long, value_name = "DIR")]
+    client: PathBuf,
+
+    /// Where to write the TOC file, defaults to client.toc in the client directory
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+impl GenerateArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("generate", client = %self.client.display()).entered();
+
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| self.client.join("client.toc"));
+        let output_dir = output
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .canonicalize()
+            .into_diagnostic()
+            .context(format!("path: {}", output.display()))?;
+
+        let config = ClientConfig::from_client_dir(&self.client)?;
+        let mut toc = TocWriter::new(TocWriterOptions::builder().build());
+        let mut archives = 0;
+        for entry in config.entries() {
+            let path = self.client.join(&entry.path);
+            if entry.kind == SearchKind::Path {
+                warn!(
+                    "skipping directory {}, TOC files only index archives",
+                    path.display()
+                );
+                continue;
+            }
+
+            let f = File::open(&path)
+                .into_diagnostic()
+                .context(format!("path: {}", path.display()))?;
+            let tre = TreArchive::new(f).context(format!("reading {}", path.display()))?;
+            toc.add_archive(relative_name(&path, &output_dir)?, &tre)?;
+            archives += 1;
+        }
+
+        let writer = BufWriter::new(
+            File::create(&output)
+                .into_diagnostic()
+                .context(format!("path: {}", output.display()))?,
+        );
+        toc.finish(writer)?;
+
+        info!("indexed {} archives into {}", archives, output.display());
+        Ok(())
+    }
+}
+
+/// The name the TOC stores for an archive, relative to the directory the TOC is written to when
+/// the archive is beneath it
+fn relative_name(archive: &Path, dir: &Path) -> Result<String> {
+    let archive = archive
+        .canonicalize()
+        .into_diagnostic()
+        .context(format!("path: {}", archive.display()))?;
+    let relative = archive.strip_prefix(dir).unwrap_or(&archive);
+
+    let components = relative
+        .components()
+        .filter_map(|component| match component {
+            Component::RootDir => Some(Some("")),
+            Component::Normal(name) => Some(name.to_str()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| miette!("{} is not valid UTF-8", archive.display()))?;
+    Ok(components.join("/"))
+}
@@ -0,0 +1,54 @@
+use clap::Args;
+use miette::{Context, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+use std::{fs::File, io::BufReader, path::PathBuf};
+use swg_tre::toc::TocFile;
+use tracing::{info, info_span};
+
+#[derive(Args)]
+pub struct InspectArgs {
+    /// The TOC file to print
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+}
+
+impl InspectArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("inspect", toc = %self.file.display()).entered();
+
+        let f = File::open(&self.file)
+            .into_diagnostic()
+            .context(format!("path: {}", self.file.display()))?;
+        let toc =
+            TocFile::new(BufReader::new(f)).context(format!("reading {}", self.file.display()))?;
+
+        let header = toc.header();
+        info!(
+            "{} entries in {} archives, records are {} and names are {}",
+            toc.len(),
+            toc.archives().len(),
+            header.record_compression,
+            header.name_compression
+        );
+
+        for (index, archive) in toc.archives().iter().enumerate() {
+            println!("{} {}", format!("[{}]", index).blue(), archive);
+        }
+
+        for entry in toc.entries() {
+            let record = &entry.record;
+            println!(
+                "{:08x} {} {} @ {:#x} ({} bytes, {} stored as {})",
+                record.checksum,
+                entry.name,
+                format!("[{}]", record.archive).blue(),
+                record.data_offset,
+                record.data_uncompressed,
+                record.data_compressed,
+                record.data_compression
+            );
+        }
+
+        Ok(())
+    }
+}
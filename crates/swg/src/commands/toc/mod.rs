@@ -0,0 +1,19 @@
+pub mod generate;
+pub mod inspect;
+
+#[derive(clap::Subcommand)]
+pub enum TocCommands {
+    /// Build a TOC file for the TRE files a client installation loads
+    Generate(generate::GenerateArgs),
+    /// Print the archives and entries of a TOC file
+    Inspect(inspect::InspectArgs),
+}
+
+impl TocCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            TocCommands::Generate(generate) => generate.handle(),
+            TocCommands::Inspect(inspect) => inspect.handle(),
+        }
+    }
+}
@@ -5,14 +5,21 @@ use owo_colors::OwoColorize;
 use similar::{ChangeTag, TextDiff};
 use std::{
     cmp::Ordering,
-    collections::HashSet,
     fmt::Display,
     fs::File,
     io::{Cursor, Read, Seek},
     path::PathBuf,
 };
-use swg_stf::{read::StringTableReader, types::StringTable};
-use swg_tre::TreArchive;
+use swg_stf::{
+    read::StringTableReader,
+    tokens::{plain_text, PlainTextOptions},
+    types::StringTable,
+};
+use swg_tre::{
+    diff::{self, Changed, DiffOptions, ModifiedEntry},
+    TreArchive,
+};
+use tracing::info_span;
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum Mode {
@@ -176,6 +183,10 @@ impl Display for Change {
     }
 }
 
+fn comparison<T: ToString>(key: &str, change: Option<Changed<T>>) -> Option<Change> {
+    change.map(|c| Change::Comparison(key.into(), c.left.to_string(), c.right.to_string()))
+}
+
 #[derive(Args)]
 pub struct DiffArgs {
     /// An input TRE file
@@ -189,288 +200,173 @@ pub struct DiffArgs {
     /// Comparison mode
     #[arg(short, long, value_enum, default_value_t=Mode::Symantic)]
     mode: Mode,
+
+    /// Compare string table values as they're shown in game, without color codes
+    #[arg(long)]
+    plain: bool,
 }
 
 impl DiffArgs {
     fn handle_stf_file(&self, left: &StringTable, right: &StringTable) -> Result<Vec<Change>> {
+        let diff = left.diff(right);
         let mut result = Vec::new();
 
-        // Find Added Entries
-        right
-            .keys()
-            .filter(|k| !left.contains_key(k.as_str()))
-            .map(|k| Change::Added("entries".into(), k.to_string()))
-            .for_each(|c| result.push(c));
-
-        // Find Removed Entries
-        left.keys()
-            .filter(|k| !right.contains_key(k.as_str()))
-            .map(|k| Change::Removed("entries".into(), k.to_string()))
-            .for_each(|c| result.push(c));
-
-        // Find Differences
-        left.keys()
-            .filter(|k| right.contains_key(k.as_str()))
-            .sorted()
-            .filter_map(|k| {
-                let old = left
-                    .get_key_value(k)
-                    .and_then(|(_, v)| v.to_string().ok())
-                    .unwrap_or("".into());
-
-                let new = right
-                    .get_key_value(k)
-                    .and_then(|(_, v)| v.to_string().ok())
-                    .unwrap_or("".into());
+        result.extend(
+            diff.added
+                .iter()
+                .map(|entry| Change::Added("entries".into(), entry.key.clone())),
+        );
+        result.extend(
+            diff.removed
+                .iter()
+                .map(|entry| Change::Removed("entries".into(), entry.key.clone())),
+        );
+
+        for entry in &diff.changed {
+            let (old, new) = if self.plain {
+                (
+                    plain_text(&entry.old, PlainTextOptions::default()),
+                    plain_text(&entry.new, PlainTextOptions::default()),
+                )
+            } else {
+                (entry.old.to_string_lossy(), entry.new.to_string_lossy())
+            };
+            if old == new {
+                // Only the color codes or formatting changed
+                continue;
+            }
 
+            let mut comparison = Vec::new();
+            if self.mode == Mode::Full {
                 let diff = TextDiff::from_lines(&old, &new);
-                if diff.ratio() < 1.0 {
-                    let mut comparison = Vec::new();
-                    if self.mode == Mode::Full {
-                        for op in diff.ops().iter() {
-                            for change in diff.iter_inline_changes(op) {
-                                let mut context = String::new();
-                                for (emphasized, value) in change.iter_strings_lossy() {
-                                    if emphasized {
-                                        if change.tag() == ChangeTag::Insert {
-                                            context.push_str(&format!(
-                                                "{}",
-                                                value.green().underline()
-                                            ));
-                                        } else {
-                                            context
-                                                .push_str(&format!("{}", value.red().underline()));
-                                        }
-                                    } else {
-                                        context.push_str(&format!("{}", value.dimmed()));
-                                    }
+                for op in diff.ops().iter() {
+                    for change in diff.iter_inline_changes(op) {
+                        let mut context = String::new();
+                        for (emphasized, value) in change.iter_strings_lossy() {
+                            if emphasized {
+                                if change.tag() == ChangeTag::Insert {
+                                    context.push_str(&format!("{}", value.green().underline()));
+                                } else {
+                                    context.push_str(&format!("{}", value.red().underline()));
                                 }
-                                comparison.push(context);
+                            } else {
+                                context.push_str(&format!("{}", value.dimmed()));
                             }
                         }
+                        comparison.push(context);
                     }
-                    Some(Change::Modified(
-                        "entries".into(),
-                        k.into(),
-                        vec![],
-                        vec![Change::Context(comparison)],
-                    ))
-                } else {
-                    None
                 }
-            })
-            .for_each(|c| result.push(c));
+            }
+            result.push(Change::Modified(
+                "entries".into(),
+                entry.key.clone(),
+                vec![],
+                vec![Change::Context(comparison)],
+            ));
+        }
 
         Ok(result)
     }
 
-    fn handle_file<'a>(
+    fn handle_file<R: Read + Seek>(
         &self,
-        name: &'a str,
-        left: &'a Vec<u8>,
-        right: &'a Vec<u8>,
-    ) -> Result<Option<Change>> {
-        let mut result: Option<Change> = None;
-
-        if left.len() != right.len() {
-            result
-                .get_or_insert(Change::Modified(
-                    "files".into(),
-                    name.into(),
-                    Vec::new(),
-                    Vec::new(),
-                ))
-                .with_related(vec![Change::Comparison(
-                    "size".into(),
-                    left.len().to_string(),
-                    right.len().to_string(),
-                )])?;
+        entry: &ModifiedEntry,
+        left: &TreArchive<R>,
+        right: &TreArchive<R>,
+    ) -> Result<Change> {
+        let mut result =
+            Change::Modified("files".into(), entry.name.clone(), Vec::new(), Vec::new());
+
+        if entry.left_size != entry.right_size {
+            result.with_related(vec![Change::Comparison(
+                "size".into(),
+                entry.left_size.to_string(),
+                entry.right_size.to_string(),
+            )])?;
         }
 
-        if name.ends_with(".stf") {
-            let stf_left = StringTableReader::decode(Cursor::new(left))?;
-            let stf_right = StringTableReader::decode(Cursor::new(right))?;
+        if entry.name.ends_with(".stf") {
+            let stf_left =
+                StringTableReader::decode(Cursor::new(left.contents_by_name(&entry.name)?))?;
+            let stf_right =
+                StringTableReader::decode(Cursor::new(right.contents_by_name(&entry.name)?))?;
             let changes = self.handle_stf_file(&stf_left, &stf_right)?;
             if !changes.is_empty() {
-                result
-                    .get_or_insert(Change::Modified(
-                        "files".into(),
-                        name.into(),
-                        Vec::new(),
-                        Vec::new(),
-                    ))
-                    .with_children(changes)?;
+                result.with_children(changes)?;
             }
         }
 
         Ok(result)
     }
 
-    fn handle_tre<'a, R: Read + Seek>(
+    fn handle_tre<R: Read + Seek>(
         &self,
-        name: &'a str,
-        left: &'a mut TreArchive<R>,
-        right: &'a mut TreArchive<R>,
+        name: &str,
+        left: &TreArchive<R>,
+        right: &TreArchive<R>,
     ) -> Result<Option<Change>> {
-        let mut result: Option<Change> = None;
-
-        if left.len() != right.len() {
-            result
-                .get_or_insert(Change::Modified(
-                    "tre".into(),
-                    name.into(),
-                    Vec::new(),
-                    Vec::new(),
-                ))
-                .with_related(vec![Change::Comparison(
-                    "entries".into(),
-                    left.len().to_string(),
-                    right.len().to_string(),
-                )])?;
+        let report = diff::compare(left, right, DiffOptions::default())?;
+        if report.is_empty() && (self.mode != Mode::Full || report.header.is_empty()) {
+            return Ok(None);
         }
 
-        if self.mode == Mode::Full {
-            if left.get_record_compression() != right.get_record_compression() {
-                result
-                    .get_or_insert(Change::Modified(
-                        "tre".into(),
-                        name.into(),
-                        Vec::new(),
-                        Vec::new(),
-                    ))
-                    .with_related(vec![Change::Comparison(
-                        "record compression".into(),
-                        left.get_record_compression().to_string(),
-                        right.get_record_compression().to_string(),
-                    )])?;
-            }
+        let mut result = Change::Modified("tre".into(), name.into(), Vec::new(), Vec::new());
 
-            if left.get_record_block_size() != right.get_record_block_size() {
-                result
-                    .get_or_insert(Change::Modified(
-                        "tre".into(),
-                        name.into(),
-                        Vec::new(),
-                        Vec::new(),
-                    ))
-                    .with_related(vec![Change::Comparison(
-                        "record block size".into(),
-                        left.get_record_block_size().to_string(),
-                        right.get_record_block_size().to_string(),
-                    )])?;
-            }
-
-            if left.get_name_block_size() != right.get_name_block_size() {
-                result
-                    .get_or_insert(Change::Modified(
-                        "tre".into(),
-                        name.into(),
-                        Vec::new(),
-                        Vec::new(),
-                    ))
-                    .with_related(vec![Change::Comparison(
-                        "name block size".into(),
-                        left.get_name_block_size().to_string(),
-                        right.get_name_block_size().to_string(),
-                    )])?;
-            }
-        }
-
-        let left_names = left
-            .file_names()
-            .map(|s| s.to_owned())
-            .collect::<HashSet<_>>();
-        let right_names = right
-            .file_names()
-            .map(|s| s.to_owned())
-            .collect::<HashSet<_>>();
-
-        let mut all_names = left_names.union(&right_names).collect::<Vec<_>>();
-        all_names.sort();
-
-        let files_added: Vec<Change> = all_names
-            .iter()
-            .copied()
-            .filter(|f| right_names.contains(f.as_str()) && !left_names.contains(f.as_str()))
-            .map(|k| Change::Added("files".into(), k.to_string()))
-            .collect();
-
-        if !files_added.is_empty() {
-            result
-                .get_or_insert(Change::Modified(
-                    "tre".into(),
-                    name.into(),
-                    Vec::new(),
-                    Vec::new(),
-                ))
-                .with_children(files_added)?;
-        }
-
-        let files_removed: Vec<Change> = all_names
-            .iter()
-            .copied()
-            .filter(|f| !right_names.contains(f.as_str()) && left_names.contains(f.as_str()))
-            .map(|k| Change::Removed("files".into(), k.to_string()))
-            .collect();
-
-        if !files_removed.is_empty() {
-            result
-                .get_or_insert(Change::Modified(
-                    "tre".into(),
-                    name.into(),
-                    Vec::new(),
-                    Vec::new(),
-                ))
-                .with_children(files_removed)?;
+        let header = &report.header;
+        let mut related = vec![comparison("entries", header.entries)];
+        if self.mode == Mode::Full {
+            related.extend([
+                comparison("record compression", header.record_compression),
+                comparison("record block size", header.record_block_size),
+                comparison("name compression", header.name_compression),
+                comparison("name block size", header.name_block_size),
+            ]);
         }
+        result.with_related(related.into_iter().flatten().collect())?;
+
+        result.with_children(
+            report
+                .added
+                .into_iter()
+                .map(|file| Change::Added("files".into(), file))
+                .chain(
+                    report
+                        .removed
+                        .into_iter()
+                        .map(|file| Change::Removed("files".into(), file)),
+                )
+                .collect(),
+        )?;
 
-        let files_shared = all_names
-            .iter()
-            .copied()
-            .filter(|f| right_names.contains(f.as_str()) && left_names.contains(f.as_str()))
-            .collect::<Vec<_>>();
-
-        if !files_shared.is_empty() {
-            for file in &files_shared {
-                let mut data_left = Vec::new();
-                let mut file_left = left.by_name(file)?;
-                std::io::copy(&mut file_left, &mut data_left).into_diagnostic()?;
-
-                let mut data_right = Vec::new();
-                let mut file_right = right.by_name(file)?;
-                std::io::copy(&mut file_right, &mut data_right).into_diagnostic()?;
-
-                let file_modified = self.handle_file(file, &data_left, &data_right)?;
-                if let Some(c) = file_modified {
-                    result
-                        .get_or_insert(Change::Modified(
-                            "tre".into(),
-                            name.into(),
-                            Vec::new(),
-                            Vec::new(),
-                        ))
-                        .with_children(vec![c])?;
-                }
-            }
+        for entry in &report.modified {
+            let change = self.handle_file(entry, left, right)?;
+            result.with_children(vec![change])?;
         }
 
-        Ok(result)
+        Ok(Some(result))
     }
 
     pub fn handle(&self) -> Result<()> {
+        let _span = info_span!(
+            "diff",
+            left = %self.left.display(),
+            right = %self.right.display()
+        )
+        .entered();
+
         let l = File::open(&self.left)
             .into_diagnostic()
             .context(format!("path: {}", &self.left.display()))?;
 
-        let mut left = TreArchive::new(&l)?;
+        let left = TreArchive::new(&l)?;
 
         let r = File::open(&self.right)
             .into_diagnostic()
             .context(format!("path: {}", &self.right.display()))?;
 
-        let mut right = TreArchive::new(&r)?;
+        let right = TreArchive::new(&r)?;
 
-        let difference = self.handle_tre(&self.left.to_string_lossy(), &mut left, &mut right)?;
+        let difference = self.handle_tre(&self.left.to_string_lossy(), &left, &right)?;
 
         if let Some(d) = difference {
             println!("{}", d);
@@ -0,0 +1,72 @@
+use clap::Args;
+use miette::{Context, Result};
+use owo_colors::OwoColorize;
+use std::path::PathBuf;
+use tracing::{info, info_span};
+
+use crate::commands::sources::open_game_dir;
+
+#[derive(Args)]
+pub struct DiffDirsArgs {
+    /// A client directory, whose .cfg files list the archives and directories to load
+    #[arg(short, long, value_name = "DIR")]
+    left: PathBuf,
+
+    /// A client directory, whose .cfg files list the archives and directories to load
+    #[arg(short, long, value_name = "DIR")]
+    right: PathBuf,
+}
+
+impl DiffDirsArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!(
+            "diff_dirs",
+            left = %self.left.display(),
+            right = %self.right.display()
+        )
+        .entered();
+
+        let left = open_game_dir(&self.left)?;
+        let right = open_game_dir(&self.right)?;
+
+        let (mut added, mut removed, mut modified) = (0, 0, 0);
+
+        for name in left.file_names() {
+            if !right.exists(name) {
+                println!("❌ {}", name.red());
+                removed += 1;
+                continue;
+            }
+
+            let left_data = left
+                .contents_by_name(name)
+                .context(format!("reading {}", name))?;
+            let right_data = right
+                .contents_by_name(name)
+                .context(format!("reading {}", name))?;
+            if left_data.len() != right_data.len() {
+                println!("🔃 {}", name.blue());
+                println!(
+                    "  * size: {} vs {}",
+                    left_data.len().red(),
+                    right_data.len().green()
+                );
+                modified += 1;
+            } else if left_data != right_data {
+                println!("🔃 {}", name.blue());
+                modified += 1;
+            }
+        }
+
+        for name in right.file_names() {
+            if !left.exists(name) {
+                println!("✅ {}", name.green());
+                added += 1;
+            }
+        }
+
+        info!(added, removed, modified, "compared game directories");
+
+        Ok(())
+    }
+}
@@ -1,8 +1,17 @@
 use clap::Args;
-use miette::{Context, IntoDiagnostic, Result};
-use std::{fs::File, path::PathBuf};
-use swg_tre::{read::TreFile, TreArchive};
-use tracing::info;
+use miette::{miette, Context, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+use std::{
+    fs::File,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+use swg_tre::{
+    error::{Error, MetadataError},
+    extract::{extract, ExtractOptions, ExtractReport},
+    TreArchive,
+};
+use tracing::info_span;
 
 #[derive(Args)]
 pub struct ExtractArgs {
@@ -17,35 +26,123 @@ pub struct ExtractArgs {
     /// Allow overwriting the target
     #[arg(long, default_value_t = false)]
     overwrite: bool,
+
+    /// Also write every shadowed entry with a duplicate name, numbered before its extension
+    #[arg(long, default_value_t = false)]
+    all_variants: bool,
+
+    /// Print what happened to every entry once the extraction is done
+    #[arg(long, default_value_t = false)]
+    report: bool,
+
+    /// List entries stored with an unknown compression method and extract the rest, instead of
+    /// failing
+    #[arg(long, default_value_t = false)]
+    ignore_unknown: bool,
+
+    /// Make every extracted file read-only
+    #[arg(long, default_value_t = false)]
+    read_only: bool,
+
+    /// Set the modification time of every extracted file, in seconds since the Unix epoch
+    #[arg(long, value_name = "SECONDS", conflicts_with = "mtime_from")]
+    mtime: Option<u64>,
+
+    /// Set the modification time of every extracted file to that of another file, such as the
+    /// archive itself
+    #[arg(long, value_name = "FILE")]
+    mtime_from: Option<PathBuf>,
+
+    /// Flush extracted files and their directories to disk before exiting
+    #[arg(long, default_value_t = false)]
+    fsync: bool,
 }
 
 impl ExtractArgs {
     pub fn handle(&self) -> Result<()> {
-        let mut f = File::open(&self.file)
+        let _span = info_span!("extract", archive = %self.file.display()).entered();
+
+        let f = File::open(&self.file)
             .into_diagnostic()
             .context(format!("path: {}", &self.file.display()))?;
-        let mut tre = TreArchive::new(&mut f)?;
+        let tre = match self.ignore_unknown {
+            false => TreArchive::new(&f)?,
+            true => Self::open_ignoring_unknown(&f)?,
+        };
 
-        let count = tre.len();
-        for i in 0..count {
-            let mut f_tre: TreFile<'_, &mut File> = tre.by_index(i)?;
+        let modified = match (&self.mtime, &self.mtime_from) {
+            (Some(seconds), _) => Some(SystemTime::UNIX_EPOCH + Duration::from_secs(*seconds)),
+            (None, Some(path)) => Some(
+                std::fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .into_diagnostic()
+                    .context(format!("path: {}", path.display()))?,
+            ),
+            (None, None) => None,
+        };
 
-            let p = self.directory.join(f_tre.name());
-            info!("writing {}", p.display());
+        let report = extract(
+            &tre,
+            &self.directory,
+            ExtractOptions::builder()
+                .overwrite(self.overwrite)
+                .variants(self.all_variants)
+                .read_only(self.read_only)
+                .maybe_modified(modified)
+                .sync(self.fsync)
+                .build(),
+        )?;
 
-            let _ = std::fs::create_dir_all(p.parent().unwrap());
-            let mut out = if !self.overwrite {
-                File::create_new(&p)
-                    .into_diagnostic()
-                    .context(format!("creating {}", &p.display()))?
-            } else {
-                File::create(&p)
-                    .into_diagnostic()
-                    .context(format!("creating {}", &p.display()))?
-            };
+        if self.report {
+            Self::print_report(&report);
+        }
+
+        match report.failed.len() {
+            0 => Ok(()),
+            failed => Err(miette!("{} entries could not be extracted", failed)),
+        }
+    }
+
+    /// Open an archive lossily, failing on anything skipped other than an unknown compression
+    fn open_ignoring_unknown(f: &File) -> Result<TreArchive<&File>> {
+        let (tre, skipped) = TreArchive::new_lossy(f)?;
+
+        for entry in skipped {
+            match entry.error {
+                Error::Metadata(MetadataError::UnknownCompression { name, value, .. }) => {
+                    println!(
+                        "⏭️ {} {}",
+                        name.yellow(),
+                        format!("(unknown compression method {})", value).dimmed()
+                    );
+                }
+                error => return Err(error.into()),
+            }
+        }
+
+        Ok(tre)
+    }
 
-            std::io::copy(&mut f_tre, &mut out).into_diagnostic()?;
+    fn print_report(report: &ExtractReport) {
+        for name in &report.written {
+            println!("✅ {}", name.green());
+        }
+        for name in &report.overwritten {
+            println!("🔃 {}", name.blue());
         }
-        Ok(())
+        for name in &report.skipped {
+            println!("⏭️ {} {}", name.yellow(), "(already exists)".dimmed());
+        }
+        for failed in &report.failed {
+            println!("❌ {}: {}", failed.name.red(), failed.error);
+        }
+
+        println!(
+            "{} written, {} overwritten, {} skipped, {} failed",
+            report.written.len(),
+            report.overwritten.len(),
+            report.skipped.len(),
+            report.failed.len()
+        );
     }
 }
@@ -1,11 +1,92 @@
-use clap::{ArgAction, Args};
+use clap::{ArgAction, Args, ValueEnum};
 use miette::miette;
 use miette::{Context, IntoDiagnostic, Result};
 use std::{fs::File, path::PathBuf};
-use swg_tre::{write::TreWriterOptions, CompressionMethod, TreWriter};
-use tracing::info;
+use swg_tre::{
+    write::{SortOrder, TreDirOptions, TreNamePolicy, TreWriterOptions},
+    CompressionMethod, TreWriter,
+};
+use tracing::{info, info_span, warn};
 use walkdir::WalkDir;
 
+/// Extensions of formats which are already compressed and don't shrink any further
+const COMPRESSED_EXTENSIONS: [&str; 6] = ["ogg", "mp3", "png", "jpg", "jpeg", "bik"];
+
+/// Sensible combinations of the compression, ordering and naming flags
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Preset {
+    /// Compress quickly, storing formats which are already compressed without trying
+    Fast,
+    /// Compress every entry which shrinks at the default level
+    #[default]
+    Balanced,
+    /// Compress as small as possible
+    Max,
+    /// Match the layout the client expects, with records sorted by checksum
+    GameCompatible,
+}
+
+/// The order records are written in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Order {
+    /// The order files are found in the directory, sorted by file name
+    Insertion,
+    /// Sorted by entry name
+    Name,
+    /// Sorted by the checksum of each entry name
+    Crc,
+}
+
+impl From<Order> for SortOrder {
+    fn from(order: Order) -> Self {
+        match order {
+            Order::Insertion => SortOrder::Insertion,
+            Order::Name => SortOrder::Name,
+            Order::Crc => SortOrder::Crc,
+        }
+    }
+}
+
+/// The settings a preset stands for
+struct Settings {
+    level: u32,
+    entry_compression: CompressionMethod,
+    store_extensions: Vec<String>,
+    order: Order,
+}
+
+impl Preset {
+    fn settings(self) -> Settings {
+        let compressed = || COMPRESSED_EXTENSIONS.map(str::to_owned).to_vec();
+        match self {
+            Preset::Fast => Settings {
+                level: 1,
+                entry_compression: CompressionMethod::Zlib,
+                store_extensions: compressed(),
+                order: Order::Insertion,
+            },
+            Preset::Balanced => Settings {
+                level: 6,
+                entry_compression: CompressionMethod::Auto,
+                store_extensions: Vec::new(),
+                order: Order::Insertion,
+            },
+            Preset::Max => Settings {
+                level: 9,
+                entry_compression: CompressionMethod::Auto,
+                store_extensions: Vec::new(),
+                order: Order::Name,
+            },
+            Preset::GameCompatible => Settings {
+                level: 6,
+                entry_compression: CompressionMethod::Auto,
+                store_extensions: compressed(),
+                order: Order::Crc,
+            },
+        }
+    }
+}
+
 #[derive(Args)]
 pub struct MergeArgs {
     /// An input directory
@@ -16,28 +97,77 @@ pub struct MergeArgs {
     #[arg(short, long, value_name = "FILE")]
     file: PathBuf,
 
-    /// Compress the file and it's entries
+    /// Compress the file and it's entries, entries which don't shrink are stored as they are
     #[arg(long, default_value_t = true, action = ArgAction::Set)]
     compress: bool,
 
+    /// A combination of the compression level, stored extensions and record order, which the
+    /// individual flags override
+    #[arg(long, value_enum, default_value_t = Preset::Balanced)]
+    preset: Preset,
+
+    /// The Zlib compression level, from 0 to 9
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=9))]
+    level: Option<u32>,
+
+    /// Extensions of files to store without compressing them
+    #[arg(long = "store", value_name = "EXT", value_delimiter = ',')]
+    store_extensions: Option<Vec<String>>,
+
+    /// The order records are written in
+    #[arg(long, value_enum)]
+    order: Option<Order>,
+
+    /// Lowercase entry names
+    #[arg(long, default_value_t = false)]
+    lowercase: bool,
+
     /// Allow overwriting the target
     #[arg(long, default_value_t = false)]
     overwrite: bool,
+
+    /// Warn when the archive would have more entries than this
+    #[arg(long, value_name = "COUNT")]
+    max_entries: Option<u64>,
+
+    /// Warn when the uncompressed name block would be larger than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_name_block: Option<u64>,
+
+    /// Warn when the archive could be larger than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_size: Option<u64>,
+
+    /// Fail instead of warning when the archive exceeds a budget
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+}
+
+/// The size of the archive a directory would produce, before any compression
+#[derive(Default)]
+struct Budget {
+    entries: u64,
+    name_block: u64,
+    data: u64,
+}
+
+impl Budget {
+    /// The header, data, records, names and hashes with every entry stored as it is
+    fn archive_size(&self) -> u64 {
+        36 + self.data + self.entries * (24 + 16) + self.name_block
+    }
 }
 
 impl MergeArgs {
     pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("merge", archive = %self.file.display()).entered();
         info!("creating {}", &self.file.display());
 
-        let files = WalkDir::new(&self.directory)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| !e.file_type().is_dir())
-            .collect::<Vec<_>>();
-
-        if files.is_empty() {
+        let budget = self.budget()?;
+        if budget.entries == 0 {
             return Err(miette!("directory is empty"));
         }
+        self.check_budget(&budget)?;
 
         let mut out = if !self.overwrite {
             File::create_new(&self.file)
@@ -49,48 +179,114 @@ impl MergeArgs {
                 .context(format!("creating {}", &self.file.display()))?
         };
 
-        let options = if self.compress {
-            TreWriterOptions::builder()
-                .name_compression(CompressionMethod::Zlib)
-                .record_compression(CompressionMethod::Zlib)
-                .build()
+        let preset = self.preset.settings();
+        let (block_compression, entry_compression) = if self.compress {
+            (CompressionMethod::Zlib, preset.entry_compression)
         } else {
-            TreWriterOptions::builder()
-                .name_compression(CompressionMethod::None)
-                .record_compression(CompressionMethod::None)
-                .build()
+            (CompressionMethod::None, CompressionMethod::None)
         };
+        let store_extensions = self
+            .store_extensions
+            .as_ref()
+            .unwrap_or(&preset.store_extensions)
+            .iter()
+            .map(|extension| extension.trim_start_matches('.'))
+            .collect::<Vec<_>>();
+
+        let options = TreWriterOptions::builder()
+            .name_compression(block_compression)
+            .record_compression(block_compression)
+            .compression_level(self.level.unwrap_or(preset.level))
+            .sort_records(self.order.unwrap_or(preset.order).into())
+            .name_policy(TreNamePolicy::builder().lowercase(self.lowercase).build())
+            .build();
 
         let mut tre = TreWriter::new(&mut out, options);
+        let added = tre
+            .add_dir_recursive(
+                &self.directory,
+                "",
+                TreDirOptions::builder()
+                    .compression(entry_compression)
+                    .store_extensions(&store_extensions)
+                    .build(),
+            )
+            .context(format!("merging {}", self.directory.display()))?;
+        info!("merged {} files", added);
+
+        tre.finish().context("finalizing tre file")?;
+
+        Ok(())
+    }
+
+    fn budget(&self) -> Result<Budget> {
+        let mut budget = Budget::default();
 
-        for file in files {
-            let name = file
+        for entry in WalkDir::new(&self.directory).min_depth(1) {
+            let entry = entry
+                .into_diagnostic()
+                .context(format!("reading {}", self.directory.display()))?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+
+            let relative = entry
                 .path()
                 .strip_prefix(&self.directory)
                 .into_diagnostic()?;
-            info!("merging {}", name.display());
-
-            tre.start_file(
-                name.to_str()
-                    .ok_or(miette!("unable to convert {} to a string", name.display()))?,
-                if self.compress {
-                    CompressionMethod::Zlib
-                } else {
-                    CompressionMethod::None
-                },
-            )
-            .context(format!("starting entry for {}", name.display()))?;
+            budget.entries += 1;
+            budget.name_block += relative.as_os_str().len() as u64 + 1;
+            budget.data += entry.metadata().into_diagnostic()?.len();
+        }
 
-            let mut f = File::open(file.path())
-                .into_diagnostic()
-                .context(format!("opening {}", file.path().display()))?;
+        Ok(budget)
+    }
 
-            std::io::copy(&mut f, &mut tre)
-                .into_diagnostic()
-                .context(format!("copying {}", file.path().display()))?;
+    /// Fail before writing anything when the archive can't be opened, and report any budgets
+    /// it exceeds
+    fn check_budget(&self, budget: &Budget) -> Result<()> {
+        let limit = u32::MAX as u64;
+        if budget.name_block > limit {
+            return Err(miette!(
+                "name block would be {} bytes which exceeds the 32-bit limit",
+                budget.name_block
+            ));
+        }
+        if budget.archive_size() > limit {
+            if !self.compress {
+                return Err(miette!(
+                    "archive would be {} bytes which exceeds the 32-bit offset limit",
+                    budget.archive_size()
+                ));
+            }
+            warn!(
+                "archive could be up to {} bytes, it must compress below the 32-bit offset limit",
+                budget.archive_size()
+            );
         }
 
-        tre.finish().context("finalizing tre file")?;
+        let exceeded = [
+            ("entry count", budget.entries, self.max_entries),
+            ("name block size", budget.name_block, self.max_name_block),
+            ("archive size", budget.archive_size(), self.max_size),
+        ]
+        .into_iter()
+        .filter_map(|(what, value, max)| {
+            max.filter(|max| value > *max).map(|max| (what, value, max))
+        })
+        .map(|(what, value, max)| format!("{} {} exceeds the budget of {}", what, value, max))
+        .collect::<Vec<_>>();
+
+        for message in &exceeded {
+            warn!("{}", message);
+        }
+
+        if self.strict && !exceeded.is_empty() {
+            return Err(miette!(
+                "archive exceeds its budget: {}",
+                exceeded.join(", ")
+            ));
+        }
 
         Ok(())
     }
@@ -1,23 +1,38 @@
 pub mod diff;
+pub mod diff_dirs;
 pub mod extract;
 pub mod merge;
+pub mod patch;
+pub mod salvage;
 
 #[derive(clap::Subcommand)]
 pub enum TreCommands {
     /// Compare Two TRE files
     Diff(diff::DiffArgs),
+    /// Compare the files two game directories provide, once their TRE files are layered
+    DiffDirs(diff_dirs::DiffDirsArgs),
     /// Extract a TRE file into a directory
     Extract(extract::ExtractArgs),
     /// Merge a directory into a TRE file
     Merge(merge::MergeArgs),
+    /// Create a patch which turns one TRE file into another, storing changed entries as deltas
+    Patch(patch::PatchArgs),
+    /// Recreate a TRE file by applying a patch to the file it was created from
+    ApplyPatch(patch::ApplyPatchArgs),
+    /// Recover whatever is readable from a damaged TRE file
+    Salvage(salvage::SalvageArgs),
 }
 
 impl TreCommands {
     pub fn handle(&self) -> miette::Result<()> {
         match self {
             TreCommands::Diff(diff) => diff.handle(),
+            TreCommands::DiffDirs(diff_dirs) => diff_dirs.handle(),
             TreCommands::Extract(extract) => extract.handle(),
             TreCommands::Merge(merge) => merge.handle(),
+            TreCommands::Patch(patch) => patch.handle(),
+            TreCommands::ApplyPatch(apply_patch) => apply_patch.handle(),
+            TreCommands::Salvage(salvage) => salvage.handle(),
         }
     }
 }
@@ -0,0 +1,129 @@
+use clap::Args;
+use miette::{Context, IntoDiagnostic, Result};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+use swg_tre::{
+    patch::{Patch, PatchData, PatchOptions},
+    TreArchive,
+};
+use tracing::{info, info_span};
+
+#[derive(Args)]
+pub struct PatchArgs {
+    /// The TRE file the patch applies to
+    #[arg(short, long, value_name = "FILE")]
+    source: PathBuf,
+
+    /// The TRE file the patch recreates
+    #[arg(short, long, value_name = "FILE")]
+    target: PathBuf,
+
+    /// The patch file to write
+    #[arg(short, long, value_name = "FILE")]
+    output: PathBuf,
+
+    /// The size of the blocks changed entries are matched against the source in
+    #[arg(long, value_name = "BYTES", default_value_t = 32)]
+    block_size: usize,
+
+    /// Allow overwriting an existing patch file
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+}
+
+#[derive(Args)]
+pub struct ApplyPatchArgs {
+    /// The TRE file the patch was created from
+    #[arg(short, long, value_name = "FILE")]
+    source: PathBuf,
+
+    /// The patch file to apply
+    #[arg(short, long, value_name = "FILE")]
+    patch: PathBuf,
+
+    /// The TRE file to write
+    #[arg(short, long, value_name = "FILE")]
+    output: PathBuf,
+
+    /// Allow overwriting an existing output TRE file
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+}
+
+impl PatchArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!(
+            "patch",
+            source = %self.source.display(),
+            target = %self.target.display()
+        )
+        .entered();
+
+        let source = open_archive(&self.source)?;
+        let target = open_archive(&self.target)?;
+        let options = PatchOptions::builder().block_size(self.block_size).build();
+        let patch = Patch::create(&source, &target, options).context("creating the patch")?;
+
+        let count = |f: fn(&PatchData) -> bool| patch.entries.iter().filter(|e| f(&e.data)).count();
+        info!(
+            "{} unchanged, {} as deltas, {} replaced, {} added and {} removed",
+            count(|d| matches!(d, PatchData::Unchanged { .. })),
+            count(|d| matches!(d, PatchData::Delta { .. })),
+            count(|d| matches!(d, PatchData::Replaced(_))),
+            count(|d| matches!(d, PatchData::Full(_))),
+            patch.removed.len()
+        );
+
+        let out = create(&self.output, self.overwrite)?;
+        patch
+            .write(BufWriter::new(out))
+            .context(format!("writing {}", self.output.display()))?;
+
+        Ok(())
+    }
+}
+
+impl ApplyPatchArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!(
+            "apply_patch",
+            source = %self.source.display(),
+            patch = %self.patch.display()
+        )
+        .entered();
+
+        let source = open_archive(&self.source)?;
+        let f = File::open(&self.patch)
+            .into_diagnostic()
+            .context(format!("path: {}", self.patch.display()))?;
+        let patch =
+            Patch::read(BufReader::new(f)).context(format!("reading {}", self.patch.display()))?;
+
+        info!("creating {}", self.output.display());
+        let out = create(&self.output, self.overwrite)?;
+        patch
+            .apply(&source, out)
+            .context(format!("applying {}", self.patch.display()))?;
+
+        Ok(())
+    }
+}
+
+fn open_archive(path: &Path) -> Result<TreArchive<File>> {
+    let f = File::open(path)
+        .into_diagnostic()
+        .context(format!("path: {}", path.display()))?;
+    TreArchive::new(f).context(format!("reading {}", path.display()))
+}
+
+fn create(path: &Path, overwrite: bool) -> Result<File> {
+    match overwrite {
+        true => File::create(path),
+        false => File::create_new(path),
+    }
+    .into_diagnostic()
+    .context(format!("creating {}", path.display()))
+}
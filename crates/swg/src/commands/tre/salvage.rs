@@ -0,0 +1,284 @@
+use binrw::BinRead;
+use clap::Args;
+use flate2::read::ZlibDecoder;
+use md5::{Digest, Md5};
+use miette::{Context, IntoDiagnostic, Result};
+use owo_colors::OwoColorize;
+use std::{
+    fs::File,
+    io::{Cursor, Read, Write},
+    ops::Range,
+    path::PathBuf,
+};
+use swg_tre::{
+    types::{TreHeader, TreRecord},
+    CompressionMethod,
+};
+use tracing::{info, info_span, warn};
+
+use crate::commands::output::output_path;
+
+const HEADER_SIZE: usize = 36;
+const RECORD_SIZE: usize = 24;
+const HASH_SIZE: usize = 16;
+
+#[derive(Args)]
+pub struct SalvageArgs {
+    /// A damaged TRE file
+    #[arg(short, long, value_name = "FILE")]
+    file: PathBuf,
+
+    /// A target directory for the recovered entries
+    #[arg(short, long, value_name = "DIR")]
+    directory: PathBuf,
+
+    /// Scan the data region for zlib streams not claimed by any recovered record
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    scan: bool,
+}
+
+enum Outcome {
+    Recovered { name: String, verified: bool },
+    Failed { name: String, reason: String },
+    Orphan { offset: usize, size: usize },
+}
+
+impl SalvageArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("salvage", archive = %self.file.display()).entered();
+
+        let mut data = Vec::new();
+        File::open(&self.file)
+            .into_diagnostic()
+            .context(format!("path: {}", &self.file.display()))?
+            .read_to_end(&mut data)
+            .into_diagnostic()?;
+
+        let mut outcomes = Vec::new();
+        let mut claimed = Vec::new();
+
+        match TreHeader::read(&mut Cursor::new(&data)) {
+            Ok(header) => self.salvage_records(&data, &header, &mut outcomes, &mut claimed)?,
+            Err(e) => warn!("unable to read header, falling back to scanning: {}", e),
+        }
+
+        if self.scan {
+            self.salvage_streams(&data, &claimed, &mut outcomes)?;
+        }
+
+        self.report(&outcomes);
+
+        Ok(())
+    }
+
+    fn salvage_records(
+        &self,
+        data: &[u8],
+        header: &TreHeader,
+        outcomes: &mut Vec<Outcome>,
+        claimed: &mut Vec<Range<usize>>,
+    ) -> Result<()> {
+        let record_start = header.record_start as usize;
+        let record_end = record_start + header.record_compressed as usize;
+        let name_end = record_end + header.name_compressed as usize;
+        claimed.push(record_start..name_end);
+
+        let record_block = read_block(data, record_start..record_end, header.record_compression);
+        let records = record_block
+            .chunks_exact(RECORD_SIZE)
+            .take(header.records as usize)
+            .filter_map(|chunk| TreRecord::read(&mut Cursor::new(chunk)).ok())
+            .collect::<Vec<_>>();
+        info!("recovered {} of {} records", records.len(), header.records);
+
+        let name_block = read_block(data, record_end..name_end, header.name_compression);
+        let hash_block = data.get(name_end..).unwrap_or_default();
+
+        for (index, record) in records.iter().enumerate() {
+            let name = name_block
+                .get(record.name_offset as usize..)
+                .and_then(|names| names.split(|b| *b == 0).next())
+                .filter(|name| !name.is_empty())
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .unwrap_or_else(|| format!("unnamed/{:05}_{:08X}.bin", index, record.data_offset));
+
+            let start = record.data_offset as usize;
+            let range = start..start + record.data_compressed as usize;
+            let Some(stored) = data.get(range.clone()) else {
+                outcomes.push(Outcome::Failed {
+                    name,
+                    reason: "data lies beyond the end of the file".into(),
+                });
+                continue;
+            };
+
+            let contents = match record.data_compression {
+                CompressionMethod::None => Ok(stored.to_vec()),
+                CompressionMethod::Zlib => inflate(stored),
+                CompressionMethod::Zstd => Err(std::io::Error::other("zstd can't be salvaged")),
+                CompressionMethod::Other(value) => Err(std::io::Error::other(format!(
+                    "compression method {} is unknown",
+                    value
+                ))),
+                CompressionMethod::Auto => unreachable!("records never decode as auto"),
+            };
+
+            match contents {
+                Ok(contents) if contents.len() == record.data_uncompressed as usize => {
+                    let verified = hash_block
+                        .get(index * HASH_SIZE..(index + 1) * HASH_SIZE)
+                        .is_some_and(|hash| Md5::digest(stored).as_slice() == hash);
+
+                    claimed.push(range);
+                    if self.write_entry(&name, &contents)? {
+                        outcomes.push(Outcome::Recovered { name, verified });
+                    } else {
+                        outcomes.push(Outcome::Failed {
+                            name,
+                            reason: "unsafe entry name, not written".to_owned(),
+                        });
+                    }
+                }
+                Ok(contents) => outcomes.push(Outcome::Failed {
+                    name,
+                    reason: format!(
+                        "expected {} bytes but recovered {}",
+                        record.data_uncompressed,
+                        contents.len()
+                    ),
+                }),
+                Err(e) => outcomes.push(Outcome::Failed {
+                    name,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn salvage_streams(
+        &self,
+        data: &[u8],
+        claimed: &[Range<usize>],
+        outcomes: &mut Vec<Outcome>,
+    ) -> Result<()> {
+        let mut offset = HEADER_SIZE;
+        while offset + 2 <= data.len() {
+            if let Some(range) = claimed.iter().find(|r| r.contains(&offset)) {
+                offset = range.end;
+                continue;
+            }
+
+            if is_zlib_header(data[offset], data[offset + 1]) {
+                let mut decoder = ZlibDecoder::new(&data[offset..]);
+                let mut contents = Vec::new();
+                if decoder.read_to_end(&mut contents).is_ok() && !contents.is_empty() {
+                    let size = decoder.total_in() as usize;
+                    self.write_entry(&format!("orphans/{:08X}.bin", offset), &contents)?;
+                    outcomes.push(Outcome::Orphan { offset, size });
+                    offset += size;
+                    continue;
+                }
+            }
+
+            offset += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Write a recovered entry below the target directory, or return `false` if its name is unsafe
+    fn write_entry(&self, name: &str, contents: &[u8]) -> Result<bool> {
+        let Some(p) = output_path(&self.directory, name) else {
+            return Ok(false);
+        };
+        let _span = info_span!("entry", name, size = contents.len()).entered();
+        info!("writing {}", p.display());
+
+        if let Some(parent) = p.parent() {
+            std::fs::create_dir_all(parent)
+                .into_diagnostic()
+                .context(format!("creating {}", parent.display()))?;
+        }
+
+        File::create(&p)
+            .into_diagnostic()
+            .context(format!("creating {}", &p.display()))?
+            .write_all(contents)
+            .into_diagnostic()?;
+
+        Ok(true)
+    }
+
+    fn report(&self, outcomes: &[Outcome]) {
+        let mut recovered = 0;
+        let mut failed = 0;
+        let mut orphans = 0;
+
+        for outcome in outcomes {
+            match outcome {
+                Outcome::Recovered { name, verified } => {
+                    recovered += 1;
+                    if *verified {
+                        println!("✅ {} {}", name.green(), "(verified)".dimmed());
+                    } else {
+                        println!("✅ {}", name.green());
+                    }
+                }
+                Outcome::Failed { name, reason } => {
+                    failed += 1;
+                    println!("❌ {}: {}", name.red(), reason);
+                }
+                Outcome::Orphan { offset, size } => {
+                    orphans += 1;
+                    println!(
+                        "❔ {} ({} bytes at {:#X})",
+                        "orphaned stream".yellow(),
+                        size,
+                        offset
+                    );
+                }
+            }
+        }
+
+        println!(
+            "{} recovered, {} failed, {} orphaned streams",
+            recovered, failed, orphans
+        );
+    }
+}
+
+/// Read a metadata block, keeping whatever can be decoded before any damage
+fn read_block(data: &[u8], range: Range<usize>, compression: CompressionMethod) -> Vec<u8> {
+    let end = range.end.min(data.len());
+    let Some(stored) = data.get(range.start.min(end)..end) else {
+        return Vec::new();
+    };
+
+    match compression {
+        CompressionMethod::None => stored.to_vec(),
+        CompressionMethod::Zlib => {
+            let mut decoder = ZlibDecoder::new(stored);
+            let mut result = Vec::new();
+            let mut buffer = [0u8; 4096];
+            while let Ok(read @ 1..) = decoder.read(&mut buffer) {
+                result.extend_from_slice(&buffer[..read]);
+            }
+            result
+        }
+        CompressionMethod::Zstd | CompressionMethod::Other(_) => Vec::new(),
+        CompressionMethod::Auto => unreachable!("headers never decode as auto"),
+    }
+}
+
+fn inflate(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut result = Vec::new();
+    ZlibDecoder::new(data).read_to_end(&mut result)?;
+    Ok(result)
+}
+
+/// Check whether two bytes form a valid zlib stream header using deflate
+fn is_zlib_header(cmf: u8, flg: u8) -> bool {
+    cmf & 0x0F == 8 && cmf >> 4 <= 7 && flg & 0x20 == 0 && (cmf as u16 * 256 + flg as u16) % 31 == 0
+}
@@ -0,0 +1,63 @@
+use clap::Args;
+use miette::{Context, IntoDiagnostic, Result};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, io::Write, path::PathBuf};
+use swg_assets::ui::{self, UiElement};
+use tracing::{info, info_span};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// A UI layout, such as `ui/ui_chat.inc`
+    layout: PathBuf,
+
+    /// Write the JSON to a file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+impl ExportArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!("ui_export", layout = %self.layout.display()).entered();
+
+        let data = std::fs::read(&self.layout)
+            .into_diagnostic()
+            .context(format!("path: {}", self.layout.display()))?;
+        let elements = ui::parse(&String::from_utf8_lossy(&data))
+            .context(format!("parsing {}", self.layout.display()))?;
+
+        info!(
+            "read {} elements",
+            elements.iter().map(count).sum::<usize>()
+        );
+
+        let mut json =
+            serde_json::to_string_pretty(&elements.iter().map(to_json).collect::<Vec<_>>())
+                .into_diagnostic()?;
+        json.push('\n');
+        match &self.output {
+            Some(path) => std::fs::write(path, json)
+                .into_diagnostic()
+                .context(format!("writing {}", path.display())),
+            None => std::io::stdout()
+                .write_all(json.as_bytes())
+                .into_diagnostic(),
+        }
+    }
+}
+
+/// An element and the elements nested in it, with its data source bindings listed on their own
+fn to_json(element: &UiElement) -> Value {
+    json!({
+        "kind": element.kind,
+        "name": element.name(),
+        "data_source": element.is_data_source(),
+        "properties": element.properties,
+        "bindings": element.bindings().collect::<BTreeMap<_, _>>(),
+        "children": element.children.iter().map(to_json).collect::<Vec<_>>(),
+    })
+}
+
+/// The number of elements in a tree
+fn count(element: &UiElement) -> usize {
+    1 + element.children.iter().map(count).sum::<usize>()
+}
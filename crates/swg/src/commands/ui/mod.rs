@@ -0,0 +1,15 @@
+pub mod export;
+
+#[derive(clap::Subcommand)]
+pub enum UiCommands {
+    /// Export the widgets, properties and data source bindings of a UI layout as JSON
+    Export(export::ExportArgs),
+}
+
+impl UiCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            UiCommands::Export(export) => export.handle(),
+        }
+    }
+}
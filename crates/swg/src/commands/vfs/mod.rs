@@ -0,0 +1,15 @@
+pub mod simulate;
+
+#[derive(clap::Subcommand)]
+pub enum VfsCommands {
+    /// Report how adding an archive would change which files the game loads
+    Simulate(simulate::SimulateArgs),
+}
+
+impl VfsCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            VfsCommands::Simulate(simulate) => simulate.handle(),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use clap::{Args, ValueEnum};
+use miette::{miette, Context, Result};
+use owo_colors::OwoColorize;
+use std::path::{Path, PathBuf};
+use swg_tre::{
+    config::ClientConfig,
+    vfs::{TreVfs, VfsCopy},
+};
+use tracing::{info, info_span};
+
+/// Where the new archive goes in the load order
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Position {
+    /// Before every existing archive, so any of them override it
+    First,
+    /// After every existing archive, so it overrides all of them
+    #[default]
+    Last,
+}
+
+#[derive(Args)]
+pub struct SimulateArgs {
+    /// A client directory, whose .cfg files list the archives and directories to load
+    #[arg(short, long, value_name = "DIR")]
+    game_dir: PathBuf,
+
+    /// The archive which would be added
+    #[arg(long, value_name = "PATH")]
+    add: PathBuf,
+
+    /// Where the archive would be loaded
+    #[arg(long, value_enum, default_value_t)]
+    position: Position,
+}
+
+impl SimulateArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!(
+            "simulate",
+            game_dir = %self.game_dir.display(),
+            add = %self.add.display()
+        )
+        .entered();
+
+        let config = ClientConfig::from_client_dir(&self.game_dir)?;
+        let mut layers = config
+            .entries()
+            .into_iter()
+            .map(|entry| self.game_dir.join(entry.path))
+            .filter(|path| !same_file(path, &self.add))
+            .collect::<Vec<_>>();
+        info!("found {} archives and directories", layers.len());
+
+        let index = match self.position {
+            Position::First => 0,
+            Position::Last => layers.len(),
+        };
+        layers.insert(index, self.add.clone());
+
+        let vfs = TreVfs::open(&layers).context("opening the load order")?;
+        let added = vfs
+            .archive(index)?
+            .ok_or_else(|| miette!("{} is not an archive", self.add.display()))?
+            .len();
+
+        let mut shadows = 0;
+        let mut shadowed = 0;
+        for file in vfs.shadowed()? {
+            let layer = |copy: &VfsCopy| layers[copy.location.layer].display();
+            if file.winner.location.layer == index {
+                shadows += 1;
+                println!(
+                    "{} {} (was {})",
+                    "overrides".yellow(),
+                    file.name,
+                    layer(&file.losers[0])
+                );
+            } else if file.losers.iter().any(|copy| copy.location.layer == index) {
+                shadowed += 1;
+                println!(
+                    "{} {} (by {})",
+                    "shadowed".red(),
+                    file.name,
+                    layer(&file.winner)
+                );
+            }
+        }
+
+        info!(
+            "{} of {} files would override existing ones, {} would be shadowed and {} are new",
+            shadows,
+            added,
+            shadowed,
+            added - shadows - shadowed
+        );
+
+        Ok(())
+    }
+}
+
+/// Whether two paths point at the same file, so a mod already in the game directory isn't counted twice
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
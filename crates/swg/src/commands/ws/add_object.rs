@@ -0,0 +1,102 @@
+use clap::Args;
+use miette::{Context, IntoDiagnostic, Result};
+use std::path::PathBuf;
+use swg_iff::snapshot::{SnapshotNode, WorldSnapshot};
+use tracing::{info, info_span};
+
+/// The CRC the game looks portal layouts up by, computed over the path
+static PORTAL_LAYOUT_CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_BZIP2);
+
+#[derive(Args)]
+pub struct AddObjectArgs {
+    /// The world snapshot to add the object to
+    #[arg(short, long, value_name = "FILE")]
+    snapshot: PathBuf,
+
+    /// The object template of the object, e.g. `object/building/tatooine/shared_cantina_tatooine.iff`
+    #[arg(short, long, value_name = "PATH")]
+    template: String,
+
+    /// The position of the object in the world, as `x,y,z` in metres
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_position, allow_hyphen_values = true)]
+    at: [f32; 3],
+
+    /// The rotation of the object about the vertical axis, in degrees
+    #[arg(
+        long,
+        value_name = "DEGREES",
+        default_value_t = 0.0,
+        allow_hyphen_values = true
+    )]
+    heading: f32,
+
+    /// The radius of the object's bounding sphere in metres
+    #[arg(long, value_name = "METRES", default_value_t = 0.0)]
+    radius: f32,
+
+    /// The portal layout of the object, for buildings which can be entered, e.g.
+    /// `appearance/thm_tato_cantina.pob`
+    #[arg(long, value_name = "PATH")]
+    portal_layout: Option<String>,
+
+    /// Write the changed snapshot to this file rather than back to `--snapshot`
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+impl AddObjectArgs {
+    pub fn handle(&self) -> Result<()> {
+        let _span = info_span!(
+            "add_object",
+            snapshot = %self.snapshot.display(),
+            template = %self.template
+        )
+        .entered();
+
+        let data = std::fs::read(&self.snapshot)
+            .into_diagnostic()
+            .context(format!("path: {}", self.snapshot.display()))?;
+        let mut snapshot =
+            WorldSnapshot::parse(&data).context(format!("reading {}", self.snapshot.display()))?;
+
+        let half_turn = self.heading.to_radians() / 2.0;
+        let node = SnapshotNode {
+            object_id: snapshot.next_object_id(),
+            container_id: 0,
+            template_index: snapshot.template_index(&self.template),
+            cell_index: 0,
+            rotation: [half_turn.cos(), 0.0, half_turn.sin(), 0.0],
+            position: self.at,
+            radius: self.radius,
+            portal_layout_crc: self
+                .portal_layout
+                .as_ref()
+                .map_or(0, |path| PORTAL_LAYOUT_CRC.checksum(path.as_bytes())),
+            children: Vec::new(),
+        };
+        info!("adding object {} at {:?}", node.object_id, node.position);
+        snapshot.nodes.push(node);
+
+        let output = self.output.as_ref().unwrap_or(&self.snapshot);
+        std::fs::write(output, snapshot.write())
+            .into_diagnostic()
+            .context(format!("writing {}", output.display()))
+    }
+}
+
+/// Parse a position written as `x,y,z`
+fn parse_position(value: &str) -> Result<[f32; 3], String> {
+    let coordinates = value
+        .split(',')
+        .map(|coordinate| {
+            coordinate
+                .trim()
+                .parse::<f32>()
+                .map_err(|e| format!("{}: {}", coordinate, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    coordinates
+        .try_into()
+        .map_err(|_| "a position is three coordinates, x,y,z".to_owned())
+}
@@ -0,0 +1,15 @@
+pub mod add_object;
+
+#[derive(clap::Subcommand)]
+pub enum WsCommands {
+    /// Place an object in the world by adding a node to a world snapshot
+    AddObject(add_object::AddObjectArgs),
+}
+
+impl WsCommands {
+    pub fn handle(&self) -> miette::Result<()> {
+        match self {
+            WsCommands::AddObject(add_object) => add_object.handle(),
+        }
+    }
+}
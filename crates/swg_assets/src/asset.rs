@@ -0,0 +1,134 @@
+//! Typed assets and the sniffing used to identify them
+
+use binrw::BinRead;
+use std::io::Cursor;
+use swg_iff::{datatable::DataTable, iff::IFFFile, snapshot::WorldSnapshot};
+use swg_stf::{read::StringTableReader, types::StringTable};
+use tracing::instrument;
+
+use crate::{error::Result, source::AssetSource};
+
+/// The magic number string tables start with, stored little endian
+const STF_MAGIC: [u8; 4] = [0xCD, 0xAB, 0x00, 0x00];
+
+/// Form types used by shared object templates
+const OBJECT_TEMPLATE_FORMS: [&[u8; 4]; 26] = [
+    b"SBMK", b"SBOT", b"SCNC", b"SCOT", b"SCOU", b"SDSC", b"SFOT", b"SGOT", b"SHOT", b"SIOT",
+    b"SITN", b"SJED", b"SMSC", b"SMSD", b"SMSO", b"SMST", b"SPLY", b"SRSC", b"SSHP", b"STAT",
+    b"STOK", b"STOT", b"SUNI", b"SVOT", b"SWAY", b"SWOT",
+];
+
+/// Form types used by static and skeletal meshes
+const MESH_FORMS: [&[u8; 4]; 3] = [b"MESH", b"MLOD", b"SKMG"];
+
+/// The kind of an asset, as identified from its contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    /// A string table
+    StringTable,
+    /// A datatable, an IFF with a `DTII` form
+    DataTable,
+    /// A shared object template
+    ObjectTemplate,
+    /// A static or skeletal mesh
+    Mesh,
+    /// A procedural terrain, an IFF with a `PTAT` form
+    Terrain,
+    /// A world snapshot, an IFF with a `WSNP` form
+    WorldSnapshot,
+    /// Any other IFF file
+    RawIff,
+    /// Anything which isn't recognised
+    Unknown,
+}
+
+impl AssetKind {
+    /// Identify an asset from the start of its contents
+    pub fn sniff(data: &[u8]) -> Self {
+        if data.starts_with(&STF_MAGIC) {
+            return AssetKind::StringTable;
+        }
+
+        let (Some(b"FORM"), Some(form)) = (data.get(0..4), data.get(8..12)) else {
+            return AssetKind::Unknown;
+        };
+
+        match form {
+            b"DTII" => AssetKind::DataTable,
+            b"PTAT" => AssetKind::Terrain,
+            b"WSNP" => AssetKind::WorldSnapshot,
+            form if MESH_FORMS.iter().any(|f| f.as_slice() == form) => AssetKind::Mesh,
+            form if OBJECT_TEMPLATE_FORMS.iter().any(|f| f.as_slice() == form) => {
+                AssetKind::ObjectTemplate
+            }
+            _ => AssetKind::RawIff,
+        }
+    }
+}
+
+/// A loaded asset
+///
+/// Kinds without a dedicated parser hold their top level IFF form, so they can still be told
+/// apart and handed to a parser once one exists.
+#[derive(Debug)]
+pub enum Asset {
+    /// A string table
+    StringTable(StringTable),
+    /// A datatable
+    DataTable(DataTable),
+    /// A shared object template
+    ObjectTemplate(IFFFile),
+    /// A static or skeletal mesh
+    Mesh(IFFFile),
+    /// A procedural terrain
+    Terrain(IFFFile),
+    /// A world snapshot
+    WorldSnapshot(WorldSnapshot),
+    /// Any other IFF file
+    RawIff(IFFFile),
+    /// Anything which isn't recognised, as it was read
+    Unknown(Vec<u8>),
+}
+
+impl Asset {
+    /// Parse an asset from its contents
+    #[instrument(skip_all, err, fields(size = data.len(), kind))]
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let kind = AssetKind::sniff(data);
+        tracing::Span::current().record("kind", tracing::field::debug(kind));
+
+        let iff = || IFFFile::read_be(&mut Cursor::new(data));
+
+        Ok(match kind {
+            AssetKind::StringTable => {
+                Asset::StringTable(StringTableReader::decode(Cursor::new(data))?)
+            }
+            AssetKind::DataTable => Asset::DataTable(DataTable::try_from(iff()?)?),
+            AssetKind::ObjectTemplate => Asset::ObjectTemplate(iff()?),
+            AssetKind::Mesh => Asset::Mesh(iff()?),
+            AssetKind::Terrain => Asset::Terrain(iff()?),
+            AssetKind::WorldSnapshot => Asset::WorldSnapshot(WorldSnapshot::parse(data)?),
+            AssetKind::RawIff => Asset::RawIff(iff()?),
+            AssetKind::Unknown => Asset::Unknown(data.to_vec()),
+        })
+    }
+
+    /// The kind of this asset
+    pub fn kind(&self) -> AssetKind {
+        match self {
+            Asset::StringTable(_) => AssetKind::StringTable,
+            Asset::DataTable(_) => AssetKind::DataTable,
+            Asset::ObjectTemplate(_) => AssetKind::ObjectTemplate,
+            Asset::Mesh(_) => AssetKind::Mesh,
+            Asset::Terrain(_) => AssetKind::Terrain,
+            Asset::WorldSnapshot(_) => AssetKind::WorldSnapshot,
+            Asset::RawIff(_) => AssetKind::RawIff,
+            Asset::Unknown(_) => AssetKind::Unknown,
+        }
+    }
+}
+
+/// Load and parse the asset at `path` from a source
+pub fn load<S: AssetSource + ?Sized>(source: &S, path: &str) -> Result<Asset> {
+    Asset::parse(&source.read(path)?)
+}
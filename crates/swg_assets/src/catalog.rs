@@ -0,0 +1,279 @@
+//! Every string table of a language, addressed the way the game refers to strings
+//!
+//! The game and server emulators address strings as `table:key`, such as `ui_radial:item_use`,
+//! where `table` is the path of a string table below `string/<language>/` without its `.stf`
+//! extension.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Cursor,
+};
+use swg_stf::{
+    diff::StfDiff, error::TokenIssue, read::StringTableReader, tokens, types::StringTable,
+};
+use tracing::{instrument, warn, Span};
+use widestring::U16String;
+
+use crate::{error::Result, source::AssetSource, strings::StringId};
+
+/// The string tables of a single language, loaded from a source up front
+///
+/// ```no_run
+/// # fn doit() -> swg_assets::error::Result<()>
+/// # {
+/// use swg_assets::{catalog::Catalog, Directory};
+///
+/// let catalog = Catalog::load(&Directory::new("/path/to/game"), "en")?;
+/// if let Some(text) = catalog.get("ui_radial:item_use") {
+///     println!("{}", text.display());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    language: String,
+    tables: BTreeMap<String, StringTable>,
+    /// Table names by their lowercase form, when lookups ignore case
+    folded: Option<HashMap<String, String>>,
+}
+
+impl Catalog {
+    /// Load every string table below `string/<language>/` in the source
+    ///
+    /// Tables which can't be read are skipped with a warning.
+    #[instrument(skip(source), err, fields(tables))]
+    pub fn load<S: AssetSource + ?Sized>(source: &S, language: &str) -> Result<Self> {
+        let prefix = format!("string/{}/", language);
+
+        let mut tables = BTreeMap::new();
+        for path in source.list(&prefix)? {
+            let Some(name) = path[prefix.len()..].strip_suffix(".stf") else {
+                continue;
+            };
+
+            let data = source.read(&path)?;
+            match StringTableReader::decode(Cursor::new(data)) {
+                Ok(table) => {
+                    tables.insert(name.to_owned(), table);
+                }
+                Err(e) => warn!("unable to read {}: {}", path, e),
+            }
+        }
+
+        Span::current().record("tables", tables.len());
+        Ok(Catalog {
+            language: language.to_owned(),
+            tables,
+            folded: None,
+        })
+    }
+
+    /// Create a catalog from tables which are already loaded, keyed by their name
+    pub fn from_tables(
+        language: impl Into<String>,
+        tables: impl IntoIterator<Item = (String, StringTable)>,
+    ) -> Self {
+        Catalog {
+            language: language.into(),
+            tables: tables.into_iter().collect(),
+            folded: None,
+        }
+    }
+
+    /// Make lookups ignore the ASCII case of table names and keys, as the client does
+    ///
+    /// Names and keys keep their casing when iterating. See
+    /// [`StringTable::set_case_insensitive`] for which entry a lookup finds.
+    pub fn set_case_insensitive(&mut self, enabled: bool) {
+        for table in self.tables.values_mut() {
+            table.set_case_insensitive(enabled);
+        }
+        self.folded = enabled.then(|| {
+            let mut folded = HashMap::with_capacity(self.tables.len());
+            for name in self.tables.keys() {
+                folded
+                    .entry(name.to_ascii_lowercase())
+                    .or_insert_with(|| name.clone());
+            }
+            folded
+        });
+    }
+
+    /// Whether lookups ignore the case of table names and keys
+    pub fn is_case_insensitive(&self) -> bool {
+        self.folded.is_some()
+    }
+
+    /// The language the tables are for, such as `en`
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The number of tables
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Whether the catalog has no tables
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Every table with its name, in name order
+    pub fn tables(&self) -> impl Iterator<Item = (&str, &StringTable)> {
+        self.tables
+            .iter()
+            .map(|(name, table)| (name.as_str(), table))
+    }
+
+    /// A table by its name, such as `ui_radial` or `quest/ground/legacy_head_to_bestine`
+    pub fn table(&self, name: &str) -> Option<&StringTable> {
+        match (self.tables.get(name), &self.folded) {
+            (Some(table), _) => Some(table),
+            (None, Some(folded)) => self.tables.get(folded.get(&name.to_ascii_lowercase())?),
+            (None, None) => None,
+        }
+    }
+
+    /// Look up a string by its fully qualified ID, such as `ui_radial:item_use`
+    ///
+    /// The `@` the ID is written with in datatables and templates is optional.
+    pub fn get(&self, id: &str) -> Option<&U16String> {
+        let (table, key) = id.strip_prefix('@').unwrap_or(id).split_once(':')?;
+        self.table(table)?.get(key)
+    }
+
+    /// The text of a string, or `None` when its table or key doesn't exist
+    pub fn resolve(&self, id: &StringId) -> Option<String> {
+        self.table(&id.table)?
+            .get(&id.key)
+            .map(|text| text.to_string_lossy())
+    }
+}
+
+/// How much of a table has been translated
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableCoverage {
+    /// The name of the table
+    pub name: String,
+    /// The number of keys in the source table
+    pub total: usize,
+    /// The number of those keys the translation has
+    pub translated: usize,
+    /// Keys only in the source table, in sorted order
+    pub missing: Vec<String>,
+    /// Keys only in the translation, in sorted order
+    pub extra: Vec<String>,
+    /// Translated strings whose placeholders differ from the source
+    pub token_issues: Vec<TokenIssue>,
+}
+
+impl TableCoverage {
+    /// The percentage of source keys which have been translated, `100` for an empty table
+    pub fn coverage(&self) -> f64 {
+        percentage(self.translated, self.total)
+    }
+
+    /// Whether the translation has every key of the source, no others, and matching placeholders
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.token_issues.is_empty()
+    }
+}
+
+/// How much of a whole catalog has been translated, with a table for every name in either
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoverageReport {
+    /// Every table, in name order
+    pub tables: Vec<TableCoverage>,
+}
+
+impl CoverageReport {
+    /// The percentage of source keys across every table which have been translated
+    pub fn coverage(&self) -> f64 {
+        percentage(
+            self.tables.iter().map(|table| table.translated).sum(),
+            self.tables.iter().map(|table| table.total).sum(),
+        )
+    }
+
+    /// Whether every table is completely translated
+    pub fn is_complete(&self) -> bool {
+        self.tables.iter().all(TableCoverage::is_complete)
+    }
+}
+
+fn percentage(part: usize, total: usize) -> f64 {
+    match total {
+        0 => 100.0,
+        total => part as f64 * 100.0 / total as f64,
+    }
+}
+
+impl Catalog {
+    /// Compare a translation with this catalog as its source
+    ///
+    /// A table missing from either catalog is treated as empty.
+    pub fn coverage(&self, translation: &Catalog) -> CoverageReport {
+        let empty = StringTable::default();
+        let names = self
+            .tables
+            .keys()
+            .chain(translation.tables.keys())
+            .collect::<BTreeSet<_>>();
+
+        let tables = names
+            .into_iter()
+            .map(|name| {
+                let source = self.tables.get(name).unwrap_or(&empty);
+                let target = translation.tables.get(name).unwrap_or(&empty);
+
+                let mut missing = source
+                    .keys()
+                    .filter(|key| !target.contains_key(key))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                missing.sort();
+                let mut extra = target
+                    .keys()
+                    .filter(|key| !source.contains_key(key))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                extra.sort();
+
+                TableCoverage {
+                    name: name.clone(),
+                    total: source.len(),
+                    translated: source.len() - missing.len(),
+                    missing,
+                    extra,
+                    token_issues: tokens::compare(source, target),
+                }
+            })
+            .collect();
+
+        CoverageReport { tables }
+    }
+
+    /// Compare this catalog, as the old one, with a new one
+    ///
+    /// Only tables which differ are returned, and a table missing from either catalog is treated
+    /// as empty.
+    pub fn diff(&self, new: &Catalog) -> BTreeMap<String, StfDiff> {
+        let empty = StringTable::default();
+        self.tables
+            .keys()
+            .chain(new.tables.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|name| {
+                let old = self.tables.get(name).unwrap_or(&empty);
+                (
+                    name.clone(),
+                    old.diff(new.tables.get(name).unwrap_or(&empty)),
+                )
+            })
+            .filter(|(_, diff)| !diff.is_empty())
+            .collect()
+    }
+}
@@ -0,0 +1,49 @@
+//! Error types that can be emitted from this library
+//!
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+/// Error type for library
+#[derive(Error, Diagnostic, Debug)]
+pub enum Error {
+    /// Transparent wrapper for [`std::io::Error`]
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+
+    /// Transparent wrapper for [`binrw::Error`]
+    #[error(transparent)]
+    BinRWError(#[from] binrw::Error),
+
+    /// Transparent wrapper for [`swg_tre::error::Error`]
+    #[error(transparent)]
+    TreError(#[from] swg_tre::error::Error),
+
+    /// Transparent wrapper for [`swg_stf::error::Error`]
+    #[error(transparent)]
+    StfError(#[from] swg_stf::error::Error),
+
+    /// Transparent wrapper for [`swg_iff::error::Error`]
+    #[error(transparent)]
+    IffError(#[from] swg_iff::error::Error),
+
+    /// unable to find asset {0}
+    #[error("unable to find asset {0}")]
+    NotFound(String),
+
+    /// asset path {0} is not a relative path inside the source
+    #[error("asset path {0} is not a relative path inside the source")]
+    InvalidPath(String),
+
+    /// invalid UI layout at line {line}: {reason}
+    #[error("invalid UI layout at line {line}: {reason}")]
+    InvalidLayout {
+        /// The line the problem was found on
+        line: usize,
+        /// What was wrong with the layout
+        reason: String,
+    },
+}
+
+/// Generic result type with crate's Error as its error variant
+pub type Result<T> = core::result::Result<T, Error>;
@@ -0,0 +1,39 @@
+//! # SWG Assets
+//!
+//! This crate provides a single entry point for loading the assets used by *Star Wars Galaxies*,
+//! regardless of which format crate knows how to parse them. Assets are read through an
+//! [`AssetSource`], such as a TRE archive or a directory of loose files, and identified by
+//! sniffing their contents rather than trusting their extension.
+//!
+//! ```no_run
+//! # fn doit() -> swg_assets::error::Result<()>
+//! # {
+//! use swg_assets::{Asset, Directory};
+//!
+//! let source = Directory::new("/path/to/game");
+//! match swg_assets::load(&source, "datatables/skill/skills.iff")? {
+//!     Asset::DataTable(table) => println!("{} rows", table.rows.len()),
+//!     other => println!("not a datatable: {:?}", other.kind()),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Sources can be stacked into an [`Overlay`], and the [`strings`] module resolves the `@table:key`
+//! string ids other assets refer to. A [`catalog::Catalog`] loads every string table of a language
+//! at once, and a [`references::ReferenceIndex`] finds the assets which refer to each string.
+//! The [`ui`] module reads the layouts of the client's UI pages.
+//!
+//! Formats without a dedicated parser yet, such as object templates and meshes, are still
+//! identified and returned as their top level IFF form.
+
+pub mod asset;
+pub mod catalog;
+pub mod error;
+pub mod references;
+pub mod source;
+pub mod strings;
+pub mod ui;
+
+pub use asset::{load, Asset, AssetKind};
+pub use source::{AssetSource, Directory, Overlay};
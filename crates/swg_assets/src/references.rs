@@ -0,0 +1,187 @@
+//! An index of the assets which refer to each string
+//!
+//! Datatables refer to strings with `@table:key` cells, and object templates with string id
+//! parameters. Indexing every one of them answers whether a string is still used before it's
+//! removed, and where.
+
+use binrw::BinRead;
+use std::{collections::BTreeMap, io::Cursor};
+use swg_iff::{datatable::DataTable, iff::IFFFile, template::ObjectTemplate};
+use swg_stf::types::StringTable;
+use tracing::{instrument, warn, Span};
+
+use crate::{
+    asset::AssetKind,
+    error::Result,
+    source::AssetSource,
+    strings::{string_cells, StringId},
+};
+
+/// Where in an asset a string is referred to
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReferenceLocation {
+    /// A cell of a datatable
+    Cell {
+        /// The index of the row the cell is in
+        row: usize,
+        /// The name of the cell's column
+        column: String,
+    },
+    /// A parameter of an object template, by name
+    Parameter(String),
+}
+
+/// An asset referring to a string
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Reference {
+    /// The path of the asset
+    pub path: String,
+    /// Where in the asset the string is referred to
+    pub location: ReferenceLocation,
+}
+
+/// The assets referring to each string, found in datatables and object templates
+///
+/// ```no_run
+/// # fn doit() -> swg_assets::error::Result<()>
+/// # {
+/// use swg_assets::{references::ReferenceIndex, strings::StringId, AssetSource, Directory};
+///
+/// let source = Directory::new("/path/to/game");
+/// let index = ReferenceIndex::build(&source, source.list("")?)?;
+/// let id = StringId::parse("@obj_n:thing").unwrap();
+/// for reference in index.references(&id) {
+///     println!("{} {:?}", reference.path, reference.location);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReferenceIndex {
+    references: BTreeMap<StringId, Vec<Reference>>,
+}
+
+impl ReferenceIndex {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index every datatable and object template among `files`
+    ///
+    /// Other assets are skipped, as are those which can't be parsed, with a warning.
+    #[instrument(skip_all, err, fields(strings))]
+    pub fn build<S, P>(source: &S, files: impl IntoIterator<Item = P>) -> Result<Self>
+    where
+        S: AssetSource + ?Sized,
+        P: AsRef<str>,
+    {
+        let mut index = Self::new();
+        for path in files {
+            let path = path.as_ref();
+            if !path.ends_with(".iff") {
+                continue;
+            }
+
+            let data = source.read(path)?;
+            if let Err(e) = index.add(path, &data) {
+                warn!("unable to index {}: {}", path, e);
+            }
+        }
+
+        Span::current().record("strings", index.len());
+        Ok(index)
+    }
+
+    /// Index the references of a single asset, which is skipped unless it's a datatable or an
+    /// object template
+    ///
+    /// Template tables given as a path, such as `string/en/obj_n`, are indexed by their name.
+    pub fn add(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        match AssetKind::sniff(data) {
+            AssetKind::DataTable => {
+                let table = DataTable::try_from(IFFFile::read_be(&mut Cursor::new(data))?)?;
+                for cell in string_cells(&table) {
+                    self.insert(
+                        cell.id,
+                        path,
+                        ReferenceLocation::Cell {
+                            row: cell.row,
+                            column: cell.column,
+                        },
+                    );
+                }
+            }
+            AssetKind::ObjectTemplate => {
+                for parameter in ObjectTemplate::parse(data)?.string_ids() {
+                    let id = StringId {
+                        table: table_name(&parameter.table).to_owned(),
+                        key: parameter.key,
+                    };
+                    self.insert(id, path, ReferenceLocation::Parameter(parameter.name));
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn insert(&mut self, id: StringId, path: &str, location: ReferenceLocation) {
+        self.references.entry(id).or_default().push(Reference {
+            path: path.to_owned(),
+            location,
+        });
+    }
+
+    /// The number of strings with at least one reference
+    pub fn len(&self) -> usize {
+        self.references.len()
+    }
+
+    /// Whether no references have been found
+    pub fn is_empty(&self) -> bool {
+        self.references.is_empty()
+    }
+
+    /// Every asset referring to a string, in the order they were indexed
+    pub fn references(&self, id: &StringId) -> &[Reference] {
+        self.references.get(id).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Whether any asset refers to a string
+    pub fn is_used(&self, id: &StringId) -> bool {
+        self.references.contains_key(id)
+    }
+
+    /// The keys of a string table, named as the game refers to it, which nothing refers to, in
+    /// table order
+    pub fn unused<'a>(&self, name: &str, table: &'a StringTable) -> Vec<&'a str> {
+        table
+            .keys()
+            .filter(|key| {
+                !self.is_used(&StringId {
+                    table: name.to_owned(),
+                    key: key.to_string(),
+                })
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Every referenced string with the assets referring to it, in string id order
+    pub fn iter(&self) -> impl Iterator<Item = (&StringId, &[Reference])> {
+        self.references
+            .iter()
+            .map(|(id, references)| (id, references.as_slice()))
+    }
+}
+
+/// The name of a string table given as either its name or its path, such as `string/en/obj_n`
+fn table_name(table: &str) -> &str {
+    table
+        .strip_prefix("string/")
+        .and_then(|path| path.split_once('/'))
+        .map(|(_, name)| name)
+        .unwrap_or(table)
+}
@@ -0,0 +1,191 @@
+//! Places assets can be loaded from
+
+use std::{
+    collections::BTreeSet,
+    io::{self, Read, Seek},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+use swg_tre::{
+    error::{Error as TreError, FileNotFoundError},
+    vfs::TreVfs,
+    TreArchive,
+};
+
+use crate::error::{Error, Result};
+
+/// A collection of assets addressed by their path, such as `datatables/skill/skills.iff`
+///
+/// Paths always use forward slashes, matching the names stored in TRE archives.
+pub trait AssetSource {
+    /// Read the whole contents of the asset at `path`
+    fn read(&self, path: &str) -> Result<Arc<[u8]>>;
+
+    /// The path of every asset starting with `prefix`, such as `string/en/`, in sorted order
+    fn list(&self, prefix: &str) -> Result<Vec<String>>;
+}
+
+impl<R: Read + Seek> AssetSource for TreArchive<R> {
+    fn read(&self, path: &str) -> Result<Arc<[u8]>> {
+        self.contents_by_name(path).map_err(|e| match e {
+            TreError::FileNotFound(FileNotFoundError::Name(name)) => Error::NotFound(name),
+            e => e.into(),
+        })
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut paths = self
+            .file_names()
+            .filter(|name| name.starts_with(prefix))
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+impl<R: Read + Seek> AssetSource for TreVfs<R> {
+    fn read(&self, path: &str) -> Result<Arc<[u8]>> {
+        self.contents_by_name(path).map_err(|e| match e {
+            TreError::FileNotFound(FileNotFoundError::Name(name)) => Error::NotFound(name),
+            e => e.into(),
+        })
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut paths = self
+            .file_names()
+            .filter(|name| name.starts_with(prefix))
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+/// A directory of loose files, laid out the same way as the entries of a TRE archive
+#[derive(Debug, Clone)]
+pub struct Directory {
+    root: PathBuf,
+}
+
+impl Directory {
+    /// Create a source reading files below `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl AssetSource for Directory {
+    fn read(&self, path: &str) -> Result<Arc<[u8]>> {
+        let relative = Path::new(path);
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(Error::InvalidPath(path.to_owned()));
+        }
+
+        match std::fs::read(self.root.join(relative)) {
+            Ok(data) => Ok(data.into()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(Error::NotFound(path.to_owned())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        // Only the deepest directory named by the prefix needs to be walked
+        let dir = prefix.rsplit_once('/').map_or("", |(dir, _)| dir);
+        if Path::new(dir)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(Error::InvalidPath(prefix.to_owned()));
+        }
+
+        let mut paths = Vec::new();
+        let mut pending = vec![dir.to_owned()];
+        while let Some(dir) = pending.pop() {
+            let entries = match std::fs::read_dir(self.root.join(&dir)) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            for entry in entries {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let path = match dir.as_str() {
+                    "" => name,
+                    dir => format!("{}/{}", dir, name),
+                };
+
+                if entry.file_type()?.is_dir() {
+                    pending.push(path);
+                } else if path.starts_with(prefix) {
+                    paths.push(path);
+                }
+            }
+        }
+
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+/// Sources layered on top of each other, where later layers override earlier ones
+///
+/// Reading an asset tries each layer from the last to the first, falling through layers which
+/// don't have it.
+#[derive(Default)]
+pub struct Overlay {
+    layers: Vec<Box<dyn AssetSource>>,
+}
+
+impl Overlay {
+    /// Create an overlay without any layers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a layer overriding every layer added before it
+    pub fn push(&mut self, source: impl AssetSource + 'static) {
+        self.layers.push(Box::new(source));
+    }
+
+    /// The number of layers
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Whether the overlay has no layers
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+impl From<Vec<Box<dyn AssetSource>>> for Overlay {
+    fn from(layers: Vec<Box<dyn AssetSource>>) -> Self {
+        Overlay { layers }
+    }
+}
+
+impl AssetSource for Overlay {
+    fn read(&self, path: &str) -> Result<Arc<[u8]>> {
+        for layer in self.layers.iter().rev() {
+            match layer.read(path) {
+                Err(Error::NotFound(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(Error::NotFound(path.to_owned()))
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut paths = BTreeSet::new();
+        for layer in &self.layers {
+            paths.extend(layer.list(prefix)?);
+        }
+        Ok(paths.into_iter().collect())
+    }
+}
@@ -0,0 +1,228 @@
+//! Localized strings referred to by other assets
+//!
+//! Datatables and templates refer to strings as `@table:key`, where `table` names a string table
+//! stored at `string/<language>/<table>.stf`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    io::Cursor,
+};
+use swg_iff::{
+    datatable::{CellData, DataTable},
+    rewrite::replace_string_id,
+};
+use swg_stf::{read::StringTableReader, types::StringTable};
+use tracing::{instrument, warn};
+
+use crate::{
+    error::{Error, Result},
+    source::AssetSource,
+};
+
+/// A reference to a localized string, written as `@table:key`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StringId {
+    /// The name of the string table, such as `skl_n`
+    pub table: String,
+    /// The key of the string within the table
+    pub key: String,
+}
+
+impl StringId {
+    /// Parse a string id from text such as `@skl_n:combat_marksman_novice`
+    pub fn parse(text: &str) -> Option<Self> {
+        let (table, key) = text.strip_prefix('@')?.split_once(':')?;
+        if table.is_empty() || key.is_empty() {
+            return None;
+        }
+
+        Some(StringId {
+            table: table.to_owned(),
+            key: key.to_owned(),
+        })
+    }
+
+    /// The path of the string table holding this string for `language`
+    pub fn path(&self, language: &str) -> String {
+        format!("string/{}/{}.stf", language, self.table)
+    }
+}
+
+impl Display for StringId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{}:{}", self.table, self.key)
+    }
+}
+
+/// A cell of a datatable holding a string id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringCell {
+    /// The index of the row the cell is in
+    pub row: usize,
+    /// The name of the cell's column
+    pub column: String,
+    /// The string the cell refers to
+    pub id: StringId,
+}
+
+/// Find every cell of a datatable which holds a string id, in row order
+pub fn string_cells(table: &DataTable) -> Vec<StringCell> {
+    table
+        .rows
+        .iter()
+        .enumerate()
+        .flat_map(|(row, cells)| {
+            cells.cells.iter().filter_map(move |cell| {
+                let CellData::String(text) = &cell.data else {
+                    return None;
+                };
+                let id = StringId::parse(std::str::from_utf8(&text.0).ok()?)?;
+
+                Some(StringCell {
+                    row,
+                    column: cell.name.to_string(),
+                    id,
+                })
+            })
+        })
+        .collect()
+}
+
+/// Looks up string ids in the string tables of a source, reading each table once
+///
+/// ```no_run
+/// # fn doit() -> swg_assets::error::Result<()>
+/// # {
+/// use swg_assets::{
+///     strings::{StringId, StringResolver},
+///     Directory,
+/// };
+///
+/// let source = Directory::new("/path/to/game");
+/// let mut strings = StringResolver::new(&source, "en");
+/// let id = StringId::parse("@skl_n:combat_marksman_novice").unwrap();
+/// println!("{:?}", strings.resolve(&id)?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct StringResolver<'a, S: AssetSource + ?Sized> {
+    source: &'a S,
+    language: String,
+    tables: HashMap<String, Option<StringTable>>,
+}
+
+impl<'a, S: AssetSource + ?Sized> StringResolver<'a, S> {
+    /// Create a resolver reading the string tables for `language` from `source`
+    pub fn new(source: &'a S, language: impl Into<String>) -> Self {
+        StringResolver {
+            source,
+            language: language.into(),
+            tables: HashMap::new(),
+        }
+    }
+
+    /// The text of a string, or `None` when its table or key doesn't exist
+    pub fn resolve(&mut self, id: &StringId) -> Result<Option<String>> {
+        if !self.tables.contains_key(&id.table) {
+            let table = match self.source.read(&id.path(&self.language)) {
+                Ok(data) => Some(StringTableReader::decode(Cursor::new(data))?),
+                Err(Error::NotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+            self.tables.insert(id.table.clone(), table);
+        }
+
+        Ok(self.tables[&id.table]
+            .as_ref()
+            .and_then(|table| table.get(&id.key))
+            .map(|text| text.to_string_lossy()))
+    }
+}
+
+/// The files which change when the key of a string is renamed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyRename {
+    /// Every string table which held the key, keyed by path, with the key renamed
+    pub tables: BTreeMap<String, StringTable>,
+    /// Every file which refers to the string, keyed by path, rewritten and with the number of
+    /// references changed
+    pub references: BTreeMap<String, (Vec<u8>, usize)>,
+}
+
+/// Rename the key of a string in the table of every language, and update the references to it in
+/// `files`, such as the datatables and object templates of the source
+///
+/// Nothing is written back to the source, the new contents of every file which changes are
+/// returned so they can be reported or written elsewhere. Files which can't be rewritten as IFF
+/// are skipped with a warning.
+///
+/// ```no_run
+/// # fn doit() -> swg_assets::error::Result<()>
+/// # {
+/// use swg_assets::{
+///     strings::{rename_key, StringId},
+///     AssetSource, Directory,
+/// };
+///
+/// let source = Directory::new("/path/to/game");
+/// let files = source.list("datatables/")?;
+/// let id = StringId::parse("@skl_n:combat_marksman_novice").unwrap();
+/// let rename = rename_key(&source, &id, "marksman_novice", &files)?;
+/// for (path, (_, references)) in &rename.references {
+///     println!("{}: {} references", path, references);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[instrument(skip(source, files), fields(id = %id), err)]
+pub fn rename_key<S, P>(
+    source: &S,
+    id: &StringId,
+    key: &str,
+    files: impl IntoIterator<Item = P>,
+) -> Result<KeyRename>
+where
+    S: AssetSource + ?Sized,
+    P: AsRef<str>,
+{
+    let mut rename = KeyRename::default();
+
+    let name = format!("/{}.stf", id.table);
+    for path in source.list("string/")? {
+        let is_table = path
+            .strip_prefix("string/")
+            .and_then(|path| path.strip_suffix(name.as_str()))
+            .is_some_and(|language| !language.contains('/'));
+        if !is_table {
+            continue;
+        }
+
+        let mut table = StringTableReader::decode(Cursor::new(source.read(&path)?))?;
+        if table.contains_key(&id.key) {
+            table.rename(&id.key, key)?;
+            rename.tables.insert(path, table);
+        }
+    }
+    if rename.tables.is_empty() {
+        return Err(Error::NotFound(id.to_string()));
+    }
+
+    for path in files {
+        let path = path.as_ref();
+        let data = source.read(path)?;
+        if !data.windows(id.key.len()).any(|w| w == id.key.as_bytes()) {
+            continue;
+        }
+
+        match replace_string_id(&data, &id.table, &id.key, key) {
+            Ok((_, 0)) => {}
+            Ok(rewritten) => {
+                rename.references.insert(path.to_owned(), rewritten);
+            }
+            Err(e) => warn!("unable to update {}: {}", path, e),
+        }
+    }
+
+    Ok(rename)
+}
@@ -0,0 +1,196 @@
+//! Reading the layouts of the client UI, the `.inc` pages saved by the UI builder
+//!
+//! A layout is a tree of elements written as tags, such as `<Page Name='chat' Size='400,300'>`.
+//! Each element is a widget, style or data source, and its attributes are its properties. Values
+//! are quoted with single or double quotes, elements without children close themselves with `/>`,
+//! and comments are written as `<!-- -->`. Any text between tags is ignored.
+//!
+//! Data sources are `DataSource` and `DataSourceContainer` elements holding `Data` items. Widgets
+//! are bound to them by path, through properties such as `DataSource`.
+//!
+//! ```
+//! # fn doit() -> swg_assets::error::Result<()>
+//! # {
+//! use swg_assets::ui;
+//!
+//! let layout = ui::parse("<Page Name='chat'><List Name='log' DataSource='lines'/></Page>")?;
+//! let list = &layout[0].children[0];
+//! assert_eq!(list.name(), Some("log"));
+//! assert_eq!(list.bindings().collect::<Vec<_>>(), [("DataSource", "lines")]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::error::{Error, Result};
+
+/// The kinds of element which hold the items of a data source
+const DATA_SOURCES: [&str; 2] = ["DataSource", "DataSourceContainer"];
+
+/// An element of a UI layout, with the elements nested in it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UiElement {
+    /// The kind of element, e.g. `Page` or `Button`
+    pub kind: String,
+    /// The attributes of the element, keyed by name
+    pub properties: BTreeMap<String, String>,
+    /// The elements nested in this one, in order
+    pub children: Vec<UiElement>,
+}
+
+impl UiElement {
+    /// The name of the element, which paths to it are made of
+    pub fn name(&self) -> Option<&str> {
+        self.properties.get("Name").map(String::as_str)
+    }
+
+    /// Whether the element is a data source, holding items for widgets to show
+    pub fn is_data_source(&self) -> bool {
+        DATA_SOURCES.contains(&self.kind.as_str())
+    }
+
+    /// The properties which bind the element to a data source, as the property and the path of
+    /// the data source
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.properties
+            .iter()
+            .filter(|(property, _)| property.starts_with("DataSource"))
+            .map(|(property, path)| (property.as_str(), path.as_str()))
+    }
+}
+
+/// Read the elements of a layout, with the elements nested in each
+pub fn parse(text: &str) -> Result<Vec<UiElement>> {
+    let mut parser = Parser { text, position: 0 };
+    let mut roots = Vec::new();
+    let mut open: Vec<UiElement> = Vec::new();
+
+    while let Some(start) = parser.rest().find('<') {
+        parser.position += start;
+        let rest = parser.rest();
+
+        if rest.starts_with("<!--") {
+            let end = rest
+                .find("-->")
+                .ok_or_else(|| parser.error("unclosed comment"))?;
+            parser.position += end + 3;
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            let end = rest
+                .find('>')
+                .ok_or_else(|| parser.error("unclosed declaration"))?;
+            parser.position += end + 1;
+        } else if let Some(closing) = rest.strip_prefix("</") {
+            let end = closing
+                .find('>')
+                .ok_or_else(|| parser.error("unclosed tag"))?;
+            let kind = closing[..end].trim();
+
+            let element = open
+                .pop()
+                .filter(|element| element.kind == kind)
+                .ok_or_else(|| {
+                    parser.error(format!("</{}> closes an element it doesn't match", kind))
+                })?;
+            parser.position += end + 3;
+            match open.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None => roots.push(element),
+            }
+        } else {
+            parser.position += 1;
+            let (element, closed) = parser.element()?;
+            match (closed, open.last_mut()) {
+                (false, _) => open.push(element),
+                (true, Some(parent)) => parent.children.push(element),
+                (true, None) => roots.push(element),
+            }
+        }
+    }
+
+    if let Some(element) = open.last() {
+        return Err(parser.error(format!("<{}> is never closed", element.kind)));
+    }
+
+    Ok(roots)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.position..]
+    }
+
+    fn error(&self, reason: impl Into<String>) -> Error {
+        Error::InvalidLayout {
+            line: self.text[..self.position].matches('\n').count() + 1,
+            reason: reason.into(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Take characters up to the first which matches `end`, or the end of the text
+    fn take_until(&mut self, end: impl Fn(char) -> bool) -> &str {
+        let rest = &self.text[self.position..];
+        let len = rest.find(end).unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    /// Read the kind and attributes of an opening tag, after its `<`, and whether it closes itself
+    fn element(&mut self) -> Result<(UiElement, bool)> {
+        let kind = self.take_until(|c: char| c.is_whitespace() || c == '/' || c == '>');
+        if kind.is_empty() {
+            return Err(self.error("expected the kind of element"));
+        }
+        let mut element = UiElement {
+            kind: kind.to_owned(),
+            ..Default::default()
+        };
+
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.position += 2;
+                return Ok((element, true));
+            } else if rest.starts_with('>') {
+                self.position += 1;
+                return Ok((element, false));
+            } else if rest.is_empty() {
+                return Err(self.error(format!("<{}> is never closed", element.kind)));
+            }
+
+            let name = self
+                .take_until(|c: char| c.is_whitespace() || matches!(c, '=' | '/' | '>'))
+                .to_owned();
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error(format!("expected a value for {}", name)));
+            }
+            self.position += 1;
+            self.skip_whitespace();
+
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('\'' | '"')) => quote,
+                _ => return Err(self.error(format!("expected a quoted value for {}", name))),
+            };
+            self.position += 1;
+            let value = self.take_until(|c| c == quote).to_owned();
+            if self.rest().is_empty() {
+                return Err(self.error(format!("unclosed value for {}", name)));
+            }
+            self.position += 1;
+
+            element.properties.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::io::{Cursor, Write};
+use swg_assets::{
+    catalog::Catalog, error::Result, strings::StringId, AssetSource, Directory, Overlay,
+};
+use swg_stf::{error::TokenIssue, types::StringTable};
+use swg_tre::{write::TreWriterOptions, CompressionMethod, TreArchive, TreWriter};
+use tracing_test::traced_test;
+use widestring::U16String;
+
+const SINGLE_ENTRY: &[u8] = include_bytes!("../../swg_stf/resources/single_entry.stf");
+
+#[traced_test]
+#[test]
+fn load_catalog() -> Result<()> {
+    let mut writer = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+    for (name, data) in [
+        ("string/en/ui_radial.stf", SINGLE_ENTRY),
+        ("string/en/quest/ground/legacy.stf", SINGLE_ENTRY),
+        ("string/en/readme.txt", SINGLE_ENTRY),
+        ("string/en/broken.stf", b"not a string table"),
+        ("string/de/ui_radial.stf", SINGLE_ENTRY),
+    ] {
+        writer.start_file(name, CompressionMethod::Zlib)?;
+        writer.write_all(data)?;
+    }
+    let tre = TreArchive::new(writer.finish()?)?;
+
+    let mut overlay = Overlay::new();
+    overlay.push(tre);
+    overlay.push(Directory::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../swg_stf"
+    )));
+    assert_eq!(overlay.list("resources/")?, ["resources/single_entry.stf"]);
+
+    let catalog = Catalog::load(&overlay, "en")?;
+    assert_eq!(catalog.language(), "en");
+    assert_eq!(
+        catalog.tables().map(|(name, _)| name).collect::<Vec<_>>(),
+        ["quest/ground/legacy", "ui_radial"]
+    );
+    assert!(logs_contain("unable to read string/en/broken.stf"));
+
+    assert_eq!(
+        catalog
+            .get("ui_radial:test")
+            .map(|text| text.to_string_lossy()),
+        Some("testing".to_owned())
+    );
+    assert!(catalog.get("@quest/ground/legacy:test").is_some());
+    assert_eq!(catalog.get("ui_radial:missing"), None);
+    assert_eq!(catalog.get("missing:test"), None);
+    assert_eq!(catalog.get("no separator"), None);
+
+    let id = StringId::parse("@ui_radial:test").unwrap();
+    assert_eq!(catalog.resolve(&id).as_deref(), Some("testing"));
+
+    assert!(Catalog::load(&overlay, "fr")?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn catalog_coverage() {
+    let english = Catalog::from_tables(
+        "en",
+        [
+            (
+                "ui".to_owned(),
+                StringTable::from_iter([
+                    ("ok", "OK"),
+                    ("cancel", "Cancel"),
+                    ("hello", "Hello %TU"),
+                ]),
+            ),
+            (
+                "quest".to_owned(),
+                StringTable::from_iter([("title", "A quest")]),
+            ),
+            ("empty".to_owned(), StringTable::default()),
+        ],
+    );
+    let german = Catalog::from_tables(
+        "de",
+        [
+            (
+                "ui".to_owned(),
+                StringTable::from_iter([("ok", "OK"), ("hello", "Hallo"), ("old", "Alt")]),
+            ),
+            ("empty".to_owned(), StringTable::default()),
+        ],
+    );
+
+    let report = english.coverage(&german);
+    assert_eq!(
+        report
+            .tables
+            .iter()
+            .map(|table| (table.name.as_str(), table.coverage()))
+            .collect::<Vec<_>>(),
+        [("empty", 100.0), ("quest", 0.0), ("ui", 200.0 / 3.0)]
+    );
+    assert_eq!(report.coverage(), 50.0);
+    assert!(!report.is_complete());
+
+    let ui = &report.tables[2];
+    assert_eq!(ui.missing, ["cancel"]);
+    assert_eq!(ui.extra, ["old"]);
+    assert_eq!(
+        ui.token_issues,
+        [TokenIssue::Missing {
+            key: "hello".to_owned(),
+            token: "%TU".to_owned()
+        }]
+    );
+    assert!(report.tables[0].is_complete());
+    assert_eq!(report.tables[1].missing, ["title"]);
+}
+
+#[test]
+fn catalog_diff() {
+    let old = Catalog::from_tables(
+        "en",
+        [
+            ("same".to_owned(), StringTable::from_iter([("ok", "OK")])),
+            (
+                "ui".to_owned(),
+                StringTable::from_iter([("ok", "OK"), ("cancel", "Cancel")]),
+            ),
+            (
+                "removed".to_owned(),
+                StringTable::from_iter([("gone", "Gone")]),
+            ),
+        ],
+    );
+    let new = Catalog::from_tables(
+        "en",
+        [
+            ("same".to_owned(), StringTable::from_iter([("ok", "OK")])),
+            (
+                "ui".to_owned(),
+                StringTable::from_iter([("ok", "Okay"), ("cancel", "Cancel")]),
+            ),
+            ("added".to_owned(), StringTable::from_iter([("new", "New")])),
+        ],
+    );
+
+    let diff = old.diff(&new);
+    assert_eq!(
+        diff.keys().map(String::as_str).collect::<Vec<_>>(),
+        ["added", "removed", "ui"]
+    );
+    assert_eq!(diff["added"].added[0].key, "new");
+    assert_eq!(diff["removed"].removed[0].key, "gone");
+    assert_eq!(diff["ui"].changed[0].new, U16String::from_str("Okay"));
+}
+
+#[test]
+fn case_insensitive_catalog() {
+    let mut catalog = Catalog::from_tables(
+        "en",
+        [(
+            "UI_Radial".to_owned(),
+            StringTable::from_iter([("Item_Use", "Use")]),
+        )],
+    );
+    assert_eq!(catalog.get("ui_radial:item_use"), None);
+
+    catalog.set_case_insensitive(true);
+    assert!(catalog.is_case_insensitive());
+    assert_eq!(
+        catalog.get("@ui_radial:ITEM_USE"),
+        Some(&U16String::from_str("Use"))
+    );
+    let id = StringId::parse("@UI_RADIAL:item_use").unwrap();
+    assert_eq!(catalog.resolve(&id).as_deref(), Some("Use"));
+    assert_eq!(
+        catalog.tables().map(|(name, _)| name).collect::<Vec<_>>(),
+        ["UI_Radial"]
+    );
+}
@@ -0,0 +1,89 @@
+use std::io::{Cursor, Write};
+use swg_assets::{error::Error, error::Result, Asset, AssetKind, Directory};
+use swg_iff::testing::form;
+use swg_tre::{write::TreWriterOptions, CompressionMethod, TreArchive, TreWriter};
+use tracing_test::traced_test;
+
+const SKILLS: &[u8] = include_bytes!("../../swg_iff/resources/skills.iff");
+const SINGLE_ENTRY: &[u8] = include_bytes!("../../swg_stf/resources/single_entry.stf");
+
+#[test]
+fn sniff_kinds() {
+    assert_eq!(AssetKind::sniff(SKILLS), AssetKind::DataTable);
+    assert_eq!(AssetKind::sniff(SINGLE_ENTRY), AssetKind::StringTable);
+    assert_eq!(
+        AssetKind::sniff(&form(b"SHOT", &[])),
+        AssetKind::ObjectTemplate
+    );
+    assert_eq!(AssetKind::sniff(&form(b"MESH", &[])), AssetKind::Mesh);
+    assert_eq!(AssetKind::sniff(&form(b"PTAT", &[])), AssetKind::Terrain);
+    assert_eq!(
+        AssetKind::sniff(&form(b"WSNP", &[])),
+        AssetKind::WorldSnapshot
+    );
+    assert_eq!(AssetKind::sniff(&form(b"ABCD", &[])), AssetKind::RawIff);
+    assert_eq!(AssetKind::sniff(b"FORM"), AssetKind::Unknown);
+    assert_eq!(AssetKind::sniff(b"hello"), AssetKind::Unknown);
+}
+
+#[traced_test]
+#[test]
+fn load_from_archive() -> Result<()> {
+    let snapshot = form(b"WSNP", &[form(b"0001", &[])]);
+
+    let mut writer = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+    for (name, data) in [
+        ("datatables/skill/skills.iff", SKILLS),
+        ("string/en/single_entry.stf", SINGLE_ENTRY),
+        ("snapshot/naboo.ws", &snapshot),
+        ("readme.txt", b"hello".as_slice()),
+    ] {
+        writer.start_file(name, CompressionMethod::Zlib)?;
+        writer.write_all(data)?;
+    }
+    let tre = TreArchive::new(writer.finish()?)?;
+
+    match swg_assets::load(&tre, "datatables/skill/skills.iff")? {
+        Asset::DataTable(table) => assert_eq!(table.rows.len(), 1068),
+        other => panic!("expected a datatable, found {:?}", other.kind()),
+    }
+
+    let asset = swg_assets::load(&tre, "string/en/single_entry.stf")?;
+    assert_eq!(asset.kind(), AssetKind::StringTable);
+
+    match swg_assets::load(&tre, "snapshot/naboo.ws")? {
+        Asset::WorldSnapshot(snapshot) => assert!(snapshot.nodes.is_empty()),
+        other => panic!("expected a world snapshot, found {:?}", other.kind()),
+    }
+
+    let asset = swg_assets::load(&tre, "readme.txt")?;
+    assert!(matches!(asset, Asset::Unknown(data) if data == b"hello"));
+
+    assert!(matches!(
+        swg_assets::load(&tre, "missing.iff"),
+        Err(Error::NotFound(_))
+    ));
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn load_from_directory() -> Result<()> {
+    let source = Directory::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../swg_iff/resources"));
+
+    assert_eq!(
+        swg_assets::load(&source, "skills.iff")?.kind(),
+        AssetKind::DataTable
+    );
+    assert!(matches!(
+        swg_assets::load(&source, "../swg_iff/resources/skills.iff"),
+        Err(Error::InvalidPath(_))
+    ));
+    assert!(matches!(
+        swg_assets::load(&source, "missing.iff"),
+        Err(Error::NotFound(_))
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use swg_assets::{
+    error::Result,
+    references::{Reference, ReferenceIndex, ReferenceLocation},
+    strings::StringId,
+    AssetSource,
+};
+use swg_iff::testing::{chunk, form};
+use swg_stf::types::StringTable;
+use swg_tre::{write::TreWriterOptions, CompressionMethod, TreArchive, TreWriter};
+
+/// A datatable with a single string column
+fn datatable(rows: &[&str]) -> Vec<u8> {
+    let mut cells = (rows.len() as u32).to_le_bytes().to_vec();
+    for row in rows {
+        cells.extend_from_slice(row.as_bytes());
+        cells.push(0);
+    }
+
+    form(
+        b"DTII",
+        &[form(
+            b"0001",
+            &[
+                chunk(b"COLS", b"\x01\0\0\0name\0"),
+                chunk(b"TYPE", b"s\0"),
+                chunk(b"ROWS", &cells),
+            ],
+        )],
+    )
+}
+
+/// An object template setting a single parameter
+fn template(parameter: &[u8]) -> Vec<u8> {
+    form(b"SHOT", &[form(b"0000", &[chunk(b"XXXX", parameter)])])
+}
+
+#[test]
+fn index_references() -> Result<()> {
+    let mut writer = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+    for (name, data) in [
+        (
+            "datatables/things.iff",
+            datatable(&["@obj_n:thing", "plain", "@obj_n:other"]),
+        ),
+        (
+            "object/tangible/shared_thing.iff",
+            template(b"objectName\0\x01obj_n\0thing\0"),
+        ),
+        (
+            "object/tangible/shared_other.iff",
+            template(b"detailedDescription\0\x01string/en/obj_d\0thing\0"),
+        ),
+        ("object/tangible/broken.iff", b"FORM\0\0\0\x08SHOT".to_vec()),
+        ("string/en/obj_n.stf", Vec::new()),
+    ] {
+        writer.start_file(name, CompressionMethod::None)?;
+        writer.write_all(&data)?;
+    }
+    let tre = TreArchive::new(writer.finish()?)?;
+
+    let index = ReferenceIndex::build(&tre, tre.list("")?)?;
+    assert_eq!(index.len(), 3);
+
+    let thing = StringId::parse("@obj_n:thing").unwrap();
+    assert!(index.is_used(&thing));
+    assert_eq!(
+        index.references(&thing),
+        [
+            Reference {
+                path: "datatables/things.iff".to_owned(),
+                location: ReferenceLocation::Cell {
+                    row: 0,
+                    column: "name".to_owned(),
+                },
+            },
+            Reference {
+                path: "object/tangible/shared_thing.iff".to_owned(),
+                location: ReferenceLocation::Parameter("objectName".to_owned()),
+            },
+        ]
+    );
+
+    // Tables given as a path are indexed by their name
+    let description = StringId::parse("@obj_d:thing").unwrap();
+    assert_eq!(
+        index.references(&description),
+        [Reference {
+            path: "object/tangible/shared_other.iff".to_owned(),
+            location: ReferenceLocation::Parameter("detailedDescription".to_owned()),
+        }]
+    );
+
+    let missing = StringId::parse("@obj_n:missing").unwrap();
+    assert!(!index.is_used(&missing));
+    assert!(index.references(&missing).is_empty());
+
+    let table = StringTable::new(HashMap::from([
+        ("thing".to_owned(), "Thing".into()),
+        ("missing".to_owned(), "Missing".into()),
+    ]));
+    assert_eq!(index.unused("obj_n", &table), ["missing"]);
+    assert_eq!(index.unused("obj_d", &table), ["missing"]);
+
+    Ok(())
+}
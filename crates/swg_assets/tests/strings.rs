@@ -0,0 +1,207 @@
+use binrw::NullString;
+use std::io::{Cursor, Write};
+use swg_assets::{
+    error::Error,
+    error::Result,
+    strings::{rename_key, string_cells, StringCell, StringId, StringResolver},
+    AssetSource, Directory, Overlay,
+};
+use swg_iff::{
+    datatable::{Cell, CellData, CellType, DataTable, Row},
+    testing::{chunk, form},
+};
+use swg_tre::{write::TreWriterOptions, CompressionMethod, TreArchive, TreWriter};
+
+const SINGLE_ENTRY: &[u8] = include_bytes!("../../swg_stf/resources/single_entry.stf");
+
+fn table(rows: &[[&str; 2]]) -> DataTable {
+    let columns = vec![NullString::from("name"), NullString::from("title")];
+    let types = vec![
+        CellType::String(String::new()),
+        CellType::String(String::new()),
+    ];
+
+    DataTable {
+        size: 0,
+        version: "0001".into(),
+        columns_count: 2,
+        columns: columns.clone(),
+        types: types.clone(),
+        row_count: rows.len() as u32,
+        rows: rows
+            .iter()
+            .map(|row| Row {
+                cells: row
+                    .iter()
+                    .zip(&columns)
+                    .zip(&types)
+                    .map(|((value, name), cell_type)| Cell {
+                        name: name.clone(),
+                        data: CellData::String(NullString::from(*value)),
+                        cell_type: cell_type.clone(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn parse_string_ids() {
+    let id = StringId::parse("@skl_n:combat_marksman_novice").unwrap();
+    assert_eq!(id.table, "skl_n");
+    assert_eq!(id.key, "combat_marksman_novice");
+    assert_eq!(id.path("en"), "string/en/skl_n.stf");
+    assert_eq!(id.to_string(), "@skl_n:combat_marksman_novice");
+
+    assert_eq!(StringId::parse("skl_n:key"), None);
+    assert_eq!(StringId::parse("@skl_n"), None);
+    assert_eq!(StringId::parse("@:key"), None);
+    assert_eq!(StringId::parse("@skl_n:"), None);
+}
+
+#[test]
+fn find_string_cells() {
+    let table = table(&[["first", "@single_entry:test"], ["@other:key", "plain"]]);
+
+    assert_eq!(
+        string_cells(&table),
+        vec![
+            StringCell {
+                row: 0,
+                column: "title".into(),
+                id: StringId::parse("@single_entry:test").unwrap(),
+            },
+            StringCell {
+                row: 1,
+                column: "name".into(),
+                id: StringId::parse("@other:key").unwrap(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn resolve_through_overlay() -> Result<()> {
+    let mut writer = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+    writer.start_file("string/en/single_entry.stf", CompressionMethod::Zlib)?;
+    writer.write_all(SINGLE_ENTRY)?;
+    let tre = TreArchive::new(writer.finish()?)?;
+
+    let mut overlay = Overlay::new();
+    overlay.push(tre);
+    overlay.push(Directory::new(env!("CARGO_MANIFEST_DIR")));
+    assert_eq!(overlay.len(), 2);
+
+    let mut strings = StringResolver::new(&overlay, "en");
+    let found = StringId::parse("@single_entry:test").unwrap();
+    assert_eq!(strings.resolve(&found)?.as_deref(), Some("testing"));
+
+    let missing_key = StringId::parse("@single_entry:missing").unwrap();
+    assert_eq!(strings.resolve(&missing_key)?, None);
+
+    let missing_table = StringId::parse("@missing:test").unwrap();
+    assert_eq!(strings.resolve(&missing_table)?, None);
+
+    Ok(())
+}
+
+#[test]
+fn rename_string_key() -> Result<()> {
+    let template = form(
+        b"SHOT",
+        &[chunk(b"XXXX", b"objectName\0\x01single_entry\0test\0")],
+    );
+    // Templates may give the table as its path
+    let path_template = form(
+        b"SHOT",
+        &[chunk(
+            b"XXXX",
+            b"detailedDescription\0\x01string/en/single_entry\0test\0",
+        )],
+    );
+    let datatable = form(
+        b"DTII",
+        &[chunk(b"ROWS", b"\0@single_entry:test\0@other:test\0")],
+    );
+    let unrelated = form(b"DTII", &[chunk(b"ROWS", b"\0@other:test\0")]);
+
+    let mut writer = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+    for (name, data) in [
+        ("string/en/single_entry.stf", SINGLE_ENTRY),
+        ("string/de/single_entry.stf", SINGLE_ENTRY),
+        ("string/en/quest/single_entry.stf", SINGLE_ENTRY),
+        ("object/tangible/shared_thing.iff", &template),
+        ("object/tangible/shared_path.iff", &path_template),
+        ("datatables/things.iff", &datatable),
+        ("datatables/other.iff", &unrelated),
+    ] {
+        writer.start_file(name, CompressionMethod::None)?;
+        writer.write_all(data)?;
+    }
+    let tre = TreArchive::new(writer.finish()?)?;
+
+    let id = StringId::parse("@single_entry:test").unwrap();
+    let files = tre
+        .list("")?
+        .into_iter()
+        .filter(|path| path.ends_with(".iff"));
+    let rename = rename_key(&tre, &id, "renamed", files)?;
+
+    assert_eq!(
+        rename.tables.keys().collect::<Vec<_>>(),
+        ["string/de/single_entry.stf", "string/en/single_entry.stf"]
+    );
+    assert!(rename
+        .tables
+        .values()
+        .all(|table| table.contains_key("renamed") && !table.contains_key("test")));
+
+    assert_eq!(
+        rename.references,
+        [
+            (
+                "datatables/things.iff".to_owned(),
+                (
+                    form(
+                        b"DTII",
+                        &[chunk(b"ROWS", b"\0@single_entry:renamed\0@other:test\0")]
+                    ),
+                    1
+                )
+            ),
+            (
+                "object/tangible/shared_path.iff".to_owned(),
+                (
+                    form(
+                        b"SHOT",
+                        &[chunk(
+                            b"XXXX",
+                            b"detailedDescription\0\x01string/en/single_entry\0renamed\0"
+                        )]
+                    ),
+                    1
+                )
+            ),
+            (
+                "object/tangible/shared_thing.iff".to_owned(),
+                (
+                    form(
+                        b"SHOT",
+                        &[chunk(b"XXXX", b"objectName\0\x01single_entry\0renamed\0")]
+                    ),
+                    1
+                )
+            ),
+        ]
+        .into()
+    );
+
+    let missing = StringId::parse("@single_entry:missing").unwrap();
+    assert!(matches!(
+        rename_key(&tre, &missing, "renamed", Vec::<String>::new()),
+        Err(Error::NotFound(_))
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use swg_assets::{error::Error, error::Result, ui};
+
+const CHAT: &str = r#"<?xml version='1.0'?>
+<!-- The chat window -->
+<Page Name='chat' Size='400,300' Visible="true">
+    <DataSource Name='channels'>
+        <Data Name='general' Text='@ui:general'/>
+        <Data Name='guild' Text='@ui:guild' />
+    </DataSource>
+    <List Name='log'
+          DataSource='/chat.channels' Style = '/styles.list' />
+    <Button Name='close'></Button>
+</Page>
+"#;
+
+#[test]
+fn parse_layout() -> Result<()> {
+    let layout = ui::parse(CHAT)?;
+    assert_eq!(layout.len(), 1);
+
+    let page = &layout[0];
+    assert_eq!(page.kind, "Page");
+    assert_eq!(page.name(), Some("chat"));
+    assert_eq!(page.properties["Size"], "400,300");
+    assert_eq!(page.properties["Visible"], "true");
+    assert_eq!(
+        page.children
+            .iter()
+            .map(|c| c.kind.as_str())
+            .collect::<Vec<_>>(),
+        ["DataSource", "List", "Button"]
+    );
+
+    let source = &page.children[0];
+    assert!(source.is_data_source());
+    assert_eq!(source.children[1].properties["Text"], "@ui:guild");
+
+    let list = &page.children[1];
+    assert!(!list.is_data_source());
+    assert_eq!(
+        list.bindings().collect::<Vec<_>>(),
+        [("DataSource", "/chat.channels")]
+    );
+    assert_eq!(list.properties["Style"], "/styles.list");
+
+    Ok(())
+}
+
+#[test]
+fn report_line() {
+    let line = |text: &str| match ui::parse(text) {
+        Err(Error::InvalidLayout { line, .. }) => line,
+        other => panic!("expected an invalid layout, found {:?}", other),
+    };
+
+    assert_eq!(line("<Page Name='a'>\n<Button>\n</Page>"), 3);
+    assert_eq!(line("<Page Name='a'>\n\n<Button/>"), 3);
+    assert_eq!(line("<Page\n Name=a/>"), 2);
+    assert_eq!(line("<Page Name='a'/>\n<!-- never closed"), 2);
+}
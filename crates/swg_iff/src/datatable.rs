@@ -130,9 +130,10 @@ pub struct DataTable {
     pub types: Vec<CellType>,
 
     // // Rows
-    #[br(magic = b"ROWS", parse_with = binrw::helpers::read_u24)]
+    #[br(magic = b"ROWS", temp)]
+    pub _rows_size: u32,
+    #[br(little)]
     pub row_count: u32,
-    pub _row_size: u32,
     #[br(count = row_count, args{ inner: (&columns, &types)})]
     pub rows: Vec<Row>,
 }
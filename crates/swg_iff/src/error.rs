@@ -20,4 +20,16 @@ pub enum Error {
 
     #[error("Unknown cell datatype")]
     UnknownCellDatatype,
+
+    #[error("Invalid object template")]
+    InvalidTemplate,
+
+    #[error("Invalid chunk")]
+    InvalidChunk,
+
+    #[error("Invalid world snapshot")]
+    InvalidSnapshot,
+
+    #[error("Invalid terrain")]
+    InvalidTerrain,
 }
@@ -47,3 +47,20 @@ pub struct IFFFile {
     #[br(count = chunk_size)]
     pub data: Vec<u8>,
 }
+
+/// The tag of a chunk, its body and whatever follows it
+pub(crate) type Split<'a> = (&'a [u8; 4], &'a [u8], &'a [u8]);
+
+/// Split the next chunk off the data, if it holds a whole one
+pub(crate) fn split_chunk(data: &[u8]) -> Option<Split<'_>> {
+    let tag = data.get(0..4)?;
+    let size = u32::from_be_bytes(data.get(4..8)?.try_into().ok()?) as usize;
+    let body = data.get(8..8usize.checked_add(size)?)?;
+
+    Some((tag.try_into().ok()?, body, &data[8 + size..]))
+}
+
+/// Split the type off the body of a form
+pub(crate) fn split_form(body: &[u8]) -> Option<(&[u8; 4], &[u8])> {
+    Some((body.get(0..4)?.try_into().ok()?, body.get(4..)?))
+}
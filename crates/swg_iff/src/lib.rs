@@ -4,3 +4,9 @@
 pub mod datatable;
 pub mod error;
 pub mod iff;
+pub mod rewrite;
+pub mod snapshot;
+pub mod template;
+pub mod terrain;
+#[cfg(feature = "testing")]
+pub mod testing;
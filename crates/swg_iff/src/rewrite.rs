@@ -0,0 +1,154 @@
+//! Rewriting the strings held by IFF files
+//!
+//! Strings are stored NUL terminated inside chunks, so replacing one with a string of another
+//! length changes the size of its chunk and of every form around it. The whole tree is rebuilt
+//! with the new sizes.
+
+use crate::{
+    error::Error,
+    iff::{split_chunk, split_form},
+};
+
+/// Replace every string equal to `from` with `to`, returning the new file and the number of
+/// strings replaced
+///
+/// Only whole strings are replaced, so `from` must be followed by a NUL and must not follow a byte
+/// which could be part of a path, such as a letter or `/`. Any other byte may come before it, like
+/// the marker object templates store ahead of each value.
+pub fn replace_string(data: &[u8], from: &str, to: &str) -> Result<(Vec<u8>, usize), Error> {
+    let mut out = Vec::with_capacity(data.len());
+    let replaced = rewrite(
+        data,
+        &|body, out| replace_in(body, from.as_bytes(), to.as_bytes(), out),
+        &mut out,
+    )?;
+    Ok((out, replaced))
+}
+
+/// Change the key of every reference to the string `table:from`, returning the new file and the
+/// number of references changed
+///
+/// Datatables refer to strings as a whole `@table:key` string, and templates store the table and
+/// key as two strings one after the other. Both are matched with the same rules as
+/// [`replace_string`]. Templates may also give the table as its path in any language, such as
+/// `string/en/obj_n`, which is kept while its key is changed.
+pub fn replace_string_id(
+    data: &[u8],
+    table: &str,
+    from: &str,
+    to: &str,
+) -> Result<(Vec<u8>, usize), Error> {
+    let at = |key: &str| format!("@{}:{}", table, key).into_bytes();
+    let pair = |key: &str| [table.as_bytes(), b"\0", key.as_bytes()].concat();
+    let (at_from, at_to) = (at(from), at(to));
+    let (pair_from, pair_to) = (pair(from), pair(to));
+
+    let mut out = Vec::with_capacity(data.len());
+    let replaced = rewrite(
+        data,
+        &|body, out| {
+            let mut rewritten = Vec::with_capacity(body.len());
+            let mut replaced = replace_in(body, &at_from, &at_to, &mut rewritten);
+            let mut paired = Vec::with_capacity(rewritten.len());
+            replaced += replace_in(&rewritten, &pair_from, &pair_to, &mut paired);
+            replaced + replace_path_pair(&paired, &pair_from, &pair_to, out)
+        },
+        &mut out,
+    )?;
+    Ok((out, replaced))
+}
+
+/// Rebuild the tree of chunks, passing the body of every chunk which isn't a form through
+/// `replace`
+fn rewrite(
+    mut data: &[u8],
+    replace: &dyn Fn(&[u8], &mut Vec<u8>) -> usize,
+    out: &mut Vec<u8>,
+) -> Result<usize, Error> {
+    let mut replaced = 0;
+    while !data.is_empty() {
+        let (tag, body, rest) = split_chunk(data).ok_or(Error::InvalidChunk)?;
+        data = rest;
+
+        out.extend_from_slice(tag);
+        let size_at = out.len();
+        out.extend_from_slice(&[0; 4]);
+
+        match tag {
+            b"FORM" => {
+                let (form, children) = split_form(body).ok_or(Error::InvalidChunk)?;
+                out.extend_from_slice(form);
+                replaced += rewrite(children, replace, out)?;
+            }
+            _ => replaced += replace(body, out),
+        }
+
+        let size = u32::try_from(out.len() - size_at - 4).map_err(|_| Error::InvalidChunk)?;
+        out[size_at..size_at + 4].copy_from_slice(&size.to_be_bytes());
+    }
+
+    Ok(replaced)
+}
+
+/// Whether a byte could be part of a path, so a string can't start right after it
+fn is_path(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b'/' | b'\\' | b'.' | b'-')
+}
+
+fn replace_in(data: &[u8], from: &[u8], to: &[u8], out: &mut Vec<u8>) -> usize {
+    let mut replaced = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let matches = !from.is_empty()
+            && data[i..].starts_with(from)
+            && data.get(i + from.len()) == Some(&0)
+            && (i == 0 || !is_path(data[i - 1]));
+
+        if matches {
+            out.extend_from_slice(to);
+            i += from.len();
+            replaced += 1;
+        } else {
+            out.push(data[i]);
+            i += 1;
+        }
+    }
+
+    replaced
+}
+
+/// Replace every `table\0key` pair whose table is given as a path, `string/<language>/<table>`,
+/// keeping the path as it is
+fn replace_path_pair(data: &[u8], from: &[u8], to: &[u8], out: &mut Vec<u8>) -> usize {
+    const PREFIX: &[u8] = b"string/";
+
+    let mut replaced = 0;
+    let mut i = 0;
+    while i < data.len() {
+        // The language is a single path component, followed by the table and key pair
+        let boundary = i == 0 || !is_path(data[i - 1]);
+        let start = data[i..]
+            .strip_prefix(PREFIX)
+            .filter(|_| boundary)
+            .and_then(|rest| {
+                let language = rest.iter().position(|&b| b == b'/' || b == 0)?;
+                (language > 0 && rest[language] == b'/').then_some(PREFIX.len() + language + 1)
+            })
+            .filter(|&start| {
+                let pair = &data[i + start..];
+                pair.starts_with(from) && pair.get(from.len()) == Some(&0)
+            });
+
+        if let Some(start) = start {
+            out.extend_from_slice(&data[i..i + start]);
+            out.extend_from_slice(to);
+            i += start + from.len();
+            replaced += 1;
+        } else {
+            out.push(data[i]);
+            i += 1;
+        }
+    }
+
+    replaced
+}
@@ -0,0 +1,260 @@
+//! Reading and writing world snapshots, the `.ws` files which place the static objects of a world
+//!
+//! A snapshot is a `WSNP` form holding a `0001` form, which holds a `NODS` form of nodes followed
+//! by an `OTNL` chunk naming the object templates they use. Each node is a `NODE` form holding a
+//! `0000` form, whose `DATA` chunk describes the object and is followed by the `NODE` forms of the
+//! objects it contains.
+//!
+//! ## Format
+//!
+//! All integers and floats of a `DATA` chunk are little endian.
+//!
+//! | Field             | Description                                                        |
+//! |-------------------|--------------------------------------------------------------------|
+//! | Object ID         | 4 bytes: The network ID of the object                              |
+//! | Container ID      | 4 bytes: The network ID of the containing object, `0` if in world  |
+//! | Template Index    | 4 bytes: The index of the object's template in the `OTNL` chunk    |
+//! | Cell Index        | 4 bytes: The cell of the container the object is in, `0` if none   |
+//! | Rotation          | 16 bytes: A quaternion of four floats, `w`, `x`, `y` then `z`      |
+//! | Position          | 12 bytes: Three floats, `x`, `y` then `z`, relative to the parent  |
+//! | Radius            | 4 bytes: The radius of the object's bounding sphere                |
+//! | Portal Layout CRC | 4 bytes: The CRC of the object's portal layout, `0` if none        |
+//!
+//! The `OTNL` chunk is a 4 byte count followed by that many NUL terminated template names.
+
+use byteorder::{ReadBytesExt, LE};
+
+use crate::{
+    error::Error,
+    iff::{split_chunk, split_form, Split},
+};
+
+/// An object placed by a world snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotNode {
+    /// The network ID of the object
+    pub object_id: i32,
+    /// The network ID of the object containing this one, `0` if it's in the world
+    pub container_id: i32,
+    /// The index of the object's template in [`WorldSnapshot::templates`]
+    pub template_index: u32,
+    /// The cell of the container the object is in, `0` if it isn't in a cell
+    pub cell_index: i32,
+    /// The rotation of the object as a quaternion, `w`, `x`, `y` then `z`
+    pub rotation: [f32; 4],
+    /// The position of the object relative to its container, `x`, `y` then `z`
+    pub position: [f32; 3],
+    /// The radius of the object's bounding sphere
+    pub radius: f32,
+    /// The CRC of the object's portal layout, `0` if it has none
+    pub portal_layout_crc: u32,
+    /// The objects contained by this one
+    pub children: Vec<SnapshotNode>,
+}
+
+/// The objects placed by a world snapshot
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorldSnapshot {
+    /// The objects in the world, each with the objects it contains
+    pub nodes: Vec<SnapshotNode>,
+    /// The names of the object templates nodes refer to by index
+    pub templates: Vec<String>,
+}
+
+impl WorldSnapshot {
+    /// Read a snapshot from the contents of its IFF file
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let (b"WSNP", body) = form(data)? else {
+            return Err(Error::InvalidSnapshot);
+        };
+        let (b"0001", mut body) = form(body)? else {
+            return Err(Error::InvalidSnapshot);
+        };
+
+        let mut snapshot = WorldSnapshot::default();
+        while !body.is_empty() {
+            let (tag, chunk_body, rest) = chunk(body)?;
+            match tag {
+                b"FORM" => {
+                    let (b"NODS", mut nodes) =
+                        split_form(chunk_body).ok_or(Error::InvalidSnapshot)?
+                    else {
+                        return Err(Error::InvalidSnapshot);
+                    };
+                    while !nodes.is_empty() {
+                        let (node, rest) = node(nodes)?;
+                        snapshot.nodes.push(node);
+                        nodes = rest;
+                    }
+                }
+                b"OTNL" => snapshot.templates = templates(chunk_body)?,
+                _ => {}
+            }
+            body = rest;
+        }
+
+        Ok(snapshot)
+    }
+
+    /// The name of the template a node uses
+    pub fn template(&self, node: &SnapshotNode) -> Option<&str> {
+        self.templates
+            .get(node.template_index as usize)
+            .map(String::as_str)
+    }
+
+    /// Every node, each followed by the nodes it contains
+    pub fn iter(&self) -> impl Iterator<Item = &SnapshotNode> {
+        let mut stack = self.nodes.iter().rev().collect::<Vec<_>>();
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// The index of a template name, adding it to the list if no node uses it yet
+    pub fn template_index(&mut self, name: &str) -> u32 {
+        let index = match self.templates.iter().position(|template| template == name) {
+            Some(index) => index,
+            None => {
+                self.templates.push(name.to_owned());
+                self.templates.len() - 1
+            }
+        };
+        index as u32
+    }
+
+    /// An object ID above that of every node, for a node to be added with
+    pub fn next_object_id(&self) -> i32 {
+        self.iter()
+            .map(|node| node.object_id)
+            .max()
+            .map_or(1, |id| id.saturating_add(1))
+    }
+
+    /// Write the snapshot as the contents of its IFF file
+    pub fn write(&self) -> Vec<u8> {
+        let nodes = self.nodes.iter().map(write_node).collect::<Vec<_>>();
+
+        let mut names = Vec::new();
+        names.extend_from_slice(&(self.templates.len() as u32).to_le_bytes());
+        for name in &self.templates {
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+
+        write_form(
+            b"WSNP",
+            &[write_form(
+                b"0001",
+                &[write_form(b"NODS", &nodes), write_chunk(b"OTNL", &names)],
+            )],
+        )
+    }
+}
+
+fn chunk(data: &[u8]) -> Result<Split<'_>, Error> {
+    split_chunk(data).ok_or(Error::InvalidSnapshot)
+}
+
+/// Split the type and children off a form which makes up all of `data`
+fn form(data: &[u8]) -> Result<(&[u8; 4], &[u8]), Error> {
+    let (b"FORM", body, _) = chunk(data)? else {
+        return Err(Error::InvalidSnapshot);
+    };
+    split_form(body).ok_or(Error::InvalidSnapshot)
+}
+
+/// Read the `NODE` form at the start of `data`, returning it and whatever follows it
+fn node(data: &[u8]) -> Result<(SnapshotNode, &[u8]), Error> {
+    let (b"FORM", body, rest) = chunk(data)? else {
+        return Err(Error::InvalidSnapshot);
+    };
+    let (b"NODE", body) = split_form(body).ok_or(Error::InvalidSnapshot)? else {
+        return Err(Error::InvalidSnapshot);
+    };
+    let (b"0000", body) = form(body)? else {
+        return Err(Error::InvalidSnapshot);
+    };
+    let (b"DATA", mut fields, mut children) = chunk(body)? else {
+        return Err(Error::InvalidSnapshot);
+    };
+
+    let (object_id, container_id) = (fields.read_i32::<LE>()?, fields.read_i32::<LE>()?);
+    let (template_index, cell_index) = (fields.read_u32::<LE>()?, fields.read_i32::<LE>()?);
+    let mut floats = [0.0; 8];
+    fields.read_f32_into::<LE>(&mut floats)?;
+
+    let mut node = SnapshotNode {
+        object_id,
+        container_id,
+        template_index,
+        cell_index,
+        rotation: [floats[0], floats[1], floats[2], floats[3]],
+        position: [floats[4], floats[5], floats[6]],
+        radius: floats[7],
+        portal_layout_crc: fields.read_u32::<LE>()?,
+        children: Vec::new(),
+    };
+
+    while !children.is_empty() {
+        let (child, rest) = self::node(children)?;
+        node.children.push(child);
+        children = rest;
+    }
+
+    Ok((node, rest))
+}
+
+/// Read the template names of an `OTNL` chunk
+fn templates(mut data: &[u8]) -> Result<Vec<String>, Error> {
+    let count = data.read_u32::<LE>()?;
+    let mut names = data
+        .split(|b| *b == 0)
+        .map(|name| std::str::from_utf8(name).map(str::to_owned))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Every name is terminated, so splitting leaves an empty remainder after the last
+    if names.pop().is_some_and(|last| !last.is_empty()) || names.len() != count as usize {
+        return Err(Error::InvalidSnapshot);
+    }
+    Ok(names)
+}
+
+/// Write a `NODE` form for a node and the nodes it contains
+fn write_node(node: &SnapshotNode) -> Vec<u8> {
+    let mut fields = Vec::with_capacity(52);
+    fields.extend_from_slice(&node.object_id.to_le_bytes());
+    fields.extend_from_slice(&node.container_id.to_le_bytes());
+    fields.extend_from_slice(&node.template_index.to_le_bytes());
+    fields.extend_from_slice(&node.cell_index.to_le_bytes());
+    for value in node
+        .rotation
+        .iter()
+        .chain(&node.position)
+        .chain([&node.radius])
+    {
+        fields.extend_from_slice(&value.to_le_bytes());
+    }
+    fields.extend_from_slice(&node.portal_layout_crc.to_le_bytes());
+
+    let mut children = vec![write_chunk(b"DATA", &fields)];
+    children.extend(node.children.iter().map(write_node));
+    write_form(b"NODE", &[write_form(b"0000", &children)])
+}
+
+fn write_chunk(tag: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut data = tag.to_vec();
+    data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    data.extend_from_slice(body);
+    data
+}
+
+fn write_form(tag: &[u8; 4], children: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = tag.to_vec();
+    children
+        .iter()
+        .for_each(|child| body.extend_from_slice(child));
+    write_chunk(b"FORM", &body)
+}
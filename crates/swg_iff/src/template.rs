@@ -0,0 +1,149 @@
+//! Reading the parameters set by object templates
+//!
+//! Templates store each parameter as an `XXXX` chunk holding its name, a NUL, and then its
+//! encoded value. The parameters of every class a template belongs to are nested in forms of their
+//! own, and a `DERV` form names the template it derives from, whose values apply to any parameter
+//! the template doesn't set itself.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    error::Error,
+    iff::{split_chunk, split_form, Split},
+};
+
+/// The chunk holding a single parameter, or the name of the base template within `DERV`
+const PARAMETER: &[u8; 4] = b"XXXX";
+
+/// The form naming the template a template derives from
+const DERIVED: &[u8; 4] = b"DERV";
+
+/// A parameter of an object template which refers to a localized string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringIdParameter {
+    /// The name of the parameter, e.g. `objectName`
+    pub name: String,
+    /// The name of the string table, e.g. `obj_n`
+    pub table: String,
+    /// The key of the string within the table
+    pub key: String,
+}
+
+/// The parameters set by an object template
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectTemplate {
+    /// The type of the top level form, e.g. `SHOT`
+    pub tag: String,
+    /// The path of the template this one derives from, if any
+    pub base: Option<String>,
+    /// The encoded value of every parameter the template sets, keyed by name
+    pub parameters: BTreeMap<String, Vec<u8>>,
+}
+
+impl ObjectTemplate {
+    /// Read the parameters of a template from the contents of its IFF file
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let (b"FORM", body, _) = chunk(data)? else {
+            return Err(Error::InvalidTemplate);
+        };
+        let (tag, children) = form(body)?;
+
+        let mut template = ObjectTemplate {
+            tag: String::from_utf8_lossy(tag).into_owned(),
+            ..Default::default()
+        };
+        template.visit(children)?;
+
+        Ok(template)
+    }
+
+    /// Apply the values of the template this one derives from, keeping the ones it sets itself
+    pub fn inherit(&mut self, base: &ObjectTemplate) {
+        for (name, value) in &base.parameters {
+            self.parameters
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    /// Every parameter whose value is a string id, in name order
+    ///
+    /// A string id is stored as a marker byte followed by its table and key as two NUL
+    /// terminated strings, so any value of that shape with a valid table name and key is taken
+    /// as one.
+    pub fn string_ids(&self) -> Vec<StringIdParameter> {
+        self.parameters
+            .iter()
+            .filter_map(|(name, value)| {
+                let (table, key) = string_id(value)?;
+                Some(StringIdParameter {
+                    name: name.clone(),
+                    table,
+                    key,
+                })
+            })
+            .collect()
+    }
+
+    fn visit(&mut self, mut data: &[u8]) -> Result<(), Error> {
+        while !data.is_empty() {
+            let (tag, body, rest) = chunk(data)?;
+            data = rest;
+
+            match tag {
+                b"FORM" => match form(body)? {
+                    (DERIVED, children) => {
+                        let (PARAMETER, name, _) = chunk(children)? else {
+                            return Err(Error::InvalidTemplate);
+                        };
+                        self.base.get_or_insert(string(name)?.0);
+                    }
+                    (_, children) => self.visit(children)?,
+                },
+                PARAMETER => {
+                    let (name, value) = string(body)?;
+                    self.parameters
+                        .entry(name)
+                        .or_insert_with(|| value.to_vec());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn chunk(data: &[u8]) -> Result<Split<'_>, Error> {
+    split_chunk(data).ok_or(Error::InvalidTemplate)
+}
+
+fn form(body: &[u8]) -> Result<(&[u8; 4], &[u8]), Error> {
+    split_form(body).ok_or(Error::InvalidTemplate)
+}
+
+/// Split a NUL terminated string off the data
+fn string(data: &[u8]) -> Result<(String, &[u8]), Error> {
+    let end = data
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or(Error::InvalidTemplate)?;
+    let value = std::str::from_utf8(&data[..end])?;
+    Ok((value.to_owned(), &data[end + 1..]))
+}
+
+/// Read a parameter value as the table and key of a string id
+fn string_id(value: &[u8]) -> Option<(String, String)> {
+    let value = value.strip_prefix(b"\x01")?;
+    let (table, rest) = string(value).ok()?;
+    let (key, rest) = string(rest).ok()?;
+
+    let is_table = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'/');
+    let is_key = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-');
+    let valid = rest.is_empty()
+        && !table.is_empty()
+        && !key.is_empty()
+        && table.bytes().all(is_table)
+        && key.bytes().all(is_key);
+    valid.then_some((table, key))
+}
@@ -0,0 +1,63 @@
+//! Reading the header of terrain files
+//!
+//! A `.trn` file is a `PTAT` form holding a version form, such as `0015`. The first chunk of the
+//! version form is `DATA`, which starts with the name the terrain was saved under as a NUL
+//! terminated string, followed by the width of the whole map and the width of a chunk of terrain,
+//! both in metres as little endian floats. The generator the terrain is built from follows, and
+//! isn't read.
+
+use byteorder::{ReadBytesExt, LE};
+
+use crate::{
+    error::Error,
+    iff::{split_chunk, split_form},
+};
+
+/// The extent of a terrain, as given by its header
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainHeader {
+    /// The name the terrain was saved under, e.g. `terrain/tatooine.trn`
+    pub name: String,
+    /// The width of the square map, centred on the origin, in metres
+    pub map_width: f32,
+    /// The width of a chunk of terrain in metres
+    pub chunk_width: f32,
+}
+
+impl TerrainHeader {
+    /// Read the header from the contents of a terrain file
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let (b"FORM", body, _) = split_chunk(data).ok_or(Error::InvalidTerrain)? else {
+            return Err(Error::InvalidTerrain);
+        };
+        let (b"PTAT", body) = split_form(body).ok_or(Error::InvalidTerrain)? else {
+            return Err(Error::InvalidTerrain);
+        };
+        let (b"FORM", version, _) = split_chunk(body).ok_or(Error::InvalidTerrain)? else {
+            return Err(Error::InvalidTerrain);
+        };
+        let (_, body) = split_form(version).ok_or(Error::InvalidTerrain)?;
+        let (b"DATA", data, _) = split_chunk(body).ok_or(Error::InvalidTerrain)? else {
+            return Err(Error::InvalidTerrain);
+        };
+
+        let end = data
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(Error::InvalidTerrain)?;
+        let name = std::str::from_utf8(&data[..end])?.to_owned();
+
+        let mut sizes = &data[end + 1..];
+        let map_width = sizes.read_f32::<LE>()?;
+        let chunk_width = sizes.read_f32::<LE>()?;
+        if !(map_width.is_finite() && map_width > 0.0) {
+            return Err(Error::InvalidTerrain);
+        }
+
+        Ok(TerrainHeader {
+            name,
+            map_width,
+            chunk_width,
+        })
+    }
+}
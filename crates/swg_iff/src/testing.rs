@@ -0,0 +1,28 @@
+//! Builders for IFF data
+//!
+//! These helpers assemble chunks and forms programmatically so tests don't need to depend on
+//! binary fixtures.
+//!
+//! ```
+//! use swg_iff::testing::{chunk, form};
+//!
+//! let data = form(b"SHOT", &[form(b"0000", &[chunk(b"XXXX", b"objectName\0")])]);
+//! assert_eq!(&data[..4], b"FORM");
+//! ```
+
+/// A chunk with the tag and body, its size written big-endian as IFF files store it
+pub fn chunk(tag: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut data = tag.to_vec();
+    data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    data.extend_from_slice(body);
+    data
+}
+
+/// A form of the type holding the children, which are each a whole chunk or form
+pub fn form(tag: &[u8; 4], children: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = tag.to_vec();
+    children
+        .iter()
+        .for_each(|child| body.extend_from_slice(child));
+    chunk(b"FORM", &body)
+}
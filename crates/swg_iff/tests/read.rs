@@ -5,6 +5,8 @@ use binrw::{BinRead, NullString};
 use swg_iff::datatable::{CellData, DataTable};
 use swg_iff::error::Error;
 use swg_iff::iff::IFFFile;
+use swg_iff::template::{ObjectTemplate, StringIdParameter};
+use swg_iff::testing::{chunk, form};
 
 #[test]
 fn parse_iff() -> Result<(), Error> {
@@ -36,9 +38,9 @@ fn parse_datatable() -> Result<(), Error> {
     assert_eq!(table.columns.len(), 27);
     assert_eq!(table.types.len(), table.columns.len());
 
-    assert_eq!(table.rows.len(), 1067);
+    assert_eq!(table.rows.len(), 1068);
 
-    let row = &table.rows[14];
+    let row = &table.rows[13];
     assert_eq!(row.cells.len(), table.columns.len());
 
     assert_eq!(row.cells[0].name, "NAME".into());
@@ -58,3 +60,92 @@ fn parse_datatable() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn parse_template() -> Result<(), Error> {
+    let data = form(
+        b"SHOT",
+        &[
+            form(b"DERV", &[chunk(b"XXXX", b"object/base/shared_base.iff\0")]),
+            form(
+                b"0000",
+                &[
+                    chunk(b"PCNT", &1u32.to_le_bytes()),
+                    chunk(b"XXXX", b"objectName\0\x01string/en/obj_n\0thing\0"),
+                ],
+            ),
+            form(
+                b"STOT",
+                &[form(b"0007", &[chunk(b"XXXX", b"volume\0\x01\x02\0\0\0")])],
+            ),
+        ],
+    );
+
+    let mut template = ObjectTemplate::parse(&data)?;
+    assert_eq!(template.tag, "SHOT");
+    assert_eq!(
+        template.base.as_deref(),
+        Some("object/base/shared_base.iff")
+    );
+    assert_eq!(template.parameters.len(), 2);
+    assert_eq!(template.parameters["volume"], b"\x01\x02\0\0\0");
+
+    let base = ObjectTemplate::parse(&form(
+        b"SHOT",
+        &[form(
+            b"0000",
+            &[
+                chunk(b"XXXX", b"volume\0\x01\x05\0\0\0"),
+                chunk(b"XXXX", b"scale\0\x01"),
+            ],
+        )],
+    ))?;
+    template.inherit(&base);
+    assert_eq!(template.parameters["volume"], b"\x01\x02\0\0\0");
+    assert_eq!(template.parameters["scale"], b"\x01");
+
+    assert!(matches!(
+        ObjectTemplate::parse(&data[..data.len() - 1]),
+        Err(Error::InvalidTemplate)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn template_string_ids() -> Result<(), Error> {
+    let template = ObjectTemplate::parse(&form(
+        b"SHOT",
+        &[form(
+            b"0000",
+            &[
+                chunk(b"XXXX", b"objectName\0\x01obj_n\0thing\0"),
+                chunk(
+                    b"XXXX",
+                    b"detailedDescription\0\x01string/en/obj_d\0thing\0",
+                ),
+                chunk(b"XXXX", b"appearanceFilename\0\x01appearance/thing.apt\0"),
+                chunk(b"XXXX", b"lookAtText\0\x01obj_n\0\0"),
+                chunk(b"XXXX", b"volume\0\x01\x02\0\0\0"),
+            ],
+        )],
+    ))?;
+
+    assert_eq!(
+        template.string_ids(),
+        [
+            StringIdParameter {
+                name: "detailedDescription".to_owned(),
+                table: "string/en/obj_d".to_owned(),
+                key: "thing".to_owned(),
+            },
+            StringIdParameter {
+                name: "objectName".to_owned(),
+                table: "obj_n".to_owned(),
+                key: "thing".to_owned(),
+            },
+        ]
+    );
+
+    Ok(())
+}
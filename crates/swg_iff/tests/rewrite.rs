@@ -0,0 +1,90 @@
+use swg_iff::{
+    error::Error,
+    rewrite::{replace_string, replace_string_id},
+    template::ObjectTemplate,
+    testing::{chunk, form},
+};
+
+#[test]
+fn replace_strings() -> Result<(), Error> {
+    let data = form(
+        b"SHOT",
+        &[
+            form(b"DERV", &[chunk(b"XXXX", b"object/a.iff\0")]),
+            form(
+                b"0000",
+                &[
+                    chunk(b"XXXX", b"appearance\0\x01object/a.iff\0"),
+                    chunk(b"XXXX", b"other\0\x01other/object/a.iff\0object/a.iffx\0"),
+                ],
+            ),
+        ],
+    );
+
+    let (rewritten, replaced) = replace_string(&data, "object/a.iff", "object/renamed/b.iff")?;
+    assert_eq!(replaced, 2);
+
+    let template = ObjectTemplate::parse(&rewritten)?;
+    assert_eq!(template.base.as_deref(), Some("object/renamed/b.iff"));
+    assert_eq!(
+        template.parameters["appearance"],
+        b"\x01object/renamed/b.iff\0"
+    );
+    assert_eq!(
+        template.parameters["other"],
+        b"\x01other/object/a.iff\0object/a.iffx\0"
+    );
+
+    let (unchanged, replaced) = replace_string(&data, "object/missing.iff", "object/b.iff")?;
+    assert_eq!(replaced, 0);
+    assert_eq!(unchanged, data);
+
+    assert!(matches!(
+        replace_string(&data[..data.len() - 1], "object/a.iff", "object/b.iff"),
+        Err(Error::InvalidChunk)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn replace_string_ids() -> Result<(), Error> {
+    let data = form(
+        b"SHOT",
+        &[form(
+            b"0000",
+            &[
+                chunk(b"XXXX", b"objectName\0\x01obj_n\0old\0"),
+                chunk(b"XXXX", b"detailedDescription\0\x01obj_d\0old\0"),
+                chunk(b"XXXX", b"lookAtText\0\x01obj_n\0older\0"),
+                chunk(b"XXXX", b"unlocalizedName\0\x01string/en/obj_n\0old\0"),
+                chunk(b"XXXX", b"tooltip\0\x01string/en/my_obj_n\0old\0"),
+                chunk(b"XXXX", b"title\0\x01string/obj_n\0old\0"),
+                chunk(b"DATA", b"\x01@obj_n:old\0@obj_n:old_2\0@my_obj_n:old\0"),
+            ],
+        )],
+    );
+
+    let (rewritten, replaced) = replace_string_id(&data, "obj_n", "old", "new")?;
+    assert_eq!(replaced, 3);
+    assert_eq!(
+        rewritten,
+        form(
+            b"SHOT",
+            &[form(
+                b"0000",
+                &[
+                    chunk(b"XXXX", b"objectName\0\x01obj_n\0new\0"),
+                    chunk(b"XXXX", b"detailedDescription\0\x01obj_d\0old\0"),
+                    chunk(b"XXXX", b"lookAtText\0\x01obj_n\0older\0"),
+                    chunk(b"XXXX", b"unlocalizedName\0\x01string/en/obj_n\0new\0"),
+                    chunk(b"XXXX", b"tooltip\0\x01string/en/my_obj_n\0old\0"),
+                    chunk(b"XXXX", b"title\0\x01string/obj_n\0old\0"),
+                    chunk(b"DATA", b"\x01@obj_n:new\0@obj_n:old_2\0@my_obj_n:old\0"),
+                ],
+            )],
+        )
+    );
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use swg_iff::{
+    error::Error,
+    snapshot::WorldSnapshot,
+    testing::{chunk, form},
+};
+
+/// A `NODE` form with the fields of its `DATA` chunk and the nodes it contains
+fn node(object_id: i32, container_id: i32, template: u32, position: [f32; 3]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for value in [object_id, container_id, template as i32, 0] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    for value in [1.0, 0.0, 0.0, 0.0]
+        .into_iter()
+        .chain(position)
+        .chain([2.5])
+    {
+        data.extend_from_slice(&f32::to_le_bytes(value));
+    }
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data
+}
+
+fn with_children(data: Vec<u8>, children: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = vec![chunk(b"DATA", &data)];
+    body.extend_from_slice(children);
+    form(b"NODE", &[form(b"0000", &body)])
+}
+
+#[test]
+fn read_nodes() -> Result<(), Error> {
+    let mut names = 2u32.to_le_bytes().to_vec();
+    names
+        .extend_from_slice(b"object/building/shared_house.iff\0object/tangible/shared_chair.iff\0");
+
+    let data = form(
+        b"WSNP",
+        &[form(
+            b"0001",
+            &[
+                form(
+                    b"NODS",
+                    &[
+                        with_children(
+                            node(10, 0, 0, [100.0, 5.0, -200.0]),
+                            &[with_children(node(11, 10, 1, [1.0, 0.0, 2.0]), &[])],
+                        ),
+                        with_children(node(12, 0, 1, [-50.0, 0.0, 50.0]), &[]),
+                    ],
+                ),
+                chunk(b"OTNL", &names),
+            ],
+        )],
+    );
+
+    let snapshot = WorldSnapshot::parse(&data)?;
+    assert_eq!(snapshot.nodes.len(), 2);
+    assert_eq!(snapshot.nodes[0].position, [100.0, 5.0, -200.0]);
+    assert_eq!(snapshot.nodes[0].radius, 2.5);
+    assert_eq!(snapshot.nodes[0].children[0].container_id, 10);
+    assert_eq!(
+        snapshot.template(&snapshot.nodes[1]),
+        Some("object/tangible/shared_chair.iff")
+    );
+    assert_eq!(
+        snapshot.iter().map(|n| n.object_id).collect::<Vec<_>>(),
+        [10, 11, 12]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reject_other_forms() {
+    let data = form(b"PTAT", &[form(b"0001", &[])]);
+    assert!(matches!(
+        WorldSnapshot::parse(&data),
+        Err(Error::InvalidSnapshot)
+    ));
+
+    let names = [&3u32.to_le_bytes()[..], b"only_one\0"].concat();
+    let data = form(b"WSNP", &[form(b"0001", &[chunk(b"OTNL", &names)])]);
+    assert!(matches!(
+        WorldSnapshot::parse(&data),
+        Err(Error::InvalidSnapshot)
+    ));
+}
+
+#[test]
+fn write_round_trip() -> Result<(), Error> {
+    let mut names = 1u32.to_le_bytes().to_vec();
+    names.extend_from_slice(b"object/building/shared_house.iff\0");
+    let data = form(
+        b"WSNP",
+        &[form(
+            b"0001",
+            &[
+                form(
+                    b"NODS",
+                    &[with_children(
+                        node(10, 0, 0, [100.0, 5.0, -200.0]),
+                        &[with_children(node(11, 10, 0, [1.0, 0.0, 2.0]), &[])],
+                    )],
+                ),
+                chunk(b"OTNL", &names),
+            ],
+        )],
+    );
+
+    let mut snapshot = WorldSnapshot::parse(&data)?;
+    assert_eq!(snapshot.write(), data);
+
+    assert_eq!(snapshot.next_object_id(), 12);
+    assert_eq!(
+        snapshot.template_index("object/building/shared_house.iff"),
+        0
+    );
+    assert_eq!(
+        snapshot.template_index("object/tangible/shared_chair.iff"),
+        1
+    );
+
+    let mut added = snapshot.nodes[0].children[0].clone();
+    added.object_id = snapshot.next_object_id();
+    added.container_id = 0;
+    added.template_index = 1;
+    snapshot.nodes.push(added);
+
+    let reread = WorldSnapshot::parse(&snapshot.write())?;
+    assert_eq!(reread, snapshot);
+    assert_eq!(
+        reread.template(&reread.nodes[1]),
+        Some("object/tangible/shared_chair.iff")
+    );
+
+    Ok(())
+}
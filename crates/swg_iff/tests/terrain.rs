@@ -0,0 +1,27 @@
+use swg_iff::{
+    error::Error,
+    terrain::TerrainHeader,
+    testing::{chunk, form},
+};
+
+#[test]
+fn read_header() -> Result<(), Error> {
+    let mut data = b"terrain/tatooine.trn\0".to_vec();
+    data.extend_from_slice(&16384f32.to_le_bytes());
+    data.extend_from_slice(&8f32.to_le_bytes());
+    data.extend_from_slice(&[0; 16]);
+
+    let trn = form(
+        b"PTAT",
+        &[form(b"0015", &[chunk(b"DATA", &data), form(b"TGEN", &[])])],
+    );
+    let header = TerrainHeader::parse(&trn)?;
+    assert_eq!(header.name, "terrain/tatooine.trn");
+    assert_eq!(header.map_width, 16384.0);
+    assert_eq!(header.chunk_width, 8.0);
+
+    let empty = form(b"PTAT", &[form(b"0015", &[chunk(b"DATA", b"x\0")])]);
+    assert!(TerrainHeader::parse(&empty).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+// divan's bench macro expands to std items that are newer than our MSRV
+#![allow(clippy::incompatible_msrv)]
+
+use divan::AllocProfiler;
+
+#[global_allocator]
+static ALLOC: AllocProfiler = AllocProfiler::system();
+
+fn main() {
+    divan::main();
+}
+
+pub mod read {
+    use divan::{counter::BytesCount, Bencher};
+    use std::{collections::HashMap, io::Cursor};
+    use swg_stf::{read::StringTableReader, types::StringTable, write::StringTableWriter};
+    use widestring::U16String;
+
+    /// Encode a table of `entries` strings, each a sentence or two long
+    fn get_input(entries: usize) -> Vec<u8> {
+        let table = StringTable::new(
+            (0..entries)
+                .map(|index| {
+                    (
+                        format!("entry_{:05}", index),
+                        U16String::from_str(&format!(
+                            "Bring %TO to %TT at the Mos Eisley cantina, entry {} of {}.",
+                            index, entries
+                        )),
+                    )
+                })
+                .collect::<HashMap<_, _>>(),
+        );
+
+        let mut data = Vec::new();
+        StringTableWriter::encode(&table, &mut data).unwrap();
+        data
+    }
+
+    #[divan::bench(args = [100, 10_000])]
+    fn decode(bencher: Bencher, entries: usize) {
+        let data = get_input(entries);
+
+        bencher
+            .counter(BytesCount::of_slice(&data))
+            .bench(|| divan::black_box(StringTableReader::decode(Cursor::new(&data)).unwrap()));
+    }
+
+    #[divan::bench(args = [100, 10_000])]
+    fn stream_entries(bencher: Bencher, entries: usize) {
+        let data = get_input(entries);
+
+        bencher.counter(BytesCount::of_slice(&data)).bench(|| {
+            for entry in StringTableReader::entries(Cursor::new(&data)).unwrap() {
+                divan::black_box(entry.unwrap());
+            }
+        });
+    }
+}
@@ -0,0 +1,99 @@
+//! Types for converting string tables to Android `strings.xml` resources
+//!
+//! Every entry becomes a `<string>` resource. Keys which aren't valid resource names are written
+//! under a sanitized name, with a `<!-- key: -->` comment before the resource recording the
+//! original key. Values are escaped the way `aapt` expects, and those containing a `%`, such as
+//! the prose tokens the client fills in, are marked `formatted="false"` so they aren't taken as
+//! format strings.
+//!
+//! Unpaired surrogates are replaced, since a resource file can't hold them.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::types::StringTable;
+
+impl StringTable {
+    /// Write every entry as a resource of an Android `strings.xml` file, in table order
+    pub fn to_android_strings(&self) -> String {
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<resources>\n");
+        let mut used = HashSet::new();
+
+        for (key, value) in self.iter() {
+            let mut name = resource_name(key);
+            if used.contains(&name) {
+                name = (2..)
+                    .map(|n| format!("{}_{}", name, n))
+                    .find(|name| !used.contains(name))
+                    .expect("there is always a free suffix");
+            }
+            if name != *key {
+                // Comments can't contain `--`
+                let _ = writeln!(output, "    <!-- key: {} -->", key.replace("--", "- -"));
+            }
+
+            let value = value.to_string_lossy();
+            let formatted = if value.contains('%') {
+                " formatted=\"false\""
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                output,
+                "    <string name=\"{}\"{}>{}</string>",
+                name,
+                formatted,
+                escape_value(&value)
+            );
+
+            used.insert(name);
+        }
+
+        output.push_str("</resources>\n");
+        output
+    }
+}
+
+/// The resource name for a key, replacing any characters Android doesn't allow
+fn resource_name(key: &str) -> String {
+    let mut name = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Escape a value for `aapt`, quoting it when its whitespace would otherwise be collapsed
+fn escape_value(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    for (index, c) in value.char_indices() {
+        match c {
+            '\\' => output.push_str("\\\\"),
+            '\'' => output.push_str("\\'"),
+            '"' => output.push_str("\\\""),
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            // A leading `@` or `?` would be read as a reference to another resource
+            '@' | '?' if index == 0 => {
+                output.push('\\');
+                output.push(c);
+            }
+            c => output.push(c),
+        }
+    }
+
+    let collapsed = value.starts_with(char::is_whitespace)
+        || value.ends_with(char::is_whitespace)
+        || value.contains("  ");
+    if collapsed {
+        format!("\"{}\"", output)
+    } else {
+        output
+    }
+}
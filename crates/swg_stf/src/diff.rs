@@ -0,0 +1,76 @@
+//! Types for comparing the entries of two string tables
+//!
+
+use std::collections::BTreeSet;
+use widestring::U16String;
+
+use crate::types::StringTable;
+
+/// An entry which is only present in one of the tables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+    /// The key of the entry
+    pub key: String,
+    /// The value of the entry
+    pub value: U16String,
+}
+
+/// An entry present in both tables whose value differs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedEntry {
+    /// The key of the entry
+    pub key: String,
+    /// The value in the old table
+    pub old: U16String,
+    /// The value in the new table
+    pub new: U16String,
+}
+
+/// The differences between two string tables, with keys in sorted order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StfDiff {
+    /// Entries only present in the new table
+    pub added: Vec<DiffEntry>,
+    /// Entries only present in the old table
+    pub removed: Vec<DiffEntry>,
+    /// Entries present in both tables whose values differ
+    pub changed: Vec<ChangedEntry>,
+}
+
+impl StfDiff {
+    /// Whether the tables hold the same entries with the same values
+    ///
+    /// Differences in IDs or entry order alone are not counted.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl StringTable {
+    /// Compare this table, as the old one, with a new one
+    pub fn diff(&self, new: &StringTable) -> StfDiff {
+        let keys = self.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+
+        let mut diff = StfDiff::default();
+        for key in keys {
+            match (self.get(key), new.get(key)) {
+                (Some(old), Some(new)) if old != new => diff.changed.push(ChangedEntry {
+                    key: key.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                (Some(_), Some(_)) => {}
+                (Some(value), None) => diff.removed.push(DiffEntry {
+                    key: key.clone(),
+                    value: value.clone(),
+                }),
+                (None, Some(value)) => diff.added.push(DiffEntry {
+                    key: key.clone(),
+                    value: value.clone(),
+                }),
+                (None, None) => unreachable!("every key is from one of the tables"),
+            }
+        }
+        diff
+    }
+}
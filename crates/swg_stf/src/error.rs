@@ -19,9 +19,157 @@ pub enum Error {
     #[error(transparent)]
     UTF16Error(#[from] std::string::FromUtf16Error),
 
+    /// Transparent warpper for [`serde_json::Error`]
+    #[cfg(feature = "json")]
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+
+    /// Transparent warpper for [`serde_yaml::Error`]
+    #[cfg(feature = "yaml")]
+    #[error(transparent)]
+    YamlError(#[from] serde_yaml::Error),
+
+    /// Transparent warpper for [`csv::Error`]
+    #[cfg(feature = "csv")]
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+
+    /// Transparent warpper for [`quick_xml::Error`]
+    #[cfg(feature = "xliff")]
+    #[error(transparent)]
+    XmlError(#[from] quick_xml::Error),
+
     /// File is an invalid string table file
     #[error("Invalid String Table")]
     InvalidFile,
+
+    /// No entry has the key {0}
+    #[error("No entry has the key {0}")]
+    MissingKey(String),
+
+    /// An entry already has the key {0}
+    #[error("An entry already has the key {0}")]
+    DuplicateKey(String),
+
+    /// A Fluent file couldn't be read as a string table
+    #[error("Line {line} of the Fluent file is invalid: {reason}")]
+    Fluent { line: usize, reason: String },
+
+    /// A XLIFF document couldn't be read as a string table
+    #[cfg(feature = "xliff")]
+    #[error("Invalid XLIFF document: {0}")]
+    InvalidXliff(String),
+
+    /// The string table has problems found by [`StringTableReader::validate`]
+    ///
+    /// [`StringTableReader::validate`]: crate::read::StringTableReader::validate
+    #[error("String table has {} problems", .0.len())]
+    Validation(#[related] Vec<ValidationIssue>),
+
+    /// No ID is left above the max index of {0} for a new entry
+    #[error("No ID is left above the max index of {0} for a new entry")]
+    IdsExhausted(u32),
+
+    /// More than one entry has the ID {0}
+    #[error("More than one entry has the ID {0}")]
+    DuplicateId(u32),
+
+    /// The value of {key} has an unpaired surrogate {unit:#06X}
+    #[error("The value of {key} has an unpaired surrogate {unit:#06X}")]
+    UnpairedSurrogate {
+        /// The key of the entry
+        key: String,
+        /// The surrogate code unit
+        unit: u16,
+    },
+}
+
+/// A problem with the structure of a string table file
+#[derive(Error, Diagnostic, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// more than one value has the ID {0}
+    #[error("more than one value has the ID {0}")]
+    DuplicateValueId(u32),
+
+    /// more than one key has the ID {0}
+    #[error("more than one key has the ID {0}")]
+    DuplicateKeyId(u32),
+
+    /// more than one entry has the key {0}
+    #[error("more than one entry has the key {0}")]
+    DuplicateKey(String),
+
+    /// the value with the ID {0} has no key
+    #[error("the value with the ID {0} has no key")]
+    ValueWithoutKey(u32),
+
+    /// the key {key} with the ID {id} has no value
+    #[error("the key {key} with the ID {id} has no value")]
+    KeyWithoutValue {
+        /// The ID of the key
+        id: u32,
+        /// The key itself
+        key: String,
+    },
+
+    /// the value with the ID {0} is not valid UTF-16
+    #[error("the value with the ID {0} is not valid UTF-16")]
+    InvalidUtf16(u32),
+
+    /// the key with the ID {0} is not valid UTF-8
+    #[error("the key with the ID {0} is not valid UTF-8")]
+    InvalidUtf8(u32),
+
+    /// the ID {id} is above the max index of {max_index}
+    #[error("the ID {id} is above the max index of {max_index}")]
+    IdAboveMaxIndex {
+        /// The ID of the value or key
+        id: u32,
+        /// The max index from the header
+        max_index: u32,
+    },
+
+    /// the file ends before the {count} entries the header counts
+    #[error("the file ends before the {count} entries the header counts")]
+    Truncated {
+        /// The entry count from the header
+        count: u32,
+    },
+
+    /// the file has {0} bytes after the entries the header counts
+    #[error("the file has {0} bytes after the entries the header counts")]
+    TrailingData(u64),
+}
+
+/// A placeholder which differs between a string and its translation
+#[derive(Error, Diagnostic, Debug, Clone, PartialEq, Eq)]
+pub enum TokenIssue {
+    /// the translation of {key} is missing {token}
+    #[error("the translation of {key} is missing {token}")]
+    Missing {
+        /// The key of the entry
+        key: String,
+        /// The placeholder as it's written in the source
+        token: String,
+    },
+
+    /// the translation of {key} has {token} which the source doesn't
+    #[error("the translation of {key} has {token} which the source doesn't")]
+    Unexpected {
+        /// The key of the entry
+        key: String,
+        /// The placeholder as it's written in the translation
+        token: String,
+    },
+
+    /// the translation of {key} has a malformed placeholder {token}
+    #[error("the translation of {key} has a malformed placeholder {token}")]
+    Malformed {
+        /// The key of the entry
+        key: String,
+        /// The text which looks like a placeholder
+        token: String,
+    },
 }
 
 /// Generic result type with crate's Error as its error variant
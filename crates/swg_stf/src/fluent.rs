@@ -0,0 +1,346 @@
+//! Types for converting string tables to and from Project Fluent files
+//!
+//! Every entry becomes a Fluent message. The prose tokens the client fills in, such as `%TU` or
+//! `%DI`, become variables like `{ $TU }`, and text which Fluent treats as syntax is escaped with
+//! string literals. Keys which aren't valid Fluent identifiers are written under a sanitized ID,
+//! with a `# key:` comment before the message so the importer can restore the original key.
+//!
+//! The importer is best effort: it understands what the exporter writes along with simple
+//! hand written messages, skips terms and attributes, and rejects placeables other than variables
+//! and string literals since a string table can't represent them.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::{
+    error::{Error, Result},
+    surrogates::{escape, unescape},
+    tokens::{segments, Token},
+    types::StringTable,
+};
+
+/// The comment which records the original key of a message
+const KEY_COMMENT: &str = "# key: ";
+
+impl StringTable {
+    /// Write every entry as a Fluent message, in table order
+    pub fn to_fluent(&self) -> String {
+        let mut output = String::new();
+        let mut used = HashSet::new();
+
+        for (key, value) in self.iter() {
+            let mut id = message_id(key);
+            if used.contains(&id) {
+                id = (2..)
+                    .map(|n| format!("{}-{}", id, n))
+                    .find(|id| !used.contains(id))
+                    .expect("there is always a free suffix");
+            }
+            if id != *key {
+                let _ = writeln!(output, "{}{}", KEY_COMMENT, key);
+            }
+
+            let lines = escape(value);
+            let lines = lines.split('\n').map(escape_line).collect::<Vec<_>>();
+            match lines.as_slice() {
+                [line] => {
+                    let _ = writeln!(output, "{} = {}", id, line);
+                }
+                lines => {
+                    let _ = writeln!(output, "{} =", id);
+                    for line in lines {
+                        let _ = writeln!(output, "    {}", line);
+                    }
+                }
+            }
+
+            used.insert(id);
+        }
+
+        output
+    }
+
+    /// Read the messages of a Fluent file as a table, numbering them from 1 in file order
+    pub fn from_fluent(data: &str) -> Result<Self> {
+        let mut table = StringTable::default();
+        let mut key = None;
+        let mut message: Option<Message> = None;
+
+        for (number, line) in (1..).zip(data.lines()) {
+            if line.starts_with(' ') || line.trim().is_empty() {
+                if let Some(message) = &mut message {
+                    message.lines.push(line);
+                }
+                continue;
+            }
+
+            if let Some(message) = message.take() {
+                message.finish(&mut table)?;
+            }
+
+            if let Some(comment) = line.strip_prefix(KEY_COMMENT) {
+                key = Some(comment.trim().to_owned());
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let (id, first) = line.split_once('=').ok_or_else(|| Error::Fluent {
+                line: number,
+                reason: "expected a message".to_owned(),
+            })?;
+            let id = id.trim();
+            let key = key.take();
+
+            // Terms can be referenced by messages but aren't entries themselves
+            if id.starts_with('-') {
+                message = Some(Message::skipped(number));
+                continue;
+            }
+            if message_id(id) != id {
+                return Err(Error::Fluent {
+                    line: number,
+                    reason: format!("{} is not a valid message identifier", id),
+                });
+            }
+
+            message = Some(Message {
+                key: Some(key.unwrap_or_else(|| id.to_owned())),
+                line: number,
+                first: first.trim_start(),
+                lines: Vec::new(),
+            });
+        }
+
+        if let Some(message) = message {
+            message.finish(&mut table)?;
+        }
+
+        Ok(table)
+    }
+}
+
+/// A message which is still being read
+struct Message<'a> {
+    /// The key of the entry, or `None` if the message is skipped
+    key: Option<String>,
+    /// The line the message starts on
+    line: usize,
+    /// The text on the same line as the identifier
+    first: &'a str,
+    /// Every indented or blank line after the identifier
+    lines: Vec<&'a str>,
+}
+
+impl<'a> Message<'a> {
+    fn skipped(line: usize) -> Self {
+        Self {
+            key: None,
+            line,
+            first: "",
+            lines: Vec::new(),
+        }
+    }
+
+    fn finish(mut self, table: &mut StringTable) -> Result<()> {
+        let Some(key) = self.key.take() else {
+            return Ok(());
+        };
+
+        // Attributes follow the value, which a string table has no room for
+        if let Some(attribute) = self
+            .lines
+            .iter()
+            .position(|line| line.trim_start().starts_with('.'))
+        {
+            self.lines.truncate(attribute);
+        }
+        while self.lines.last().is_some_and(|line| line.trim().is_empty()) {
+            self.lines.pop();
+        }
+
+        let indent = self
+            .lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start_matches(' ').len())
+            .min()
+            .unwrap_or(0);
+
+        let mut lines = Vec::with_capacity(self.lines.len() + 1);
+        if !self.first.trim().is_empty() {
+            lines.push(self.first);
+        }
+        lines.extend(
+            self.lines
+                .iter()
+                .map(|line| line.get(indent..).unwrap_or("")),
+        );
+
+        let pattern = lines.join("\n");
+        let value =
+            unescape_pattern(pattern.trim_end_matches(' ')).map_err(|reason| Error::Fluent {
+                line: self.line,
+                reason,
+            })?;
+
+        if table.contains_key(&key) {
+            return Err(Error::DuplicateKey(key));
+        }
+        table.insert(key, unescape(&value))?;
+        Ok(())
+    }
+}
+
+/// The Fluent identifier for a key, replacing any characters Fluent doesn't allow
+fn message_id(key: &str) -> String {
+    let mut id = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if !id.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        id.insert(0, 'k');
+    }
+    id
+}
+
+/// Write a character as a Fluent string literal
+fn literal(output: &mut String, c: char) {
+    let _ = match c {
+        '"' => write!(output, r#"{{"\""}}"#),
+        '\\' => write!(output, r#"{{"\\"}}"#),
+        c if c.is_control() => write!(output, r#"{{"\u{:04X}"}}"#, c as u32),
+        c => write!(output, r#"{{"{}"}}"#, c),
+    };
+}
+
+/// Escape a single line of a value, mapping prose tokens to variables
+fn escape_line(line: &str) -> String {
+    if line.is_empty() {
+        return r#"{""}"#.to_owned();
+    }
+
+    let mut output = String::with_capacity(line.len());
+    let mut offset = 0;
+    for segment in segments(line) {
+        let text = match segment {
+            Token::Prose(token) => {
+                let _ = write!(output, "{{ ${} }}", &token[1..]);
+                offset += token.len();
+                continue;
+            }
+            segment => segment.as_str(),
+        };
+
+        for (index, c) in text.char_indices() {
+            let first = offset + index == 0;
+            let last = offset + index + c.len_utf8() == line.len();
+            let special = match c {
+                '{' | '}' => true,
+                ' ' => first || last,
+                '[' | '*' | '.' => first,
+                c => c.is_control(),
+            };
+            if special {
+                literal(&mut output, c);
+            } else {
+                output.push(c);
+            }
+        }
+        offset += text.len();
+    }
+    output
+}
+
+/// Replace the placeables of a pattern with the text they stand for
+fn unescape_pattern(pattern: &str) -> std::result::Result<String, String> {
+    let mut output = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {}
+            '}' => return Err("unmatched }".to_owned()),
+            c => {
+                output.push(c);
+                continue;
+            }
+        }
+
+        let mut placeable = String::new();
+        let mut quoted = false;
+        let mut closed = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => quoted = !quoted,
+                '\\' if quoted => {
+                    placeable.push(c);
+                    if let Some(c) = chars.next() {
+                        placeable.push(c);
+                    }
+                    continue;
+                }
+                '}' if !quoted => {
+                    closed = true;
+                    break;
+                }
+                _ => {}
+            }
+            placeable.push(c);
+        }
+        if !closed {
+            return Err("unmatched {".to_owned());
+        }
+
+        let placeable = placeable.trim();
+        if let Some(variable) = placeable.strip_prefix('$') {
+            output.push('%');
+            output.push_str(variable);
+        } else if let Some(text) = placeable
+            .strip_prefix('"')
+            .and_then(|text| text.strip_suffix('"'))
+        {
+            unescape_literal(text, &mut output)?;
+        } else {
+            return Err(format!("unsupported placeable {{{}}}", placeable));
+        }
+    }
+
+    Ok(output)
+}
+
+/// Append the text of a string literal, without its quotes
+fn unescape_literal(text: &str, output: &mut String) -> std::result::Result<(), String> {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+
+        let digits = match chars.next() {
+            Some('u') => 4,
+            Some('U') => 6,
+            Some(c @ ('"' | '\\')) => {
+                output.push(c);
+                continue;
+            }
+            _ => return Err(format!("invalid escape in \"{}\"", text)),
+        };
+        let code = chars.by_ref().take(digits).collect::<String>();
+        let c = u32::from_str_radix(&code, 16)
+            .ok()
+            .filter(|_| code.len() == digits)
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("invalid escape in \"{}\"", text))?;
+        output.push(c);
+    }
+    Ok(())
+}
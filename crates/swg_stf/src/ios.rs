@@ -0,0 +1,45 @@
+//! Types for converting string tables to iOS `.strings` files
+//!
+//! Every entry becomes a `"key" = "value";` line, written as UTF-8 which Xcode accepts alongside
+//! UTF-16. Keys are quoted, so they don't need to be sanitized.
+//!
+//! Unpaired surrogates are replaced, since a `.strings` file can't hold them.
+
+use std::fmt::Write;
+
+use crate::types::StringTable;
+
+impl StringTable {
+    /// Write every entry as a line of an iOS `.strings` file, in table order
+    pub fn to_ios_strings(&self) -> String {
+        let mut output = String::new();
+        for (key, value) in self.iter() {
+            let _ = writeln!(
+                output,
+                "\"{}\" = \"{}\";",
+                escape(key),
+                escape(&value.to_string_lossy())
+            );
+        }
+        output
+    }
+}
+
+/// Escape text for a quoted string of a `.strings` file
+fn escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => output.push_str("\\\\"),
+            '"' => output.push_str("\\\""),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(output, "\\U{:04X}", c as u32);
+            }
+            c => output.push(c),
+        }
+    }
+    output
+}
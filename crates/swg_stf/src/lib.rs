@@ -1,6 +1,6 @@
 //! # STF Format Documentation
 //!
-//! This crate provides utilities to read and extract data from the **STF** format used by
+//! This crate provides utilities to read, extract and write data in the **STF** format used by
 //! the game *Star Wars Galaxies*. The STF format is a custom binary format that stores a list of string keys and values
 //! within a single file. STF files are typically identified with the `.stf` extension.
 //!
@@ -53,8 +53,22 @@
 //! - **Endianness**: Little-endian for all multi-byte integers
 //!
 
+#[cfg(feature = "android")]
+pub mod android;
+pub mod diff;
 pub mod error;
+pub mod fluent;
+#[cfg(feature = "ios")]
+pub mod ios;
 pub mod read;
+pub mod report;
+pub mod surrogates;
+pub mod text;
+pub mod tokens;
 pub mod types;
+pub mod write;
+#[cfg(feature = "xliff")]
+pub mod xliff;
 
 pub use read::StringTableReader;
+pub use write::StringTableWriter;
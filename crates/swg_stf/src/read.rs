@@ -3,26 +3,35 @@
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::{
-    collections::HashMap,
-    io::{Read, Seek},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    io::{ErrorKind, Read, Seek, SeekFrom},
 };
+use tracing::{instrument, warn, Span};
 use widestring::U16String;
 
 use crate::{
-    error::{Error, Result},
+    error::{Error, Result, ValidationIssue},
     types::StringTable,
 };
 
+/// The most a length read from a file is trusted with when allocating, so a corrupt length
+/// fails when the data runs out rather than by exhausting memory
+const MAX_PREALLOCATION: usize = 1 << 16;
+
 /// STF file reader
 ///
 /// ```no_run
 /// use std::io::prelude::*;
+/// use swg_stf::StringTableReader;
 ///
 /// fn list_entries(reader: impl Read + Seek) -> swg_stf::error::Result<()> {
-///     let stf = swg_stf::TreArchive::new(reader)?;
+///     let stf = StringTableReader::decode(reader)?;
 ///
-///     for (key, value) in stf.get_entries() {
-///         println!("{}: {}", &key, &value.display());
+///     for (id, key, value) in stf.entries_with_ids() {
+///         println!("{} {}: {}", id, key, value.display());
+///     }
+///     if let Some((key, value)) = stf.by_id(1) {
+///         println!("the first entry is {}: {}", key, value.display());
 ///     }
 ///
 ///     Ok(())
@@ -32,50 +41,322 @@ pub struct StringTableReader {}
 
 impl StringTableReader {
     /// Read a STF file and parse it's entries.
+    #[instrument(skip_all, err, fields(count))]
     pub fn decode<R: Read + Seek>(mut reader: R) -> Result<StringTable> {
-        let magic = reader.read_u32::<LittleEndian>()?;
-        if magic != 0x0000ABCD {
-            return Err(Error::InvalidFile);
+        let (flag, max_index, count) = read_header(&mut reader)?;
+        Span::current().record("count", count);
+
+        let mut values = Vec::with_capacity((count as usize).min(MAX_PREALLOCATION));
+        let mut keys = Vec::with_capacity((count as usize).min(MAX_PREALLOCATION));
+        read_records(&mut reader, count, &mut values, &mut keys)?;
+
+        let mut values = values
+            .into_iter()
+            .map(|(id, value)| (id, U16String::from_vec(value)))
+            .collect::<HashMap<_, _>>();
+        let mut entries = Vec::with_capacity(keys.len());
+        // Entries take the order of the key list, which the game's tables share with the values
+        for (id, key) in keys {
+            let key = String::from_utf8(key)?;
+            if let Some(value) = values.remove(&id) {
+                entries.push((id, key, value));
+            }
         }
 
-        let _flag = reader.read_u8()?;
-        let _next_index = reader.read_u32::<LittleEndian>()?;
-        let count = reader.read_u32::<LittleEndian>()?;
+        if entries.len() != count as usize {
+            warn!(
+                "dropped {} of {} entries without a matching key or value",
+                count as usize - entries.len(),
+                count
+            );
+        }
 
-        let mut values = HashMap::with_capacity(count as usize);
-        for _ in 0..count {
-            let id = reader.read_u32::<LittleEndian>()?;
-            let _unknown = reader.read_u32::<LittleEndian>()?; // 0xFFFFFFFF
-            let runes = reader.read_u32::<LittleEndian>()? as usize;
+        Ok(StringTable::with_ids(entries, max_index, flag))
+    }
 
-            let mut buffer = Vec::with_capacity(runes);
-            for _ in 0..runes {
-                let rune = reader.read_u16::<LittleEndian>()?;
-                buffer.push(rune);
+    /// Read a STF file like [`StringTableReader::decode`], but fail if
+    /// [`StringTableReader::validate`] finds any problems with it
+    pub fn decode_strict<R: Read + Seek>(mut reader: R) -> Result<StringTable> {
+        let start = reader.stream_position()?;
+        let issues = Self::validate(&mut reader)?;
+        if !issues.is_empty() {
+            return Err(Error::Validation(issues));
+        }
+
+        reader.seek(SeekFrom::Start(start))?;
+        Self::decode(reader)
+    }
+
+    /// Check a STF file for problems which [`StringTableReader::decode`] works around, such as
+    /// values without a key, duplicate IDs or a header which doesn't match the records
+    #[instrument(skip_all, err, fields(issues))]
+    pub fn validate<R: Read + Seek>(mut reader: R) -> Result<Vec<ValidationIssue>> {
+        let (_, max_index, count) = read_header(&mut reader)?;
+        let mut issues = Vec::new();
+
+        let mut values = Vec::with_capacity((count as usize).min(MAX_PREALLOCATION));
+        let mut keys = Vec::with_capacity((count as usize).min(MAX_PREALLOCATION));
+        match read_records(&mut reader, count, &mut values, &mut keys) {
+            Ok(()) => {
+                let end = reader.stream_position()?;
+                let length = reader.seek(SeekFrom::End(0))?;
+                if length > end {
+                    issues.push(ValidationIssue::TrailingData(length - end));
+                }
             }
+            Err(Error::IOError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                issues.push(ValidationIssue::Truncated { count })
+            }
+            Err(e) => return Err(e),
+        }
 
-            values.insert(id, U16String::from_vec(buffer));
+        let duplicates = |ids: &mut dyn Iterator<Item = u32>| {
+            let mut counts = BTreeMap::new();
+            ids.for_each(|id| *counts.entry(id).or_insert(0) += 1);
+            counts
+                .into_iter()
+                .filter(|(_, count)| *count > 1)
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        };
+        issues.extend(
+            duplicates(&mut values.iter().map(|(id, _)| *id))
+                .into_iter()
+                .map(ValidationIssue::DuplicateValueId),
+        );
+        issues.extend(
+            duplicates(&mut keys.iter().map(|(id, _)| *id))
+                .into_iter()
+                .map(ValidationIssue::DuplicateKeyId),
+        );
+
+        let mut seen = HashSet::new();
+        let mut reported = HashSet::new();
+        for (_, key) in &keys {
+            if !seen.insert(key) && reported.insert(key) {
+                issues.push(ValidationIssue::DuplicateKey(
+                    String::from_utf8_lossy(key).into_owned(),
+                ));
+            }
         }
 
-        let mut names = HashMap::with_capacity(count as usize);
+        let value_ids = values.iter().map(|(id, _)| *id).collect::<BTreeSet<_>>();
+        let key_ids = keys.iter().map(|(id, _)| *id).collect::<BTreeSet<_>>();
+        issues.extend(
+            value_ids
+                .difference(&key_ids)
+                .map(|id| ValidationIssue::ValueWithoutKey(*id)),
+        );
+        issues.extend(
+            keys.iter()
+                .filter(|(id, _)| !value_ids.contains(id))
+                .map(|(id, key)| ValidationIssue::KeyWithoutValue {
+                    id: *id,
+                    key: String::from_utf8_lossy(key).into_owned(),
+                }),
+        );
+
+        issues.extend(
+            values
+                .iter()
+                .filter(|(_, value)| String::from_utf16(value).is_err())
+                .map(|(id, _)| ValidationIssue::InvalidUtf16(*id)),
+        );
+        issues.extend(
+            keys.iter()
+                .filter(|(_, key)| std::str::from_utf8(key).is_err())
+                .map(|(id, _)| ValidationIssue::InvalidUtf8(*id)),
+        );
+
+        issues.extend(
+            value_ids
+                .union(&key_ids)
+                .filter(|id| **id > max_index)
+                .map(|id| ValidationIssue::IdAboveMaxIndex { id: *id, max_index }),
+        );
+
+        Span::current().record("issues", issues.len());
+        Ok(issues)
+    }
+
+    /// Read the entries of a STF file one at a time, without holding the whole table in memory
+    ///
+    /// Only the position of each value is read up front, every key and value is read as the
+    /// iterator reaches it.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use swg_stf::StringTableReader;
+    ///
+    /// fn find(key: &str) -> swg_stf::error::Result<Option<String>> {
+    ///     for entry in StringTableReader::entries(File::open("quest.stf")?)? {
+    ///         let (name, value) = entry?;
+    ///         if name == key {
+    ///             return Ok(Some(value.to_string_lossy()));
+    ///         }
+    ///     }
+    ///     Ok(None)
+    /// }
+    /// ```
+    #[instrument(skip_all, err, fields(count))]
+    pub fn entries<R: Read + Seek>(mut reader: R) -> Result<StringTableEntries<R>> {
+        let (flag, max_index, count) = read_header(&mut reader)?;
+        Span::current().record("count", count);
+
+        let mut values = HashMap::with_capacity((count as usize).min(MAX_PREALLOCATION));
         for _ in 0..count {
             let id = reader.read_u32::<LittleEndian>()?;
+            let _unknown = reader.read_u32::<LittleEndian>()?; // 0xFFFFFFFF
             let runes = reader.read_u32::<LittleEndian>()? as usize;
 
-            let mut buffer = Vec::with_capacity(runes);
-            for _ in 0..runes {
-                let rune = reader.read_u8()?;
-                buffer.push(rune);
-            }
-
-            names.insert(id, String::from_utf8(buffer)?);
+            let position = reader.stream_position()?;
+            values.insert(id, (position, runes));
+            reader.seek(SeekFrom::Current(runes as i64 * 2))?;
         }
 
-        let entries = names
-            .iter()
-            .filter_map(|(id, name)| values.get(id).map(|value| (name.clone(), value.clone())))
-            .collect();
+        Ok(StringTableEntries {
+            position: reader.stream_position()?,
+            reader,
+            values,
+            remaining: count,
+            count,
+            max_index,
+            flag,
+        })
+    }
+}
+
+/// Read the value and key records of a STF file, keeping those read before any error
+fn read_records<R: Read>(
+    reader: &mut R,
+    count: u32,
+    values: &mut Vec<(u32, Vec<u16>)>,
+    keys: &mut Vec<(u32, Vec<u8>)>,
+) -> Result<()> {
+    for _ in 0..count {
+        let id = reader.read_u32::<LittleEndian>()?;
+        let _unknown = reader.read_u32::<LittleEndian>()?; // 0xFFFFFFFF
+        let runes = reader.read_u32::<LittleEndian>()? as usize;
+
+        values.push((id, read_utf16(reader, runes)?));
+    }
+
+    for _ in 0..count {
+        let id = reader.read_u32::<LittleEndian>()?;
+        let runes = reader.read_u32::<LittleEndian>()? as usize;
+
+        keys.push((id, read_bytes(reader, runes)?));
+    }
+
+    Ok(())
+}
+
+/// Read a run of little endian UTF-16 code units in bulk, a chunk at a time so a corrupt length
+/// can't allocate more than the data holds
+fn read_utf16<R: Read>(reader: &mut R, runes: usize) -> Result<Vec<u16>> {
+    let mut buffer = Vec::with_capacity(runes.min(MAX_PREALLOCATION));
+    while buffer.len() < runes {
+        let start = buffer.len();
+        buffer.resize(start + (runes - start).min(MAX_PREALLOCATION), 0);
+        reader.read_u16_into::<LittleEndian>(&mut buffer[start..])?;
+    }
+    Ok(buffer)
+}
+
+/// Read a run of bytes in bulk, with the same chunking as [`read_utf16`]
+fn read_bytes<R: Read>(reader: &mut R, length: usize) -> Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+    while buffer.len() < length {
+        let start = buffer.len();
+        buffer.resize(start + (length - start).min(MAX_PREALLOCATION), 0);
+        reader.read_exact(&mut buffer[start..])?;
+    }
+    Ok(buffer)
+}
+
+/// Read the header of a STF file, returning its flag, max index and entry count
+fn read_header<R: Read>(reader: &mut R) -> Result<(u8, u32, u32)> {
+    let magic = reader.read_u32::<LittleEndian>()?;
+    if magic != 0x0000ABCD {
+        return Err(Error::InvalidFile);
+    }
+
+    let flag = reader.read_u8()?;
+    let max_index = reader.read_u32::<LittleEndian>()?;
+    let count = reader.read_u32::<LittleEndian>()?;
+    Ok((flag, max_index, count))
+}
+
+/// An iterator over the `(key, value)` pairs of a STF file, in file order
+///
+/// Created by [`StringTableReader::entries`]. Keys without a value are skipped, and iteration
+/// stops after the first error.
+pub struct StringTableEntries<R> {
+    reader: R,
+    /// Where the characters of each value start, and how many there are
+    values: HashMap<u32, (u64, usize)>,
+    /// Where the next key starts
+    position: u64,
+    /// The number of keys left to read
+    remaining: u32,
+    count: u32,
+    max_index: u32,
+    flag: u8,
+}
+
+impl<R: Read + Seek> StringTableEntries<R> {
+    /// The number of entries the header says the file has
+    pub fn entry_count(&self) -> u32 {
+        self.count
+    }
+
+    /// The highest ID handed out so far, from the header
+    pub fn max_index(&self) -> u32 {
+        self.max_index
+    }
+
+    /// The unknown flag from the header
+    pub fn flag(&self) -> u8 {
+        self.flag
+    }
+
+    fn read_entry(&mut self) -> Result<Option<(String, U16String)>> {
+        self.reader.seek(SeekFrom::Start(self.position))?;
+        let id = self.reader.read_u32::<LittleEndian>()?;
+        let runes = self.reader.read_u32::<LittleEndian>()? as usize;
+
+        let key = String::from_utf8(read_bytes(&mut self.reader, runes)?)?;
+        self.position = self.reader.stream_position()?;
+
+        let Some((position, runes)) = self.values.get(&id).copied() else {
+            return Ok(None);
+        };
+        self.reader.seek(SeekFrom::Start(position))?;
+        let value = read_utf16(&mut self.reader, runes)?;
+
+        Ok(Some((key, U16String::from_vec(value))))
+    }
+}
+
+impl<R: Read + Seek> Iterator for StringTableEntries<R> {
+    type Item = Result<(String, U16String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            self.remaining -= 1;
+            match self.read_entry() {
+                Ok(Some(entry)) => return Some(Ok(entry)),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.remaining = 0;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
 
-        Ok(StringTable::new(entries))
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining as usize))
     }
 }
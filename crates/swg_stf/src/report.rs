@@ -0,0 +1,207 @@
+//! Types for writing the differences between string tables as a standalone HTML page
+//!
+//! A report groups the [`StfDiff`] of each table under its name, with added, removed and changed
+//! entries color coded and every column sortable by clicking its header. The page has no external
+//! styles or scripts, so it can be shared as a single file, such as alongside patch notes.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::diff::StfDiff;
+
+/// The styles of the page, with a row color for each kind of change
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.5em; text-align: left; vertical-align: top; }
+th { background: #eee; cursor: pointer; user-select: none; }
+td { white-space: pre-wrap; }
+summary { cursor: pointer; font-size: 1.2em; margin: 0.5em 0; }
+tr.added { background: #e6ffec; }
+tr.removed { background: #ffebe9; }
+tr.changed { background: #fff8c5; }
+del { background: #ffc1bd; }
+ins { background: #aceebb; text-decoration: none; }
+.counts { color: #666; font-size: 0.9em; }
+"#;
+
+/// Sorts a table by the clicked column, reversing the order on a second click
+const SCRIPT: &str = r#"
+document.querySelectorAll("th").forEach((th) => th.addEventListener("click", () => {
+  const body = th.closest("table").tBodies[0];
+  const column = th.cellIndex;
+  const ascending = th.dataset.order !== "asc";
+  th.closest("tr").querySelectorAll("th").forEach((other) => delete other.dataset.order);
+  th.dataset.order = ascending ? "asc" : "desc";
+  Array.from(body.rows)
+    .sort((a, b) => {
+      const order = a.cells[column].textContent.localeCompare(b.cells[column].textContent);
+      return ascending ? order : -order;
+    })
+    .forEach((row) => body.appendChild(row));
+}));
+"#;
+
+/// The differences between sets of string tables, keyed by table name
+///
+/// ```
+/// use std::collections::HashMap;
+/// use swg_stf::{report::DiffReport, types::StringTable};
+///
+/// let old = StringTable::new(HashMap::from([("greeting".to_owned(), "Hello".into())]));
+/// let new = StringTable::new(HashMap::from([("greeting".to_owned(), "Hello there".into())]));
+///
+/// let mut report = DiffReport::new("Publish 14");
+/// report.add("ui", old.diff(&new));
+/// assert!(report.to_html().contains("Hello<ins> there</ins>"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffReport {
+    /// The heading of the page
+    pub title: String,
+    /// The differences of each table, in name order
+    pub tables: BTreeMap<String, StfDiff>,
+}
+
+impl DiffReport {
+    /// Create a report without any tables
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// Add the differences of a table, replacing any the report already has for it
+    pub fn add(&mut self, name: impl Into<String>, diff: StfDiff) {
+        self.tables.insert(name.into(), diff);
+    }
+
+    /// Write the report as a standalone HTML page
+    ///
+    /// Tables without any differences are left out.
+    pub fn to_html(&self) -> String {
+        let changed = self
+            .tables
+            .iter()
+            .filter(|(_, diff)| !diff.is_empty())
+            .collect::<Vec<_>>();
+        let (added, removed, modified) = changed.iter().fold((0, 0, 0), |counts, (_, diff)| {
+            (
+                counts.0 + diff.added.len(),
+                counts.1 + diff.removed.len(),
+                counts.2 + diff.changed.len(),
+            )
+        });
+
+        let title = escape(&self.title);
+        let mut html = String::new();
+        // Writing to a string can't fail
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+             <p class=\"counts\">{} tables differ: {added} added, {removed} removed, \
+             {modified} changed</p>\n",
+            changed.len(),
+        );
+
+        for (name, diff) in changed {
+            let _ = write!(
+                html,
+                "<details open>\n<summary>{} <span class=\"counts\">{} added, {} removed, \
+                 {} changed</span></summary>\n<table>\n<thead><tr><th>Change</th><th>Key</th>\
+                 <th>Old</th><th>New</th></tr></thead>\n<tbody>\n",
+                escape(name),
+                diff.added.len(),
+                diff.removed.len(),
+                diff.changed.len(),
+            );
+
+            for entry in &diff.added {
+                row(
+                    &mut html,
+                    "added",
+                    &entry.key,
+                    "",
+                    &escape(&entry.value.to_string_lossy()),
+                );
+            }
+            for entry in &diff.removed {
+                row(
+                    &mut html,
+                    "removed",
+                    &entry.key,
+                    &escape(&entry.value.to_string_lossy()),
+                    "",
+                );
+            }
+            for entry in &diff.changed {
+                let (old, new) =
+                    highlight(&entry.old.to_string_lossy(), &entry.new.to_string_lossy());
+                row(&mut html, "changed", &entry.key, &old, &new);
+            }
+
+            html.push_str("</tbody>\n</table>\n</details>\n");
+        }
+
+        let _ = write!(html, "<script>{SCRIPT}</script>\n</body>\n</html>\n");
+        html
+    }
+}
+
+/// Write a row of a table, with the values already escaped
+fn row(html: &mut String, change: &str, key: &str, old: &str, new: &str) {
+    let _ = writeln!(
+        html,
+        "<tr class=\"{change}\"><td>{change}</td><td>{}</td><td>{old}</td><td>{new}</td></tr>",
+        escape(key),
+    );
+}
+
+/// Escape both values, marking the part between their common start and end as deleted and
+/// inserted
+fn highlight(old: &str, new: &str) -> (String, String) {
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map(|((index, _), _)| index)
+        .unwrap_or(old.len().min(new.len()));
+    let suffix = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+
+    let mark = |text: &str, tag: &str| {
+        let middle = &text[prefix..text.len() - suffix];
+        if middle.is_empty() {
+            return escape(text);
+        }
+        format!(
+            "{}<{tag}>{}</{tag}>{}",
+            escape(&text[..prefix]),
+            escape(middle),
+            escape(&text[text.len() - suffix..]),
+        )
+    };
+    (mark(old, "del"), mark(new, "ins"))
+}
+
+/// Escape the characters HTML gives a meaning to
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,108 @@
+//! Types for converting values which aren't valid UTF-16 to text
+//!
+//! Some retail values contain surrogates without their pair, which can't be held by a [`String`].
+//! A [`SurrogatePolicy`] decides what happens to them when a table is converted to text. The
+//! default preserves them as a `\uD800` style escape, which is turned back into the raw code
+//! unit when the text is read again, so exporting and importing a table doesn't change it.
+//!
+//! So that text which already looks like an escape isn't mistaken for one, a backslash followed
+//! by a `u`, another backslash or an escape is doubled. Other backslashes, such as those of color
+//! codes, are written as is.
+
+use widestring::{U16Str, U16String};
+
+use crate::error::{Error, Result};
+
+/// What to do with an unpaired surrogate when converting a value to text
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum SurrogatePolicy {
+    /// Write the code unit as a `\uD800` style escape, which is read back as the code unit,
+    /// doubling any backslash which would otherwise be read as part of an escape
+    #[default]
+    Preserve,
+    /// Replace the code unit with `U+FFFD`, losing it
+    Replace,
+    /// Fail with [`Error::UnpairedSurrogate`]
+    Error,
+}
+
+impl SurrogatePolicy {
+    /// Convert a value to text, with `key` naming the entry in the error
+    pub fn decode(self, key: &str, value: &U16Str) -> Result<String> {
+        let mut text = String::with_capacity(value.len());
+        let mut chars = char::decode_utf16(value.as_slice().iter().copied()).peekable();
+        while let Some(c) = chars.next() {
+            match (c, self) {
+                (Ok('\\'), SurrogatePolicy::Preserve) => {
+                    text.push('\\');
+                    if matches!(chars.peek(), Some(Ok('\\' | 'u') | Err(_))) {
+                        text.push('\\');
+                    }
+                }
+                (Ok(c), _) => text.push(c),
+                (Err(e), SurrogatePolicy::Preserve) => {
+                    text.push_str(&format!("\\u{:04X}", e.unpaired_surrogate()))
+                }
+                (Err(_), SurrogatePolicy::Replace) => text.push(char::REPLACEMENT_CHARACTER),
+                (Err(e), SurrogatePolicy::Error) => {
+                    return Err(Error::UnpairedSurrogate {
+                        key: key.to_owned(),
+                        unit: e.unpaired_surrogate(),
+                    })
+                }
+            }
+        }
+        Ok(text)
+    }
+
+    /// Convert text back to a value, turning escapes written by
+    /// [`SurrogatePolicy::Preserve`] back into their code unit
+    ///
+    /// Only escapes of a surrogate and doubled backslashes are read, any other backslash is kept
+    /// as text.
+    pub fn encode(self, text: &str) -> U16String {
+        if self != SurrogatePolicy::Preserve {
+            return U16String::from_str(text);
+        }
+        unescape(text)
+    }
+}
+
+/// Convert a value to text, preserving unpaired surrogates
+pub(crate) fn escape(value: &U16Str) -> String {
+    SurrogatePolicy::Preserve
+        .decode("", value)
+        .expect("preserving surrogates can't fail")
+}
+
+/// Convert text written by [`escape`] back to a value
+pub(crate) fn unescape(text: &str) -> U16String {
+    let mut value = U16String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('\\') {
+        value.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        if let Some(after) = rest.strip_prefix('\\') {
+            value.push_str("\\");
+            rest = after;
+            continue;
+        }
+
+        let surrogate = rest
+            .strip_prefix('u')
+            .and_then(|rest| rest.get(..4))
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|digits| u16::from_str_radix(digits, 16).ok())
+            .filter(|unit| (0xD800..=0xDFFF).contains(unit));
+        match surrogate {
+            Some(unit) => {
+                value.push_slice([unit]);
+                rest = &rest[5..];
+            }
+            None => value.push_str("\\"),
+        }
+    }
+    value.push_str(rest);
+    value
+}
@@ -0,0 +1,189 @@
+//! Types for converting string tables to and from text formats
+//!
+//! Every format shares one schema, so a table exported for translation can be edited in a
+//! normal text tool and imported again under the same IDs:
+//!
+//! ```json
+//! {
+//!   "flag": 1,
+//!   "max_index": 2,
+//!   "entries": [
+//!     { "id": 1, "key": "greeting", "value": "Hello there" },
+//!     { "id": 2, "key": "farewell", "value": "Goodbye" }
+//!   ]
+//! }
+//! ```
+//!
+//! JSON and YAML are available with the `json` and `yaml` features. CSV, with the `csv` feature,
+//! has an `id,key,value` row for every entry but no header fields, so an imported table has the
+//! default flag and continues numbering from its highest ID.
+//!
+//! Values with unpaired surrogates are preserved as escapes, see [`crate::surrogates`]. Use
+//! [`TextTable::with_policy`] to replace them or fail instead.
+
+use std::collections::HashSet;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    surrogates::{unescape, SurrogatePolicy},
+    types::StringTable,
+};
+
+/// A string table as it's written to a text format
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TextTable {
+    /// The unknown flag from the file header
+    pub flag: u8,
+    /// The highest ID handed out so far, including those of removed entries
+    pub max_index: u32,
+    /// Every entry, in table order
+    pub entries: Vec<TextEntry>,
+}
+
+/// A single entry of a [`TextTable`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TextEntry {
+    /// The ID the entry is stored under
+    pub id: u32,
+    /// The key the entry is looked up by
+    pub key: String,
+    /// The string itself
+    pub value: String,
+}
+
+impl TextTable {
+    /// Convert a table, handling values which aren't valid UTF-16 with the policy
+    pub fn with_policy(table: &StringTable, policy: SurrogatePolicy) -> Result<Self> {
+        Ok(Self {
+            flag: table.flag(),
+            max_index: table.max_index(),
+            entries: table
+                .entries_with_ids()
+                .map(|(id, key, value)| {
+                    Ok(TextEntry {
+                        id,
+                        key: key.to_owned(),
+                        value: policy.decode(key, value)?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Write the table as pretty printed JSON
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Write the table as YAML
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    /// Write the entries of the table as CSV, with an `id,key,value` header
+    #[cfg(feature = "csv")]
+    pub fn to_csv(&self) -> Result<String> {
+        // The header is written by hand so that a table without entries still has one
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(Vec::new());
+        writer.write_record(["id", "key", "value"])?;
+        for entry in &self.entries {
+            writer.serialize(entry)?;
+        }
+        let data = writer
+            .into_inner()
+            .map_err(|e| Error::IOError(e.into_error()))?;
+        Ok(String::from_utf8(data)?)
+    }
+}
+
+impl From<&StringTable> for TextTable {
+    fn from(table: &StringTable) -> Self {
+        Self::with_policy(table, SurrogatePolicy::Preserve)
+            .expect("preserving surrogates can't fail")
+    }
+}
+
+impl From<StringTable> for TextTable {
+    fn from(table: StringTable) -> Self {
+        Self::from(&table)
+    }
+}
+
+impl TryFrom<TextTable> for StringTable {
+    type Error = Error;
+
+    fn try_from(table: TextTable) -> Result<Self> {
+        let mut ids = HashSet::with_capacity(table.entries.len());
+        let mut keys = HashSet::with_capacity(table.entries.len());
+        for entry in &table.entries {
+            if !ids.insert(entry.id) {
+                return Err(Error::DuplicateId(entry.id));
+            }
+            if !keys.insert(entry.key.as_str()) {
+                return Err(Error::DuplicateKey(entry.key.clone()));
+            }
+        }
+
+        let entries = table
+            .entries
+            .into_iter()
+            .map(|entry| (entry.id, entry.key, unescape(&entry.value)))
+            .collect();
+        Ok(StringTable::with_ids(entries, table.max_index, table.flag))
+    }
+}
+
+impl StringTable {
+    /// Write the table as pretty printed JSON
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        TextTable::from(self).to_json()
+    }
+
+    /// Read a table written by [`StringTable::to_json`]
+    #[cfg(feature = "json")]
+    pub fn from_json(data: &str) -> Result<Self> {
+        serde_json::from_str::<TextTable>(data)?.try_into()
+    }
+
+    /// Write the table as YAML
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String> {
+        TextTable::from(self).to_yaml()
+    }
+
+    /// Read a table written by [`StringTable::to_yaml`]
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(data: &str) -> Result<Self> {
+        serde_yaml::from_str::<TextTable>(data)?.try_into()
+    }
+
+    /// Write the entries of the table as CSV, with an `id,key,value` header
+    #[cfg(feature = "csv")]
+    pub fn to_csv(&self) -> Result<String> {
+        TextTable::from(self).to_csv()
+    }
+
+    /// Read a table written by [`StringTable::to_csv`]
+    #[cfg(feature = "csv")]
+    pub fn from_csv(data: &str) -> Result<Self> {
+        let entries = csv::Reader::from_reader(data.as_bytes())
+            .deserialize()
+            .collect::<std::result::Result<Vec<TextEntry>, _>>()?;
+        TextTable {
+            flag: 1,
+            max_index: 0,
+            entries,
+        }
+        .try_into()
+    }
+}
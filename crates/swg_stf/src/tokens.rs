@@ -0,0 +1,281 @@
+//! Types for finding the tokens the client substitutes into strings
+//!
+//! Strings can hold prose tokens such as `%TU` and `%DI`, which the client replaces with a name
+//! or number, and color codes such as `\#ff0000` or `\#pcontrast1 `, with `\#.` returning to
+//! the previous color. A translation has to keep every token of the string it translates, which
+//! [`compare`] checks for a whole table, and [`plain_text`] renders a string the way a reader
+//! sees it, without the codes.
+
+use bon::Builder;
+use std::collections::BTreeMap;
+use widestring::U16String;
+
+use crate::{error::TokenIssue, types::StringTable};
+
+/// The prose tokens, each written as `%` followed by the token
+const PROSE_TOKENS: [&str; 5] = ["TU", "TT", "TO", "DI", "DF"];
+
+/// A run of plain text or a single token within a string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// Text which is shown as it is
+    Text(&'a str),
+    /// A prose token, including its `%`, e.g. `%TU`
+    Prose(&'a str),
+    /// A color code, including its `\#`, e.g. `\#ff0000`
+    Color(&'a str),
+    /// A color from the client's palette, including its `\#p` and the space ending the name,
+    /// e.g. `\#pcontrast1 `
+    Palette(&'a str),
+    /// `\#.`, which returns to the previous color
+    ColorReset(&'a str),
+    /// `\n`, written out as a marker for a line break
+    Newline(&'a str),
+    /// Something which looks like a token but isn't one, e.g. `%TX` or `\#ff00`
+    Malformed(&'a str),
+}
+
+impl<'a> Token<'a> {
+    /// The token as it's written in the string
+    pub fn as_str(&self) -> &'a str {
+        match self {
+            Token::Text(text)
+            | Token::Prose(text)
+            | Token::Color(text)
+            | Token::Palette(text)
+            | Token::ColorReset(text)
+            | Token::Newline(text)
+            | Token::Malformed(text) => text,
+        }
+    }
+
+    /// Whether the client substitutes the token, rather than showing it
+    pub fn is_placeholder(&self) -> bool {
+        matches!(
+            self,
+            Token::Prose(_) | Token::Color(_) | Token::Palette(_) | Token::ColorReset(_)
+        )
+    }
+}
+
+/// Split a string into its text and tokens
+///
+/// Only `%` followed by `T` or `D` and another capital letter is taken as a malformed prose
+/// token, so text such as `50%` is left alone.
+pub fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let token = match (bytes[index], bytes.get(index + 1)) {
+            (b'%', _) => prose(&text[index..]),
+            (b'\\', Some(b'#')) => Some(color(&text[index..])),
+            (b'\\', Some(b'n')) => Some(Token::Newline(&text[index..index + 2])),
+            _ => None,
+        };
+
+        let Some(token) = token else {
+            index += 1;
+            continue;
+        };
+        if start < index {
+            tokens.push(Token::Text(&text[start..index]));
+        }
+        index += token.as_str().len();
+        start = index;
+        tokens.push(token);
+    }
+
+    if start < text.len() {
+        tokens.push(Token::Text(&text[start..]));
+    }
+    tokens
+}
+
+/// Read the prose token at the start of the text, if there is one
+fn prose(text: &str) -> Option<Token<'_>> {
+    let code = text.get(1..3)?;
+    if PROSE_TOKENS.contains(&code) {
+        Some(Token::Prose(&text[..3]))
+    } else if code.starts_with(['T', 'D']) && code.bytes().all(|b| b.is_ascii_uppercase()) {
+        Some(Token::Malformed(&text[..3]))
+    } else {
+        None
+    }
+}
+
+/// Read the color code at the start of the text, which starts with `\#`
+fn color(text: &str) -> Token<'_> {
+    if text[2..].starts_with('.') {
+        return Token::ColorReset(&text[..3]);
+    }
+    if text[2..].starts_with('p') {
+        let name = text[3..]
+            .bytes()
+            .take_while(u8::is_ascii_alphanumeric)
+            .count();
+        return match (name, text.as_bytes().get(3 + name)) {
+            (0, _) => Token::Malformed(&text[..3]),
+            (name, Some(b' ')) => Token::Palette(&text[..4 + name]),
+            (name, _) => Token::Palette(&text[..3 + name]),
+        };
+    }
+
+    let digits = text[2..]
+        .bytes()
+        .take(6)
+        .take_while(u8::is_ascii_hexdigit)
+        .count();
+    match digits {
+        6 => Token::Color(&text[..8]),
+        digits => Token::Malformed(&text[..2 + digits]),
+    }
+}
+
+/// Count each placeholder of a string, ignoring the case of color codes
+fn placeholders(text: &str) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for token in tokenize(text).iter().filter(|token| token.is_placeholder()) {
+        let token = match token {
+            Token::Color(color) => color.to_ascii_lowercase(),
+            token => token.as_str().to_owned(),
+        };
+        *counts.entry(token).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Check that every entry of a translation has the same placeholders as the source, and no
+/// malformed ones
+///
+/// Placeholders can be in any order, and entries only in one of the tables are skipped. Issues
+/// are returned in key order.
+pub fn compare(source: &StringTable, translation: &StringTable) -> Vec<TokenIssue> {
+    let mut entries = translation
+        .iter()
+        .filter_map(|(key, translated)| Some((key, source.get(key)?, translated)))
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|(key, _, _)| *key);
+
+    let mut issues = Vec::new();
+    for (key, original, translated) in entries {
+        let original = original.to_string_lossy();
+        let translated = translated.to_string_lossy();
+
+        let expected = placeholders(&original);
+        let mut found = placeholders(&translated);
+        for (token, count) in expected {
+            let present = found.remove(&token).unwrap_or(0);
+            for _ in present..count {
+                issues.push(TokenIssue::Missing {
+                    key: key.clone(),
+                    token: token.clone(),
+                });
+            }
+            for _ in count..present {
+                issues.push(TokenIssue::Unexpected {
+                    key: key.clone(),
+                    token: token.clone(),
+                });
+            }
+        }
+        for (token, count) in found {
+            for _ in 0..count {
+                issues.push(TokenIssue::Unexpected {
+                    key: key.clone(),
+                    token: token.clone(),
+                });
+            }
+        }
+
+        issues.extend(
+            tokenize(&translated)
+                .into_iter()
+                .filter(|token| matches!(token, Token::Malformed(_)))
+                .map(|token| TokenIssue::Malformed {
+                    key: key.clone(),
+                    token: token.as_str().to_owned(),
+                }),
+        );
+    }
+    issues
+}
+
+/// How [`plain_text`] renders a string
+#[derive(Debug, Clone, Copy, Default, Builder)]
+pub struct PlainTextOptions {
+    /// Replace prose tokens with a readable placeholder, such as `<target>` for `%TT`
+    #[builder(default)]
+    pub expand_tokens: bool,
+
+    /// Put the string on a single line, with every line break replaced by a space
+    #[builder(default)]
+    pub single_line: bool,
+}
+
+/// Render a string as the text a reader sees, without its color codes
+///
+/// Line break markers become line breaks, and malformed tokens are kept as they're written.
+///
+/// ```
+/// use swg_stf::tokens::{plain_text, PlainTextOptions};
+/// use widestring::U16String;
+///
+/// let value = U16String::from_str(r"\#ff0000Warning:\#. %TT is\nhostile");
+/// let options = PlainTextOptions::builder()
+///     .expand_tokens(true)
+///     .single_line(true)
+///     .build();
+/// assert_eq!(plain_text(&value, options), "Warning: <target> is hostile");
+/// ```
+pub fn plain_text(value: &U16String, options: PlainTextOptions) -> String {
+    let value = value.to_string_lossy();
+    let mut text = String::with_capacity(value.len());
+
+    for token in tokenize(&value) {
+        match token {
+            Token::Color(_) | Token::Palette(_) | Token::ColorReset(_) => {}
+            Token::Prose(token) if options.expand_tokens => text.push_str(match token {
+                "%TU" => "<user>",
+                "%TT" => "<target>",
+                "%TO" => "<object>",
+                "%DI" => "<number>",
+                "%DF" => "<decimal>",
+                token => token,
+            }),
+            Token::Newline(_) => text.push('\n'),
+            token => text.push_str(token.as_str()),
+        }
+    }
+
+    if options.single_line {
+        text = text.lines().collect::<Vec<_>>().join(" ");
+    }
+    text
+}
+
+/// Split a string into its text and prose tokens, leaving color codes as text
+pub(crate) fn segments(text: &str) -> Vec<Token<'_>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+
+    for token in tokenize(text) {
+        let length = token.as_str().len();
+        if let Token::Prose(_) = token {
+            if start < offset {
+                segments.push(Token::Text(&text[start..offset]));
+            }
+            segments.push(token);
+            start = offset + length;
+        }
+        offset += length;
+    }
+
+    if start < text.len() {
+        segments.push(Token::Text(&text[start..]));
+    }
+    segments
+}
@@ -1,32 +1,295 @@
-use derive_more::derive::{Constructor, Deref};
-use std::collections::HashMap;
+use derive_more::derive::Deref;
+use indexmap::IndexMap;
+use std::{collections::HashMap, ops::Index};
 use widestring::U16String;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Constructor, Clone, Debug, PartialEq, Eq, Deref)]
+#[cfg(feature = "serde")]
+use crate::text::TextTable;
+
+use crate::error::{Error, Result};
+
+/// The entries of a string table, along with the ID each is stored under
+///
+/// Entries keep their ID across edits, and removed IDs aren't handed out again, so the client
+/// and tools which refer to entries by ID see the same numbering after a table is rewritten.
+/// Entries also keep the order they were read in, with new entries added at the end, and the
+/// header flag is carried through, so an unedited table is written back byte for byte.
+#[derive(Clone, Debug, Deref)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[cfg_attr(
-    feature = "serde",
-    serde(from = "HashMap<String, String>", into = "HashMap<String, String>")
-)]
-pub struct StringTable(HashMap<String, U16String>);
+#[cfg_attr(feature = "serde", serde(try_from = "TextTable", into = "TextTable"))]
+pub struct StringTable {
+    #[deref]
+    entries: IndexMap<String, U16String>,
+    ids: HashMap<String, u32>,
+    keys: HashMap<u32, String>,
+    /// Keys by their lowercase form, when lookups ignore case
+    folded: Option<HashMap<String, String>>,
+    max_index: u32,
+    flag: u8,
+}
 
-#[cfg(feature = "serde")]
-impl From<HashMap<String, String>> for StringTable {
-    fn from(value: HashMap<String, String>) -> Self {
-        Self::new(value.into_iter().map(|(k, v)| (k, v.into())).collect())
+impl StringTable {
+    /// Create a table from its entries, ordering and numbering them from 1 by key
+    pub fn new(entries: HashMap<String, U16String>) -> Self {
+        let mut entries = entries.into_iter().collect::<Vec<_>>();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let ids = entries
+            .iter()
+            .map(|(key, _)| key.clone())
+            .zip(1u32..)
+            .collect::<HashMap<_, _>>();
+        Self {
+            max_index: ids.len() as u32,
+            entries: entries.into_iter().collect(),
+            keys: ids.iter().map(|(key, id)| (*id, key.clone())).collect(),
+            ids,
+            folded: None,
+            flag: 1,
+        }
     }
-}
 
-#[cfg(feature = "serde")]
-impl From<StringTable> for HashMap<String, String> {
-    fn from(value: StringTable) -> Self {
+    /// Create a table from entries which already have IDs, in file order, as read from a file
+    pub(crate) fn with_ids(
+        entries: Vec<(u32, String, U16String)>,
+        max_index: u32,
+        flag: u8,
+    ) -> Self {
+        let mut table = Self {
+            entries: IndexMap::with_capacity(entries.len()),
+            ids: HashMap::with_capacity(entries.len()),
+            keys: HashMap::with_capacity(entries.len()),
+            folded: None,
+            max_index,
+            flag,
+        };
+        for (id, key, value) in entries {
+            table.max_index = table.max_index.max(id);
+            if let Some(previous) = table.ids.insert(key.clone(), id) {
+                table.keys.remove(&previous);
+            }
+            table.keys.insert(id, key.clone());
+            table.entries.insert(key, value);
+        }
+        table
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the table has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether the table has an entry with the key
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// The value of an entry by its key
+    pub fn get(&self, key: &str) -> Option<&U16String> {
+        self.entries.get(self.find(key)?)
+    }
+
+    /// Make [`StringTable::get`], [`StringTable::contains_key`] and [`StringTable::id`] ignore
+    /// the ASCII case of the key, as the client does
+    ///
+    /// Keys keep their casing when iterating and editing, which still needs the exact key. An
+    /// exact match is preferred, otherwise the first key in table order which only differs in
+    /// case is found.
+    pub fn set_case_insensitive(&mut self, enabled: bool) {
+        self.folded = enabled.then(|| {
+            let mut folded = HashMap::with_capacity(self.entries.len());
+            for key in self.entries.keys() {
+                folded
+                    .entry(key.to_ascii_lowercase())
+                    .or_insert_with(|| key.clone());
+            }
+            folded
+        });
+    }
+
+    /// Whether lookups ignore the case of the key
+    pub fn is_case_insensitive(&self) -> bool {
+        self.folded.is_some()
+    }
+
+    /// The key of the entry a lookup finds
+    fn find<'a>(&'a self, key: &'a str) -> Option<&'a str> {
+        if self.entries.contains_key(key) {
+            return Some(key);
+        }
+        let folded = self.folded.as_ref()?;
+        folded.get(&key.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    /// Point the lowercase form of a key at the first key in table order which has it
+    fn refold(&mut self, key: &str) {
+        let Some(folded) = &mut self.folded else {
+            return;
+        };
+
+        let lower = key.to_ascii_lowercase();
+        match self
+            .entries
+            .keys()
+            .find(|key| key.eq_ignore_ascii_case(&lower))
+        {
+            Some(key) => folded.insert(lower, key.clone()),
+            None => folded.remove(&lower),
+        };
+    }
+
+    /// The key and value of an entry by the ID it's stored under
+    pub fn by_id(&self, id: u32) -> Option<(&str, &U16String)> {
+        let key = self.keys.get(&id)?;
+        Some((key.as_str(), &self.entries[key]))
+    }
+
+    /// The ID an entry is stored under
+    pub fn id(&self, key: &str) -> Option<u32> {
+        self.ids.get(self.find(key)?).copied()
+    }
+
+    /// The highest ID handed out so far, including those of removed entries
+    pub fn max_index(&self) -> u32 {
+        self.max_index
+    }
+
+    /// The unknown flag from the file header, `1` for new tables
+    pub fn flag(&self) -> u8 {
+        self.flag
+    }
+
+    /// Every entry with its ID, in table order
+    pub fn entries_with_ids(&self) -> impl Iterator<Item = (u32, &str, &U16String)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (self.ids[key], key.as_str(), value))
+    }
+
+    /// Set the value of an entry, adding it under the next free ID if it's new
+    ///
+    /// Returns the ID of the entry, or [`Error::IdsExhausted`] if the entry is new but the max
+    /// index is already [`u32::MAX`].
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<U16String>) -> Result<u32> {
+        let key = key.into();
+        let id = match self.ids.get(&key) {
+            Some(id) => *id,
+            None => {
+                self.max_index = self
+                    .max_index
+                    .checked_add(1)
+                    .ok_or(Error::IdsExhausted(self.max_index))?;
+                self.ids.insert(key.clone(), self.max_index);
+                self.keys.insert(self.max_index, key.clone());
+                if let Some(folded) = &mut self.folded {
+                    // New entries go last, so an existing key keeps the lowercase form
+                    folded
+                        .entry(key.to_ascii_lowercase())
+                        .or_insert_with(|| key.clone());
+                }
+                self.max_index
+            }
+        };
+        self.entries.insert(key, value.into());
+        Ok(id)
+    }
+
+    /// Change the value of an existing entry, returning the value it had
+    pub fn update(&mut self, key: &str, value: impl Into<U16String>) -> Result<U16String> {
+        let entry = self
+            .entries
+            .get_mut(key)
+            .ok_or_else(|| Error::MissingKey(key.to_owned()))?;
+        Ok(std::mem::replace(entry, value.into()))
+    }
+
+    /// Remove an entry, its ID isn't reused
+    pub fn remove(&mut self, key: &str) -> Option<U16String> {
+        if let Some(id) = self.ids.remove(key) {
+            self.keys.remove(&id);
+        }
+        let value = self.entries.shift_remove(key);
+        self.refold(key);
         value
-            .0
-            .into_iter()
-            .map(|(k, v)| (k, v.to_string_lossy()))
-            .collect()
+    }
+
+    /// Give an entry a new key, keeping its value, ID and position
+    ///
+    /// Renaming an entry to the key it already has does nothing.
+    pub fn rename(&mut self, from: &str, to: impl Into<String>) -> Result<()> {
+        let to = to.into();
+        if from == to && self.entries.contains_key(from) {
+            return Ok(());
+        }
+        if self.entries.contains_key(&to) {
+            return Err(Error::DuplicateKey(to));
+        }
+        let (index, _, value) = self
+            .entries
+            .shift_remove_full(from)
+            .ok_or_else(|| Error::MissingKey(from.to_owned()))?;
+        let id = self.ids.remove(from).expect("every entry has an id");
+
+        self.ids.insert(to.clone(), id);
+        self.keys.insert(id, to.clone());
+        let (last, _) = self.entries.insert_full(to.clone(), value);
+        self.entries.move_index(last, index);
+        self.refold(from);
+        self.refold(&to);
+        Ok(())
+    }
+}
+
+impl<K: Into<String>, V: Into<U16String>> FromIterator<(K, V)> for StringTable {
+    /// Create a table from its entries, ordering and numbering them from 1 by key like
+    /// [`StringTable::new`]
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(entries: I) -> Self {
+        Self::new(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+impl PartialEq for StringTable {
+    /// Tables are equal when they have the same entries under the same IDs and header, whether or
+    /// not lookups ignore case
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+            && self.ids == other.ids
+            && self.max_index == other.max_index
+            && self.flag == other.flag
+    }
+}
+
+impl Eq for StringTable {}
+
+impl Index<&str> for StringTable {
+    type Output = U16String;
+
+    /// The value of an entry by its key, ignoring case like [`StringTable::get`] when enabled
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table has no entry with the key.
+    fn index(&self, key: &str) -> &U16String {
+        self.get(key)
+            .unwrap_or_else(|| panic!("no entry with the key {}", key))
+    }
+}
+
+impl Default for StringTable {
+    fn default() -> Self {
+        Self::new(HashMap::new())
     }
 }
@@ -0,0 +1,94 @@
+//! Types for writing string table files
+//!
+
+use bon::Builder;
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::io::Write;
+use tracing::instrument;
+
+use crate::{error::Result, types::StringTable};
+
+/// Options for the header fields of a written STF file
+///
+/// Each field defaults to the value the table holds, which is what the file it was read from
+/// had. Overriding them reproduces a file exactly, or lets the meaning of the flag be explored.
+#[derive(Debug, Clone, Copy, Default, Builder)]
+pub struct StringTableWriterOptions {
+    /// The unknown flag after the magic number, in place of [`StringTable::flag`]
+    pub flag: Option<u8>,
+
+    /// The highest ID handed out so far, in place of [`StringTable::max_index`]
+    ///
+    /// It's written as given, even when it's lower than an ID in the table.
+    pub max_index: Option<u32>,
+}
+
+/// STF file writer
+///
+/// ```no_run
+/// use std::{collections::HashMap, fs::File};
+/// use swg_stf::{types::StringTable, StringTableWriter};
+///
+/// fn write_table() -> swg_stf::error::Result<()> {
+///     let table = StringTable::new(HashMap::from([("test".to_owned(), "testing".into())]));
+///     StringTableWriter::encode(&table, File::create("single_entry.stf")?)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub struct StringTableWriter {}
+
+impl StringTableWriter {
+    /// Write a STF file, with each entry under the ID the table has for it.
+    ///
+    /// Values and keys are both written in table order, so a table read from a file is written
+    /// back unchanged.
+    pub fn encode<W: Write>(table: &StringTable, writer: W) -> Result<()> {
+        Self::encode_with_options(table, writer, &StringTableWriterOptions::default())
+    }
+
+    /// Write a STF file like [`StringTableWriter::encode`], with header fields from the options
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use swg_stf::{types::StringTable, write::StringTableWriterOptions, StringTableWriter};
+    ///
+    /// fn write_retail(table: &StringTable) -> swg_stf::error::Result<()> {
+    ///     let options = StringTableWriterOptions::builder().flag(0).build();
+    ///     StringTableWriter::encode_with_options(table, File::create("retail.stf")?, &options)?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[instrument(skip_all, err, fields(count = table.len()))]
+    pub fn encode_with_options<W: Write>(
+        table: &StringTable,
+        mut writer: W,
+        options: &StringTableWriterOptions,
+    ) -> Result<()> {
+        let entries = table.entries_with_ids().collect::<Vec<_>>();
+
+        writer.write_u32::<LittleEndian>(0x0000ABCD)?;
+        writer.write_u8(options.flag.unwrap_or(table.flag()))?;
+        writer.write_u32::<LittleEndian>(options.max_index.unwrap_or(table.max_index()))?;
+        writer.write_u32::<LittleEndian>(entries.len() as u32)?;
+
+        for (id, _, value) in &entries {
+            writer.write_u32::<LittleEndian>(*id)?;
+            writer.write_u32::<LittleEndian>(u32::MAX)?;
+            writer.write_u32::<LittleEndian>(value.len() as u32)?;
+            for rune in value.as_slice() {
+                writer.write_u16::<LittleEndian>(*rune)?;
+            }
+        }
+
+        for (id, key, _) in &entries {
+            writer.write_u32::<LittleEndian>(*id)?;
+            writer.write_u32::<LittleEndian>(key.len() as u32)?;
+            writer.write_all(key.as_bytes())?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,430 @@
+//! Types for exchanging string tables with translation tools as XLIFF
+//!
+//! A [`XliffDocument`] holds one unit per entry of a source table, along with its translation
+//! and translation state. Documents can be written as XLIFF 1.2 or 2.0, and either version can be
+//! read back. Entries are identified by their key, and prose tokens such as `%TU` are written as
+//! placeholders so translation tools keep them intact.
+//!
+//! ```no_run
+//! use swg_stf::{types::StringTable, xliff::{XliffDocument, XliffVersion}};
+//!
+//! fn export(english: &StringTable, german: &StringTable) -> String {
+//!     XliffDocument::new(XliffVersion::V2_0, "string/en/quest.stf", "en", english)
+//!         .with_target("de", german)
+//!         .to_xml()
+//! }
+//! ```
+
+use quick_xml::{escape::escape, events::Event, Reader};
+use std::fmt::Write;
+
+use crate::{
+    error::{Error, Result},
+    surrogates::{self, unescape},
+    tokens::{segments, Token},
+    types::StringTable,
+};
+
+/// The version of XLIFF a document is written as
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum XliffVersion {
+    V1_2,
+    #[default]
+    V2_0,
+}
+
+/// How far along the translation of a unit is
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TranslationState {
+    /// Not translated yet
+    #[default]
+    Initial,
+    /// Translated but not reviewed
+    Translated,
+    /// Translated and reviewed
+    Reviewed,
+    /// Finished, and not to be changed
+    Final,
+}
+
+impl TranslationState {
+    fn v1_2(self) -> &'static str {
+        match self {
+            TranslationState::Initial => "new",
+            TranslationState::Translated => "translated",
+            TranslationState::Reviewed => "signed-off",
+            TranslationState::Final => "final",
+        }
+    }
+
+    fn v2_0(self) -> &'static str {
+        match self {
+            TranslationState::Initial => "initial",
+            TranslationState::Translated => "translated",
+            TranslationState::Reviewed => "reviewed",
+            TranslationState::Final => "final",
+        }
+    }
+
+    /// Parse the state of either version, treating states which need more work as the step
+    /// before them
+    fn parse(state: &str) -> Result<Self> {
+        match state {
+            "new" | "initial" | "needs-translation" | "needs-adaptation" | "needs-l10n" => {
+                Ok(TranslationState::Initial)
+            }
+            "translated"
+            | "needs-review-translation"
+            | "needs-review-adaptation"
+            | "needs-review-l10n" => Ok(TranslationState::Translated),
+            "signed-off" | "reviewed" => Ok(TranslationState::Reviewed),
+            "final" => Ok(TranslationState::Final),
+            state => Err(Error::InvalidXliff(format!(
+                "unknown translation state {}",
+                state
+            ))),
+        }
+    }
+}
+
+/// A single entry and its translation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XliffUnit {
+    /// The key of the entry
+    pub key: String,
+    /// The string in the source language
+    pub source: String,
+    /// The string in the target language, if it has been translated
+    pub target: Option<String>,
+    /// How far along the translation is
+    pub state: TranslationState,
+}
+
+/// A string table and its translation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XliffDocument {
+    /// The version the document is written as
+    pub version: XliffVersion,
+    /// The path of the string table, e.g. `string/en/quest.stf`
+    pub original: String,
+    /// The language of the sources, e.g. `en`
+    pub source_language: String,
+    /// The language of the targets, e.g. `de`
+    pub target_language: Option<String>,
+    /// Every entry, in table order
+    pub units: Vec<XliffUnit>,
+}
+
+impl XliffDocument {
+    /// Create a document with a unit for every entry of the source table, in table order
+    pub fn new(
+        version: XliffVersion,
+        original: impl Into<String>,
+        source_language: impl Into<String>,
+        source: &StringTable,
+    ) -> Self {
+        Self {
+            version,
+            original: original.into(),
+            source_language: source_language.into(),
+            target_language: None,
+            units: source
+                .iter()
+                .map(|(key, value)| XliffUnit {
+                    key: key.clone(),
+                    source: surrogates::escape(value),
+                    target: None,
+                    state: TranslationState::Initial,
+                })
+                .collect(),
+        }
+    }
+
+    /// Fill in the translation of every unit the target table has an entry for
+    pub fn with_target(mut self, target_language: impl Into<String>, target: &StringTable) -> Self {
+        self.target_language = Some(target_language.into());
+        for unit in &mut self.units {
+            if let Some(value) = target.get(&unit.key) {
+                unit.target = Some(surrogates::escape(value));
+                unit.state = TranslationState::Translated;
+            }
+        }
+        self
+    }
+
+    /// The sources as a table, numbered from 1 in unit order
+    pub fn source_table(&self) -> StringTable {
+        let mut table = StringTable::default();
+        for unit in &self.units {
+            table
+                .insert(unit.key.clone(), unescape(&unit.source))
+                .expect("a new table has an ID for every unit");
+        }
+        table
+    }
+
+    /// The translations which have reached at least the given state as a table, numbered from 1
+    /// in unit order
+    pub fn target_table(&self, state: TranslationState) -> StringTable {
+        let mut table = StringTable::default();
+        for unit in self.units.iter().filter(|unit| unit.state >= state) {
+            if let Some(target) = &unit.target {
+                table
+                    .insert(unit.key.clone(), unescape(target))
+                    .expect("a new table has an ID for every unit");
+            }
+        }
+        table
+    }
+
+    /// Write the document as XML
+    pub fn to_xml(&self) -> String {
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let target_language = self.target_language.as_deref().map(escape);
+
+        let _ = match self.version {
+            XliffVersion::V1_2 => writeln!(
+                output,
+                "<xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">\n  \
+                 <file original=\"{}\" source-language=\"{}\"{} datatype=\"plaintext\">\n    \
+                 <body>",
+                escape(&self.original),
+                escape(&self.source_language),
+                target_language
+                    .map(|language| format!(" target-language=\"{}\"", language))
+                    .unwrap_or_default(),
+            ),
+            XliffVersion::V2_0 => writeln!(
+                output,
+                "<xliff version=\"2.0\" xmlns=\"urn:oasis:names:tc:xliff:document:2.0\" \
+                 srcLang=\"{}\"{}>\n  <file id=\"f1\" original=\"{}\">",
+                escape(&self.source_language),
+                target_language
+                    .map(|language| format!(" trgLang=\"{}\"", language))
+                    .unwrap_or_default(),
+                escape(&self.original),
+            ),
+        };
+
+        for unit in &self.units {
+            let key = escape(&unit.key);
+            let source = placeholders(&unit.source, self.version);
+            let target = unit
+                .target
+                .as_deref()
+                .map(|target| placeholders(target, self.version));
+
+            let _ = match self.version {
+                XliffVersion::V1_2 => {
+                    let _ = writeln!(
+                        output,
+                        "      <trans-unit id=\"{}\" resname=\"{}\" xml:space=\"preserve\">\n        \
+                         <source>{}</source>",
+                        key, key, source
+                    );
+                    if let Some(target) = target {
+                        let _ = writeln!(
+                            output,
+                            "        <target state=\"{}\">{}</target>",
+                            unit.state.v1_2(),
+                            target
+                        );
+                    }
+                    writeln!(output, "      </trans-unit>")
+                }
+                XliffVersion::V2_0 => {
+                    let _ = writeln!(
+                        output,
+                        "    <unit id=\"{}\" xml:space=\"preserve\">\n      \
+                         <segment state=\"{}\">\n        <source>{}</source>",
+                        key,
+                        unit.state.v2_0(),
+                        source
+                    );
+                    if let Some(target) = target {
+                        let _ = writeln!(output, "        <target>{}</target>", target);
+                    }
+                    writeln!(output, "      </segment>\n    </unit>")
+                }
+            };
+        }
+
+        let _ = match self.version {
+            XliffVersion::V1_2 => writeln!(output, "    </body>\n  </file>\n</xliff>"),
+            XliffVersion::V2_0 => writeln!(output, "  </file>\n</xliff>"),
+        };
+        output
+    }
+
+    /// Read a XLIFF 1.2 or 2.0 document, every `file` element is read into the same document
+    pub fn from_xml(data: &str) -> Result<Self> {
+        let mut reader = Reader::from_str(data);
+        reader.config_mut().trim_text(false);
+
+        let mut document = None::<Self>;
+        let mut unit = None::<XliffUnit>;
+        // The text being read, and whether it's the target
+        let mut text = None::<(String, bool)>;
+
+        loop {
+            let event = reader.read_event()?;
+            match &event {
+                Event::Start(e) | Event::Empty(e) => {
+                    let attribute = |name: &str| -> Result<Option<String>> {
+                        Ok(
+                            match e.try_get_attribute(name).map_err(quick_xml::Error::from)? {
+                                Some(attribute) => Some(attribute.unescape_value()?.into_owned()),
+                                None => None,
+                            },
+                        )
+                    };
+
+                    match e.local_name().as_ref() {
+                        b"xliff" => {
+                            let version = match attribute("version")?.as_deref() {
+                                Some("1.2") => XliffVersion::V1_2,
+                                Some(version) if version.starts_with("2.") => XliffVersion::V2_0,
+                                version => {
+                                    return Err(Error::InvalidXliff(format!(
+                                        "unsupported version {}",
+                                        version.unwrap_or("(none)")
+                                    )))
+                                }
+                            };
+                            document = Some(Self {
+                                version,
+                                original: String::new(),
+                                source_language: attribute("srcLang")?.unwrap_or_default(),
+                                target_language: attribute("trgLang")?,
+                                units: Vec::new(),
+                            });
+                        }
+                        b"file" => {
+                            let document = document.as_mut().ok_or_else(missing_root)?;
+                            if let Some(original) = attribute("original")? {
+                                document.original = original;
+                            }
+                            if let Some(language) = attribute("source-language")? {
+                                document.source_language = language;
+                            }
+                            if let Some(language) = attribute("target-language")? {
+                                document.target_language = Some(language);
+                            }
+                        }
+                        b"trans-unit" | b"unit" => {
+                            let key = match attribute("resname")? {
+                                Some(key) => key,
+                                None => attribute("id")?.ok_or_else(|| {
+                                    Error::InvalidXliff("a unit has no id".to_owned())
+                                })?,
+                            };
+                            unit = Some(XliffUnit {
+                                key,
+                                source: String::new(),
+                                target: None,
+                                state: TranslationState::Initial,
+                            });
+                        }
+                        b"segment" => {
+                            if let (Some(unit), Some(state)) = (&mut unit, attribute("state")?) {
+                                unit.state = TranslationState::parse(&state)?;
+                            }
+                        }
+                        b"source" if unit.is_some() => text = Some((String::new(), false)),
+                        b"target" if unit.is_some() => {
+                            // XLIFF 1.2 keeps the state on the target, where it's optional
+                            let version = document.as_ref().map(|document| document.version);
+                            if let (Some(unit), Some(XliffVersion::V1_2)) = (&mut unit, version) {
+                                unit.state = match attribute("state")? {
+                                    Some(state) => TranslationState::parse(&state)?,
+                                    None => TranslationState::Translated,
+                                };
+                            }
+                            text = Some((String::new(), true));
+                        }
+                        // XLIFF 2.0 placeholders are empty, with the original text as `equiv`
+                        b"ph" => {
+                            if let (Some((text, _)), Some(equiv)) = (&mut text, attribute("equiv")?)
+                            {
+                                text.push_str(&equiv);
+                            }
+                        }
+                        _ => {}
+                    }
+
+                    // An empty source or target has no end event
+                    if matches!(event, Event::Empty(_)) {
+                        finish_text(&mut unit, &mut text, e.local_name().as_ref());
+                    }
+                }
+                Event::Text(e) => {
+                    if let Some((text, _)) = &mut text {
+                        text.push_str(&e.unescape()?);
+                    }
+                }
+                Event::CData(e) => {
+                    if let Some((text, _)) = &mut text {
+                        text.push_str(&String::from_utf8_lossy(e));
+                    }
+                }
+                Event::End(e) => match e.local_name().as_ref() {
+                    b"trans-unit" | b"unit" => {
+                        if let Some(unit) = unit.take() {
+                            document.as_mut().ok_or_else(missing_root)?.units.push(unit);
+                        }
+                    }
+                    name => finish_text(&mut unit, &mut text, name),
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        document.ok_or_else(missing_root)
+    }
+}
+
+fn missing_root() -> Error {
+    Error::InvalidXliff("the document has no xliff element".to_owned())
+}
+
+/// Add the text being read to the unit when its source or target ends
+///
+/// XLIFF 2.0 splits units into segments, so the sources and targets of each are joined.
+fn finish_text(unit: &mut Option<XliffUnit>, text: &mut Option<(String, bool)>, name: &[u8]) {
+    if !matches!(name, b"source" | b"target") {
+        return;
+    }
+    let (Some(unit), Some((text, target))) = (unit, text.take()) else {
+        return;
+    };
+
+    if target {
+        unit.target.get_or_insert_with(String::new).push_str(&text);
+    } else {
+        unit.source.push_str(&text);
+    }
+}
+
+/// Escape a string, writing its prose tokens as placeholders
+fn placeholders(text: &str, version: XliffVersion) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut id = 0;
+    for segment in segments(text) {
+        if matches!(segment, Token::Prose(_)) {
+            id += 1;
+        }
+        let _ = match (segment, version) {
+            (Token::Prose(token), XliffVersion::V1_2) => {
+                write!(output, "<ph id=\"{}\">{}</ph>", id, token)
+            }
+            (Token::Prose(token), XliffVersion::V2_0) => write!(
+                output,
+                "<ph id=\"{}\" equiv=\"{}\" disp=\"{}\"/>",
+                id, token, token
+            ),
+            (text, _) => write!(output, "{}", escape(text.as_str())),
+        };
+    }
+    output
+}
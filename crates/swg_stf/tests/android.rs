@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use swg_stf::types::StringTable;
+use widestring::U16String;
+
+#[test]
+fn write_android_strings() {
+    let stf = StringTable::new(HashMap::from([
+        ("greeting".to_owned(), U16String::from_str("Hello %TU")),
+        (
+            "a".to_owned(),
+            U16String::from_str("Don't say \"<b>\" & \\ or\nthat"),
+        ),
+        ("b-c".to_owned(), U16String::from_str("  spaced")),
+        (
+            "b_c".to_owned(),
+            U16String::from_str("@string/not_a_reference"),
+        ),
+        ("9lives".to_owned(), U16String::from_str("ok? yes")),
+    ]));
+
+    assert_eq!(
+        stf.to_android_strings(),
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+            "<resources>\n",
+            "    <!-- key: 9lives -->\n",
+            "    <string name=\"_9lives\">ok? yes</string>\n",
+            "    <string name=\"a\">Don\\'t say \\\"&lt;b&gt;\\\" &amp; \\\\ or\\nthat</string>\n",
+            "    <!-- key: b-c -->\n",
+            "    <string name=\"b_c\">\"  spaced\"</string>\n",
+            "    <!-- key: b_c -->\n",
+            "    <string name=\"b_c_2\">\\@string/not_a_reference</string>\n",
+            "    <string name=\"greeting\" formatted=\"false\">Hello %TU</string>\n",
+            "</resources>\n",
+        )
+    );
+}
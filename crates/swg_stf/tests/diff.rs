@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use swg_stf::diff::{ChangedEntry, DiffEntry};
+use swg_stf::types::StringTable;
+use widestring::U16String;
+
+#[test]
+fn diff_tables() {
+    let old = StringTable::new(HashMap::from([
+        ("kept".to_owned(), U16String::from_str("same")),
+        ("changed".to_owned(), U16String::from_str("before")),
+        ("removed".to_owned(), U16String::from_str("gone")),
+    ]));
+    let mut new = old.clone();
+    new.update("changed", U16String::from_str("after")).unwrap();
+    new.remove("removed");
+    new.insert("added", U16String::from_str("fresh")).unwrap();
+
+    let diff = old.diff(&new);
+    assert_eq!(
+        diff.added,
+        [DiffEntry {
+            key: "added".to_owned(),
+            value: U16String::from_str("fresh")
+        }]
+    );
+    assert_eq!(
+        diff.removed,
+        [DiffEntry {
+            key: "removed".to_owned(),
+            value: U16String::from_str("gone")
+        }]
+    );
+    assert_eq!(
+        diff.changed,
+        [ChangedEntry {
+            key: "changed".to_owned(),
+            old: U16String::from_str("before"),
+            new: U16String::from_str("after")
+        }]
+    );
+
+    // IDs and order aren't compared
+    let mut renumbered = StringTable::default();
+    for (key, value) in old.iter().rev() {
+        renumbered.insert(key.clone(), value.clone()).unwrap();
+    }
+    assert!(old.diff(&renumbered).is_empty());
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use swg_stf::error::{Error, Result};
+use swg_stf::types::StringTable;
+use widestring::U16String;
+
+#[test]
+fn fluent_export() {
+    let stf = StringTable::new(HashMap::from([
+        (
+            "greeting".to_owned(),
+            U16String::from_str("Hello %TU, you owe %DI credits."),
+        ),
+        ("multi".to_owned(), U16String::from_str("first\n\n[second]")),
+        ("braces".to_owned(), U16String::from_str(" {odd} ")),
+        ("1.bad key".to_owned(), U16String::from_str("")),
+    ]));
+
+    assert_eq!(
+        stf.to_fluent(),
+        concat!(
+            "# key: 1.bad key\n",
+            "k1_bad_key = {\"\"}\n",
+            "braces = {\" \"}{\"{\"}odd{\"}\"}{\" \"}\n",
+            "greeting = Hello { $TU }, you owe { $DI } credits.\n",
+            "multi =\n",
+            "    first\n",
+            "    {\"\"}\n",
+            "    {\"[\"}second]\n",
+        )
+    );
+}
+
+#[test]
+fn fluent_round_trip() -> Result<()> {
+    let stf = StringTable::new(HashMap::from([
+        (
+            "greeting".to_owned(),
+            U16String::from_str("Hello %TU, you owe %DI credits."),
+        ),
+        (
+            "multi".to_owned(),
+            U16String::from_str("first\n\n  [second]\n"),
+        ),
+        (
+            "braces".to_owned(),
+            U16String::from_str(" {odd} \"quoted\" \\ \t"),
+        ),
+        ("1.bad key".to_owned(), U16String::from_str("")),
+        ("a.b".to_owned(), U16String::from_str("collides")),
+        ("a_b".to_owned(), U16String::from_str("with this")),
+    ]));
+
+    let decoded = StringTable::from_fluent(&stf.to_fluent())?;
+    assert_eq!(decoded, stf);
+
+    Ok(())
+}
+
+#[test]
+fn fluent_import() -> Result<()> {
+    let stf = StringTable::from_fluent(concat!(
+        "### Resource comment\n",
+        "-brand = Galaxies\n",
+        "\n",
+        "# A message\n",
+        "welcome = Welcome to\n",
+        "      the { $TT }\n",
+        "    galaxy\n",
+        "    .title = ignored\n",
+        "\n",
+        "literal = {\"\\u0041\"} and %DF\n",
+    ))?;
+
+    assert_eq!(
+        stf.entries_with_ids()
+            .map(|(id, key, value)| (id, key, value.to_string_lossy()))
+            .collect::<Vec<_>>(),
+        [
+            (1, "welcome", "Welcome to\n  the %TT\ngalaxy".to_owned()),
+            (2, "literal", "A and %DF".to_owned()),
+        ]
+    );
+
+    assert!(matches!(
+        StringTable::from_fluent("select = { $n ->\n *[other] many\n}\n"),
+        Err(Error::Fluent { line: 1, .. })
+    ));
+    assert!(matches!(
+        StringTable::from_fluent("a = one\na = two\n"),
+        Err(Error::DuplicateKey(_))
+    ));
+
+    Ok(())
+}
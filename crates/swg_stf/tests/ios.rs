@@ -0,0 +1,23 @@
+use std::collections::HashMap;
+
+use swg_stf::types::StringTable;
+use widestring::U16String;
+
+#[test]
+fn write_ios_strings() {
+    let stf = StringTable::new(HashMap::from([
+        ("greeting".to_owned(), U16String::from_str("Hello %TU")),
+        (
+            "quoted \"key\"".to_owned(),
+            U16String::from_str("Say \"hi\"\\\n\tnow\u{7}"),
+        ),
+    ]));
+
+    assert_eq!(
+        stf.to_ios_strings(),
+        concat!(
+            "\"greeting\" = \"Hello %TU\";\n",
+            "\"quoted \\\"key\\\"\" = \"Say \\\"hi\\\"\\\\\\n\\tnow\\U0007\";\n",
+        )
+    );
+}
@@ -1,10 +1,14 @@
+use std::collections::HashMap;
 use std::fs::File;
+use std::io::Cursor;
 use std::path::PathBuf;
 
-use swg_stf::error::Result;
+use swg_stf::error::{Error, Result, ValidationIssue};
 use swg_stf::read::StringTableReader;
+use swg_stf::types::StringTable;
+use swg_stf::write::StringTableWriter;
 use tracing_test::traced_test;
-use widestring::u16cstr;
+use widestring::{u16cstr, U16String};
 
 #[traced_test]
 #[test]
@@ -27,3 +31,133 @@ fn parse_stf() -> Result<()> {
 
     Ok(())
 }
+
+#[traced_test]
+#[test]
+fn stream_entries() -> Result<()> {
+    let mut stf = StringTable::new(HashMap::from([
+        ("b".to_owned(), U16String::from_str("second")),
+        ("a".to_owned(), U16String::from_str("first")),
+        ("c".to_owned(), U16String::from_str("third")),
+    ]));
+    stf.remove("b");
+
+    let mut data = Vec::new();
+    StringTableWriter::encode(&stf, &mut data)?;
+
+    let entries = StringTableReader::entries(Cursor::new(&data))?;
+    assert_eq!((entries.entry_count(), entries.max_index()), (2, 3));
+    assert_eq!(
+        entries.collect::<Result<Vec<_>>>()?,
+        [
+            ("a".to_owned(), U16String::from_str("first")),
+            ("c".to_owned(), U16String::from_str("third"))
+        ]
+    );
+
+    // A truncated file stops with an error
+    data.truncate(data.len() - 1);
+    let entries = StringTableReader::entries(Cursor::new(&data))?;
+    assert!(entries.last().is_some_and(|entry| entry.is_err()));
+
+    Ok(())
+}
+
+/// Build a file by hand, with values and keys which needn't match up
+fn raw_stf(max_index: u32, count: u32, values: &[(u32, &[u16])], keys: &[(u32, &[u8])]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend(0x0000ABCDu32.to_le_bytes());
+    data.push(1);
+    data.extend(max_index.to_le_bytes());
+    data.extend(count.to_le_bytes());
+    for (id, value) in values {
+        data.extend(id.to_le_bytes());
+        data.extend(u32::MAX.to_le_bytes());
+        data.extend((value.len() as u32).to_le_bytes());
+        data.extend(value.iter().flat_map(|rune| rune.to_le_bytes()));
+    }
+    for (id, key) in keys {
+        data.extend(id.to_le_bytes());
+        data.extend((key.len() as u32).to_le_bytes());
+        data.extend(*key);
+    }
+    data
+}
+
+#[traced_test]
+#[test]
+fn validate_stf() -> Result<()> {
+    let valid = raw_stf(2, 2, &[(1, &[0x61]), (2, &[0x62])], &[(1, b"a"), (2, b"b")]);
+    assert_eq!(StringTableReader::validate(Cursor::new(&valid))?, []);
+    assert_eq!(
+        StringTableReader::decode_strict(Cursor::new(&valid))?.len(),
+        2
+    );
+
+    let data = raw_stf(
+        3,
+        4,
+        &[(1, &[0x61]), (1, &[0xD800]), (4, &[]), (5, &[])],
+        &[(1, b"a"), (2, b"a"), (4, &[0xFF]), (6, b"orphan")],
+    );
+    assert_eq!(
+        StringTableReader::validate(Cursor::new(&data))?,
+        [
+            ValidationIssue::DuplicateValueId(1),
+            ValidationIssue::DuplicateKey("a".to_owned()),
+            ValidationIssue::ValueWithoutKey(5),
+            ValidationIssue::KeyWithoutValue {
+                id: 2,
+                key: "a".to_owned()
+            },
+            ValidationIssue::KeyWithoutValue {
+                id: 6,
+                key: "orphan".to_owned()
+            },
+            ValidationIssue::InvalidUtf16(1),
+            ValidationIssue::InvalidUtf8(4),
+            ValidationIssue::IdAboveMaxIndex {
+                id: 4,
+                max_index: 3
+            },
+            ValidationIssue::IdAboveMaxIndex {
+                id: 5,
+                max_index: 3
+            },
+            ValidationIssue::IdAboveMaxIndex {
+                id: 6,
+                max_index: 3
+            },
+        ]
+    );
+    assert!(matches!(
+        StringTableReader::decode_strict(Cursor::new(&data)),
+        Err(Error::Validation(issues)) if issues.len() == 10
+    ));
+
+    // The header counts one entry too few, then one too many
+    let short = raw_stf(2, 1, &[(1, &[0x61])], &[(1, b"a"), (2, b"b")]);
+    assert_eq!(
+        StringTableReader::validate(Cursor::new(&short))?,
+        [ValidationIssue::TrailingData(9)]
+    );
+    let long = raw_stf(2, 2, &[(1, &[0x61]), (2, &[0x62])], &[(1, b"a")]);
+    assert_eq!(
+        StringTableReader::validate(Cursor::new(&long))?,
+        [
+            ValidationIssue::Truncated { count: 2 },
+            ValidationIssue::ValueWithoutKey(2)
+        ]
+    );
+
+    // A huge entry count is reported rather than allocated up front
+    let huge = raw_stf(0, 0xFFFFFFF0, &[], &[]);
+    assert_eq!(
+        StringTableReader::validate(Cursor::new(&huge))?,
+        [ValidationIssue::Truncated { count: 0xFFFFFFF0 }]
+    );
+    assert!(StringTableReader::decode(Cursor::new(&huge)).is_err());
+    assert!(StringTableReader::entries(Cursor::new(&huge)).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use swg_stf::diff::StfDiff;
+use swg_stf::report::DiffReport;
+use swg_stf::types::StringTable;
+
+#[test]
+fn html_report() {
+    let old = StringTable::from_iter([
+        ("kept", "same"),
+        ("gone", "<b>removed</b>"),
+        ("edited", "Deal 50 damage"),
+    ]);
+    let new = StringTable::from_iter([
+        ("kept", "same"),
+        ("new", "Tom & Jerry"),
+        ("edited", "Deal 75 damage"),
+    ]);
+
+    let mut report = DiffReport::new("Publish <14>");
+    report.add("ui/skills", old.diff(&new));
+    report.add("unchanged", StfDiff::default());
+    let html = report.to_html();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<h1>Publish &lt;14&gt;</h1>"));
+    assert!(html.contains("1 tables differ: 1 added, 1 removed, 1 changed"));
+    assert!(html.contains("<summary>ui/skills "));
+    assert!(!html.contains("unchanged"));
+
+    assert!(html.contains(
+        "<tr class=\"added\"><td>added</td><td>new</td><td></td><td>Tom &amp; Jerry</td></tr>"
+    ));
+    assert!(html.contains("<td>&lt;b&gt;removed&lt;/b&gt;</td><td></td></tr>"));
+    assert!(html.contains("<td>Deal <del>50</del> damage</td><td>Deal <ins>75</ins> damage</td>"));
+    assert!(!html.contains("<td>kept</td>"));
+}
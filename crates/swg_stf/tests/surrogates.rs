@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use swg_stf::error::{Error, Result};
+use swg_stf::surrogates::SurrogatePolicy;
+use swg_stf::text::TextTable;
+use swg_stf::types::StringTable;
+use widestring::{U16Str, U16String};
+
+/// `a`, an unpaired high surrogate, then a valid pair
+const BROKEN: &[u16] = &[0x61, 0xD800, 0xD83D, 0xDE00];
+
+#[test]
+fn surrogate_policies() -> Result<()> {
+    let value = U16Str::from_slice(BROKEN);
+
+    assert_eq!(
+        SurrogatePolicy::Preserve.decode("key", value)?,
+        "a\\uD800\u{1F600}"
+    );
+    assert_eq!(
+        SurrogatePolicy::Replace.decode("key", value)?,
+        "a\u{FFFD}\u{1F600}"
+    );
+    assert!(matches!(
+        SurrogatePolicy::Error.decode("key", value),
+        Err(Error::UnpairedSurrogate { key, unit: 0xD800 }) if key == "key"
+    ));
+
+    // Only escapes of a surrogate are read back
+    assert_eq!(
+        SurrogatePolicy::Preserve.encode("a\\uD800\u{1F600}"),
+        U16Str::from_slice(BROKEN)
+    );
+    assert_eq!(
+        SurrogatePolicy::Preserve.encode(r"A \uD8 \u"),
+        U16String::from_str(r"A \uD8 \u")
+    );
+    assert_eq!(
+        SurrogatePolicy::Replace.encode(r"\uD800"),
+        U16String::from_str(r"\uD800")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn preserve_surrogates_through_exports() -> Result<()> {
+    let stf = StringTable::new(HashMap::from([
+        ("broken".to_owned(), U16String::from_vec(BROKEN)),
+        ("fine".to_owned(), U16String::from_str("fine")),
+    ]));
+
+    assert_eq!(StringTable::from_json(&stf.to_json()?)?, stf);
+    assert_eq!(StringTable::from_yaml(&stf.to_yaml()?)?, stf);
+    assert_eq!(StringTable::from_fluent(&stf.to_fluent())?, stf);
+
+    let replaced = TextTable::with_policy(&stf, SurrogatePolicy::Replace)?;
+    assert_eq!(replaced.entries[0].value, "a\u{FFFD}\u{1F600}");
+    assert!(matches!(
+        TextTable::with_policy(&stf, SurrogatePolicy::Error),
+        Err(Error::UnpairedSurrogate { key, .. }) if key == "broken"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn preserve_escape_like_text() -> Result<()> {
+    let stf = StringTable::new(HashMap::from([
+        ("path".to_owned(), U16String::from_str(r"C:\uD800\file")),
+        ("doubled".to_owned(), U16String::from_str(r"a\\b\")),
+        ("color".to_owned(), U16String::from_str(r"\#FF0000red")),
+        (
+            "mixed".to_owned(),
+            U16String::from_vec([0x5C, 0xD800, 0x5C, 0x5C, 0x75]),
+        ),
+    ]));
+
+    // Only backslashes which would be read as part of an escape are doubled
+    let policy = SurrogatePolicy::Preserve;
+    assert_eq!(
+        policy.decode("path", stf.get("path").unwrap())?,
+        r"C:\\uD800\file"
+    );
+    assert_eq!(
+        policy.decode("color", stf.get("color").unwrap())?,
+        r"\#FF0000red"
+    );
+    assert_eq!(
+        policy.decode("mixed", stf.get("mixed").unwrap())?,
+        r"\\\uD800\\\\u"
+    );
+    for (key, value) in stf.iter() {
+        assert_eq!(&policy.encode(&policy.decode(key, value)?), value);
+    }
+
+    assert_eq!(StringTable::from_json(&stf.to_json()?)?, stf);
+    assert_eq!(StringTable::from_yaml(&stf.to_yaml()?)?, stf);
+    assert_eq!(StringTable::from_fluent(&stf.to_fluent())?, stf);
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use swg_stf::error::{Error, Result};
+use swg_stf::text::{TextEntry, TextTable};
+use swg_stf::types::StringTable;
+use widestring::U16String;
+
+fn edited_table() -> StringTable {
+    let mut stf = StringTable::new(HashMap::from([
+        (
+            "b".to_owned(),
+            U16String::from_str("second, with \"quotes\""),
+        ),
+        ("a".to_owned(), U16String::from_str("first\nline")),
+        ("c".to_owned(), U16String::from_str("ünïcødé ✓")),
+    ]));
+    stf.remove("b");
+    stf.insert("d", U16String::new()).unwrap();
+    stf
+}
+
+#[test]
+fn text_table() -> Result<()> {
+    let stf = edited_table();
+    let text = TextTable::from(&stf);
+    assert_eq!((text.flag, text.max_index), (1, 4));
+    assert_eq!(
+        text.entries
+            .iter()
+            .map(|entry| (entry.id, entry.key.as_str()))
+            .collect::<Vec<_>>(),
+        [(1, "a"), (3, "c"), (4, "d")]
+    );
+    assert_eq!(StringTable::try_from(text)?, stf);
+
+    let entry = |id, key: &str| TextEntry {
+        id,
+        key: key.to_owned(),
+        value: String::new(),
+    };
+    let duplicate_id = TextTable {
+        flag: 1,
+        max_index: 1,
+        entries: vec![entry(1, "a"), entry(1, "b")],
+    };
+    assert!(matches!(
+        StringTable::try_from(duplicate_id),
+        Err(Error::DuplicateId(1))
+    ));
+    let duplicate_key = TextTable {
+        flag: 1,
+        max_index: 2,
+        entries: vec![entry(1, "a"), entry(2, "a")],
+    };
+    assert!(matches!(
+        StringTable::try_from(duplicate_key),
+        Err(Error::DuplicateKey(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn json_round_trip() -> Result<()> {
+    let stf = edited_table();
+    let json = stf.to_json()?;
+    assert!(json.contains("\"max_index\": 4"));
+
+    let decoded = StringTable::from_json(&json)?;
+    assert_eq!(decoded, stf);
+    assert_eq!((decoded.id("d"), decoded.max_index()), (Some(4), 4));
+
+    Ok(())
+}
+
+#[test]
+fn serde_round_trip() -> Result<()> {
+    let mut stf = edited_table();
+    stf.rename("a", "z")?;
+    let json = serde_json::to_string(&stf)?;
+    assert_eq!(json, serde_json::to_string(&TextTable::from(&stf))?);
+
+    let decoded = serde_json::from_str::<StringTable>(&json)?;
+    assert_eq!(decoded, stf);
+    assert_eq!(
+        decoded.entries_with_ids().collect::<Vec<_>>(),
+        stf.entries_with_ids().collect::<Vec<_>>()
+    );
+    assert_eq!((decoded.max_index(), decoded.flag()), (4, 1));
+
+    Ok(())
+}
+
+#[test]
+fn yaml_round_trip() -> Result<()> {
+    let stf = edited_table();
+    let decoded = StringTable::from_yaml(&stf.to_yaml()?)?;
+    assert_eq!(decoded, stf);
+    assert_eq!(decoded.id("c"), Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn csv_round_trip() -> Result<()> {
+    let stf = edited_table();
+    let csv = stf.to_csv()?;
+    assert!(csv.starts_with("id,key,value\n1,a,\"first\nline\"\n"));
+
+    let decoded = StringTable::from_csv(&csv)?;
+    assert_eq!(decoded, stf);
+    assert_eq!((decoded.id("d"), decoded.max_index()), (Some(4), 4));
+
+    assert_eq!(StringTable::default().to_csv()?, "id,key,value\n");
+
+    Ok(())
+}
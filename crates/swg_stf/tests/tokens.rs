@@ -0,0 +1,116 @@
+use swg_stf::error::TokenIssue;
+use swg_stf::tokens::{compare, plain_text, tokenize, PlainTextOptions, Token};
+use swg_stf::types::StringTable;
+use widestring::U16String;
+
+#[test]
+fn tokenize_strings() {
+    assert_eq!(
+        tokenize(r"\#ff0000%TU\#. has 50% of %DI, not %TX or \#12 or %tu"),
+        [
+            Token::Color(r"\#ff0000"),
+            Token::Prose("%TU"),
+            Token::ColorReset(r"\#."),
+            Token::Text(" has 50% of "),
+            Token::Prose("%DI"),
+            Token::Text(", not "),
+            Token::Malformed("%TX"),
+            Token::Text(" or "),
+            Token::Malformed(r"\#12"),
+            Token::Text(" or %tu"),
+        ]
+    );
+    assert_eq!(tokenize("ünïcødé %"), [Token::Text("ünïcødé %")]);
+    assert_eq!(tokenize(""), []);
+}
+
+#[test]
+fn compare_translation() {
+    let english = StringTable::from_iter([
+        ("reordered", "%TU gave %TT %DI credits"),
+        ("colors", r"\#FF0000Warning\#."),
+        ("missing", "Hello %TU and %TU"),
+        ("untranslated", "%TO"),
+    ]);
+    let german = StringTable::from_iter([
+        ("reordered", "%DI Credits von %TU an %TT"),
+        ("colors", r"\#ff0000Warnung\#."),
+        ("missing", "Hallo %TU und %TT, %TX"),
+        ("extra", "%DF"),
+    ]);
+
+    assert_eq!(
+        compare(&english, &german),
+        [
+            TokenIssue::Missing {
+                key: "missing".to_owned(),
+                token: "%TU".to_owned()
+            },
+            TokenIssue::Unexpected {
+                key: "missing".to_owned(),
+                token: "%TT".to_owned()
+            },
+            TokenIssue::Malformed {
+                key: "missing".to_owned(),
+                token: "%TX".to_owned()
+            },
+        ]
+    );
+}
+
+#[test]
+fn compare_case_insensitive() {
+    let mut english = StringTable::from_iter([("Greeting", "Hello %TU")]);
+    english.set_case_insensitive(true);
+    let german = StringTable::from_iter([("greeting", "Hallo")]);
+
+    assert_eq!(
+        compare(&english, &german),
+        [TokenIssue::Missing {
+            key: "greeting".to_owned(),
+            token: "%TU".to_owned()
+        }]
+    );
+    assert_eq!(english["GREETING"], U16String::from_str("Hello %TU"));
+}
+
+#[test]
+fn render_plain_text() {
+    assert_eq!(
+        tokenize(r"\#pcontrast1 Range:\#. 5m\n\#palert"),
+        [
+            Token::Palette(r"\#pcontrast1 "),
+            Token::Text("Range:"),
+            Token::ColorReset(r"\#."),
+            Token::Text(" 5m"),
+            Token::Newline(r"\n"),
+            Token::Palette(r"\#palert"),
+        ]
+    );
+
+    let value =
+        U16String::from_str("\\#pcontrast1 %TU\\#. deals %DI damage\\nto \\#00ff00%TT\nnow");
+    assert_eq!(
+        plain_text(&value, PlainTextOptions::default()),
+        "%TU deals %DI damage\nto %TT\nnow"
+    );
+    assert_eq!(
+        plain_text(
+            &value,
+            PlainTextOptions::builder()
+                .expand_tokens(true)
+                .single_line(true)
+                .build()
+        ),
+        "<user> deals <number> damage to <target> now"
+    );
+
+    // Malformed codes are left for the reader to see
+    assert_eq!(
+        plain_text(
+            &U16String::from_str(r"\#12 %TX \#p"),
+            PlainTextOptions::default()
+        ),
+        r"\#12 %TX \#p"
+    );
+}
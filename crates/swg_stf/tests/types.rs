@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use swg_stf::error::{Error, Result};
+use swg_stf::read::StringTableReader;
+use swg_stf::types::StringTable;
+use swg_stf::write::{StringTableWriter, StringTableWriterOptions};
+use widestring::U16String;
+
+#[test]
+fn edit_entries() -> Result<()> {
+    let mut stf = StringTable::new(HashMap::from([
+        ("b".to_owned(), U16String::from_str("second")),
+        ("a".to_owned(), U16String::from_str("first")),
+    ]));
+    assert_eq!((stf.id("a"), stf.id("b")), (Some(1), Some(2)));
+    assert_eq!(stf.max_index(), 2);
+
+    assert_eq!(stf.insert("c", U16String::from_str("third"))?, 3);
+    assert_eq!(stf.insert("a", U16String::from_str("replaced"))?, 1);
+    assert_eq!(
+        stf.update("b", U16String::from_str("updated"))?,
+        U16String::from_str("second")
+    );
+    assert!(matches!(
+        stf.update("missing", U16String::new()),
+        Err(Error::MissingKey(_))
+    ));
+
+    // Removed IDs aren't handed out again
+    assert_eq!(stf.remove("c"), Some(U16String::from_str("third")));
+    assert_eq!(stf.insert("d", U16String::from_str("fourth"))?, 4);
+
+    stf.rename("a", "renamed")?;
+    assert_eq!(stf.id("renamed"), Some(1));
+    assert_eq!(stf.get("renamed"), Some(&U16String::from_str("replaced")));
+    assert!(matches!(stf.rename("b", "d"), Err(Error::DuplicateKey(_))));
+    assert!(matches!(stf.rename("a", "e"), Err(Error::MissingKey(_))));
+    let unchanged = stf.clone();
+    stf.rename("b", "b")?;
+    assert_eq!(stf, unchanged);
+    assert!(matches!(stf.rename("e", "e"), Err(Error::MissingKey(_))));
+
+    let mut data = Vec::new();
+    StringTableWriter::encode(&stf, &mut data)?;
+    let decoded = StringTableReader::decode(Cursor::new(data))?;
+    assert_eq!(decoded, stf);
+    assert_eq!(
+        decoded
+            .entries_with_ids()
+            .map(|(id, key, _)| (id, key))
+            .collect::<Vec<_>>(),
+        [(1, "renamed"), (2, "b"), (4, "d")]
+    );
+    assert_eq!(decoded.max_index(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn exhausted_ids() -> Result<()> {
+    let stf = StringTable::from_iter([("a", "first")]);
+    let mut data = Vec::new();
+    let options = StringTableWriterOptions::builder()
+        .max_index(u32::MAX)
+        .build();
+    StringTableWriter::encode_with_options(&stf, &mut data, &options)?;
+
+    let mut stf = StringTableReader::decode(Cursor::new(data))?;
+    assert!(matches!(
+        stf.insert("b", U16String::from_str("second")),
+        Err(Error::IdsExhausted(u32::MAX))
+    ));
+    assert_eq!(stf.len(), 1);
+    assert_eq!(stf.max_index(), u32::MAX);
+
+    // Existing entries can still be changed
+    assert_eq!(stf.insert("a", U16String::from_str("replaced"))?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn lookup_entries() -> Result<()> {
+    let mut stf = StringTable::new(HashMap::from([
+        ("b".to_owned(), U16String::from_str("second")),
+        ("a".to_owned(), U16String::from_str("first")),
+    ]));
+    assert_eq!((stf.len(), stf.is_empty()), (2, false));
+    assert!(stf.contains_key("a") && !stf.contains_key("c"));
+    assert_eq!(stf.get("b"), Some(&U16String::from_str("second")));
+    assert_eq!(stf.by_id(1), Some(("a", &U16String::from_str("first"))));
+    assert_eq!(stf.by_id(3), None);
+
+    // Lookups by ID follow edits
+    stf.rename("a", "renamed")?;
+    stf.remove("b");
+    stf.insert("c", U16String::from_str("third"))?;
+    assert_eq!(
+        stf.by_id(1),
+        Some(("renamed", &U16String::from_str("first")))
+    );
+    assert_eq!(stf.by_id(2), None);
+    assert_eq!(stf.by_id(3), Some(("c", &U16String::from_str("third"))));
+
+    assert!(StringTable::default().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn case_insensitive_lookup() -> Result<()> {
+    let mut stf = StringTable::new(HashMap::from([
+        ("Item_Use".to_owned(), U16String::from_str("Use")),
+        ("item_use".to_owned(), U16String::from_str("use")),
+        ("Examine".to_owned(), U16String::from_str("Examine")),
+    ]));
+    assert_eq!(stf.get("examine"), None);
+
+    stf.set_case_insensitive(true);
+    assert!(stf.is_case_insensitive());
+    assert_eq!(stf.get("EXAMINE"), Some(&U16String::from_str("Examine")));
+    assert_eq!(stf.id("examine"), Some(1));
+    // An exact match wins, otherwise the first key in table order
+    assert_eq!(stf.get("item_use"), Some(&U16String::from_str("use")));
+    assert_eq!(stf.get("ITEM_USE"), Some(&U16String::from_str("Use")));
+
+    // Keys keep their casing
+    assert_eq!(
+        stf.keys().map(String::as_str).collect::<Vec<_>>(),
+        ["Examine", "Item_Use", "item_use"]
+    );
+
+    // The index follows edits
+    stf.remove("Item_Use");
+    assert_eq!(stf.get("ITEM_USE"), Some(&U16String::from_str("use")));
+    stf.rename("Examine", "Look")?;
+    assert!(!stf.contains_key("examine"));
+    assert!(stf.contains_key("LOOK"));
+    stf.insert("New_Key", U16String::from_str("new"))?;
+    assert!(stf.contains_key("new_key"));
+
+    stf.set_case_insensitive(false);
+    assert!(!stf.contains_key("look"));
+
+    // Equality ignores the lookup index
+    let copy = stf.clone();
+    stf.set_case_insensitive(true);
+    stf.set_case_insensitive(false);
+    assert_eq!(stf, copy);
+    stf.set_case_insensitive(true);
+    assert_eq!(stf, copy);
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use swg_stf::error::Result;
+use swg_stf::read::StringTableReader;
+use swg_stf::types::StringTable;
+use swg_stf::write::{StringTableWriter, StringTableWriterOptions};
+use tracing_test::traced_test;
+use widestring::U16String;
+
+const SINGLE_ENTRY: &[u8] = include_bytes!("../resources/single_entry.stf");
+
+#[traced_test]
+#[test]
+fn write_matches_game_file() -> Result<()> {
+    let stf = StringTableReader::decode(Cursor::new(SINGLE_ENTRY))?;
+
+    let mut data = Vec::new();
+    StringTableWriter::encode(&stf, &mut data)?;
+
+    assert_eq!(data, SINGLE_ENTRY);
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn round_trip() -> Result<()> {
+    let stf = StringTable::new(HashMap::from([
+        ("zeta".to_owned(), U16String::from_str("last")),
+        ("alpha".to_owned(), U16String::from_str("first")),
+        ("unicode".to_owned(), U16String::from_str("ünïcødé ✓")),
+        ("empty".to_owned(), U16String::new()),
+    ]));
+
+    let mut data = Vec::new();
+    StringTableWriter::encode(&stf, &mut data)?;
+
+    assert_eq!(StringTableReader::decode(Cursor::new(data))?, stf);
+
+    Ok(())
+}
+
+/// Build a table by hand, with its values and keys in the given order
+fn stf_bytes(flag: u8, max_index: u32, entries: &[(u32, &str, &str)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend(0x0000ABCDu32.to_le_bytes());
+    data.push(flag);
+    data.extend(max_index.to_le_bytes());
+    data.extend((entries.len() as u32).to_le_bytes());
+    for (id, _, value) in entries {
+        let value = U16String::from_str(value);
+        data.extend(id.to_le_bytes());
+        data.extend(u32::MAX.to_le_bytes());
+        data.extend((value.len() as u32).to_le_bytes());
+        data.extend(value.as_slice().iter().flat_map(|rune| rune.to_le_bytes()));
+    }
+    for (id, key, _) in entries {
+        data.extend(id.to_le_bytes());
+        data.extend((key.len() as u32).to_le_bytes());
+        data.extend(key.as_bytes());
+    }
+    data
+}
+
+#[traced_test]
+#[test]
+fn byte_stable_round_trip() -> Result<()> {
+    let original = stf_bytes(
+        0,
+        9,
+        &[(7, "zeta", "last"), (2, "alpha", "first"), (5, "mid", "")],
+    );
+    let mut stf = StringTableReader::decode(Cursor::new(&original[..]))?;
+    assert_eq!((stf.flag(), stf.max_index()), (0, 9));
+
+    let mut data = Vec::new();
+    StringTableWriter::encode(&stf, &mut data)?;
+    assert_eq!(data, original);
+
+    // Edits keep the position of existing entries and add new ones at the end
+    stf.update("alpha", U16String::from_str("changed"))?;
+    stf.rename("zeta", "omega")?;
+    stf.insert("new", U16String::from_str("added"))?;
+
+    data.clear();
+    StringTableWriter::encode(&stf, &mut data)?;
+    assert_eq!(
+        data,
+        stf_bytes(
+            0,
+            10,
+            &[
+                (7, "omega", "last"),
+                (2, "alpha", "changed"),
+                (5, "mid", ""),
+                (10, "new", "added")
+            ]
+        )
+    );
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn header_options() -> Result<()> {
+    let entries = [(1, "a", "first"), (2, "b", "second")];
+    let stf = StringTableReader::decode(Cursor::new(stf_bytes(1, 2, &entries)))?;
+
+    let mut data = Vec::new();
+    let options = StringTableWriterOptions::builder()
+        .flag(0)
+        .max_index(40)
+        .build();
+    StringTableWriter::encode_with_options(&stf, &mut data, &options)?;
+    assert_eq!(data, stf_bytes(0, 40, &entries));
+
+    // Fields without an option keep the table's value
+    data.clear();
+    let options = StringTableWriterOptions::builder().flag(7).build();
+    StringTableWriter::encode_with_options(&stf, &mut data, &options)?;
+    assert_eq!(data, stf_bytes(7, 2, &entries));
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use swg_stf::error::Result;
+use swg_stf::types::StringTable;
+use swg_stf::xliff::{TranslationState, XliffDocument, XliffVersion};
+
+#[test]
+fn xliff_round_trip() -> Result<()> {
+    let english = StringTable::from_iter([
+        ("greeting", "Hello %TU, you owe %DI credits."),
+        ("escaped", "<b>Tom & \"Jerry\"</b>\n  second line"),
+        ("untranslated", "Only in English"),
+        ("empty", ""),
+    ]);
+    let german = StringTable::from_iter([
+        ("greeting", "Hallo %TU, du schuldest %DI Credits."),
+        ("escaped", "<b>Tom & \"Jerry\"</b>\n  zweite Zeile"),
+        ("empty", ""),
+    ]);
+
+    for version in [XliffVersion::V1_2, XliffVersion::V2_0] {
+        let mut document = XliffDocument::new(version, "string/en/test.stf", "en", &english)
+            .with_target("de", &german);
+        document.units[0].state = TranslationState::Final;
+
+        let xml = document.to_xml();
+        let decoded = XliffDocument::from_xml(&xml)?;
+        assert_eq!(decoded, document, "{:?}\n{}", version, xml);
+
+        assert_eq!(decoded.source_table(), english);
+        assert_eq!(decoded.target_table(TranslationState::Translated), german);
+        assert_eq!(
+            decoded
+                .target_table(TranslationState::Final)
+                .keys()
+                .collect::<Vec<_>>(),
+            ["empty"]
+        );
+    }
+
+    let xml = XliffDocument::new(XliffVersion::V2_0, "test.stf", "en", &english).to_xml();
+    assert!(xml.contains(r#"Hello <ph id="1" equiv="%TU" disp="%TU"/>, you owe <ph id="2""#));
+
+    Ok(())
+}
+
+#[test]
+fn xliff_import() -> Result<()> {
+    let document = XliffDocument::from_xml(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">
+  <file original="quest.stf" source-language="en" target-language="fr" datatype="plaintext">
+    <body>
+      <trans-unit id="1" resname="title">
+        <source>The <ph id="1">%TT</ph> task</source>
+        <target state="needs-review-translation">La tâche <ph id="1">%TT</ph></target>
+      </trans-unit>
+      <trans-unit id="description">
+        <source><![CDATA[Find <it>]]></source>
+        <target>Trouver</target>
+      </trans-unit>
+      <trans-unit id="pending">
+        <source>Later</source>
+      </trans-unit>
+    </body>
+  </file>
+</xliff>"#,
+    )?;
+
+    assert_eq!(document.version, XliffVersion::V1_2);
+    assert_eq!(document.target_language.as_deref(), Some("fr"));
+    assert_eq!(
+        document
+            .units
+            .iter()
+            .map(|unit| (unit.key.as_str(), unit.source.as_str(), unit.state))
+            .collect::<Vec<_>>(),
+        [
+            ("title", "The %TT task", TranslationState::Translated),
+            ("description", "Find <it>", TranslationState::Translated),
+            ("pending", "Later", TranslationState::Initial),
+        ]
+    );
+    assert_eq!(
+        document
+            .target_table(TranslationState::Translated)
+            .entries_with_ids()
+            .map(|(id, key, value)| (id, key, value.to_string_lossy()))
+            .collect::<Vec<_>>(),
+        [
+            (1, "title", "La tâche %TT".to_owned()),
+            (2, "description", "Trouver".to_owned())
+        ]
+    );
+
+    Ok(())
+}
@@ -1,3 +1,6 @@
+// divan's bench macro expands to std items that are newer than our MSRV
+#![allow(clippy::incompatible_msrv)]
+
 use divan::AllocProfiler;
 
 #[global_allocator]
@@ -9,8 +12,11 @@ fn main() {
 
 pub mod read {
     use divan::Bencher;
-    use std::io::{prelude::*, Cursor};
-    use swg_tre::TreArchive;
+    use std::{
+        fs::File,
+        io::{prelude::*, Cursor},
+    };
+    use swg_tre::{testing::SyntheticArchive, CompressionMethod, TreArchive};
 
     fn get_input() -> Vec<u8> {
         std::fs::read(format!(
@@ -27,6 +33,40 @@ pub mod read {
         });
     }
 
+    /// Opens straight from disk, where every read of the metadata is a syscall
+    #[divan::bench(args = [1_000, 50_000])]
+    fn open_file(bencher: Bencher, entries: usize) {
+        let data = SyntheticArchive::builder()
+            .entries(entries)
+            .min_entry_size(0)
+            .max_entry_size(16)
+            .record_compression(CompressionMethod::None)
+            .build()
+            .generate()
+            .unwrap();
+        let path = std::env::temp_dir().join(format!("swg_tre_bench_{}.tre", entries));
+        std::fs::write(&path, data).unwrap();
+
+        bencher.bench(|| {
+            divan::black_box(TreArchive::new(File::open(&path).unwrap()).unwrap());
+        });
+    }
+
+    #[divan::bench(args = [1_000, 50_000])]
+    fn open_many_entries(bencher: Bencher, entries: usize) {
+        let data = SyntheticArchive::builder()
+            .entries(entries)
+            .min_entry_size(0)
+            .max_entry_size(16)
+            .build()
+            .generate()
+            .unwrap();
+
+        bencher.bench(|| {
+            divan::black_box(TreArchive::new(Cursor::new(&data)).unwrap());
+        });
+    }
+
     #[divan::bench]
     fn access_file(bencher: Bencher) {
         bencher
@@ -38,7 +78,7 @@ pub mod read {
 
     #[divan::bench(sample_count = 1)]
     fn read_file_first(bencher: Bencher) {
-        let mut tre = TreArchive::new(Cursor::new(get_input())).unwrap();
+        let tre = TreArchive::new(Cursor::new(get_input())).unwrap();
         bencher.bench_local(move || {
             let mut buffer = Vec::new();
 
@@ -49,7 +89,7 @@ pub mod read {
 
     #[divan::bench(sample_count = 1)]
     fn read_file_all(bencher: Bencher) {
-        let mut tre = TreArchive::new(Cursor::new(get_input())).unwrap();
+        let tre = TreArchive::new(Cursor::new(get_input())).unwrap();
 
         bencher.bench_local(move || {
             let mut buffer = Vec::new();
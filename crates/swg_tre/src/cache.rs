@@ -0,0 +1,149 @@
+//! A cache of decompressed entries bounded by their total size.
+
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    sync::Arc,
+};
+
+/// Least recently used cache of entry contents, keyed by entry index unless told otherwise
+#[derive(Debug)]
+pub(crate) struct EntryCache<K = usize> {
+    capacity: u64,
+    size: u64,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    entries: HashMap<K, (Arc<[u8]>, u64)>,
+    recency: BTreeMap<u64, K>,
+}
+
+impl<K: Hash + Eq + Clone> EntryCache<K> {
+    pub fn new(capacity: u64) -> Self {
+        Self {
+            capacity,
+            size: 0,
+            tick: 0,
+            hits: 0,
+            misses: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    /// Get the contents of an entry, marking it as the most recently used
+    pub fn get<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<Arc<[u8]>>
+    where
+        K: Borrow<Q>,
+    {
+        let tick = self.next_tick();
+        let Some((data, last_used)) = self.entries.get_mut(key) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+
+        if let Some(key) = self.recency.remove(last_used) {
+            self.recency.insert(tick, key);
+        }
+        *last_used = tick;
+
+        Some(data.clone())
+    }
+
+    /// Store the contents of an entry, evicting the least recently used entries to make room
+    ///
+    /// Entries larger than the whole cache are never stored.
+    pub fn insert(&mut self, key: K, data: Arc<[u8]>) {
+        let len = data.len() as u64;
+        if len > self.capacity {
+            return;
+        }
+
+        self.remove(&key);
+        while self.size + len > self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some((evicted, _)) = self.entries.remove(&oldest) {
+                self.size -= evicted.len() as u64;
+            }
+        }
+
+        let tick = self.next_tick();
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(key, (data, tick));
+        self.size += len;
+    }
+
+    /// The total size of the cached entries
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The most the cached entries may add up to
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// How many lookups found their entry, and how many didn't
+    pub fn lookups(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some((data, last_used)) = self.entries.remove(key) {
+            self.recency.remove(&last_used);
+            self.size -= data.len() as u64;
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EntryCache;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = EntryCache::<usize>::new(10);
+        cache.insert(0, vec![0; 4].into());
+        cache.insert(1, vec![1; 4].into());
+
+        // Touch the first entry so the second becomes the oldest
+        assert!(cache.get(&0).is_some());
+        cache.insert(2, vec![2; 4].into());
+
+        assert!(cache.get(&0).is_some());
+        assert!(cache.get(&1).is_none());
+        assert!(cache.get(&2).is_some());
+        assert_eq!(cache.size(), 8);
+        assert_eq!(cache.lookups(), (3, 1));
+    }
+
+    #[test]
+    fn skips_oversized_entries() {
+        let mut cache = EntryCache::<usize>::new(4);
+        cache.insert(0, vec![0; 4].into());
+        cache.insert(1, vec![1; 5].into());
+
+        assert!(cache.get(&0).is_some());
+        assert!(cache.get(&1).is_none());
+        assert_eq!(cache.size(), 4);
+    }
+
+    #[test]
+    fn replaces_existing_entries() {
+        let mut cache = EntryCache::<usize>::new(8);
+        cache.insert(0, vec![0; 4].into());
+        cache.insert(0, vec![0; 6].into());
+
+        assert_eq!(cache.get(&0).map(|data| data.len()), Some(6));
+        assert_eq!(cache.size(), 6);
+    }
+}
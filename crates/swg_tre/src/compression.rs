@@ -1,15 +1,20 @@
 //! Block compression and decompression handling.
 
 use std::{
-    fmt::Display,
+    collections::HashMap,
+    fmt::{Debug, Display},
     io::{self, Read, Seek, Write},
+    sync::Arc,
 };
 
-use binrw::{io::NoSeek, BinRead, BinWrite};
+use binrw::{io::NoSeek, BinRead, BinResult, BinWrite, Endian};
 use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
 use tracing::instrument;
 
-use crate::error::Result;
+use crate::{
+    error::{DecompressionBombError, Error, Result},
+    transform::{BlockKind, BlockTransform, Decoded},
+};
 
 /// Identifies the storage format used to compress a block inside the TRE file
 ///
@@ -17,15 +22,78 @@ use crate::error::Result;
 ///
 /// Files added to the TRE can specify it's compression method via [`crate::write::TreWriter::start_file`]
 ///
-#[derive(BinRead, BinWrite, Debug, Copy, Clone, Default, PartialEq)]
-#[brw(repr=u32)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompressionMethod {
     /// Stores the data as it is
-    None = 0,
+    None,
 
     /// Compress the data using Zlib
     #[default]
-    Zlib = 2,
+    Zlib,
+
+    /// Compress the data using Zstandard
+    ///
+    /// This isn't understood by the game client and is only meant for archives distributed
+    /// between tools. Reading or writing it requires the `zstd` feature, without which it fails
+    /// with [`Error::UnsupportedCompression`].
+    Zstd,
+
+    /// Compress the data using Zlib, but store it as it is when that doesn't make it smaller
+    ///
+    /// This is only meaningful when writing, the method that was chosen is what ends up in the
+    /// archive.
+    Auto,
+
+    /// A value this crate has no method for, as some community tools store their own codecs
+    ///
+    /// Blocks stored this way can only be read by registering a [`BlockDecoder`] for the value in
+    /// [`crate::read::TreArchiveOptions::decoders`], and can't be written.
+    Other(u32),
+}
+
+impl CompressionMethod {
+    /// The value an archive stores for this method, which [`CompressionMethod::Auto`] has none of
+    pub fn stored(self) -> Option<u32> {
+        match self {
+            CompressionMethod::None => Some(0),
+            CompressionMethod::Zlib => Some(2),
+            CompressionMethod::Zstd => Some(3),
+            CompressionMethod::Auto => None,
+            CompressionMethod::Other(value) => Some(value),
+        }
+    }
+}
+
+impl BinRead for CompressionMethod {
+    type Args<'a> = ();
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        endian: Endian,
+        _args: Self::Args<'_>,
+    ) -> BinResult<Self> {
+        u32::read_options(reader, endian, ()).map(CompressionMethod::from)
+    }
+}
+
+impl BinWrite for CompressionMethod {
+    type Args<'a> = ();
+
+    fn write_options<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        endian: Endian,
+        _args: Self::Args<'_>,
+    ) -> BinResult<()> {
+        let Some(value) = self.stored() else {
+            return Err(binrw::Error::AssertFail {
+                pos: writer.stream_position()?,
+                message: "automatic compression must be resolved before writing".into(),
+            });
+        };
+        value.write_options(writer, endian, ())
+    }
 }
 
 impl Display for CompressionMethod {
@@ -33,6 +101,9 @@ impl Display for CompressionMethod {
         match self {
             CompressionMethod::None => write!(f, "None"),
             CompressionMethod::Zlib => write!(f, "Zlib"),
+            CompressionMethod::Auto => write!(f, "Auto"),
+            CompressionMethod::Zstd => write!(f, "Zstd"),
+            CompressionMethod::Other(value) => write!(f, "Other({})", value),
         }
     }
 }
@@ -42,103 +113,263 @@ impl From<u32> for CompressionMethod {
         match value {
             0 => CompressionMethod::None,
             2 => CompressionMethod::Zlib,
-            _ => unreachable!(),
+            3 => CompressionMethod::Zstd,
+            value => CompressionMethod::Other(value),
         }
     }
 }
 
-pub(crate) enum TreBlockReader<'a, W: Read + Seek> {
-    Raw(io::Take<&'a mut W>),
-    Compressed(Box<ZlibDecoder<io::Take<&'a mut W>>>),
+/// Decompresses blocks stored with a [`CompressionMethod::Other`] value
+pub trait BlockDecoder: Debug + Send + Sync {
+    /// Decompress a whole block from its stored bytes
+    ///
+    /// Any [`crate::transform::BlockTransform`] has already been undone on `stored`.
+    fn decode(&self, block: BlockKind, stored: &mut dyn Read) -> io::Result<Vec<u8>>;
 }
 
-impl<'a, W: Read + Seek> TreBlockReader<'a, W> {
-    #[tracing::instrument(skip(reader))]
+/// The decoders to read [`CompressionMethod::Other`] blocks with, by their stored value
+///
+/// ```
+/// use std::io::{self, Read};
+/// use swg_tre::{
+///     compression::{BlockDecoder, DecoderRegistry},
+///     read::TreArchiveOptions,
+///     transform::BlockKind,
+/// };
+///
+/// /// A fan tool which stores blocks reversed
+/// #[derive(Debug)]
+/// struct Reversed;
+///
+/// impl BlockDecoder for Reversed {
+///     fn decode(&self, _block: BlockKind, stored: &mut dyn Read) -> io::Result<Vec<u8>> {
+///         let mut data = Vec::new();
+///         stored.read_to_end(&mut data)?;
+///         data.reverse();
+///         Ok(data)
+///     }
+/// }
+///
+/// let options = TreArchiveOptions::builder()
+///     .decoders(DecoderRegistry::new().register(0x52455645, Reversed))
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DecoderRegistry {
+    decoders: HashMap<u32, Arc<dyn BlockDecoder>>,
+}
+
+impl DecoderRegistry {
+    /// Create a registry without any decoders
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode blocks stored with `value` using `decoder`, replacing any earlier decoder for it
+    ///
+    /// The values of the methods this crate implements are never looked up.
+    pub fn register(mut self, value: u32, decoder: impl BlockDecoder + 'static) -> Self {
+        self.decoders.insert(value, Arc::new(decoder));
+        self
+    }
+
+    /// The decoder for blocks stored with `value`
+    pub fn get(&self, value: u32) -> Option<&Arc<dyn BlockDecoder>> {
+        self.decoders.get(&value)
+    }
+}
+
+pub(crate) enum TreBlockReader<R: Read> {
+    Raw(Decoded<io::Take<R>>),
+    Compressed(Box<ZlibDecoder<Decoded<io::Take<R>>>>),
+    #[cfg(feature = "zstd")]
+    Zstd(Box<zstd::Decoder<'static, io::BufReader<Decoded<io::Take<R>>>>>),
+    Other(io::Cursor<Vec<u8>>),
+}
+
+impl<R: Read + Seek> TreBlockReader<R> {
+    /// Create a block reader, undoing `transform` on the stored bytes before decompressing them
+    ///
+    /// [`CompressionMethod::Other`] blocks are decoded whole by the decoder `decoders` has for
+    /// them.
+    #[tracing::instrument(skip(reader, transform, decoders))]
     pub fn new(
-        reader: &'a mut W,
+        mut reader: R,
         start: u64,
         limit: u64,
         compression: CompressionMethod,
+        block: BlockKind,
+        transform: Option<Arc<dyn BlockTransform>>,
+        decoders: Option<&DecoderRegistry>,
     ) -> Result<Self> {
         reader.seek(io::SeekFrom::Start(start))?;
 
-        let limit_reader = reader.by_ref().take(limit);
+        let transform = transform.map(|transform| (transform, block));
+        let mut limit_reader = Decoded::new(reader.take(limit), transform, start);
         Ok(match compression {
             CompressionMethod::None => TreBlockReader::Raw(limit_reader),
             CompressionMethod::Zlib => {
                 TreBlockReader::Compressed(Box::new(ZlibDecoder::new(limit_reader)))
             }
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                TreBlockReader::Zstd(Box::new(zstd::Decoder::new(limit_reader)?))
+            }
+            #[cfg(not(feature = "zstd"))]
+            CompressionMethod::Zstd => return Err(Error::UnsupportedCompression(compression)),
+            CompressionMethod::Other(value) => {
+                match decoders.and_then(|decoders| decoders.get(value)) {
+                    Some(decoder) => TreBlockReader::Other(io::Cursor::new(
+                        decoder.decode(block, &mut limit_reader)?,
+                    )),
+                    None => return Err(Error::UnsupportedCompression(compression)),
+                }
+            }
+            CompressionMethod::Auto => return Err(Error::UnsupportedCompression(compression)),
         })
     }
-
-    #[instrument(skip(self), err)]
-    pub fn into_inner(self) -> io::Result<io::Take<&'a mut W>> {
-        match self {
-            TreBlockReader::Raw(r) => Ok(r),
-            TreBlockReader::Compressed(r) => Ok(r.into_inner()),
-        }
-    }
 }
 
-impl<W: Read + Seek> Seek for TreBlockReader<'_, W> {
+impl<R: Read> Seek for TreBlockReader<R> {
     #[instrument(skip(self), err)]
     fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
         match self {
             TreBlockReader::Raw(r) => NoSeek::new(r).seek(pos),
             TreBlockReader::Compressed(r) => NoSeek::new(r).seek(pos),
+            #[cfg(feature = "zstd")]
+            TreBlockReader::Zstd(r) => NoSeek::new(r).seek(pos),
+            TreBlockReader::Other(r) => NoSeek::new(r).seek(pos),
         }
     }
 }
 
-impl<W: Read + Seek> Read for TreBlockReader<'_, W> {
-    #[instrument(skip(self), err)]
+impl<R: Read> Read for TreBlockReader<R> {
+    #[instrument(level = "trace", skip_all, err, fields(size = buf.len()))]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self {
             TreBlockReader::Raw(r) => r.read(buf),
             TreBlockReader::Compressed(r) => r.read(buf),
+            #[cfg(feature = "zstd")]
+            TreBlockReader::Zstd(r) => r.read(buf),
+            TreBlockReader::Other(r) => r.read(buf),
         }
     }
 
-    #[instrument(skip(self), err)]
+    #[instrument(level = "trace", skip_all, err, fields(size = buf.len()))]
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
         match self {
             TreBlockReader::Raw(r) => r.read_exact(buf),
             TreBlockReader::Compressed(r) => r.read_exact(buf),
+            #[cfg(feature = "zstd")]
+            TreBlockReader::Zstd(r) => r.read_exact(buf),
+            TreBlockReader::Other(r) => r.read_exact(buf),
         }
     }
 
-    #[instrument(skip(self), err)]
+    #[instrument(skip_all, err, ret)]
     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
         match self {
             TreBlockReader::Raw(r) => r.read_to_end(buf),
             TreBlockReader::Compressed(r) => r.read_to_end(buf),
+            #[cfg(feature = "zstd")]
+            TreBlockReader::Zstd(r) => r.read_to_end(buf),
+            TreBlockReader::Other(r) => r.read_to_end(buf),
         }
     }
 
-    #[instrument(skip(self), err)]
+    #[instrument(skip_all, err, ret)]
     fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
         match self {
             TreBlockReader::Raw(r) => r.read_to_string(buf),
             TreBlockReader::Compressed(r) => r.read_to_string(buf),
+            #[cfg(feature = "zstd")]
+            TreBlockReader::Zstd(r) => r.read_to_string(buf),
+            TreBlockReader::Other(r) => r.read_to_string(buf),
+        }
+    }
+}
+
+/// Passes on the decompressed data of an entry, failing once it grows past its declared size
+///
+/// The size is checked against the configured limits before an entry is opened, so this keeps a
+/// record from understating its size to get around them.
+pub(crate) struct SizeGuard<R> {
+    inner: R,
+    size: u64,
+    read: u64,
+}
+
+impl<R: Read> SizeGuard<R> {
+    pub fn new(inner: R, size: u64) -> Self {
+        SizeGuard {
+            inner,
+            size,
+            read: 0,
         }
     }
 }
 
+impl<R: Read> Read for SizeGuard<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+        if self.read > self.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                DecompressionBombError::Size { size: self.size },
+            ));
+        }
+        Ok(read)
+    }
+}
+
+/// Compress `data` with Zlib, keeping the result only if it is smaller than the input
+#[instrument(skip_all, err, fields(size = data.len(), compressed_size))]
+pub(crate) fn compress_if_smaller(
+    data: Vec<u8>,
+    level: Compression,
+) -> io::Result<(CompressionMethod, Vec<u8>)> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), level);
+    encoder.write_all(&data)?;
+    let compressed = encoder.finish()?;
+
+    tracing::Span::current().record("compressed_size", compressed.len());
+
+    if compressed.len() < data.len() {
+        Ok((CompressionMethod::Zlib, compressed))
+    } else {
+        Ok((CompressionMethod::None, data))
+    }
+}
+
 pub(crate) enum TreBlockWriter<W: Write + Seek> {
     Raw(W, usize),
     Compressed(Box<ZlibEncoder<W>>),
+    #[cfg(feature = "zstd")]
+    Zstd(Box<zstd::Encoder<'static, W>>, usize),
 }
 
 impl<W: Write + Seek> TreBlockWriter<W> {
+    /// Create a block writer, [`CompressionMethod::Auto`] blocks are buffered as they are
+    /// and resolved with [`compress_if_smaller`] once complete
+    ///
+    /// Zstandard blocks use the same numeric level as Zlib would.
     #[tracing::instrument(skip(writer))]
-    pub fn new(writer: W, compression: CompressionMethod) -> Self {
-        match compression {
-            CompressionMethod::None => TreBlockWriter::Raw(writer, 0),
-            CompressionMethod::Zlib => TreBlockWriter::Compressed(Box::new(ZlibEncoder::new(
-                writer,
-                Compression::default(),
-            ))),
-        }
+    pub fn new(writer: W, compression: CompressionMethod, level: Compression) -> Result<Self> {
+        Ok(match compression {
+            CompressionMethod::None | CompressionMethod::Auto => TreBlockWriter::Raw(writer, 0),
+            CompressionMethod::Zlib => {
+                TreBlockWriter::Compressed(Box::new(ZlibEncoder::new(writer, level)))
+            }
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => TreBlockWriter::Zstd(
+                Box::new(zstd::Encoder::new(writer, level.level() as i32)?),
+                0,
+            ),
+            #[cfg(not(feature = "zstd"))]
+            CompressionMethod::Zstd => return Err(Error::UnsupportedCompression(compression)),
+            CompressionMethod::Other(_) => return Err(Error::UnsupportedCompression(compression)),
+        })
     }
 
     #[instrument(skip(self), err)]
@@ -146,6 +377,8 @@ impl<W: Write + Seek> TreBlockWriter<W> {
         match self {
             TreBlockWriter::Raw(r, _) => Ok(r),
             TreBlockWriter::Compressed(r) => r.finish(),
+            #[cfg(feature = "zstd")]
+            TreBlockWriter::Zstd(r, _) => r.finish(),
         }
     }
 
@@ -153,6 +386,8 @@ impl<W: Write + Seek> TreBlockWriter<W> {
         match self {
             TreBlockWriter::Raw(_, c) => *c as u64,
             TreBlockWriter::Compressed(r) => r.total_in(),
+            #[cfg(feature = "zstd")]
+            TreBlockWriter::Zstd(_, c) => *c as u64,
         }
     }
 }
@@ -163,12 +398,14 @@ impl<W: Write + Seek> Seek for TreBlockWriter<W> {
         match self {
             TreBlockWriter::Raw(r, _) => NoSeek::new(r).seek(pos),
             TreBlockWriter::Compressed(r) => NoSeek::new(r).seek(pos),
+            #[cfg(feature = "zstd")]
+            TreBlockWriter::Zstd(r, _) => NoSeek::new(r).seek(pos),
         }
     }
 }
 
 impl<W: Write + Seek> Write for TreBlockWriter<W> {
-    #[instrument(skip(self), err)]
+    #[instrument(level = "trace", skip_all, err, fields(size = buf.len()))]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self {
             TreBlockWriter::Raw(r, c) => {
@@ -177,6 +414,12 @@ impl<W: Write + Seek> Write for TreBlockWriter<W> {
                 Ok(written)
             }
             TreBlockWriter::Compressed(r) => r.write(buf),
+            #[cfg(feature = "zstd")]
+            TreBlockWriter::Zstd(r, c) => {
+                let written = r.write(buf)?;
+                *c += written;
+                Ok(written)
+            }
         }
     }
 
@@ -185,6 +428,8 @@ impl<W: Write + Seek> Write for TreBlockWriter<W> {
         match self {
             TreBlockWriter::Raw(r, _) => r.flush(),
             TreBlockWriter::Compressed(r) => r.flush(),
+            #[cfg(feature = "zstd")]
+            TreBlockWriter::Zstd(r, _) => r.flush(),
         }
     }
 }
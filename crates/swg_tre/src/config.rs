@@ -0,0 +1,261 @@
+//! Client configuration files which decide the archives the game loads
+//!
+//! The client reads its archives from the `[SharedFile]` section of its `.cfg` files:
+//!
+//! ```text
+//! [SharedFile]
+//!     maxSearchPriority=26
+//!     searchTree_00_0=bottom.tre
+//!     searchTree_00_26=patch_14_00.tre
+//!     searchPath_00_30=override
+//! .include "preload.cfg"
+//! ```
+//!
+//! `searchTree_<sku>_<priority>` names an archive and `searchPath_<sku>_<priority>` a directory of
+//! loose files, and entries with a higher priority override those with a lower one. Paths are
+//! relative to the directory the client runs in.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use tracing::instrument;
+
+use crate::error::{ConfigError, Result};
+
+/// The section of a config which lists the archives
+const SHARED_FILE: &str = "SharedFile";
+
+/// Whether a search entry is an archive or a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SearchKind {
+    /// A TRE archive, set by `searchTree_*`
+    Tree,
+    /// A directory of loose files, set by `searchPath_*`
+    Path,
+}
+
+/// An archive or directory the client searches for files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchEntry {
+    /// Whether this is an archive or a directory
+    pub kind: SearchKind,
+    /// The product the entry belongs to, the first number of its key
+    pub sku: u32,
+    /// The priority of the entry, higher priorities override lower ones
+    pub priority: u32,
+    /// The path of the archive or directory, as written in the config
+    pub path: PathBuf,
+}
+
+/// The search entries of one or more client config files
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientConfig {
+    entries: BTreeMap<(SearchKind, u32, u32), PathBuf>,
+}
+
+impl ClientConfig {
+    /// Parse the text of a single config, ignoring any `.include` directives
+    pub fn parse(text: &str) -> Result<ClientConfig> {
+        let mut config = ClientConfig::default();
+        config.parse_into(text, None, &mut Vec::new())?;
+        Ok(config)
+    }
+
+    /// Read a config from disk, along with every config it includes
+    ///
+    /// Included files are found relative to the file including them.
+    #[instrument(skip_all, err, fields(path = %path.as_ref().display()))]
+    pub fn load(path: impl AsRef<Path>) -> Result<ClientConfig> {
+        let mut config = ClientConfig::default();
+        config.load_into(path.as_ref(), &mut Vec::new())?;
+        Ok(config)
+    }
+
+    /// Read the configs of a client installation, every `.cfg` file directly within `dir`
+    ///
+    /// Files are read in name order, so a later file overrides the keys an earlier one sets.
+    #[instrument(skip_all, err, fields(dir = %dir.as_ref().display()))]
+    pub fn from_client_dir(dir: impl AsRef<Path>) -> Result<ClientConfig> {
+        let mut paths = std::fs::read_dir(dir.as_ref())?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "cfg"));
+        paths.sort();
+
+        let mut config = ClientConfig::default();
+        for path in &paths {
+            config.load_into(path, &mut Vec::new())?;
+        }
+        Ok(config)
+    }
+
+    /// Every search entry, from the lowest priority to the highest
+    pub fn entries(&self) -> Vec<SearchEntry> {
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|(&(kind, sku, priority), path)| SearchEntry {
+                kind,
+                sku,
+                priority,
+                path: path.clone(),
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| (entry.priority, entry.sku, entry.kind));
+        entries
+    }
+
+    /// The archives the client loads, from the lowest priority to the highest
+    pub fn search_trees(&self) -> Vec<PathBuf> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| entry.kind == SearchKind::Tree)
+            .map(|entry| entry.path)
+            .collect()
+    }
+
+    fn load_into(&mut self, path: &Path, including: &mut Vec<PathBuf>) -> Result<()> {
+        let canonical = path.canonicalize()?;
+        if including.contains(&canonical) {
+            return Err(ConfigError::IncludeLoop(path.to_owned()).into());
+        }
+
+        let text = std::fs::read(path)?;
+        including.push(canonical);
+        self.parse_into(&String::from_utf8_lossy(&text), path.parent(), including)?;
+        including.pop();
+
+        Ok(())
+    }
+
+    fn parse_into(
+        &mut self,
+        text: &str,
+        dir: Option<&Path>,
+        including: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let mut section = String::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(include) = line.strip_prefix(".include") {
+                if let Some(dir) = dir {
+                    let include = include.trim().trim_matches('"');
+                    self.load_into(&dir.join(include), including)?;
+                }
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_owned();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if section != SHARED_FILE {
+                continue;
+            }
+
+            let key = key.trim();
+            let (kind, rest) = if let Some(rest) = key.strip_prefix("searchTree_") {
+                (SearchKind::Tree, rest)
+            } else if let Some(rest) = key.strip_prefix("searchPath_") {
+                (SearchKind::Path, rest)
+            } else {
+                continue;
+            };
+            let (sku, priority) = rest
+                .split_once('_')
+                .and_then(|(sku, priority)| Some((sku.parse().ok()?, priority.parse().ok()?)))
+                .ok_or_else(|| ConfigError::InvalidKey {
+                    line: number + 1,
+                    key: key.to_owned(),
+                })?;
+
+            self.entries
+                .insert((kind, sku, priority), PathBuf::from(value.trim()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::{
+        config::{ClientConfig, SearchKind},
+        error::{ConfigError, Error, Result},
+    };
+
+    #[test]
+    fn parse_search_entries() -> Result<()> {
+        let config = ClientConfig::parse(
+            "[ClientGame]\n\
+             \tsearchTree_00_99=ignored.tre\n\
+             [SharedFile]\n\
+             \tmaxSearchPriority=26\n\
+             # a comment\n\
+             \tsearchTree_00_26=patch_14_00.tre\n\
+             \tsearchTree_00_0=bottom.tre\n\
+             \tsearchPath_00_30=override\n\
+             \tsearchTree_00_3=replaced.tre\n\
+             \tsearchTree_00_3=data_03.tre\n",
+        )?;
+
+        assert_eq!(
+            config.search_trees(),
+            ["bottom.tre", "data_03.tre", "patch_14_00.tre"].map(PathBuf::from)
+        );
+
+        let entries = config.entries();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[3].kind, SearchKind::Path);
+        assert_eq!(entries[3].priority, 30);
+
+        assert!(matches!(
+            ClientConfig::parse("[SharedFile]\nsearchTree_00=bottom.tre"),
+            Err(Error::Config(ConfigError::InvalidKey { line: 2, .. }))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_includes() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("swg_tre_config_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested"))?;
+        std::fs::write(
+            dir.join("client.cfg"),
+            "[SharedFile]\nsearchTree_00_0=bottom.tre\n.include \"nested/live.cfg\"\n",
+        )?;
+        std::fs::write(
+            dir.join("nested/live.cfg"),
+            "[SharedFile]\nsearchTree_00_1=live.tre\n",
+        )?;
+
+        let config = ClientConfig::load(dir.join("client.cfg"))?;
+        assert_eq!(
+            config.search_trees(),
+            ["bottom.tre", "live.tre"].map(PathBuf::from)
+        );
+        assert_eq!(ClientConfig::from_client_dir(&dir)?, config);
+
+        std::fs::write(dir.join("nested/live.cfg"), ".include \"../client.cfg\"\n")?;
+        assert!(matches!(
+            ClientConfig::load(dir.join("client.cfg")),
+            Err(Error::Config(ConfigError::IncludeLoop(_)))
+        ));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+//! Types for comparing the entries of two TRE archives
+//!
+
+use bon::Builder;
+use std::{
+    collections::BTreeSet,
+    io::{Read, Seek},
+};
+use tracing::{instrument, Span};
+
+use crate::{compression::CompressionMethod, error::Result, read::TreArchive};
+
+/// How the contents of entries found in both archives are compared
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CompareMode {
+    /// Decompress and compare the contents of every shared entry
+    #[default]
+    Contents,
+
+    /// Compare the MD5 hashes stored in each archive's hash block, without reading any data
+    ///
+    /// The hashes cover the stored data, so an entry whose contents are unchanged but which was
+    /// compressed differently is reported as modified. Entries without a hash in either archive
+    /// are compared by their contents instead.
+    Hashes,
+}
+
+/// Options for how two archives are compared
+#[derive(Debug, Clone, Copy, Default, Builder)]
+pub struct DiffOptions {
+    /// How shared entries are compared
+    #[builder(default)]
+    pub mode: CompareMode,
+}
+
+/// A value which differs between the left and right archive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Changed<T> {
+    /// The value in the left archive
+    pub left: T,
+    /// The value in the right archive
+    pub right: T,
+}
+
+impl<T: PartialEq> Changed<T> {
+    fn compare(left: T, right: T) -> Option<Self> {
+        (left != right).then_some(Changed { left, right })
+    }
+}
+
+/// The header fields which differ between two archives
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderDiff {
+    /// The number of entries
+    pub entries: Option<Changed<usize>>,
+    /// How the record block is compressed
+    pub record_compression: Option<Changed<CompressionMethod>>,
+    /// The stored size of the record block
+    pub record_block_size: Option<Changed<u32>>,
+    /// How the name block is compressed
+    pub name_compression: Option<Changed<CompressionMethod>>,
+    /// The stored size of the name block
+    pub name_block_size: Option<Changed<u32>>,
+}
+
+impl HeaderDiff {
+    /// Whether every compared header field is the same
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// An entry present in both archives whose contents differ
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModifiedEntry {
+    /// The name of the entry
+    pub name: String,
+    /// The uncompressed size of the entry in the left archive
+    pub left_size: u64,
+    /// The uncompressed size of the entry in the right archive
+    pub right_size: u64,
+}
+
+/// The differences between two archives, with entry names in sorted order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffReport {
+    /// The header fields which differ
+    pub header: HeaderDiff,
+    /// Entries only present in the right archive
+    pub added: Vec<String>,
+    /// Entries only present in the left archive
+    pub removed: Vec<String>,
+    /// Entries present in both archives whose contents differ
+    pub modified: Vec<ModifiedEntry>,
+    /// The number of shared entries compared by their hashes
+    pub compared_by_hash: usize,
+    /// The number of shared entries compared by their contents
+    pub compared_by_contents: usize,
+}
+
+impl DiffReport {
+    /// Whether the archives hold the same entries with the same contents
+    ///
+    /// Differences in the [`header`](Self::header) alone, such as how the archives were
+    /// compressed, are not counted.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Compare the entries of two archives
+#[instrument(skip_all, err, fields(added, removed, modified))]
+pub fn compare<A: Read + Seek, B: Read + Seek>(
+    left: &TreArchive<A>,
+    right: &TreArchive<B>,
+    options: DiffOptions,
+) -> Result<DiffReport> {
+    let left_names = left.file_names().collect::<BTreeSet<_>>();
+    let right_names = right.file_names().collect::<BTreeSet<_>>();
+
+    let mut report = DiffReport {
+        header: HeaderDiff {
+            entries: Changed::compare(left.len(), right.len()),
+            record_compression: Changed::compare(
+                left.get_record_compression(),
+                right.get_record_compression(),
+            ),
+            record_block_size: Changed::compare(
+                left.get_record_block_size(),
+                right.get_record_block_size(),
+            ),
+            name_compression: Changed::compare(
+                left.get_name_compression(),
+                right.get_name_compression(),
+            ),
+            name_block_size: Changed::compare(
+                left.get_name_block_size(),
+                right.get_name_block_size(),
+            ),
+        },
+        added: right_names
+            .difference(&left_names)
+            .map(|name| name.to_string())
+            .collect(),
+        removed: left_names
+            .difference(&right_names)
+            .map(|name| name.to_string())
+            .collect(),
+        ..Default::default()
+    };
+
+    for name in left_names.intersection(&right_names) {
+        let mut left_file = left.by_name(name)?;
+        let mut right_file = right.by_name(name)?;
+
+        let modified = match (options.mode, left_file.md5(), right_file.md5()) {
+            (CompareMode::Hashes, Some(left_hash), Some(right_hash)) => {
+                report.compared_by_hash += 1;
+                left_hash != right_hash
+            }
+            _ => {
+                report.compared_by_contents += 1;
+                if left_file.size() != right_file.size() {
+                    true
+                } else {
+                    let mut left_data = Vec::with_capacity(left_file.size() as usize);
+                    left_file.read_to_end(&mut left_data)?;
+                    let mut right_data = Vec::with_capacity(right_file.size() as usize);
+                    right_file.read_to_end(&mut right_data)?;
+                    left_data != right_data
+                }
+            }
+        };
+
+        if modified {
+            report.modified.push(ModifiedEntry {
+                name: name.to_string(),
+                left_size: left_file.size(),
+                right_size: right_file.size(),
+            });
+        }
+    }
+
+    Span::current()
+        .record("added", report.added.len())
+        .record("removed", report.removed.len())
+        .record("modified", report.modified.len());
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Write};
+
+    use crate::{
+        compression::CompressionMethod,
+        diff::{compare, Changed, CompareMode, DiffOptions, ModifiedEntry},
+        error::Result,
+        read::TreArchive,
+        write::{TreWriter, TreWriterOptions},
+    };
+
+    fn archive(entries: &[(&str, &str)], hash_block: bool) -> Result<TreArchive<Cursor<Vec<u8>>>> {
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder().hash_block(hash_block).build(),
+        );
+        for (name, contents) in entries {
+            tre.start_file(*name, CompressionMethod::Zlib)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+
+        TreArchive::new(tre.finish()?)
+    }
+
+    #[test]
+    fn compare_archives() -> Result<()> {
+        let left_entries = [("a.txt", "one"), ("b.txt", "two"), ("c.txt", "three")];
+        let right_entries = [("b.txt", "two"), ("c.txt", "THREE"), ("d.txt", "four")];
+
+        for (mode, hash_block, by_hash) in [
+            (CompareMode::Contents, true, 0),
+            (CompareMode::Hashes, true, 2),
+            (CompareMode::Hashes, false, 0),
+        ] {
+            let left = archive(&left_entries, hash_block)?;
+            let right = archive(&right_entries, hash_block)?;
+
+            let report = compare(&left, &right, DiffOptions::builder().mode(mode).build())?;
+            assert_eq!(report.added, ["d.txt"]);
+            assert_eq!(report.removed, ["a.txt"]);
+            assert_eq!(
+                report.modified,
+                [ModifiedEntry {
+                    name: "c.txt".into(),
+                    left_size: 5,
+                    right_size: 5,
+                }]
+            );
+            assert!(report.header.is_empty());
+            assert_eq!(report.compared_by_hash, by_hash);
+            assert_eq!(report.compared_by_contents, 2 - by_hash);
+        }
+
+        let left = archive(&left_entries, true)?;
+        let right = archive(&right_entries[..2], true)?;
+        let report = compare(&left, &right, DiffOptions::default())?;
+        assert_eq!(report.header.entries, Some(Changed { left: 3, right: 2 }));
+
+        Ok(())
+    }
+}
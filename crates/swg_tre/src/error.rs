@@ -22,6 +22,59 @@ pub enum Error {
     #[error("unable to find requested file")]
     FileNotFound(#[from] FileNotFoundError),
 
+    /// archive exceeds a configured parsing limit
+    #[error("archive exceeds a configured parsing limit")]
+    LimitExceeded(#[from] LimitExceededError),
+
+    /// entry exceeds a configured decompression limit
+    #[error("entry exceeds a configured decompression limit")]
+    DecompressionBomb(#[from] DecompressionBombError),
+
+    /// archive references data outside of the file
+    #[error("archive references data outside of the file")]
+    OutOfBounds(#[from] OutOfBoundsError),
+
+    /// archive layout doesn't match its header
+    #[error("archive layout doesn't match its header")]
+    Layout(#[from] LayoutError),
+
+    /// unable to read archive metadata
+    #[error("unable to read archive metadata")]
+    Metadata(#[from] MetadataError),
+
+    /// entry name is not allowed
+    #[error("entry name is not allowed")]
+    InvalidName(#[from] InvalidNameError),
+
+    /// archive needs offset {0} which exceeds the 32-bit limit
+    #[error("archive needs offset {0} which exceeds the 32-bit limit")]
+    TooLarge(u64),
+
+    /// Transparent wrapper for [`zip::result::ZipError`]
+    #[cfg(feature = "zip")]
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+
+    /// unable to use patch
+    #[error("unable to use patch")]
+    Patch(#[from] PatchError),
+
+    /// archive signature doesn't check out
+    #[error("archive signature doesn't check out")]
+    Signature(#[from] SignatureError),
+
+    /// unable to read client config
+    #[error("unable to read client config")]
+    Config(#[from] ConfigError),
+
+    /// table of contents is invalid
+    #[error("table of contents is invalid")]
+    Toc(#[from] TocError),
+
+    /// compression method {0} can not be used here
+    #[error("compression method {0} can not be used here")]
+    UnsupportedCompression(crate::compression::CompressionMethod),
+
     /// {0}
     #[error("{0}")]
     CustomError(String),
@@ -40,5 +93,326 @@ pub enum FileNotFoundError {
     Name(String),
 }
 
+/// Error type to provide further information when a parsing limit has been exceeded
+#[derive(Error, Diagnostic, Debug)]
+pub enum LimitExceededError {
+    /// record count {count} exceeds the limit of {limit}
+    #[error("record count {count} exceeds the limit of {limit}")]
+    Records {
+        /// The number of records declared by the header
+        count: u32,
+        /// The configured limit
+        limit: u32,
+    },
+
+    /// name block size {size} exceeds the limit of {limit}
+    #[error("name block size {size} exceeds the limit of {limit}")]
+    NameBlock {
+        /// The size of the name block declared by the header
+        size: u32,
+        /// The configured limit
+        limit: u32,
+    },
+
+    /// entry {index} size {size} exceeds the limit of {limit}
+    #[error("entry {index} size {size} exceeds the limit of {limit}")]
+    EntrySize {
+        /// The index of the offending record
+        index: usize,
+        /// The size declared by the record
+        size: u64,
+        /// The configured limit
+        limit: u64,
+    },
+}
+
+/// Error type to provide further information when an entry would decompress to more data than
+/// allowed
+#[derive(Error, Diagnostic, Debug, Clone)]
+pub enum DecompressionBombError {
+    /// entry decompresses to more than its declared size of {size}
+    #[error("entry decompresses to more than its declared size of {size}")]
+    Size {
+        /// The uncompressed size declared by the record
+        size: u64,
+    },
+
+    /// entry size {size} is more than {limit} times its compressed size {compressed_size}
+    #[error("entry size {size} is more than {limit} times its compressed size {compressed_size}")]
+    Ratio {
+        /// The uncompressed size declared by the record
+        size: u64,
+        /// The compressed size declared by the record
+        compressed_size: u64,
+        /// The configured limit
+        limit: u32,
+    },
+}
+
+impl Error {
+    /// Unwrap a [`DecompressionBombError`] raised while reading an entry, which [`std::io::Read`]
+    /// can only report as an I/O error
+    pub(crate) fn from_read(error: std::io::Error) -> Self {
+        match error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<DecompressionBombError>())
+        {
+            Some(bomb) => bomb.clone().into(),
+            None => error.into(),
+        }
+    }
+}
+
+/// Error type to provide further information when a block lies outside of the file
+#[derive(Error, Diagnostic, Debug)]
+pub enum OutOfBoundsError {
+    /// record block ({start}..{end}) exceeds file length {length}
+    #[error("record block ({start}..{end}) exceeds file length {length}")]
+    RecordBlock {
+        /// Start offset of the block
+        start: u64,
+        /// End offset of the block
+        end: u64,
+        /// Length of the file
+        length: u64,
+    },
+
+    /// name block ({start}..{end}) exceeds file length {length}
+    #[error("name block ({start}..{end}) exceeds file length {length}")]
+    NameBlock {
+        /// Start offset of the block
+        start: u64,
+        /// End offset of the block
+        end: u64,
+        /// Length of the file
+        length: u64,
+    },
+
+    /// archive name block ({start}..{end}) of a table of contents exceeds file length {length}
+    #[error(
+        "archive name block ({start}..{end}) of a table of contents exceeds file length {length}"
+    )]
+    ArchiveNameBlock {
+        /// Start offset of the block
+        start: u64,
+        /// End offset of the block
+        end: u64,
+        /// Length of the file
+        length: u64,
+    },
+
+    /// entry {index} data ({start}..{end}) exceeds file length {length}
+    #[error("entry {index} data ({start}..{end}) exceeds file length {length}")]
+    EntryData {
+        /// The index of the offending record
+        index: usize,
+        /// Start offset of the data
+        start: u64,
+        /// End offset of the data
+        end: u64,
+        /// Length of the file
+        length: u64,
+    },
+}
+
+/// Error type to provide further information when strict validation finds a block out of place
+#[derive(Error, Diagnostic, Debug)]
+pub enum LayoutError {
+    /// record block starts at {start}, inside the {size} byte header
+    #[error("record block starts at {start}, inside the {size} byte header")]
+    RecordStart {
+        /// Start offset of the record block
+        start: u64,
+        /// Size of the header
+        size: u64,
+    },
+
+    /// metadata blocks end at {end} but the file length is {length}
+    #[error("metadata blocks end at {end} but the file length is {length}")]
+    Length {
+        /// End offset of the last metadata block, including the hash block if there is one
+        end: u64,
+        /// Length of the file
+        length: u64,
+    },
+
+    /// entry {index} data ({start}..{end}) overlaps the {size} byte header
+    #[error("entry {index} data ({start}..{end}) overlaps the {size} byte header")]
+    Header {
+        /// The index of the offending record
+        index: usize,
+        /// Start offset of the data
+        start: u64,
+        /// End offset of the data
+        end: u64,
+        /// Size of the header
+        size: u64,
+    },
+
+    /// entry {index} data ({start}..{end}) overlaps the metadata blocks ({metadata_start}..{metadata_end})
+    #[error(
+        "entry {index} data ({start}..{end}) overlaps the metadata blocks ({metadata_start}..{metadata_end})"
+    )]
+    Metadata {
+        /// The index of the offending record
+        index: usize,
+        /// Start offset of the data
+        start: u64,
+        /// End offset of the data
+        end: u64,
+        /// Start offset of the record block
+        metadata_start: u64,
+        /// End offset of the last metadata block
+        metadata_end: u64,
+    },
+}
+
+/// Error type to provide further information when part of the metadata could not be read
+#[derive(Error, Diagnostic, Debug)]
+pub enum MetadataError {
+    /// record {0} could not be read
+    #[error("record {0} could not be read")]
+    Record(usize),
+
+    /// name of record {0} could not be read
+    #[error("name of record {0} could not be read")]
+    Name(usize),
+
+    /// name of record {0} is not valid UTF-8
+    #[error("name of record {0} is not valid UTF-8")]
+    NameEncoding(usize),
+
+    /// name of record {0} is used by an earlier record
+    #[error("name of record {0} is used by an earlier record")]
+    DuplicateName(usize),
+
+    /// record {index} ({name}) is compressed with unknown method {value}
+    #[error("record {index} ({name}) is compressed with unknown method {value}")]
+    UnknownCompression {
+        /// The index of the record
+        index: usize,
+        /// The name of the entry, lossily decoded if it isn't valid UTF-8
+        name: String,
+        /// The compression method the record stores
+        value: u32,
+    },
+}
+
+/// Error type to provide further information when a patch can't be read or applied
+#[derive(Error, Diagnostic, Debug)]
+pub enum PatchError {
+    /// file is not a tre patch
+    #[error("file is not a tre patch")]
+    InvalidPatch,
+
+    /// patch version {0} is not supported
+    #[error("patch version {0} is not supported")]
+    UnsupportedVersion(u32),
+
+    /// entry {0} the patch is based on is missing
+    #[error("entry {0} the patch is based on is missing")]
+    MissingBase(String),
+
+    /// entry {0} differs from the one the patch is based on
+    #[error("entry {0} differs from the one the patch is based on")]
+    BaseMismatch(String),
+}
+
+/// Error type to provide further information when an archive's signature can't be verified
+#[derive(Error, Diagnostic, Debug)]
+pub enum SignatureError {
+    /// archive is not signed
+    #[error("archive is not signed")]
+    Missing,
+
+    /// archive was not signed by this key or was changed after signing
+    #[error("archive was not signed by this key or was changed after signing")]
+    Invalid,
+}
+
+/// Error type to provide further information when a client config can't be read
+#[derive(Error, Diagnostic, Debug)]
+pub enum ConfigError {
+    /// config {0} includes itself
+    #[error("config {0} includes itself")]
+    IncludeLoop(std::path::PathBuf),
+
+    /// line {line} has malformed search key {key}
+    #[error("line {line} has malformed search key {key}")]
+    InvalidKey {
+        /// The line the key is on, starting from 1
+        line: usize,
+        /// The malformed key
+        key: String,
+    },
+}
+
+/// Error type to provide further information when a table of contents is invalid
+#[derive(Error, Diagnostic, Debug)]
+pub enum TocError {
+    /// entry {index} refers to archive {archive} of {count}
+    #[error("entry {index} refers to archive {archive} of {count}")]
+    ArchiveIndex {
+        /// The index of the offending entry
+        index: usize,
+        /// The archive the entry refers to
+        archive: u16,
+        /// The number of archives in the table
+        count: usize,
+    },
+
+    /// entry {index} name offset {offset} is outside the name block
+    #[error("entry {index} name offset {offset} is outside the name block")]
+    NameOffset {
+        /// The index of the offending entry
+        index: usize,
+        /// The offset of the entry's name
+        offset: u32,
+    },
+
+    /// table of contents can't refer to more than {0} archives
+    #[error("table of contents can't refer to more than {0} archives")]
+    TooManyArchives(usize),
+}
+
 /// Generic result type with crate's Error as its error variant
 pub type Result<T> = core::result::Result<T, Error>;
+
+/// Error type to provide further information when an entry name is rejected by the writer
+#[derive(Error, Diagnostic, Debug)]
+pub enum InvalidNameError {
+    /// name is empty
+    #[error("name is empty")]
+    Empty,
+
+    /// name {0:?} contains a NUL byte
+    #[error("name {0:?} contains a NUL byte")]
+    Nul(String),
+
+    /// name {name:?} is {length} bytes which exceeds the limit of {limit}
+    #[error("name {name:?} is {length} bytes which exceeds the limit of {limit}")]
+    TooLong {
+        /// The rejected name
+        name: String,
+        /// The length of the name in bytes
+        length: usize,
+        /// The configured limit
+        limit: usize,
+    },
+
+    /// name {0:?} contains a backslash separator
+    #[error("name {0:?} contains a backslash separator")]
+    Separator(String),
+
+    /// name {0:?} is not a normalized relative path
+    #[error("name {0:?} is not a normalized relative path")]
+    NotNormalized(String),
+
+    /// path {0:?} is not valid unicode
+    #[error("path {0:?} is not valid unicode")]
+    NotUnicode(String),
+
+    /// name {0:?} has already been written
+    #[error("name {0:?} has already been written")]
+    Duplicate(String),
+}
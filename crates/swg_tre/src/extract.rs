@@ -0,0 +1,395 @@
+//! Types for extracting the entries of TRE archives into a directory
+//!
+
+use bon::Builder;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{self, Read, Seek},
+    path::{Component, Path},
+    time::SystemTime,
+};
+use tracing::{info, instrument, warn, Span};
+
+use crate::{
+    error::{Error, InvalidNameError, Result},
+    read::{TreArchive, TreFile},
+};
+
+/// Options for how an archive is extracted
+#[derive(Debug, Clone, Copy, Default, Builder)]
+pub struct ExtractOptions {
+    /// Replace files which already exist in the target directory instead of skipping them
+    #[builder(default)]
+    pub overwrite: bool,
+
+    /// Also write every entry listed by [`TreArchive::duplicates`], numbering each name
+    ///
+    /// The variants of a name are numbered from 1 in the order they're stored, before the
+    /// extension, so the shadowed copies of `a/b.txt` are written to `a/b.1.txt`, `a/b.2.txt` and
+    /// so on.
+    #[builder(default)]
+    pub variants: bool,
+
+    /// Make every written file read-only, as for a mirror which is served as it is
+    ///
+    /// Read-only files are made writable again before they are overwritten.
+    #[builder(default)]
+    pub read_only: bool,
+
+    /// The modification time to give every written file instead of the time it was written
+    ///
+    /// A fixed time keeps extracted trees identical between runs, and callers can take it from a
+    /// sidecar such as the archive itself.
+    pub modified: Option<SystemTime>,
+
+    /// Flush every written file, and the directories they're written into, to disk
+    ///
+    /// Directories are only flushed on Unix, where their entries are otherwise not durable.
+    #[builder(default)]
+    pub sync: bool,
+}
+
+/// An entry which couldn't be extracted
+#[derive(Debug)]
+pub struct FailedEntry {
+    /// The name of the entry
+    pub name: String,
+    /// Why the entry couldn't be extracted
+    pub error: Error,
+}
+
+/// What happened to each entry of an extracted archive, in the order the entries are stored
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    /// Entries written to files which didn't exist yet
+    pub written: Vec<String>,
+    /// Entries written over files which already existed
+    pub overwritten: Vec<String>,
+    /// Entries skipped because their file already existed
+    pub skipped: Vec<String>,
+    /// Entries which couldn't be extracted
+    pub failed: Vec<FailedEntry>,
+}
+
+impl ExtractReport {
+    /// Whether every entry was either written or skipped
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+enum Outcome {
+    Written,
+    Overwritten,
+    Skipped,
+}
+
+impl ExtractReport {
+    fn record(&mut self, name: String, outcome: Result<Outcome>) {
+        match outcome {
+            Ok(Outcome::Written) => self.written.push(name),
+            Ok(Outcome::Overwritten) => self.overwritten.push(name),
+            Ok(Outcome::Skipped) => {
+                info!("skipping {}, it already exists", name);
+                self.skipped.push(name);
+            }
+            Err(error) => {
+                warn!("unable to extract {}: {}", name, error);
+                self.failed.push(FailedEntry { name, error });
+            }
+        }
+    }
+}
+
+/// Insert a number before the extension of the last component of a name
+fn numbered(name: &str, number: usize) -> String {
+    let file_start = name.rfind('/').map_or(0, |i| i + 1);
+    match name[file_start..].rfind('.') {
+        Some(0) | None => format!("{}.{}", name, number),
+        Some(dot) => {
+            let (stem, extension) = name.split_at(file_start + dot);
+            format!("{}.{}{}", stem, number, extension)
+        }
+    }
+}
+
+/// Extract every entry of an archive into a directory
+///
+/// An entry which fails to extract is recorded in the report and doesn't stop the remaining
+/// entries from being extracted. Names which would escape the directory, such as absolute paths
+/// or those with `..` components, are rejected with [`InvalidNameError::NotNormalized`].
+#[instrument(skip_all, err, fields(directory = %directory.as_ref().display(), written, skipped, failed))]
+pub fn extract<R: Read + Seek>(
+    archive: &TreArchive<R>,
+    directory: impl AsRef<Path>,
+    options: ExtractOptions,
+) -> Result<ExtractReport> {
+    let directory = directory.as_ref();
+    let mut report = ExtractReport::default();
+
+    for name in archive.file_names() {
+        let outcome = extract_entry(|| archive.by_name(name), name, directory, options);
+        report.record(name.to_owned(), outcome);
+    }
+
+    if options.variants {
+        let mut counts = HashMap::new();
+        for (index, duplicate) in archive.duplicates().iter().enumerate() {
+            let count = counts.entry(duplicate.file_name()).or_insert(0);
+            *count += 1;
+
+            let name = numbered(duplicate.file_name(), *count);
+            let outcome = extract_entry(|| archive.by_duplicate(index), &name, directory, options);
+            report.record(name, outcome);
+        }
+    }
+
+    if options.sync {
+        let directories = report
+            .written
+            .iter()
+            .chain(&report.overwritten)
+            .filter_map(|name| directory.join(name).parent().map(Path::to_path_buf))
+            .collect::<BTreeSet<_>>();
+        for directory in directories {
+            sync_directory(&directory)?;
+        }
+    }
+
+    Span::current()
+        .record("written", report.written.len() + report.overwritten.len())
+        .record("skipped", report.skipped.len())
+        .record("failed", report.failed.len());
+
+    Ok(report)
+}
+
+#[instrument(name = "entry", skip(open, directory, options), fields(size, written))]
+fn extract_entry<'a, R: Read + Seek + 'a>(
+    open: impl FnOnce() -> Result<TreFile<'a, R>>,
+    name: &str,
+    directory: &Path,
+    options: ExtractOptions,
+) -> Result<Outcome> {
+    let relative = Path::new(name);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(InvalidNameError::NotNormalized(name.to_owned()).into());
+    }
+
+    let mut entry = open()?;
+    Span::current().record("size", entry.size());
+
+    let path = directory.join(relative);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let existed = path.exists();
+    if existed && options.overwrite {
+        make_writable(&path)?;
+    }
+    let mut out = match options.overwrite {
+        true => File::create(&path)?,
+        false => match File::create_new(&path) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(Outcome::Skipped),
+            result => result?,
+        },
+    };
+
+    info!("writing {}", path.display());
+    match io::copy(&mut entry, &mut out) {
+        Ok(written) => Span::current().record("written", written),
+        Err(e) => {
+            drop(out);
+            let _ = std::fs::remove_file(&path);
+            return Err(Error::from_read(e));
+        }
+    };
+
+    if let Some(modified) = options.modified {
+        out.set_modified(modified)?;
+    }
+    if options.sync {
+        out.sync_all()?;
+    }
+    drop(out);
+
+    if options.read_only {
+        let mut permissions = std::fs::metadata(&path)?.permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions)?;
+    }
+
+    Ok(if existed {
+        Outcome::Overwritten
+    } else {
+        Outcome::Written
+    })
+}
+
+/// Make a file which is about to be overwritten writable, in case an earlier extraction made it
+/// read-only
+fn make_writable(path: &Path) -> io::Result<()> {
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    if permissions.readonly() {
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        std::fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+/// Flush the entries of a directory to disk
+#[cfg(unix)]
+fn sync_directory(directory: &Path) -> io::Result<()> {
+    File::open(directory)?.sync_all()
+}
+
+/// Flush the entries of a directory to disk, which only Unix needs
+#[cfg(not(unix))]
+fn sync_directory(_directory: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Cursor, Write},
+        time::{Duration, SystemTime},
+    };
+
+    use crate::{
+        compression::CompressionMethod,
+        error::{Error, InvalidNameError, Result},
+        extract::{extract, numbered, ExtractOptions},
+        read::TreArchive,
+        write::{DuplicatePolicy, SeparatorPolicy, TreNamePolicy, TreWriter, TreWriterOptions},
+    };
+
+    #[test]
+    fn extract_report() -> Result<()> {
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .name_policy(
+                    TreNamePolicy::builder()
+                        .separators(SeparatorPolicy::Allow)
+                        .build(),
+                )
+                .build(),
+        );
+        for (name, contents) in [
+            ("a.txt", "one"),
+            ("dir/b.txt", "two"),
+            ("../c.txt", "three"),
+        ] {
+            tre.start_file(name, CompressionMethod::Zlib)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+        let tre = TreArchive::new(tre.finish()?)?;
+
+        let root = std::env::temp_dir().join(format!("swg_tre_extract_{}", std::process::id()));
+        let directory = root.join("out");
+        std::fs::create_dir_all(&directory)?;
+        std::fs::write(directory.join("a.txt"), "existing")?;
+
+        let report = extract(&tre, &directory, ExtractOptions::default())?;
+        assert_eq!(report.written, ["dir/b.txt"]);
+        assert_eq!(report.skipped, ["a.txt"]);
+        assert!(report.overwritten.is_empty());
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].name, "../c.txt");
+        assert!(matches!(
+            report.failed[0].error,
+            Error::InvalidName(InvalidNameError::NotNormalized(_))
+        ));
+        assert_eq!(
+            std::fs::read_to_string(directory.join("a.txt"))?,
+            "existing"
+        );
+        assert!(!root.join("c.txt").exists());
+
+        let options = ExtractOptions::builder().overwrite(true).build();
+        let report = extract(&tre, &directory, options)?;
+        assert_eq!(report.overwritten, ["a.txt", "dir/b.txt"]);
+        assert_eq!(std::fs::read_to_string(directory.join("a.txt"))?, "one");
+
+        std::fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn extract_permissions_and_times() -> Result<()> {
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        tre.start_file("dir/a.txt", CompressionMethod::Zlib)?;
+        tre.write_all(b"one")?;
+        let tre = TreArchive::new(tre.finish()?)?;
+
+        let directory = std::env::temp_dir().join(format!("swg_tre_times_{}", std::process::id()));
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let options = ExtractOptions::builder()
+            .overwrite(true)
+            .read_only(true)
+            .modified(modified)
+            .sync(true)
+            .build();
+
+        for _ in 0..2 {
+            let report = extract(&tre, &directory, options)?;
+            assert!(report.is_success());
+
+            let metadata = std::fs::metadata(directory.join("dir/a.txt"))?;
+            assert!(metadata.permissions().readonly());
+            assert_eq!(metadata.modified()?, modified);
+        }
+
+        std::fs::remove_dir_all(&directory)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn extract_variants() -> Result<()> {
+        assert_eq!(numbered("a/b.txt", 1), "a/b.1.txt");
+        assert_eq!(numbered("a.d/b", 2), "a.d/b.2");
+        assert_eq!(numbered(".hidden", 3), ".hidden.3");
+
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .duplicates(DuplicatePolicy::Allow)
+                .build(),
+        );
+        for contents in ["first", "second", "third"] {
+            tre.start_file("a/b.txt", CompressionMethod::Zlib)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+        let tre = TreArchive::new(tre.finish()?)?;
+
+        let directory =
+            std::env::temp_dir().join(format!("swg_tre_variants_{}", std::process::id()));
+        let options = ExtractOptions::builder().variants(true).build();
+        let report = extract(&tre, &directory, options)?;
+        assert_eq!(report.written, ["a/b.txt", "a/b.1.txt", "a/b.2.txt"]);
+
+        for (name, contents) in [
+            ("b.txt", "third"),
+            ("b.1.txt", "first"),
+            ("b.2.txt", "second"),
+        ] {
+            assert_eq!(
+                std::fs::read_to_string(directory.join("a").join(name))?,
+                contents
+            );
+        }
+
+        std::fs::remove_dir_all(&directory)?;
+
+        Ok(())
+    }
+}
@@ -87,6 +87,12 @@
 //! **Name Block Uncompressed Size**. The names are stored sequentially as UTF-8 strings, each ending with a
 //! null terminator. The offsets within the record metadata point to positions within this block.
 //!
+//! ### Hash Block
+//!
+//! Archives usually end with a block of 16-byte MD5 hashes, one per record and in record order, computed over
+//! each record's stored (possibly compressed) data. Some community tools omit this block, so readers detect it
+//! by checking whether the file contains a hash for every record after the name block.
+//!
 //! ## Additional Information
 //!
 //! - **File Extension**: `.tre`
@@ -96,11 +102,27 @@
 //!   - `2`: Zlib (compressed with Zlib)
 //!
 
+mod cache;
 pub mod compression;
+pub mod config;
+pub mod diff;
 pub mod error;
+pub mod extract;
+pub mod manifest;
+pub mod patch;
 pub mod read;
+pub mod sign;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod toc;
+pub mod transform;
 pub mod types;
+pub mod vfs;
+#[cfg(feature = "watch")]
+pub mod watch;
 pub mod write;
+#[cfg(feature = "zip")]
+pub mod zip;
 
 pub use compression::CompressionMethod;
 pub use read::TreArchive;
@@ -0,0 +1,69 @@
+//! Types describing the contents of a TRE archive, as produced by [`TreArchive::manifest`]
+//!
+//! [`TreArchive::manifest`]: crate::read::TreArchive::manifest
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::compression::CompressionMethod;
+
+/// A description of an archive and each of its entries
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Manifest {
+    /// The size of the whole archive
+    pub size: u64,
+    /// The MD5 hash of the whole archive
+    #[cfg_attr(feature = "serde", serde(with = "hex"))]
+    pub md5: [u8; 16],
+    /// Every entry, in the order they're listed by the archive
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// A description of a single entry
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ManifestEntry {
+    /// The name of the entry
+    pub name: String,
+    /// The size of the entry when extracted
+    pub size: u64,
+    /// The size of the entry as stored in the archive
+    pub compressed_size: u64,
+    /// How the entry is stored in the archive
+    pub compression: CompressionMethod,
+    /// The checksum stored in the entry's record, normally of its name
+    pub checksum: u32,
+    /// The CRC-32 of the extracted contents, as computed by zlib
+    pub crc32: u32,
+    /// The MD5 hash of the stored data, the same as the archive's hash block holds
+    #[cfg_attr(feature = "serde", serde(with = "hex"))]
+    pub md5: [u8; 16],
+}
+
+#[cfg(feature = "serde")]
+mod hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::fmt::Write;
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 16], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(32);
+        for byte in hash {
+            let _ = write!(hex, "{:02x}", byte);
+        }
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 16], D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() != 32 || !hex.is_ascii() {
+            return Err(D::Error::custom("expected 32 hex digits"));
+        }
+
+        let mut hash = [0; 16];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(D::Error::custom)?;
+        }
+        Ok(hash)
+    }
+}
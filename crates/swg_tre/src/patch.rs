@@ -0,0 +1,607 @@
+//! Types for creating and applying delta patches between two TRE archives
+//!
+//! A patch lists every entry of the target archive in order. Entries whose contents are unchanged
+//! refer back to the source archive, changed entries are stored as a delta against their previous
+//! contents and added entries are stored in full. Changed entries whose delta would be no smaller
+//! than their contents are stored in full as well. Applying a patch to the source archive writes an
+//! archive with the same entries, contents, compression methods and checksums as the target,
+//! though the stored data may be laid out differently.
+//!
+//! ## Format
+//!
+//! A patch starts with the magic `TPAT` and a version, followed by a zlib compressed body. All
+//! integers are little endian, names are a `u16` length followed by UTF-8 bytes and byte strings
+//! a `u32` length followed by the bytes.
+//!
+//! | Field              | Description                                                  |
+//! |--------------------|--------------------------------------------------------------|
+//! | Record Compression | 4 bytes: Compression method of the target's record block     |
+//! | Name Compression   | 4 bytes: Compression method of the target's name block       |
+//! | Hash Block         | 1 byte: Whether the target has a hash block                  |
+//! | Entry Count        | 4 bytes: The number of entries in the target                 |
+//! | Entries            | Each entry of the target, see below                          |
+//! | Removed Count      | 4 bytes: The number of entries only present in the source    |
+//! | Removed            | The name of each removed entry                               |
+//!
+//! Each entry is its name, compression method, checksum and a 1 byte kind:
+//!
+//! - `0`: Unchanged, followed by the checksum of the source contents
+//! - `1`: Full, followed by the contents as a byte string
+//! - `2`: Delta, followed by the checksum of the source contents, an operation count and the
+//!   operations. A `0` copies a `u32` length of bytes from a `u32` offset of the source contents
+//!   and a `1` inserts a byte string.
+//! - `3`: Replaced, followed by the contents as a byte string
+//!
+//! Checksums of contents are [`crc::CRC_32_BZIP2`], like record checksums.
+
+use bon::Builder;
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{Read, Seek, Write},
+};
+use tracing::{instrument, Span};
+
+use crate::{
+    compression::CompressionMethod,
+    error::{Error, PatchError, Result},
+    read::TreArchive,
+    write::{
+        ChecksumPolicy, DuplicatePolicy, SeparatorPolicy, TreNamePolicy, TreWriter,
+        TreWriterOptions, CHECKSUM,
+    },
+};
+
+const MAGIC: &[u8; 4] = b"TPAT";
+const VERSION: u32 = 1;
+
+/// Options for how a patch is created
+#[derive(Debug, Clone, Copy, Builder)]
+pub struct PatchOptions {
+    /// The size of the blocks of source contents that deltas look for in the target
+    ///
+    /// Smaller blocks find more matches in heavily edited entries but take longer to search.
+    #[builder(default = 32)]
+    pub block_size: usize,
+}
+
+impl Default for PatchOptions {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// A step in rebuilding an entry from its previous contents
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeltaOp {
+    /// Copy a range of the previous contents
+    Copy {
+        /// The offset into the previous contents
+        offset: u32,
+        /// The number of bytes to copy
+        len: u32,
+    },
+
+    /// Insert new bytes
+    Insert(Vec<u8>),
+}
+
+/// How the contents of an entry are recreated
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchData {
+    /// The contents are the same as in the source archive
+    Unchanged {
+        /// The checksum of the contents in the source archive
+        base_checksum: u32,
+    },
+
+    /// The entry is new and its contents are stored in the patch
+    Full(Vec<u8>),
+
+    /// The entry changed and its contents are stored in the patch, as a delta wouldn't be smaller
+    Replaced(Vec<u8>),
+
+    /// The contents are rebuilt from the contents in the source archive
+    Delta {
+        /// The checksum of the contents in the source archive
+        base_checksum: u32,
+        /// The steps to rebuild the contents
+        ops: Vec<DeltaOp>,
+    },
+}
+
+/// An entry of the target archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchEntry {
+    /// The name of the entry
+    pub name: String,
+    /// The compression method the entry is stored with
+    pub compression: CompressionMethod,
+    /// The checksum stored in the entry's record
+    pub checksum: u32,
+    /// How the entry's contents are recreated
+    pub data: PatchData,
+}
+
+/// The changes needed to turn one archive into another
+#[derive(Debug, Clone, PartialEq)]
+pub struct Patch {
+    /// The compression method of the target's record block
+    pub record_compression: CompressionMethod,
+    /// The compression method of the target's name block
+    pub name_compression: CompressionMethod,
+    /// Whether the target has a hash block
+    pub hash_block: bool,
+    /// Every entry of the target, in order
+    pub entries: Vec<PatchEntry>,
+    /// Entries only present in the source, in sorted order
+    pub removed: Vec<String>,
+}
+
+impl Patch {
+    /// Create a patch which turns `source` into `target`
+    #[instrument(skip_all, err, fields(entries, full, replaced, delta))]
+    pub fn create<A: Read + Seek, B: Read + Seek>(
+        source: &TreArchive<A>,
+        target: &TreArchive<B>,
+        options: PatchOptions,
+    ) -> Result<Patch> {
+        let target_names = target.file_names().collect::<BTreeSet<_>>();
+        let removed = source
+            .file_names()
+            .filter(|name| !target_names.contains(name))
+            .map(str::to_owned)
+            .collect::<BTreeSet<_>>();
+
+        let mut entries = Vec::with_capacity(target.len());
+        for name in target.file_names() {
+            let file = target.by_name(name)?;
+            let (compression, checksum) = (file.compression_method(), file.crc32());
+            let contents = target.contents_by_name(name)?;
+
+            let data = match source.index_for_name(name) {
+                None => PatchData::Full(contents.to_vec()),
+                Some(index) => {
+                    let base = source.contents_by_index(index)?;
+                    let base_checksum = CHECKSUM.checksum(&base);
+
+                    if base == contents {
+                        PatchData::Unchanged { base_checksum }
+                    } else {
+                        let ops = delta(&base, &contents, options.block_size.max(1));
+                        match delta_size(&ops) < contents.len() + 4 {
+                            true => PatchData::Delta { base_checksum, ops },
+                            false => PatchData::Replaced(contents.to_vec()),
+                        }
+                    }
+                }
+            };
+
+            entries.push(PatchEntry {
+                name: name.to_owned(),
+                compression,
+                checksum,
+                data,
+            });
+        }
+
+        let count = |f: fn(&PatchData) -> bool| entries.iter().filter(|e| f(&e.data)).count();
+        Span::current()
+            .record("entries", entries.len())
+            .record("full", count(|d| matches!(d, PatchData::Full(_))))
+            .record("replaced", count(|d| matches!(d, PatchData::Replaced(_))))
+            .record("delta", count(|d| matches!(d, PatchData::Delta { .. })));
+
+        Ok(Patch {
+            record_compression: target.get_record_compression(),
+            name_compression: target.get_name_compression(),
+            hash_block: target.has_hash_block(),
+            entries,
+            removed: removed.into_iter().collect(),
+        })
+    }
+
+    /// Entries of the target which aren't in the source
+    pub fn added(&self) -> impl Iterator<Item = &PatchEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.data, PatchData::Full(_)))
+    }
+
+    /// Apply the patch to `source`, writing the target archive to `writer`
+    ///
+    /// Every entry the patch refers to must have the same contents as when the patch was
+    /// created, otherwise [`PatchError::BaseMismatch`] is returned.
+    #[instrument(skip_all, err, fields(entries = self.entries.len()))]
+    pub fn apply<R: Read + Seek, W: Write + Seek>(
+        &self,
+        source: &TreArchive<R>,
+        writer: W,
+    ) -> Result<W> {
+        let mut tre = TreWriter::new(
+            writer,
+            TreWriterOptions::builder()
+                .record_compression(self.record_compression)
+                .name_compression(self.name_compression)
+                .hash_block(self.hash_block)
+                .duplicates(DuplicatePolicy::Error)
+                .name_policy(
+                    TreNamePolicy::builder()
+                        .max_length(u16::MAX as usize)
+                        .separators(SeparatorPolicy::Allow)
+                        .build(),
+                )
+                .build(),
+        );
+
+        for entry in &self.entries {
+            let base = |base_checksum: u32| -> Result<_> {
+                let base = source
+                    .contents_by_name(&entry.name)
+                    .map_err(|_| PatchError::MissingBase(entry.name.clone()))?;
+                if CHECKSUM.checksum(&base) != base_checksum {
+                    return Err(PatchError::BaseMismatch(entry.name.clone()).into());
+                }
+                Ok(base)
+            };
+
+            tre.start_file_with_checksum(
+                &entry.name,
+                entry.compression,
+                ChecksumPolicy::Value(entry.checksum),
+            )?;
+
+            match &entry.data {
+                PatchData::Unchanged { base_checksum } => tre.write_all(&base(*base_checksum)?)?,
+                PatchData::Full(contents) | PatchData::Replaced(contents) => {
+                    tre.write_all(contents)?
+                }
+                PatchData::Delta { base_checksum, ops } => {
+                    let base = base(*base_checksum)?;
+                    for op in ops {
+                        match op {
+                            DeltaOp::Copy { offset, len } => {
+                                let range = *offset as usize..(*offset as usize + *len as usize);
+                                let bytes = base
+                                    .get(range)
+                                    .ok_or_else(|| PatchError::BaseMismatch(entry.name.clone()))?;
+                                tre.write_all(bytes)?;
+                            }
+                            DeltaOp::Insert(bytes) => tre.write_all(bytes)?,
+                        }
+                    }
+                }
+            }
+        }
+
+        tre.finish()
+    }
+
+    /// Write the patch in its binary format
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LE>(VERSION)?;
+
+        let mut body = ZlibEncoder::new(writer, Compression::default());
+        write_compression(&mut body, self.record_compression)?;
+        write_compression(&mut body, self.name_compression)?;
+        body.write_u8(self.hash_block as u8)?;
+
+        body.write_u32::<LE>(self.entries.len() as u32)?;
+        for entry in &self.entries {
+            write_name(&mut body, &entry.name)?;
+            write_compression(&mut body, entry.compression)?;
+            body.write_u32::<LE>(entry.checksum)?;
+
+            match &entry.data {
+                PatchData::Unchanged { base_checksum } => {
+                    body.write_u8(0)?;
+                    body.write_u32::<LE>(*base_checksum)?;
+                }
+                PatchData::Full(contents) => {
+                    body.write_u8(1)?;
+                    write_bytes(&mut body, contents)?;
+                }
+                PatchData::Replaced(contents) => {
+                    body.write_u8(3)?;
+                    write_bytes(&mut body, contents)?;
+                }
+                PatchData::Delta { base_checksum, ops } => {
+                    body.write_u8(2)?;
+                    body.write_u32::<LE>(*base_checksum)?;
+                    body.write_u32::<LE>(ops.len() as u32)?;
+                    for op in ops {
+                        match op {
+                            DeltaOp::Copy { offset, len } => {
+                                body.write_u8(0)?;
+                                body.write_u32::<LE>(*offset)?;
+                                body.write_u32::<LE>(*len)?;
+                            }
+                            DeltaOp::Insert(bytes) => {
+                                body.write_u8(1)?;
+                                write_bytes(&mut body, bytes)?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        body.write_u32::<LE>(self.removed.len() as u32)?;
+        for name in &self.removed {
+            write_name(&mut body, name)?;
+        }
+
+        body.finish()?;
+
+        Ok(())
+    }
+
+    /// Read a patch from its binary format
+    pub fn read<R: Read>(mut reader: R) -> Result<Patch> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(PatchError::InvalidPatch.into());
+        }
+        match reader.read_u32::<LE>()? {
+            VERSION => {}
+            version => return Err(PatchError::UnsupportedVersion(version).into()),
+        }
+
+        let mut body = ZlibDecoder::new(reader);
+        let record_compression = read_compression(&mut body)?;
+        let name_compression = read_compression(&mut body)?;
+        let hash_block = body.read_u8()? != 0;
+
+        let count = body.read_u32::<LE>()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let name = read_name(&mut body)?;
+            let compression = read_compression(&mut body)?;
+            let checksum = body.read_u32::<LE>()?;
+
+            let data = match body.read_u8()? {
+                0 => PatchData::Unchanged {
+                    base_checksum: body.read_u32::<LE>()?,
+                },
+                1 => PatchData::Full(read_bytes(&mut body)?),
+                2 => {
+                    let base_checksum = body.read_u32::<LE>()?;
+                    let mut ops = Vec::new();
+                    for _ in 0..body.read_u32::<LE>()? {
+                        ops.push(match body.read_u8()? {
+                            0 => DeltaOp::Copy {
+                                offset: body.read_u32::<LE>()?,
+                                len: body.read_u32::<LE>()?,
+                            },
+                            1 => DeltaOp::Insert(read_bytes(&mut body)?),
+                            _ => return Err(PatchError::InvalidPatch.into()),
+                        });
+                    }
+                    PatchData::Delta { base_checksum, ops }
+                }
+                3 => PatchData::Replaced(read_bytes(&mut body)?),
+                _ => return Err(PatchError::InvalidPatch.into()),
+            };
+
+            entries.push(PatchEntry {
+                name,
+                compression,
+                checksum,
+                data,
+            });
+        }
+
+        let mut removed = Vec::new();
+        for _ in 0..body.read_u32::<LE>()? {
+            removed.push(read_name(&mut body)?);
+        }
+
+        Ok(Patch {
+            record_compression,
+            name_compression,
+            hash_block,
+            entries,
+            removed,
+        })
+    }
+}
+
+/// Find the steps to rebuild `data` from `base`, copying every run of `base` found in `data`
+/// which starts with a whole block
+fn delta(base: &[u8], data: &[u8], block_size: usize) -> Vec<DeltaOp> {
+    let mut blocks = HashMap::new();
+    for (i, block) in base.chunks_exact(block_size).enumerate() {
+        blocks.entry(block).or_insert(i * block_size);
+    }
+
+    let mut ops = Vec::new();
+    let mut literal_start = 0;
+    let mut position = 0;
+    while position + block_size <= data.len() {
+        let Some(&offset) = blocks.get(&data[position..position + block_size]) else {
+            position += 1;
+            continue;
+        };
+
+        // Grow the match in both directions, backwards only as far as the pending insert
+        let (mut base_start, mut data_start) = (offset, position);
+        while data_start > literal_start
+            && base_start > 0
+            && base[base_start - 1] == data[data_start - 1]
+        {
+            base_start -= 1;
+            data_start -= 1;
+        }
+        let (mut base_end, mut data_end) = (offset + block_size, position + block_size);
+        while base_end < base.len() && data_end < data.len() && base[base_end] == data[data_end] {
+            base_end += 1;
+            data_end += 1;
+        }
+
+        if data_start > literal_start {
+            ops.push(DeltaOp::Insert(data[literal_start..data_start].to_vec()));
+        }
+        ops.push(DeltaOp::Copy {
+            offset: base_start as u32,
+            len: (base_end - base_start) as u32,
+        });
+
+        position = data_end;
+        literal_start = data_end;
+    }
+
+    if literal_start < data.len() {
+        ops.push(DeltaOp::Insert(data[literal_start..].to_vec()));
+    }
+
+    ops
+}
+
+/// The number of bytes the operations of a delta take up in a patch, along with its checksum and
+/// operation count
+fn delta_size(ops: &[DeltaOp]) -> usize {
+    let op = |op: &DeltaOp| match op {
+        DeltaOp::Copy { .. } => 9,
+        DeltaOp::Insert(bytes) => 5 + bytes.len(),
+    };
+    8 + ops.iter().map(op).sum::<usize>()
+}
+
+fn write_name<W: Write>(writer: &mut W, name: &str) -> Result<()> {
+    writer.write_u16::<LE>(name.len() as u16)?;
+    writer.write_all(name.as_bytes())?;
+    Ok(())
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<()> {
+    writer.write_u32::<LE>(bytes.len() as u32)?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+fn read_compression<R: Read>(reader: &mut R) -> Result<CompressionMethod> {
+    match reader.read_u32::<LE>()? {
+        0 => Ok(CompressionMethod::None),
+        2 => Ok(CompressionMethod::Zlib),
+        3 => Ok(CompressionMethod::Zstd),
+        _ => Err(PatchError::InvalidPatch.into()),
+    }
+}
+
+fn write_compression<W: Write>(writer: &mut W, compression: CompressionMethod) -> Result<()> {
+    let value = match compression {
+        CompressionMethod::None => 0,
+        CompressionMethod::Zlib => 2,
+        CompressionMethod::Zstd => 3,
+        _ => return Err(Error::UnsupportedCompression(compression)),
+    };
+    Ok(writer.write_u32::<LE>(value)?)
+}
+
+fn read_name<R: Read>(reader: &mut R) -> Result<String> {
+    let len = reader.read_u16::<LE>()? as u64;
+    let mut name = Vec::new();
+    if reader.take(len).read_to_end(&mut name)? as u64 != len {
+        return Err(PatchError::InvalidPatch.into());
+    }
+    String::from_utf8(name).map_err(|_| PatchError::InvalidPatch.into())
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let len = reader.read_u32::<LE>()? as u64;
+    let mut bytes = Vec::new();
+    if reader.take(len).read_to_end(&mut bytes)? as u64 != len {
+        return Err(PatchError::InvalidPatch.into());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Read, Write};
+
+    use crate::{
+        compression::CompressionMethod,
+        error::{Error, PatchError, Result},
+        patch::{Patch, PatchData, PatchOptions},
+        read::TreArchive,
+        write::{TreWriter, TreWriterOptions},
+    };
+
+    fn archive(entries: &[(&str, &[u8])]) -> Result<TreArchive<Cursor<Vec<u8>>>> {
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        for (name, contents) in entries {
+            tre.start_file(*name, CompressionMethod::Zlib)?;
+            tre.write_all(contents)?;
+        }
+        TreArchive::new(tre.finish()?)
+    }
+
+    #[test]
+    fn patch_round_trip() -> Result<()> {
+        let original = (0..4096u32)
+            .flat_map(|i| (i * 7).to_le_bytes())
+            .collect::<Vec<_>>();
+        let mut edited = original.clone();
+        edited[1000..1010].copy_from_slice(b"0123456789");
+        edited.extend_from_slice(b"appended");
+
+        let source = archive(&[
+            ("same.txt", b"unchanged"),
+            ("gone.txt", b"removed"),
+            ("data.bin", &original),
+            ("small.txt", b"before"),
+        ])?;
+        let target = archive(&[
+            ("data.bin", &edited),
+            ("same.txt", b"unchanged"),
+            ("new.txt", b"added"),
+            ("small.txt", b"after"),
+        ])?;
+
+        let patch = Patch::create(&source, &target, PatchOptions::default())?;
+        assert_eq!(patch.removed, ["gone.txt"]);
+        assert_eq!(
+            patch.added().map(|e| e.name.as_str()).collect::<Vec<_>>(),
+            ["new.txt"]
+        );
+        assert!(matches!(patch.entries[0].data, PatchData::Delta { .. }));
+        assert!(matches!(patch.entries[1].data, PatchData::Unchanged { .. }));
+        assert_eq!(
+            patch.entries[3].data,
+            PatchData::Replaced(b"after".to_vec())
+        );
+
+        let mut encoded = Vec::new();
+        patch.write(&mut encoded)?;
+        assert!(encoded.len() < 200);
+        let patch = Patch::read(Cursor::new(&encoded))?;
+
+        let patched = TreArchive::new(patch.apply(&source, Cursor::new(Vec::new()))?)?;
+        assert_eq!(
+            patched.file_names().collect::<Vec<_>>(),
+            target.file_names().collect::<Vec<_>>()
+        );
+        for name in target.file_names() {
+            let (mut expected, mut actual) = (Vec::new(), Vec::new());
+            target.by_name(name)?.read_to_end(&mut expected)?;
+            patched.by_name(name)?.read_to_end(&mut actual)?;
+            assert_eq!(actual, expected);
+            assert_eq!(
+                patched.by_name(name)?.crc32(),
+                target.by_name(name)?.crc32()
+            );
+        }
+
+        // The patch only applies to the archive it was created from
+        assert!(matches!(
+            patch.apply(&target, Cursor::new(Vec::new())),
+            Err(Error::Patch(PatchError::BaseMismatch(_)))
+        ));
+
+        Ok(())
+    }
+}
@@ -2,25 +2,87 @@
 //!
 
 use binrw::BinRead;
-use byteorder::ReadBytesExt;
-use indexmap::IndexMap;
+use bon::Builder;
+use md5::{Digest, Md5};
 use std::{
     borrow::Cow,
-    fmt::{self, Debug},
-    io::{Read, Seek},
-    sync::Arc,
+    collections::HashMap,
+    fmt::{self, Debug, Write as _},
+    io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
 };
 
 use crate::{
-    compression::{CompressionMethod, TreBlockReader},
-    error::{Error, FileNotFoundError, Result},
+    cache::EntryCache,
+    compression::{CompressionMethod, DecoderRegistry, SizeGuard, TreBlockReader},
+    error::{
+        DecompressionBombError, Error, FileNotFoundError, LayoutError, LimitExceededError,
+        MetadataError, OutOfBoundsError, Result,
+    },
+    manifest::{Manifest, ManifestEntry},
+    sign,
+    transform::{BlockKind, BlockTransform},
     types::{TreHeader, TreRecord},
 };
+use tracing::{instrument, Span};
+
+/// The size of the header every archive starts with
+const HEADER_SIZE: u64 = 36;
+
+/// The most an entry's reader buffers at once, smaller entries get a buffer of their own size
+const BUFFER_SIZE: usize = 8 * 1024;
 
 /// A struct for reading an entry from a TRE file
+///
+/// Reads are buffered, so the entry can be used as a [`BufRead`] without wrapping it in a
+/// [`BufReader`].
 pub struct TreFile<'a, W: Read + Seek> {
     data: Cow<'a, TreFileData>,
-    reader: TreBlockReader<'a, W>,
+    reader: BufReader<SizeGuard<TreBlockReader<PositionedReader<'a, W>>>>,
+}
+
+/// A handle onto the archive's shared reader which tracks its own position
+///
+/// Every read locks the underlying reader and seeks to where this handle left off, so any number
+/// of handles can be read from at the same time.
+struct PositionedReader<'a, R> {
+    inner: &'a Mutex<R>,
+    position: u64,
+}
+
+impl<R: Read + Seek> Read for PositionedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // The reader holds no state besides its position, which is restored on every read
+        let mut reader = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        reader.seek(SeekFrom::Start(self.position))?;
+
+        let read = reader.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for PositionedReader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::Current(offset) => {
+                self.position.checked_add_signed(offset).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "invalid seek to a negative position",
+                    )
+                })?
+            }
+            SeekFrom::End(_) => self
+                .inner
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .seek(pos)?,
+        };
+        Ok(self.position)
+    }
 }
 
 impl<'a, W: Read + Seek> Debug for TreFile<'a, W> {
@@ -42,14 +104,14 @@ impl<'a, W: Read + Seek> TreFile<'a, W> {
     /// files.
     ///
     pub fn name(&self) -> &str {
-        &self.get_metadata().file_name
+        self.get_metadata().file_name()
     }
 
     /// Get the name of the file, in the raw (internal) byte representation.
     ///
     /// The encoding of this data is currently undefined.
     pub fn name_raw(&self) -> &[u8] {
-        &self.get_metadata().file_name_raw
+        self.get_metadata().file_name_raw()
     }
 
     /// Get the size of the file, in bytes, in the archive
@@ -77,6 +139,16 @@ impl<'a, W: Read + Seek> TreFile<'a, W> {
         self.get_metadata().compression_method
     }
 
+    /// Get the MD5 hash of the stored (possibly compressed) data, if the archive has a hash block
+    pub fn md5(&self) -> Option<[u8; 16]> {
+        self.get_metadata().md5
+    }
+
+    /// Get the record describing this file, exactly as the archive stores it
+    pub fn record(&self) -> TreRecord {
+        self.get_metadata().record()
+    }
+
     fn get_metadata(&self) -> &TreFileData {
         self.data.as_ref()
     }
@@ -88,8 +160,66 @@ impl<W: Read + Seek> Read for TreFile<'_, W> {
     }
 }
 
+impl<W: Read + Seek> BufRead for TreFile<'_, W> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.reader.consume(amount)
+    }
+}
+
+/// A struct for reading an entry from a TRE file through its own reader
+///
+/// Unlike [`TreFile`], this does not borrow the archive, which allows it to be moved between
+/// threads. See [`TreArchive::by_index_owned`].
+pub struct TreEntry<R: Read> {
+    data: TreFileData,
+    reader: SizeGuard<TreBlockReader<R>>,
+}
+
+impl<R: Read> Debug for TreEntry<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TreEntry({:#?})", self.data)
+    }
+}
+
+impl<R: Read> TreEntry<R> {
+    /// Get the name of the file
+    ///
+    /// The same warnings as [`TreFile::name`] apply when using this name to extract files.
+    pub fn name(&self) -> &str {
+        self.data.file_name()
+    }
+
+    /// Get the size of the file, in bytes, when uncompressed
+    pub fn size(&self) -> u64 {
+        self.data.uncompressed_size
+    }
+
+    /// Get the compression method used for this file
+    pub fn compression_method(&self) -> CompressionMethod {
+        self.data.compression_method
+    }
+
+    /// Get the metadata describing this file
+    pub fn metadata(&self) -> &TreFileData {
+        &self.data
+    }
+}
+
+impl<R: Read> Read for TreEntry<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
 /// Structure representing a TRE file entry.
-#[derive(Debug, Clone, Default)]
+///
+/// Names are kept in a single buffer shared by every entry of an archive, so cloning an entry is
+/// cheap.
+#[derive(Clone)]
 pub struct TreFileData {
     /// CRC32 checksum
     pub crc32: u32,
@@ -99,20 +229,296 @@ pub struct TreFileData {
     pub compressed_size: u64,
     /// Size of the file when extracted
     pub uncompressed_size: u64,
-    /// Name of the file
-    pub file_name: Box<str>,
-    /// Raw file name. To be used when file_name was incorrectly decoded.
-    pub file_name_raw: Box<[u8]>,
+    /// The buffer holding the name of the file
+    names: Arc<NameArena>,
+    /// Where the decoded name lies in [`NameArena::text`]
+    name: Range<u32>,
+    /// Where the stored name lies in [`NameArena::raw`]
+    name_raw: Range<u32>,
+    /// The offset of the name the record points at in the name block
+    name_offset: u32,
     /// Specifies where the local header of the file starts
     pub header_start: u64,
     /// Specifies where the compressed data of the file starts
     pub data_start: u64,
+    /// MD5 hash of the stored data, if the archive has a hash block
+    pub md5: Option<[u8; 16]>,
+}
+
+impl Default for TreFileData {
+    fn default() -> Self {
+        Self {
+            crc32: Default::default(),
+            compression_method: Default::default(),
+            compressed_size: Default::default(),
+            uncompressed_size: Default::default(),
+            names: Default::default(),
+            name: 0..0,
+            name_raw: 0..0,
+            name_offset: 0,
+            header_start: Default::default(),
+            data_start: Default::default(),
+            md5: Default::default(),
+        }
+    }
+}
+
+impl Debug for TreFileData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TreFileData")
+            .field("crc32", &self.crc32)
+            .field("compression_method", &self.compression_method)
+            .field("compressed_size", &self.compressed_size)
+            .field("uncompressed_size", &self.uncompressed_size)
+            .field("file_name", &self.file_name())
+            .field("header_start", &self.header_start)
+            .field("data_start", &self.data_start)
+            .field("md5", &self.md5)
+            .finish()
+    }
+}
+
+impl TreFileData {
+    /// Name of the file
+    pub fn file_name(&self) -> &str {
+        &self.names.text[self.name.start as usize..self.name.end as usize]
+    }
+
+    /// Raw file name. To be used when file_name was incorrectly decoded.
+    pub fn file_name_raw(&self) -> &[u8] {
+        &self.names.raw[self.name_raw.start as usize..self.name_raw.end as usize]
+    }
+
+    /// The record describing this file, exactly as the archive stores it
+    ///
+    /// Names are read in the order they're stored rather than from [`TreRecord::name_offset`], so
+    /// a damaged offset is kept here as it is.
+    pub fn record(&self) -> TreRecord {
+        TreRecord {
+            checksum: self.crc32,
+            data_uncompressed: self.uncompressed_size as u32,
+            data_offset: self.data_start as u32,
+            data_compression: self.compression_method,
+            data_compressed: self.compressed_size as u32,
+            name_offset: self.name_offset,
+        }
+    }
+}
+
+/// Every name of an archive's entries, which entries refer to by their position
+#[derive(Debug, Default)]
+struct NameArena {
+    /// Every name as decoded by the archive's [`NameEncoding`], one after another
+    text: String,
+    /// The name block as stored
+    raw: Box<[u8]>,
+}
+
+/// Limits applied while parsing the metadata of an archive and reading its entries
+///
+/// The defaults comfortably fit every retail archive, but callers handling untrusted
+/// files may want to tighten them further.
+///
+/// Entries are also never read past the uncompressed size their record declares, so a record
+/// can't understate its size to get around [`TreLimits::max_entry_size`]. Reading such an entry
+/// fails with [`Error::DecompressionBomb`].
+#[derive(Debug, Clone, Copy, Builder)]
+pub struct TreLimits {
+    /// The maximum number of records an archive may declare
+    #[builder(default = 1 << 20)]
+    pub max_records: u32,
+
+    /// The maximum size of the name block, both compressed and uncompressed
+    #[builder(default = 64 << 20)]
+    pub max_name_block_size: u32,
+
+    /// The maximum size of a single entry, both compressed and uncompressed
+    #[builder(default = 512 << 20)]
+    pub max_entry_size: u64,
+
+    /// The most an entry's uncompressed size may be a multiple of its compressed size
+    ///
+    /// The default is the most Zlib can achieve, which only rejects entries whose declared sizes
+    /// can't be genuine. Opening an entry over the limit fails with [`Error::DecompressionBomb`].
+    #[builder(default = 1032)]
+    pub max_compression_ratio: u32,
+}
+
+impl Default for TreLimits {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Options for how a TRE file should be read
+#[derive(Debug, Clone, Default, Builder)]
+pub struct TreArchiveOptions {
+    /// The limits to enforce while parsing the archive
+    #[builder(default)]
+    pub limits: TreLimits,
+
+    /// The number of bytes of decompressed entries to keep in memory, zero disables the cache
+    ///
+    /// Only [`TreArchive::contents_by_index`] and [`TreArchive::contents_by_name`] go through
+    /// the cache.
+    #[builder(default)]
+    pub cache_size: u64,
+
+    /// How entry names which aren't valid UTF-8 are handled
+    #[builder(default)]
+    pub name_encoding: NameEncoding,
+
+    /// How entries which share a name with an earlier entry are handled
+    #[builder(default)]
+    pub duplicates: DuplicatePolicy,
+
+    /// Check that every block lies where the header says it does
+    ///
+    /// The metadata blocks must start after the header and end exactly at the end of the file,
+    /// and no entry's data may overlap either of them. Archives which don't are rejected with
+    /// [`Error::Layout`], and when reading lossily the misplaced entries are skipped instead.
+    #[builder(default)]
+    pub strict: bool,
+
+    /// A transform to undo on every stored block before it is decompressed
+    ///
+    /// See [`crate::transform`] for archives which need one.
+    pub transform: Option<Arc<dyn BlockTransform>>,
+
+    /// The decoders for blocks stored with a [`CompressionMethod::Other`] value
+    ///
+    /// Entries stored with a value no decoder is registered for are rejected with
+    /// [`MetadataError::UnknownCompression`], which reading lossily skips.
+    #[builder(default)]
+    pub decoders: DecoderRegistry,
+}
+
+/// How entries which share a name with an earlier entry in the same archive are handled
+///
+/// Retail archives do contain such shadowed entries. Whatever the policy, every entry a name no
+/// longer finds is listed by [`TreArchive::duplicates`], except under [`DuplicatePolicy::Error`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Reject the entry with [`MetadataError::DuplicateName`]
+    Error,
+
+    /// Keep the earliest entry with the name and ignore the rest
+    KeepFirst,
+
+    /// Keep the latest entry with the name, at the index of the earliest one
+    #[default]
+    KeepLast,
+
+    /// Keep every entry at its own index, looking a name up finds the latest one
+    KeepAll,
+}
+
+/// How entry names which aren't valid UTF-8 are turned into the names entries are listed under
+///
+/// Whatever the policy, entries with such names can always be looked up by their original bytes
+/// with [`TreArchive::by_name_raw`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NameEncoding {
+    /// Replace invalid sequences with U+FFFD, names which decode the same replace each other
+    #[default]
+    Lossy,
+
+    /// Reject the entry with [`MetadataError::NameEncoding`]
+    Error,
+
+    /// Escape each invalid byte as `\xNN`, which keeps every distinct name distinct
+    Raw,
+}
+
+impl NameEncoding {
+    /// Decode a name onto the end of `decoded`, which is left as it is when the name is rejected
+    fn decode(&self, index: usize, name: &[u8], decoded: &mut String) -> Result<()> {
+        let mut bytes = name;
+        loop {
+            match std::str::from_utf8(bytes) {
+                Ok(valid) => {
+                    decoded.push_str(valid);
+                    return Ok(());
+                }
+                Err(_) if *self == NameEncoding::Error => {
+                    return Err(MetadataError::NameEncoding(index).into())
+                }
+                Err(_) if *self == NameEncoding::Lossy => {
+                    decoded.push_str(&String::from_utf8_lossy(name));
+                    return Ok(());
+                }
+                Err(e) => {
+                    let (valid, rest) = bytes.split_at(e.valid_up_to());
+                    decoded.push_str(std::str::from_utf8(valid).expect("checked by valid_up_to"));
+
+                    let invalid = e.error_len().unwrap_or(rest.len());
+                    for b in &rest[..invalid] {
+                        let _ = write!(decoded, "\\x{:02X}", b);
+                    }
+                    bytes = &rest[invalid..];
+                }
+            }
+        }
+    }
+}
+
+/// An entry which was skipped while reading an archive with [`TreArchive::new_lossy`]
+#[derive(Debug)]
+pub struct SkippedEntry {
+    /// The index of the record in the archive
+    pub index: usize,
+    /// The name of the entry, if it could be read
+    pub name: Option<Box<str>>,
+    /// Why the entry was skipped
+    pub error: Error,
+}
+
+/// A summary of an archive read from its header alone, see [`TreArchive::peek_header`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreInfo {
+    /// The number of records the archive declares
+    pub records: u32,
+    /// How the record block is compressed
+    pub record_compression: CompressionMethod,
+    /// The size of the record block within the file
+    pub record_block_size: u32,
+    /// How the name block is compressed
+    pub name_compression: CompressionMethod,
+    /// The size of the name block within the file
+    pub name_block_size: u32,
+    /// The size of the name block once decompressed
+    pub name_size: u32,
+    /// The size of the whole file
+    pub length: u64,
 }
 
 #[derive(Debug)]
 pub(crate) struct Shared {
     header: TreHeader,
-    files: IndexMap<Box<str>, TreFileData>,
+    files: Vec<TreFileData>,
+    /// The index of the entry each name resolves to, sorted by name
+    names: Vec<u32>,
+    /// Every entry shadowed by a later or earlier entry with the same name
+    duplicates: Vec<TreFileData>,
+    /// The index of every entry whose name isn't valid UTF-8, keyed by the name's bytes
+    raw_names: HashMap<Box<[u8]>, usize>,
+    has_hash_block: bool,
+    limits: TreLimits,
+    transform: Option<Arc<dyn BlockTransform>>,
+    /// The decoders for blocks stored with a [`CompressionMethod::Other`] value
+    decoders: DecoderRegistry,
+    /// The signature the archive ends with, alongside the length of the archive it signs
+    signature: Option<([u8; 64], u64)>,
+}
+
+impl Shared {
+    /// The index of the entry a name resolves to
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.names
+            .binary_search_by(|index| self.files[*index as usize].file_name().cmp(name))
+            .ok()
+            .map(|found| self.names[found] as usize)
+    }
 }
 
 /// TRE archive reader
@@ -121,7 +527,7 @@ pub(crate) struct Shared {
 /// use std::io::prelude::*;
 ///
 /// fn list_tre_contents(reader: impl Read + Seek) -> swg_tre::error::Result<()> {
-///     let mut tre = swg_tre::TreArchive::new(reader)?;
+///     let tre = swg_tre::TreArchive::new(reader)?;
 ///
 ///     for i in 0..tre.len() {
 ///         let mut file = tre.by_index(i)?;
@@ -132,17 +538,30 @@ pub(crate) struct Shared {
 ///     Ok(())
 /// }
 /// ```
+///
+/// Entries are looked up through a shared reference, so an archive can be shared between threads
+/// and several entries read at once. Reads of the underlying reader are serialized internally.
 pub struct TreArchive<R> {
-    reader: R,
+    reader: Mutex<R>,
     shared: Arc<Shared>,
+    cache: Option<Mutex<EntryCache>>,
 }
 
 impl<R> TreArchive<R> {
+    fn from_parts(reader: R, shared: Shared, options: &TreArchiveOptions) -> Self {
+        TreArchive {
+            reader: Mutex::new(reader),
+            shared: shared.into(),
+            cache: (options.cache_size > 0)
+                .then(|| Mutex::new(EntryCache::new(options.cache_size))),
+        }
+    }
+
     /// Total size of the files in the archive, if it can be known. Doesn't include directories or
     /// metadata.
     pub fn decompressed_size(&self) -> Option<u128> {
         let mut total = 0u128;
-        for file in self.shared.files.values() {
+        for file in &self.shared.files {
             total = total.checked_add(file.uncompressed_size as u128)?;
         }
         Some(total)
@@ -150,16 +569,84 @@ impl<R> TreArchive<R> {
 }
 
 impl<R: Read + Seek> TreArchive<R> {
+    /// Read only the header of a TRE archive, without reading any of its records or names
+    ///
+    /// This is much cheaper than opening the archive when scanning many files, but nothing beyond
+    /// the header is validated. A reader which doesn't start with a header fails with
+    /// [`Error::InvalidArchive`].
+    pub fn peek_header(mut reader: R) -> Result<TreInfo> {
+        let length = reader.seek(SeekFrom::End(0))?;
+        reader.rewind()?;
+
+        let header = TreHeader::read(&mut reader).map_err(|_| Error::InvalidArchive)?;
+
+        Ok(TreInfo {
+            records: header.records,
+            record_compression: header.record_compression,
+            record_block_size: header.record_compressed,
+            name_compression: header.name_compression,
+            name_block_size: header.name_compressed,
+            name_size: header.name_uncompressed,
+            length,
+        })
+    }
+
     /// Read a TRE archive collecting the files it contains.
-    pub fn new(mut reader: R) -> Result<TreArchive<R>> {
-        if let Ok(shared) = Self::get_metadata(&mut reader) {
-            return Ok(TreArchive {
-                reader,
-                shared: shared.into(),
-            });
+    pub fn new(reader: R) -> Result<TreArchive<R>> {
+        Self::with_options(reader, TreArchiveOptions::default())
+    }
+
+    /// Read a TRE archive collecting the files it contains, using the provided options.
+    ///
+    /// Archives that exceed the configured [`TreLimits`] or reference data outside of the file
+    /// are rejected with [`Error::LimitExceeded`] or [`Error::OutOfBounds`] respectively, and
+    /// names rejected by [`NameEncoding::Error`] or [`DuplicatePolicy::Error`] and entries with an
+    /// unknown compression method with [`Error::Metadata`]. With [`TreArchiveOptions::strict`],
+    /// misplaced blocks are rejected with [`Error::Layout`]. Metadata compressed with
+    /// [`CompressionMethod::Zstd`] fails with [`Error::UnsupportedCompression`] without the `zstd`
+    /// feature, as does metadata stored with a [`CompressionMethod::Other`] value no decoder is
+    /// registered for.
+    pub fn with_options(mut reader: R, options: TreArchiveOptions) -> Result<TreArchive<R>> {
+        match Self::get_metadata(&mut reader, &options, false) {
+            Ok((shared, _)) => Ok(Self::from_parts(reader, shared, &options)),
+            Err(
+                e @ (Error::LimitExceeded(_)
+                | Error::OutOfBounds(_)
+                | Error::Layout(_)
+                | Error::UnsupportedCompression(_)
+                | Error::Metadata(
+                    MetadataError::NameEncoding(_)
+                    | MetadataError::DuplicateName(_)
+                    | MetadataError::UnknownCompression { .. },
+                )),
+            ) => Err(e),
+            Err(_) => Err(Error::InvalidArchive),
         }
+    }
 
-        Err(Error::InvalidArchive)
+    /// Read a TRE archive, skipping any entries which can't be parsed instead of failing.
+    ///
+    /// This returns the readable subset of the archive alongside a description of every entry that
+    /// was skipped. Only a missing header, a configured [`TreLimits`] being exceeded or metadata
+    /// which can't be decompressed causes this to fail outright.
+    pub fn new_lossy(reader: R) -> Result<(TreArchive<R>, Vec<SkippedEntry>)> {
+        Self::with_options_lossy(reader, TreArchiveOptions::default())
+    }
+
+    /// Read a TRE archive using the provided options, skipping any entries which can't be parsed.
+    ///
+    /// See [`TreArchive::new_lossy`] for details.
+    pub fn with_options_lossy(
+        mut reader: R,
+        options: TreArchiveOptions,
+    ) -> Result<(TreArchive<R>, Vec<SkippedEntry>)> {
+        match Self::get_metadata(&mut reader, &options, true) {
+            Ok((shared, skipped)) => Ok((Self::from_parts(reader, shared, &options), skipped)),
+            Err(
+                e @ (Error::LimitExceeded(_) | Error::Layout(_) | Error::UnsupportedCompression(_)),
+            ) => Err(e),
+            Err(_) => Err(Error::InvalidArchive),
+        }
     }
 
     /// Number of entries contained in this TRE.
@@ -174,7 +661,33 @@ impl<R: Read + Seek> TreArchive<R> {
 
     /// Returns an iterator over all the file and directory names in this archive.
     pub fn file_names(&self) -> impl Iterator<Item = &str> {
-        self.shared.files.keys().map(|s| s.as_ref())
+        self.shared.files.iter().map(TreFileData::file_name)
+    }
+
+    /// Returns an iterator over the record of every lookup entry, in the same order as
+    /// [`TreArchive::file_names`]
+    ///
+    /// Entries listed by [`TreArchive::duplicates`] and those skipped by a lossy read are left
+    /// out, so this may hold fewer records than the archive stores.
+    pub fn records(&self) -> impl Iterator<Item = TreRecord> + '_ {
+        self.shared.files.iter().map(TreFileData::record)
+    }
+
+    /// Returns an iterator over the hash block's MD5 hash of every lookup entry, if the archive
+    /// has one, in the same order as [`TreArchive::records`]
+    pub fn hashes(&self) -> Option<impl Iterator<Item = [u8; 16]> + '_> {
+        self.shared.has_hash_block.then(|| {
+            self.shared.files.iter().map(|file| {
+                // The hash block holds a hash for every record, so no entry goes without
+                file.md5
+                    .expect("every entry has a hash when there's a hash block")
+            })
+        })
+    }
+
+    /// The header of the archive, exactly as it is stored
+    pub fn header(&self) -> &TreHeader {
+        &self.shared.header
     }
 
     /// Returns how the records data was compressed.
@@ -197,24 +710,95 @@ impl<R: Read + Seek> TreArchive<R> {
         self.shared.header.name_compressed
     }
 
+    /// Whether the archive ends with a block of MD5 hashes for each record
+    ///
+    /// Some community tools omit this block. It is detected by checking whether the file holds a
+    /// hash for every record after the name block, so an archive without records never has one.
+    pub fn has_hash_block(&self) -> bool {
+        self.shared.has_hash_block
+    }
+
+    /// The signature the archive ends with, if it is signed
+    ///
+    /// See [`crate::sign`] for how the signature is stored.
+    pub fn signature(&self) -> Option<&[u8; 64]> {
+        self.shared
+            .signature
+            .as_ref()
+            .map(|(signature, _)| signature)
+    }
+
+    /// Check the archive was signed by `key` and hasn't changed since
+    #[cfg(feature = "signing")]
+    #[instrument(skip_all, err)]
+    pub fn verify_signature(&self, key: &ed25519_dalek::VerifyingKey) -> Result<()> {
+        let Some((signature, length)) = &self.shared.signature else {
+            return Err(crate::error::SignatureError::Missing.into());
+        };
+
+        let mut digest = sha2::Sha512::default();
+        let mut reader = TreBlockReader::new(
+            PositionedReader {
+                inner: &self.reader,
+                position: 0,
+            },
+            0,
+            *length,
+            CompressionMethod::None,
+            BlockKind::Entry,
+            None,
+            None,
+        )?;
+        io::copy(&mut reader, &mut digest)?;
+
+        let signature = ed25519_dalek::Signature::from_bytes(signature);
+        key.verify_prehashed(digest, Some(sign::CONTEXT), &signature)
+            .map_err(|_| crate::error::SignatureError::Invalid.into())
+    }
+
+    /// Every entry shadowed by another entry with the same name, in the order they're stored
+    ///
+    /// These are the entries which looking their name up doesn't find, whether or not
+    /// [`TreArchiveOptions::duplicates`] kept them at an index of their own.
+    pub fn duplicates(&self) -> &[TreFileData] {
+        &self.shared.duplicates
+    }
+
+    /// Get an entry listed by [`TreArchive::duplicates`] by its position in that list
+    pub fn by_duplicate(&self, duplicate: usize) -> Result<TreFile<'_, R>> {
+        let data = self
+            .shared
+            .duplicates
+            .get(duplicate)
+            .ok_or(Error::FileNotFound(FileNotFoundError::Index(duplicate)))?;
+        self.open(data)
+    }
+
     /// Get the index of a file entry by name, if it's present.
     #[inline(always)]
     pub fn index_for_name(&self, name: &str) -> Option<usize> {
-        self.shared.files.get_index_of(name)
+        self.shared.index_of(name)
     }
 
     /// Get the name of a file entry, if it's present.
     #[inline(always)]
     pub fn name_for_index(&self, index: usize) -> Option<&str> {
+        self.shared.files.get(index).map(TreFileData::file_name)
+    }
+
+    /// The decompressed size of an entry, as recorded in the archive
+    ///
+    /// Useful for sizing a buffer before reading an entry without opening it.
+    pub fn entry_size(&self, index: usize) -> Option<u64> {
         self.shared
             .files
-            .get_index(index)
-            .map(|(name, _)| name.as_ref())
+            .get(index)
+            .map(|data| data.uncompressed_size)
     }
 
     /// Search for a file entry by name
-    pub fn by_name(&mut self, name: &str) -> Result<TreFile<'_, R>> {
-        let Some(index) = self.shared.files.get_index_of(name) else {
+    pub fn by_name(&self, name: &str) -> Result<TreFile<'_, R>> {
+        let Some(index) = self.shared.index_of(name) else {
             return Err(Error::FileNotFound(FileNotFoundError::Name(
                 name.to_owned(),
             )));
@@ -222,92 +806,791 @@ impl<R: Read + Seek> TreArchive<R> {
         self.by_index(index)
     }
 
+    /// Search for a file entry by the bytes of its name, as stored in the archive
+    ///
+    /// Unlike [`TreArchive::by_name`] this finds entries whose names aren't valid UTF-8, whatever
+    /// [`TreArchiveOptions::name_encoding`] turned their name into.
+    pub fn by_name_raw(&self, name: &[u8]) -> Result<TreFile<'_, R>> {
+        let index = match std::str::from_utf8(name) {
+            Ok(name) => self.shared.index_of(name),
+            Err(_) => self.shared.raw_names.get(name).copied(),
+        };
+
+        let Some(index) = index else {
+            return Err(Error::FileNotFound(FileNotFoundError::Name(
+                String::from_utf8_lossy(name).into_owned(),
+            )));
+        };
+        self.by_index(index)
+    }
+
     /// Get a contained file by index
-    pub fn by_index(&mut self, file_number: usize) -> Result<TreFile<'_, R>> {
-        let (_, data) = self
+    #[instrument(skip(self), fields(name, size, compressed_size))]
+    pub fn by_index(&self, file_number: usize) -> Result<TreFile<'_, R>> {
+        let data = self
             .shared
             .files
-            .get_index(file_number)
+            .get(file_number)
             .ok_or(Error::FileNotFound(FileNotFoundError::Index(file_number)))?;
 
+        Span::current()
+            .record("name", data.file_name())
+            .record("size", data.uncompressed_size)
+            .record("compressed_size", data.compressed_size);
+
+        self.open(data)
+    }
+
+    fn open<'a>(&'a self, data: &'a TreFileData) -> Result<TreFile<'a, R>> {
+        let reader = entry_reader(
+            PositionedReader {
+                inner: &self.reader,
+                position: 0,
+            },
+            data,
+            &self.shared,
+        )?;
+        let capacity = data.uncompressed_size.clamp(1, BUFFER_SIZE as u64) as usize;
+
         Ok(TreFile {
             data: Cow::Borrowed(data),
-            reader: TreBlockReader::new(
-                &mut self.reader,
-                data.data_start,
-                data.compressed_size,
-                data.compression_method,
-            )?,
+            reader: BufReader::with_capacity(capacity, reader),
         })
     }
 
+    /// Read the whole contents of a file by name
+    ///
+    /// See [`TreArchive::contents_by_index`].
+    pub fn contents_by_name(&self, name: &str) -> Result<Arc<[u8]>> {
+        let Some(index) = self.shared.index_of(name) else {
+            return Err(Error::FileNotFound(FileNotFoundError::Name(
+                name.to_owned(),
+            )));
+        };
+        self.contents_by_index(index)
+    }
+
+    /// Read the whole contents of a file by index
+    ///
+    /// When [`TreArchiveOptions::cache_size`] is set, recently read files are served from memory
+    /// instead of being decompressed again.
+    pub fn contents_by_index(&self, file_number: usize) -> Result<Arc<[u8]>> {
+        if let Some(data) = self
+            .lock_cache()
+            .and_then(|mut cache| cache.get(&file_number))
+        {
+            return Ok(data);
+        }
+
+        let mut file = self.by_index(file_number)?;
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data).map_err(Error::from_read)?;
+        let data: Arc<[u8]> = data.into();
+
+        if let Some(mut cache) = self.lock_cache() {
+            cache.insert(file_number, data.clone());
+        }
+
+        Ok(data)
+    }
+
+    /// Read the whole contents of a file by index into `buffer`, replacing what it held
+    ///
+    /// Unlike [`TreArchive::contents_by_index`] this reuses the buffer's allocation, so loops
+    /// reading many entries only allocate when an entry is larger than any before it. Cached
+    /// contents are copied out, but newly read entries aren't added to the cache.
+    pub fn read_into(&self, file_number: usize, buffer: &mut Vec<u8>) -> Result<()> {
+        buffer.clear();
+
+        if let Some(data) = self
+            .lock_cache()
+            .and_then(|mut cache| cache.get(&file_number))
+        {
+            buffer.extend_from_slice(&data);
+            return Ok(());
+        }
+
+        let mut file = self.by_index(file_number)?;
+        buffer.reserve(file.size() as usize);
+        file.read_to_end(buffer).map_err(Error::from_read)?;
+
+        Ok(())
+    }
+
+    /// Describe the archive and every entry, with the checksums launchers and patch servers use
+    ///
+    /// This reads the whole archive and decompresses every entry. MD5 hashes are taken from the
+    /// hash block when there is one.
+    #[instrument(skip(self), err, fields(entries = self.len(), size))]
+    pub fn manifest(&self) -> Result<Manifest> {
+        let mut archive = Md5::new();
+        let size = io::copy(
+            &mut PositionedReader {
+                inner: &self.reader,
+                position: 0,
+            },
+            &mut archive,
+        )?;
+        Span::current().record("size", size);
+
+        let entries = (0..self.shared.files.len())
+            .map(|index| self.copy_entry_to(index, &mut io::sink()))
+            .collect::<Result<_>>()?;
+
+        Ok(Manifest {
+            size,
+            md5: archive.finalize().into(),
+            entries,
+        })
+    }
+
+    /// Copy the contents of an entry into `writer`, describing it along the way
+    ///
+    /// This saves reading an entry a second time to verify or describe what was copied. The
+    /// description is the same as the entry's in [`TreArchive::manifest`], so the MD5 hash is of
+    /// the stored data and can be checked against the hash block.
+    #[instrument(skip(self, writer), err)]
+    pub fn copy_entry_to(&self, index: usize, writer: &mut impl Write) -> Result<ManifestEntry> {
+        let mut file = self.by_index(index)?;
+        let data = &self.shared.files[index];
+
+        let md5 = match data.md5 {
+            Some(md5) => md5,
+            None => {
+                let mut stored = Md5::new();
+                let mut reader = TreBlockReader::new(
+                    PositionedReader {
+                        inner: &self.reader,
+                        position: 0,
+                    },
+                    data.data_start,
+                    data.compressed_size,
+                    CompressionMethod::None,
+                    BlockKind::Entry,
+                    None,
+                    None,
+                )?;
+                io::copy(&mut reader, &mut stored)?;
+                stored.finalize().into()
+            }
+        };
+
+        let mut crc32 = crc32fast::Hasher::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let chunk = match file.read(&mut buffer).map_err(Error::from_read)? {
+                0 => break,
+                read => &buffer[..read],
+            };
+            crc32.update(chunk);
+            writer.write_all(chunk)?;
+        }
+
+        Ok(ManifestEntry {
+            name: data.file_name().to_owned(),
+            size: data.uncompressed_size,
+            compressed_size: data.compressed_size,
+            compression: data.compression_method,
+            checksum: data.crc32,
+            crc32: crc32.finalize(),
+            md5,
+        })
+    }
+
+    /// The number of bytes of decompressed entries currently held in the cache
+    pub fn cached_size(&self) -> u64 {
+        self.lock_cache().map_or(0, |cache| cache.size())
+    }
+
+    fn lock_cache(&self) -> Option<MutexGuard<'_, EntryCache>> {
+        // A panic while holding the lock can't leave the cache inconsistent
+        self.cache
+            .as_ref()
+            .map(|cache| cache.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
     /// Unwrap and return the inner reader object
     ///
     /// The position of the reader is undefined.
     pub fn into_inner(self) -> R {
         self.reader
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
     }
 
-    fn get_records(reader: &mut R, header: &TreHeader) -> Result<Vec<TreRecord>> {
+    /// Read the record block, stopping at the first record which can't be decoded
+    fn get_records<S: Read + Seek>(
+        reader: &mut S,
+        header: &TreHeader,
+        options: &TreArchiveOptions,
+    ) -> Result<Vec<TreRecord>> {
         let mut record_reader = TreBlockReader::new(
             reader,
             header.record_start as u64,
             header.record_compressed as u64,
             header.record_compression,
+            BlockKind::Records,
+            options.transform.clone(),
+            Some(&options.decoders),
         )?;
 
-        (0..header.records)
-            .map(|_| TreRecord::read(&mut record_reader).map_err(Error::from))
-            .collect()
+        Ok((0..header.records)
+            .map_while(|_| TreRecord::read(&mut record_reader).ok())
+            .collect())
     }
 
-    fn get_names(reader: &mut R, header: &TreHeader) -> Result<Vec<Vec<u8>>> {
-        let mut name_reader = TreBlockReader::new(
+    /// Read the whole name block, which [`split_names`] then splits into each record's name
+    fn get_names<S: Read + Seek>(
+        reader: &mut S,
+        header: &TreHeader,
+        options: &TreArchiveOptions,
+    ) -> Result<Vec<u8>> {
+        let limits = &options.limits;
+        let mut block =
+            Vec::with_capacity(header.name_uncompressed.min(limits.max_name_block_size) as usize);
+
+        // A damaged block still yields the names decoded before the damage
+        let _ = TreBlockReader::new(
             reader,
-            (header.record_start + header.record_compressed) as u64,
+            header.record_start as u64 + header.record_compressed as u64,
             header.name_compressed as u64,
             header.name_compression,
-        )?;
+            BlockKind::Names,
+            options.transform.clone(),
+            Some(&options.decoders),
+        )?
+        .take(limits.max_name_block_size as u64)
+        .read_to_end(&mut block);
+
+        Ok(block)
+    }
+
+    /// Read the trailing hash block, if the file is large enough to contain one
+    fn get_hashes<S: Read + Seek>(
+        reader: &mut S,
+        header: &TreHeader,
+        length: u64,
+    ) -> Result<Vec<[u8; 16]>> {
+        let start = header.record_start as u64
+            + header.record_compressed as u64
+            + header.name_compressed as u64;
+        let size = header.records as u64 * 16;
+
+        if size == 0 || start + size > length {
+            return Ok(Vec::new());
+        }
 
+        reader.seek(SeekFrom::Start(start))?;
         (0..header.records)
             .map(|_| {
-                let mut name_raw: Vec<u8> = Vec::new();
-                loop {
-                    let char = name_reader.read_u8()?;
-                    if char == b'\0' {
-                        break;
-                    }
-                    name_raw.push(char);
-                }
-                Ok(name_raw)
+                let mut hash = [0u8; 16];
+                reader.read_exact(&mut hash)?;
+                Ok(hash)
             })
             .collect()
     }
 
-    fn get_metadata(reader: &mut R) -> Result<Shared> {
+    fn check_header(header: &TreHeader, limits: &TreLimits, length: u64) -> Result<()> {
+        if header.records > limits.max_records {
+            return Err(LimitExceededError::Records {
+                count: header.records,
+                limit: limits.max_records,
+            }
+            .into());
+        }
+
+        for size in [header.name_compressed, header.name_uncompressed] {
+            if size > limits.max_name_block_size {
+                return Err(LimitExceededError::NameBlock {
+                    size,
+                    limit: limits.max_name_block_size,
+                }
+                .into());
+            }
+        }
+
+        let record_start = header.record_start as u64;
+        let record_end = record_start + header.record_compressed as u64;
+        if header.record_compressed > 0 && record_end > length {
+            return Err(OutOfBoundsError::RecordBlock {
+                start: record_start,
+                end: record_end,
+                length,
+            }
+            .into());
+        }
+
+        let name_end = record_end + header.name_compressed as u64;
+        if header.name_compressed > 0 && name_end > length {
+            return Err(OutOfBoundsError::NameBlock {
+                start: record_end,
+                end: name_end,
+                length,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn check_record(
+        index: usize,
+        record: &TreRecord,
+        limits: &TreLimits,
+        length: u64,
+    ) -> Result<()> {
+        for size in [record.data_compressed, record.data_uncompressed] {
+            if size as u64 > limits.max_entry_size {
+                return Err(LimitExceededError::EntrySize {
+                    index,
+                    size: size as u64,
+                    limit: limits.max_entry_size,
+                }
+                .into());
+            }
+        }
+
+        let start = record.data_offset as u64;
+        let end = start + record.data_compressed as u64;
+        if end > length {
+            return Err(OutOfBoundsError::EntryData {
+                index,
+                start,
+                end,
+                length,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Check that a record's data can be decompressed, which needs a registered decoder for a
+    /// [`CompressionMethod::Other`] value
+    fn check_compression(
+        index: usize,
+        record: &TreRecord,
+        name: &[u8],
+        decoders: &DecoderRegistry,
+    ) -> Result<()> {
+        match record.data_compression {
+            CompressionMethod::Other(value) if decoders.get(value).is_none() => {
+                Err(MetadataError::UnknownCompression {
+                    index,
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    value,
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the metadata blocks lie between the header and the end of the file, returning where
+    fn check_layout(header: &TreHeader, length: u64, hash_block: bool) -> Result<Range<u64>> {
+        let start = header.record_start as u64;
+        if start < HEADER_SIZE {
+            return Err(LayoutError::RecordStart {
+                start,
+                size: HEADER_SIZE,
+            }
+            .into());
+        }
+
+        let hashes = match hash_block {
+            true => header.records as u64 * 16,
+            false => 0,
+        };
+        let end = start + header.record_compressed as u64 + header.name_compressed as u64 + hashes;
+        if end != length {
+            return Err(LayoutError::Length { end, length }.into());
+        }
+
+        Ok(start..end)
+    }
+
+    /// Check the data of an entry lies between the header and the metadata blocks
+    fn check_placement(index: usize, record: &TreRecord, metadata: &Range<u64>) -> Result<()> {
+        let start = record.data_offset as u64;
+        let end = start + record.data_compressed as u64;
+
+        if start < HEADER_SIZE {
+            return Err(LayoutError::Header {
+                index,
+                start,
+                end,
+                size: HEADER_SIZE,
+            }
+            .into());
+        }
+
+        if start < metadata.end && end > metadata.start {
+            return Err(LayoutError::Metadata {
+                index,
+                start,
+                end,
+                metadata_start: metadata.start,
+                metadata_end: metadata.end,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, err, fields(length, records, skipped))]
+    fn get_metadata(
+        reader: &mut R,
+        options: &TreArchiveOptions,
+        lossy: bool,
+    ) -> Result<(Shared, Vec<SkippedEntry>)> {
+        // The metadata is parsed with many small reads, which shouldn't each reach the reader.
+        // Entries seek on the reader itself, so nothing depends on where this leaves it.
+        let reader = &mut BufReader::new(reader);
+
+        let length = reader.seek(SeekFrom::End(0))?;
+        let signature = sign::read_trailer(reader, length)?;
+        let length = match signature {
+            Some(_) => length - sign::TRAILER_SIZE,
+            None => length,
+        };
+        reader.rewind()?;
+
         let header = TreHeader::read(reader)?;
-        let records = Self::get_records(reader, &header)?;
-        let names = Self::get_names(reader, &header)?;
-
-        let mut index_map = IndexMap::with_capacity(header.records as usize);
-        records.into_iter().zip(names).for_each(|(r, n)| {
-            let file = TreFileData {
-                crc32: r.checksum,
-                compression_method: r.data_compression,
-                compressed_size: r.data_compressed as u64,
-                uncompressed_size: r.data_uncompressed as u64,
-                data_start: r.data_offset as u64,
-                file_name: String::from_utf8_lossy(&n).into(),
-                file_name_raw: n.into(),
-                ..Default::default()
-            };
-            index_map.insert(file.file_name.clone(), file);
+        Span::current()
+            .record("length", length)
+            .record("records", header.records);
+
+        match Self::check_header(&header, &options.limits, length) {
+            Err(Error::OutOfBounds(_)) if lossy => {}
+            result => result?,
+        }
+
+        let records = Self::get_records(reader, &header, options)?;
+        let name_block = Self::get_names(reader, &header, options)?;
+        let names = split_names(&name_block, header.records);
+
+        // Every name is decoded into one buffer up front, so entries can share it
+        let mut text = String::with_capacity(name_block.len());
+        let decoded = names
+            .iter()
+            .enumerate()
+            .map(|(index, n)| {
+                let start = text.len();
+                let decoded =
+                    options
+                        .name_encoding
+                        .decode(index, &name_block[n.clone()], &mut text);
+                match (decoded, u32::try_from(text.len())) {
+                    (Ok(()), Ok(end)) => Ok(start as u32..end),
+                    (Ok(()), Err(_)) => {
+                        text.truncate(start);
+                        Err(MetadataError::NameEncoding(index).into())
+                    }
+                    (Err(e), _) => Err(e),
+                }
+            })
+            .collect::<Vec<Result<_>>>();
+        let arena = Arc::new(NameArena {
+            text,
+            raw: name_block.into_boxed_slice(),
         });
 
-        Ok(Shared {
-            header,
-            files: index_map,
+        let hashes = Self::get_hashes(reader, &header, length)?;
+        let metadata = match options.strict {
+            true => Some(Self::check_layout(&header, length, !hashes.is_empty())?),
+            false => None,
+        };
+
+        let mut skipped = Vec::new();
+        let mut files: Vec<TreFileData> = Vec::with_capacity(records.len());
+        let mut file_names: HashMap<&str, usize> = HashMap::with_capacity(records.len());
+        let mut duplicates = Vec::new();
+        let mut raw_names = HashMap::new();
+        let mut decoded = names.iter().zip(decoded);
+        for index in 0..header.records as usize {
+            let entry = match (records.get(index), decoded.next()) {
+                (None, _) => Err(MetadataError::Record(index).into()),
+                (Some(_), None) => Err(MetadataError::Name(index).into()),
+                (Some(r), Some((n, name))) => Self::check_record(index, r, &options.limits, length)
+                    .and_then(|_| match &metadata {
+                        Some(metadata) => Self::check_placement(index, r, metadata),
+                        None => Ok(()),
+                    })
+                    .and_then(|_| {
+                        Self::check_compression(index, r, &arena.raw[n.clone()], &options.decoders)
+                    })
+                    .and_then(|_| Ok((r, n, name?))),
+            };
+
+            match entry {
+                Ok((r, n, name)) => {
+                    let file_name = &arena.text[name.start as usize..name.end as usize];
+                    let raw_name = std::str::from_utf8(&arena.raw[n.clone()])
+                        .is_err()
+                        .then(|| Box::from(&arena.raw[n.clone()]));
+                    let file = TreFileData {
+                        crc32: r.checksum,
+                        compression_method: r.data_compression,
+                        compressed_size: r.data_compressed as u64,
+                        uncompressed_size: r.data_uncompressed as u64,
+                        data_start: r.data_offset as u64,
+                        names: arena.clone(),
+                        name,
+                        name_raw: n.start as u32..n.end as u32,
+                        name_offset: r.name_offset,
+                        header_start: 0,
+                        md5: hashes.get(index).copied(),
+                    };
+                    let position = match file_names.get(file_name).copied() {
+                        None => {
+                            file_names.insert(file_name, files.len());
+                            files.push(file);
+                            files.len() - 1
+                        }
+                        Some(existing) => match options.duplicates {
+                            DuplicatePolicy::KeepFirst => {
+                                duplicates.push(file);
+                                continue;
+                            }
+                            DuplicatePolicy::KeepLast => {
+                                duplicates.push(std::mem::replace(&mut files[existing], file));
+                                existing
+                            }
+                            DuplicatePolicy::KeepAll => {
+                                duplicates.push(files[existing].clone());
+                                file_names.insert(file_name, files.len());
+                                files.push(file);
+                                files.len() - 1
+                            }
+                            DuplicatePolicy::Error => {
+                                let error = MetadataError::DuplicateName(index).into();
+                                if !lossy {
+                                    return Err(error);
+                                }
+                                skipped.push(SkippedEntry {
+                                    index,
+                                    name: Some(file_name.into()),
+                                    error,
+                                });
+                                continue;
+                            }
+                        },
+                    };
+                    if let Some(raw_name) = raw_name {
+                        raw_names.insert(raw_name, position);
+                    }
+                }
+                Err(error) if lossy => skipped.push(SkippedEntry {
+                    index,
+                    name: names
+                        .get(index)
+                        .map(|n| String::from_utf8_lossy(&arena.raw[n.clone()]).into()),
+                    error,
+                }),
+                Err(error) => return Err(error),
+            }
+        }
+
+        Span::current().record("skipped", skipped.len());
+
+        let mut sorted = file_names
+            .into_values()
+            .map(|index| index as u32)
+            .collect::<Vec<_>>();
+        sorted.sort_unstable_by_key(|index| files[*index as usize].file_name());
+
+        Ok((
+            Shared {
+                header,
+                files,
+                names: sorted,
+                duplicates,
+                raw_names,
+                has_hash_block: !hashes.is_empty(),
+                limits: options.limits,
+                transform: options.transform.clone(),
+                decoders: options.decoders.clone(),
+                signature: signature.map(|signature| (signature, length)),
+            },
+            skipped,
+        ))
+    }
+}
+
+/// Split a name block into where the name of each record lies within it
+///
+/// A damaged block ends the names early, at the last complete one.
+fn split_names(block: &[u8], records: u32) -> Vec<Range<usize>> {
+    let mut start = 0;
+    block
+        .split_inclusive(|b| *b == b'\0')
+        .take(records as usize)
+        .map_while(|name| {
+            let name = start..start + name.strip_suffix(b"\0")?.len();
+            start += name.len() + 1;
+            Some(name)
         })
+        .collect()
+}
+
+/// Open the data of an entry for reading, once its sizes are checked against the limits
+fn entry_reader<R: Read + Seek>(
+    reader: R,
+    data: &TreFileData,
+    shared: &Shared,
+) -> Result<SizeGuard<TreBlockReader<R>>> {
+    let limits = &shared.limits;
+    let limit = limits.max_compression_ratio;
+    if data.compression_method == CompressionMethod::Zlib
+        && data.uncompressed_size > data.compressed_size.saturating_mul(limit as u64)
+    {
+        return Err(DecompressionBombError::Ratio {
+            size: data.uncompressed_size,
+            compressed_size: data.compressed_size,
+            limit,
+        }
+        .into());
+    }
+
+    let reader = TreBlockReader::new(
+        reader,
+        data.data_start,
+        data.compressed_size,
+        data.compression_method,
+        BlockKind::Entry,
+        shared.transform.clone(),
+        Some(&shared.decoders),
+    )?;
+    Ok(SizeGuard::new(reader, data.uncompressed_size))
+}
+
+impl<R: Read + Seek + Clone> TreArchive<R> {
+    /// Get a contained file by index, reading it through a clone of the underlying reader
+    ///
+    /// Each clone must track its own position, as is the case for [`std::io::Cursor`] over a
+    /// shared buffer. Clones of a `&File` share their position and are not suitable.
+    pub fn by_index_owned(&self, file_number: usize) -> Result<TreEntry<R>> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(Error::FileNotFound(FileNotFoundError::Index(file_number)))?;
+
+        Ok(TreEntry {
+            data: data.clone(),
+            reader: entry_reader(
+                self.reader
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone(),
+                data,
+                &self.shared,
+            )?,
+        })
+    }
+}
+
+impl<'a> TreArchive<Cursor<&'a [u8]>> {
+    /// Read a TRE archive which is already held in memory
+    ///
+    /// The archive borrows `data` rather than copying it, so entries can be handed out as slices
+    /// of it through [`TreArchive::bytes_by_index`].
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self> {
+        Self::new(Cursor::new(data))
+    }
+
+    /// Get the stored data of a file by index, which is still compressed if the file is
+    ///
+    /// The data is exactly as stored, any [`TreArchiveOptions::transform`] isn't undone.
+    pub fn stored_by_index(&self, file_number: usize) -> Result<&'a [u8]> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(Error::FileNotFound(FileNotFoundError::Index(file_number)))?;
+
+        let bytes: &'a [u8] = self
+            .reader
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_ref();
+
+        let start = data.data_start;
+        let end = start + data.compressed_size;
+        usize::try_from(start)
+            .ok()
+            .zip(usize::try_from(end).ok())
+            .and_then(|(start, end)| bytes.get(start..end))
+            .ok_or(Error::OutOfBounds(OutOfBoundsError::EntryData {
+                index: file_number,
+                start,
+                end,
+                length: bytes.len() as u64,
+            }))
+    }
+
+    /// Get the contents of a file by index
+    ///
+    /// Uncompressed files are borrowed straight from the archive, only compressed files, or every
+    /// file when the archive has a [`TreArchiveOptions::transform`], are read into a new buffer.
+    pub fn bytes_by_index(&self, file_number: usize) -> Result<Cow<'a, [u8]>> {
+        let stored = self.stored_by_index(file_number)?;
+
+        let mut file = self.by_index(file_number)?;
+        if file.compression_method() == CompressionMethod::None && self.shared.transform.is_none() {
+            return Ok(Cow::Borrowed(stored));
+        }
+
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data).map_err(Error::from_read)?;
+        Ok(Cow::Owned(data))
+    }
+
+    /// Get the contents of a file by name
+    ///
+    /// See [`TreArchive::bytes_by_index`].
+    pub fn bytes_by_name(&self, name: &str) -> Result<Cow<'a, [u8]>> {
+        let Some(index) = self.shared.index_of(name) else {
+            return Err(Error::FileNotFound(FileNotFoundError::Name(
+                name.to_owned(),
+            )));
+        };
+        self.bytes_by_index(index)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<R: Read + Seek + Clone + Send + Sync> TreArchive<R> {
+    /// Returns a parallel iterator over owned handles to every entry in the archive
+    ///
+    /// ```
+    /// # fn doit() -> swg_tre::error::Result<()>
+    /// # {
+    /// use rayon::prelude::*;
+    /// use std::io::{Cursor, Read};
+    ///
+    /// # let options = swg_tre::write::TreWriterOptions::builder().build();
+    /// # let buffer = swg_tre::TreWriter::new(Cursor::new(Vec::new()), options).finish()?.into_inner();
+    /// let tre = swg_tre::TreArchive::new(Cursor::new(buffer.as_slice()))?;
+    ///
+    /// tre.par_entries().try_for_each(|entry| {
+    ///     let mut data = Vec::new();
+    ///     entry?.read_to_end(&mut data)?;
+    ///     Ok::<_, swg_tre::error::Error>(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// # doit().unwrap();
+    /// ```
+    pub fn par_entries(
+        &self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = Result<TreEntry<R>>> + '_ {
+        use rayon::prelude::*;
+
+        (0..self.len())
+            .into_par_iter()
+            .map(|index| self.by_index_owned(index))
     }
 }
 
@@ -315,7 +1598,10 @@ impl<R: Read + Seek> TreArchive<R> {
 mod test {
     use std::io::prelude::*;
 
-    use crate::{error::Result, read::TreArchive};
+    use crate::{
+        error::{Error, LimitExceededError, MetadataError, OutOfBoundsError, Result},
+        read::{DuplicatePolicy, NameEncoding, TreArchive, TreArchiveOptions, TreLimits},
+    };
     use std::io::Cursor;
 
     #[test]
@@ -336,6 +1622,88 @@ mod test {
         assert!(archive.is_err());
     }
 
+    #[test]
+    fn split_names() {
+        let names = super::split_names(b"a.txt\0dir/b.txt\0trunc", 3);
+        assert_eq!(names, [0..5, 6..15]);
+
+        let names = super::split_names(b"a.txt\0dir/b.txt\0", 1);
+        assert_eq!(names, vec![0..5]);
+    }
+
+    #[test]
+    fn peek_header() -> Result<()> {
+        use crate::{
+            compression::CompressionMethod,
+            read::TreInfo,
+            write::{TreWriter, TreWriterOptions},
+        };
+
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        for name in ["a.txt", "b.txt"] {
+            tre.start_file(name, CompressionMethod::None)?;
+            tre.write_all(b"contents")?;
+        }
+        let data = tre.finish()?.into_inner();
+        let archive = TreArchive::new(Cursor::new(&data))?;
+
+        let info = TreArchive::peek_header(Cursor::new(&data))?;
+        assert_eq!(
+            info,
+            TreInfo {
+                records: 2,
+                record_compression: archive.get_record_compression(),
+                record_block_size: archive.get_record_block_size(),
+                name_compression: archive.get_name_compression(),
+                name_block_size: archive.get_name_block_size(),
+                name_size: 12,
+                length: data.len() as u64,
+            }
+        );
+
+        assert!(matches!(
+            TreArchive::peek_header(Cursor::new(&data[..20])),
+            Err(Error::InvalidArchive)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn raw_metadata() -> Result<()> {
+        use crate::{
+            compression::CompressionMethod,
+            types::{TreHeader, TreRecord},
+            write::{TreWriter, TreWriterOptions},
+        };
+        use binrw::BinRead;
+
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .record_compression(CompressionMethod::None)
+                .build(),
+        );
+        for name in ["a.txt", "dir/b.txt"] {
+            tre.start_file(name, CompressionMethod::Zlib)?;
+            tre.write_all(b"contents")?;
+        }
+        let data = tre.finish()?.into_inner();
+        let archive = TreArchive::new(Cursor::new(&data))?;
+
+        let header = TreHeader::read(&mut Cursor::new(&data))?;
+        assert_eq!(archive.header(), &header);
+
+        let mut records = Cursor::new(&data[header.record_start as usize..]);
+        for index in 0..archive.len() {
+            let record = TreRecord::read(&mut records)?;
+            assert_eq!(archive.by_index(index)?.record(), record);
+        }
+        assert_eq!(archive.by_index(1)?.record().name_offset, 6);
+
+        Ok(())
+    }
+
     #[test]
     fn read_empty_uncompressed_tre() {
         let input = [
@@ -376,7 +1744,7 @@ mod test {
             0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x2E, 0x74, 0x78, 0x74, 0x00,
         ];
 
-        let mut archive = TreArchive::new(Cursor::new(input))?;
+        let archive = TreArchive::new(Cursor::new(input))?;
         assert_eq!(archive.len(), 1);
 
         let mut buffer = Vec::new();
@@ -408,7 +1776,7 @@ mod test {
             0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x2E, 0x74, 0x78, 0x74, 0x00,
         ];
 
-        let mut archive = TreArchive::new(Cursor::new(input))?;
+        let archive = TreArchive::new(Cursor::new(input))?;
         assert_eq!(archive.len(), 1);
 
         let mut buffer = Vec::new();
@@ -444,7 +1812,7 @@ mod test {
             0x64, 0x2E, 0x74, 0x78, 0x74, 0x00,
         ];
 
-        let mut archive = TreArchive::new(Cursor::new(input))?;
+        let archive = TreArchive::new(Cursor::new(input))?;
         assert_eq!(archive.len(), 2);
 
         let mut buffer = Vec::new();
@@ -490,7 +1858,7 @@ mod test {
             0x64, 0x2E, 0x74, 0x78, 0x74, 0x00,
         ];
 
-        let mut archive = TreArchive::new(Cursor::new(input))?;
+        let archive = TreArchive::new(Cursor::new(input))?;
         assert_eq!(archive.len(), 2);
 
         let mut buffer = Vec::new();
@@ -518,4 +1886,321 @@ mod test {
 
         Ok(())
     }
+
+    #[rustfmt::skip]
+    const HELLO_UNCOMPRESSED: [u8; 81] = [
+        // Header (36)
+        0x45, 0x45, 0x52, 0x54, 0x35, 0x30, 0x30, 0x30, 0x01, 0x00, 0x00, 0x00, 0x2F, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x0A, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, // Data (11)
+        0x48, 0x65, 0x6C, 0x6C, 0x6F, 0x20, 0x57, 0x6F, 0x72, 0x6C, 0x64,
+        // Records (24)
+        0x00, 0x00, 0x00, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Names (10)
+        0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x2E, 0x74, 0x78, 0x74, 0x00,
+    ];
+
+    #[test]
+    fn read_exceeding_record_limit() {
+        let options = TreArchiveOptions::builder()
+            .limits(TreLimits::builder().max_records(0).build())
+            .build();
+
+        let archive = TreArchive::with_options(Cursor::new(HELLO_UNCOMPRESSED), options);
+        assert!(matches!(
+            archive,
+            Err(Error::LimitExceeded(LimitExceededError::Records {
+                count: 1,
+                limit: 0
+            }))
+        ));
+    }
+
+    #[test]
+    fn read_exceeding_name_block_limit() {
+        let options = TreArchiveOptions::builder()
+            .limits(TreLimits::builder().max_name_block_size(4).build())
+            .build();
+
+        let archive = TreArchive::with_options(Cursor::new(HELLO_UNCOMPRESSED), options);
+        assert!(matches!(
+            archive,
+            Err(Error::LimitExceeded(LimitExceededError::NameBlock {
+                size: 10,
+                limit: 4
+            }))
+        ));
+    }
+
+    #[test]
+    fn read_exceeding_entry_limit() {
+        let options = TreArchiveOptions::builder()
+            .limits(TreLimits::builder().max_entry_size(10).build())
+            .build();
+
+        let archive = TreArchive::with_options(Cursor::new(HELLO_UNCOMPRESSED), options);
+        assert!(matches!(
+            archive,
+            Err(Error::LimitExceeded(LimitExceededError::EntrySize {
+                index: 0,
+                size: 11,
+                limit: 10
+            }))
+        ));
+    }
+
+    #[test]
+    fn read_entry_out_of_bounds() {
+        let mut input = HELLO_UNCOMPRESSED;
+        // Point the data offset of the only record past the end of the file
+        input[55] = 0xFF;
+
+        let archive = TreArchive::new(Cursor::new(input));
+        assert!(matches!(
+            archive,
+            Err(Error::OutOfBounds(OutOfBoundsError::EntryData {
+                index: 0,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn read_truncated_record_block() {
+        let input = &HELLO_UNCOMPRESSED[..60];
+
+        let archive = TreArchive::new(Cursor::new(input));
+        assert!(matches!(
+            archive,
+            Err(Error::OutOfBounds(OutOfBoundsError::RecordBlock { .. }))
+        ));
+    }
+
+    #[test]
+    fn read_hash_block_detection() -> Result<()> {
+        let archive = TreArchive::new(Cursor::new(HELLO_UNCOMPRESSED))?;
+        assert!(!archive.has_hash_block());
+
+        #[rustfmt::skip]
+        let hash = [
+            0xB1, 0x0A, 0x8D, 0xB1, 0x64, 0xE0, 0x75, 0x41, 0x05, 0xB7, 0xA9, 0x9B, 0xE7, 0x2E, 0x3F, 0xE5
+        ];
+
+        let mut input = HELLO_UNCOMPRESSED.to_vec();
+        input.extend_from_slice(&hash);
+
+        let archive = TreArchive::new(Cursor::new(input))?;
+        assert!(archive.has_hash_block());
+        assert_eq!(archive.by_index(0)?.md5(), Some(hash));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_non_utf8_names() -> Result<()> {
+        // Turn the name into `he\xFFlo.txt`
+        let mut input = HELLO_UNCOMPRESSED.to_vec();
+        let name_start = input.len() - 10;
+        input[name_start + 2] = 0xFF;
+        let raw_name = input[name_start..input.len() - 1].to_vec();
+
+        let options = |name_encoding| {
+            TreArchiveOptions::builder()
+                .name_encoding(name_encoding)
+                .build()
+        };
+
+        let archive = TreArchive::new(Cursor::new(&input))?;
+        assert_eq!(archive.name_for_index(0), Some("he\u{FFFD}lo.txt"));
+        assert_eq!(archive.by_name_raw(&raw_name)?.name_raw(), raw_name);
+
+        let archive = TreArchive::with_options(Cursor::new(&input), options(NameEncoding::Raw))?;
+        assert_eq!(archive.name_for_index(0), Some("he\\xFFlo.txt"));
+        assert!(archive.by_name("he\\xFFlo.txt").is_ok());
+        assert!(archive.by_name_raw(&raw_name).is_ok());
+
+        let archive = TreArchive::with_options(Cursor::new(&input), options(NameEncoding::Error));
+        assert!(matches!(
+            archive,
+            Err(Error::Metadata(MetadataError::NameEncoding(0)))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_duplicate_names() -> Result<()> {
+        use crate::{
+            compression::CompressionMethod,
+            write::{self, TreWriter, TreWriterOptions},
+        };
+
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .duplicates(write::DuplicatePolicy::Allow)
+                .build(),
+        );
+        for (name, contents) in [("a.txt", "first"), ("b.txt", "other"), ("a.txt", "second")] {
+            tre.start_file(name, CompressionMethod::None)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+        let input = tre.finish()?.into_inner();
+
+        let read = |policy| -> Result<(usize, String, usize)> {
+            let archive = TreArchive::with_options(
+                Cursor::new(&input),
+                TreArchiveOptions::builder().duplicates(policy).build(),
+            )?;
+            let mut contents = String::new();
+            archive.by_name("a.txt")?.read_to_string(&mut contents)?;
+            assert_eq!(archive.records().count(), archive.len());
+            assert_eq!(archive.hashes().map(Iterator::count), Some(archive.len()));
+            Ok((archive.len(), contents, archive.duplicates().len()))
+        };
+
+        assert_eq!(read(DuplicatePolicy::KeepLast)?, (2, "second".into(), 1));
+        assert_eq!(read(DuplicatePolicy::KeepFirst)?, (2, "first".into(), 1));
+        assert_eq!(read(DuplicatePolicy::KeepAll)?, (3, "second".into(), 1));
+        assert!(matches!(
+            read(DuplicatePolicy::Error),
+            Err(Error::Metadata(MetadataError::DuplicateName(2)))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_manifest() -> Result<()> {
+        use crate::{
+            compression::CompressionMethod,
+            write::{TreWriter, TreWriterOptions},
+        };
+        use md5::{Digest, Md5};
+
+        let crc = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+        let mut manifests = Vec::new();
+        for hash_block in [true, false] {
+            let mut tre = TreWriter::new(
+                Cursor::new(Vec::new()),
+                TreWriterOptions::builder().hash_block(hash_block).build(),
+            );
+            tre.start_file("a.txt", CompressionMethod::Zlib)?;
+            tre.write_all(b"Hello, World!")?;
+            let input = tre.finish()?.into_inner();
+
+            let archive = TreArchive::new(Cursor::new(&input))?;
+            let manifest = archive.manifest()?;
+            assert_eq!(manifest.size, input.len() as u64);
+            assert_eq!(manifest.md5, <[u8; 16]>::from(Md5::digest(&input)));
+
+            let entry = &manifest.entries[0];
+            assert_eq!(entry.name, "a.txt");
+            assert_eq!(entry.size, 13);
+            assert_eq!(entry.crc32, crc.checksum(b"Hello, World!"));
+            assert_eq!(entry.compression, CompressionMethod::Zlib);
+            assert_eq!(entry.checksum, archive.records().next().unwrap().checksum);
+            assert_eq!(
+                archive.hashes().map(|hashes| hashes.collect::<Vec<_>>()),
+                hash_block.then(|| vec![entry.md5])
+            );
+            manifests.push(entry.clone());
+        }
+
+        // The hash from the hash block matches the one computed from the stored data
+        assert_eq!(manifests[0], manifests[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_into() -> Result<()> {
+        use crate::{
+            compression::CompressionMethod,
+            write::{TreWriter, TreWriterOptions},
+        };
+
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        tre.start_file("a.txt", CompressionMethod::Zlib)?;
+        tre.write_all(&[b'a'; 1000])?;
+        tre.start_file("b.txt", CompressionMethod::None)?;
+        tre.write_all(b"b")?;
+        let archive = TreArchive::new(tre.finish()?)?;
+
+        assert_eq!(archive.entry_size(0), Some(1000));
+        assert_eq!(archive.entry_size(2), None);
+
+        let mut buffer = Vec::new();
+        archive.read_into(0, &mut buffer)?;
+        assert_eq!(buffer, [b'a'; 1000]);
+        let capacity = buffer.capacity();
+
+        archive.read_into(1, &mut buffer)?;
+        assert_eq!(buffer, b"b");
+        assert_eq!(buffer.capacity(), capacity);
+
+        assert!(archive.read_into(2, &mut buffer).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn copy_entry_to() -> Result<()> {
+        use crate::{
+            compression::CompressionMethod,
+            write::{TreWriter, TreWriterOptions},
+        };
+        use md5::{Digest, Md5};
+
+        let contents = "Hello, World! ".repeat(10_000);
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        tre.start_file("a.txt", CompressionMethod::Zlib)?;
+        tre.write_all(contents.as_bytes())?;
+        let archive = TreArchive::new(tre.finish()?)?;
+
+        let mut copy = Vec::new();
+        let entry = archive.copy_entry_to(0, &mut copy)?;
+        assert_eq!(copy, contents.as_bytes());
+        assert_eq!(entry.size, contents.len() as u64);
+        assert_eq!(entry, archive.manifest()?.entries[0]);
+        assert_ne!(entry.md5, <[u8; 16]>::from(Md5::digest(&copy)));
+
+        assert!(archive.copy_entry_to(1, &mut Vec::new()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_buffered_lines() -> Result<()> {
+        use crate::{
+            compression::CompressionMethod,
+            write::{TreWriter, TreWriterOptions},
+        };
+
+        let contents = (0..2000)
+            .map(|i| format!("line {}\n", i))
+            .collect::<String>();
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        for (name, compression) in [
+            ("a.txt", CompressionMethod::Zlib),
+            ("b.txt", CompressionMethod::None),
+        ] {
+            tre.start_file(name, compression)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+        let archive = TreArchive::new(tre.finish()?)?;
+
+        for name in ["a.txt", "b.txt"] {
+            let mut file = archive.by_name(name)?;
+            assert_eq!(file.fill_buf()?.len(), 8 * 1024);
+            assert!(file.fill_buf()?.starts_with(b"line 0\n"));
+
+            let lines = file.lines().collect::<std::io::Result<Vec<_>>>()?;
+            assert_eq!(lines.len(), 2000);
+            assert_eq!(lines[1999], "line 1999");
+        }
+
+        Ok(())
+    }
 }
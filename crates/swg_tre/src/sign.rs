@@ -0,0 +1,151 @@
+//! Detached signatures for distributed archives
+//!
+//! A signed archive ends with a trailer holding an Ed25519ph signature of every byte before it,
+//! followed by [`SIGNATURE_MAGIC`]. The trailer lies past everything the header points at, so the
+//! client and readers without the `signing` feature still read a signed archive as usual.
+//!
+//! Archives are signed by setting [`crate::write::TreWriterOptions::signing_key`], and checked
+//! with [`crate::TreArchive::verify_signature`]. Both need the `signing` feature.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// The bytes which end a signature trailer
+pub const SIGNATURE_MAGIC: &[u8; 8] = b"EERTSIG1";
+
+/// The size of a signature trailer, the signature followed by [`SIGNATURE_MAGIC`]
+pub const TRAILER_SIZE: u64 = 64 + SIGNATURE_MAGIC.len() as u64;
+
+/// The context signatures are made in, keeping them from being valid for anything else
+#[cfg(feature = "signing")]
+pub(crate) const CONTEXT: &[u8] = b"swg_tre archive";
+
+/// Read the signature from the end of a file of `length` bytes, if it has one
+pub(crate) fn read_trailer<R: Read + Seek>(
+    reader: &mut R,
+    length: u64,
+) -> io::Result<Option<[u8; 64]>> {
+    if length < TRAILER_SIZE {
+        return Ok(None);
+    }
+
+    let mut trailer = [0u8; TRAILER_SIZE as usize];
+    reader.seek(SeekFrom::Start(length - TRAILER_SIZE))?;
+    reader.read_exact(&mut trailer)?;
+
+    let (signature, magic) = trailer.split_at(64);
+    if magic != SIGNATURE_MAGIC {
+        return Ok(None);
+    }
+    Ok(Some(signature.try_into().expect("split at 64")))
+}
+
+/// Passes writes on while hashing them, to sign everything written once the archive is complete
+#[cfg(feature = "signing")]
+pub(crate) struct Signer<W> {
+    inner: W,
+    digest: Option<sha2::Sha512>,
+}
+
+#[cfg(feature = "signing")]
+impl<W: io::Write> Signer<W> {
+    /// Wrap a writer, hashing what is written only when it is going to be signed
+    pub fn new(inner: W, signing: bool) -> Self {
+        Signer {
+            inner,
+            digest: signing.then(sha2::Digest::new),
+        }
+    }
+
+    /// Append the trailer signing everything written so far with `key`
+    pub fn finish(mut self, key: &ed25519_dalek::SigningKey) -> io::Result<()> {
+        let digest = self
+            .digest
+            .take()
+            .expect("only signers which hash are finished");
+        let signature = key
+            .sign_prehashed(digest, Some(CONTEXT))
+            .map_err(io::Error::other)?;
+
+        self.inner.write_all(&signature.to_bytes())?;
+        self.inner.write_all(SIGNATURE_MAGIC)
+    }
+}
+
+#[cfg(feature = "signing")]
+impl<W: io::Write> io::Write for Signer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(digest) = &mut self.digest {
+            sha2::Digest::update(digest, &buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod test {
+    use std::io::{Cursor, Read, Write};
+
+    use ed25519_dalek::SigningKey;
+
+    use crate::{
+        compression::CompressionMethod,
+        error::{Error, Result, SignatureError},
+        read::{TreArchive, TreArchiveOptions},
+        write::{TreWriter, TreWriterOptions},
+    };
+
+    fn write(signing_key: Option<SigningKey>) -> Result<Vec<u8>> {
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .maybe_signing_key(signing_key)
+                .build(),
+        );
+        tre.start_file("a.txt", CompressionMethod::Zlib)?;
+        tre.write_all(b"Hello, World!")?;
+        Ok(tre.finish()?.into_inner())
+    }
+
+    #[test]
+    fn sign_round_trip() -> Result<()> {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let mut data = write(Some(key.clone()))?;
+
+        let tre = TreArchive::with_options(
+            Cursor::new(&data),
+            TreArchiveOptions::builder().strict(true).build(),
+        )?;
+        let mut actual = String::new();
+        tre.by_name("a.txt")?.read_to_string(&mut actual)?;
+        assert_eq!(actual, "Hello, World!");
+        assert!(tre.has_hash_block());
+        tre.verify_signature(&key.verifying_key())?;
+
+        let other = SigningKey::from_bytes(&[8; 32]);
+        assert!(matches!(
+            tre.verify_signature(&other.verifying_key()),
+            Err(Error::Signature(SignatureError::Invalid))
+        ));
+
+        data[40] ^= 0xFF;
+        let tre = TreArchive::new(Cursor::new(&data))?;
+        assert!(matches!(
+            tre.verify_signature(&key.verifying_key()),
+            Err(Error::Signature(SignatureError::Invalid))
+        ));
+
+        let tre = TreArchive::new(Cursor::new(write(None)?))?;
+        assert!(tre.signature().is_none());
+        assert!(matches!(
+            tre.verify_signature(&key.verifying_key()),
+            Err(Error::Signature(SignatureError::Missing))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! Generators for synthetic TRE archives
+//!
+//! These helpers build archives programmatically so tests don't need to depend on binary
+//! fixtures. Archives are generated deterministically from a seed, and can be deliberately
+//! damaged to exercise error handling.
+//!
+//! ```
+//! # fn doit() -> swg_tre::error::Result<()>
+//! # {
+//! use swg_tre::testing::{Corruption, SyntheticArchive};
+//!
+//! let archive = SyntheticArchive::builder()
+//!     .entries(8)
+//!     .corruptions(vec![Corruption::Truncate { len: 64 }])
+//!     .build();
+//!
+//! assert!(swg_tre::TreArchive::new(std::io::Cursor::new(archive.generate()?)).is_err());
+//! # Ok(())
+//! # }
+//! # doit().unwrap();
+//! ```
+
+use binrw::{BinRead, BinWrite};
+use bon::Builder;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Cursor, Read, Write};
+
+use crate::{
+    compression::CompressionMethod,
+    error::{Error, FileNotFoundError, OutOfBoundsError, Result},
+    types::{TreHeader, TreRecord},
+    write::{TreWriter, TreWriterOptions},
+};
+
+/// Controls which compression method each generated entry uses
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CompressionMix {
+    /// Every entry is stored uncompressed
+    None,
+
+    /// Every entry is compressed with Zlib
+    #[default]
+    Zlib,
+
+    /// Entries alternate between uncompressed and Zlib, starting with uncompressed
+    Alternating,
+}
+
+impl CompressionMix {
+    /// The compression method used for the entry at `index`
+    pub fn method(&self, index: usize) -> CompressionMethod {
+        match self {
+            CompressionMix::None => CompressionMethod::None,
+            CompressionMix::Zlib => CompressionMethod::Zlib,
+            CompressionMix::Alternating if index % 2 == 0 => CompressionMethod::None,
+            CompressionMix::Alternating => CompressionMethod::Zlib,
+        }
+    }
+}
+
+/// Controls what data the generated entries contain
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum EntryContent {
+    /// Repeated text which compresses well
+    #[default]
+    Text,
+
+    /// Pseudo-random bytes which do not compress
+    Random,
+}
+
+/// A deliberate defect applied to a generated archive
+#[derive(Debug, Clone, PartialEq)]
+pub enum Corruption {
+    /// Cut the archive off after `len` bytes
+    Truncate {
+        /// The number of bytes to keep
+        len: usize,
+    },
+
+    /// Invert every bit of the byte at `offset`
+    FlipByte {
+        /// The offset of the byte to flip
+        offset: usize,
+    },
+
+    /// Overwrite the record count stored in the header
+    RecordCount(u32),
+
+    /// Overwrite the data offset of a record
+    EntryOffset {
+        /// The index of the record to modify
+        index: usize,
+        /// The new data offset
+        offset: u32,
+    },
+
+    /// Overwrite the uncompressed size of a record
+    EntrySize {
+        /// The index of the record to modify
+        index: usize,
+        /// The new uncompressed size
+        size: u32,
+    },
+
+    /// Overwrite the compression method stored in a record
+    EntryCompression {
+        /// The index of the record to modify
+        index: usize,
+        /// The new compression method
+        compression: CompressionMethod,
+    },
+}
+
+/// Description of a synthetic archive to generate
+#[derive(Debug, Clone, Builder)]
+pub struct SyntheticArchive {
+    /// The number of entries to generate
+    #[builder(default = 16)]
+    pub entries: usize,
+
+    /// The smallest size of a generated entry
+    #[builder(default = 64)]
+    pub min_entry_size: usize,
+
+    /// The largest size of a generated entry
+    #[builder(default = 4096)]
+    pub max_entry_size: usize,
+
+    /// How the entries are compressed
+    #[builder(default)]
+    pub compression: CompressionMix,
+
+    /// What the entries contain
+    #[builder(default)]
+    pub content: EntryContent,
+
+    /// The compression method to use for the record block
+    #[builder(default)]
+    pub record_compression: CompressionMethod,
+
+    /// The compression method to use for the name block
+    #[builder(default)]
+    pub name_compression: CompressionMethod,
+
+    /// The seed used to derive entry sizes and random content
+    #[builder(default)]
+    pub seed: u64,
+
+    /// Defects to apply once the archive has been written, in order
+    #[builder(default)]
+    pub corruptions: Vec<Corruption>,
+}
+
+impl SyntheticArchive {
+    /// The name of the entry at `index`
+    pub fn entry_name(&self, index: usize) -> String {
+        format!("synthetic/{:02}/entry_{:05}.bin", index % 7, index)
+    }
+
+    /// The expected contents of the entry at `index`
+    pub fn entry_data(&self, index: usize) -> Vec<u8> {
+        let mut rng = XorShift::new(self.seed ^ (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+        let span = self.max_entry_size.saturating_sub(self.min_entry_size) as u64;
+        let size = self.min_entry_size + (rng.next_u64() % (span + 1)) as usize;
+
+        match self.content {
+            EntryContent::Text => self
+                .entry_name(index)
+                .bytes()
+                .chain(std::iter::once(b'\n'))
+                .cycle()
+                .take(size)
+                .collect(),
+            EntryContent::Random => (0..size).map(|_| rng.next_u64() as u8).collect(),
+        }
+    }
+
+    /// Write the archive and apply the configured corruptions
+    pub fn generate(&self) -> Result<Vec<u8>> {
+        let mut writer = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .record_compression(self.record_compression)
+                .name_compression(self.name_compression)
+                .build(),
+        );
+
+        for index in 0..self.entries {
+            writer.start_file(self.entry_name(index), self.compression.method(index))?;
+            writer.write_all(&self.entry_data(index))?;
+        }
+
+        let mut data = writer.finish()?.into_inner();
+        for corruption in &self.corruptions {
+            apply(&mut data, corruption)?;
+        }
+
+        Ok(data)
+    }
+}
+
+fn apply(data: &mut Vec<u8>, corruption: &Corruption) -> Result<()> {
+    match *corruption {
+        Corruption::Truncate { len } => data.truncate(len),
+        Corruption::FlipByte { offset } => {
+            if let Some(byte) = data.get_mut(offset) {
+                *byte = !*byte;
+            }
+        }
+        Corruption::RecordCount(count) => {
+            if let Some(bytes) = data.get_mut(8..12) {
+                bytes.copy_from_slice(&count.to_le_bytes());
+            }
+        }
+        Corruption::EntryOffset { index, offset } => {
+            patch_record(data, index, |record| record.data_offset = offset)?
+        }
+        Corruption::EntrySize { index, size } => {
+            patch_record(data, index, |record| record.data_uncompressed = size)?
+        }
+        Corruption::EntryCompression { index, compression } => {
+            patch_record(data, index, |record| record.data_compression = compression)?
+        }
+    }
+
+    Ok(())
+}
+
+/// Modify the record at `index`, failing if there's no such record or its block is compressed
+/// with a method other than Zlib
+fn patch_record(
+    data: &mut Vec<u8>,
+    index: usize,
+    patch: impl FnOnce(&mut TreRecord),
+) -> Result<()> {
+    let mut header = TreHeader::read(&mut Cursor::new(&data))?;
+
+    let start = header.record_start as usize;
+    let end = start + header.record_compressed as usize;
+    let stored = data.get(start..end).ok_or(OutOfBoundsError::RecordBlock {
+        start: start as u64,
+        end: end as u64,
+        length: data.len() as u64,
+    })?;
+
+    let mut block = Vec::new();
+    match header.record_compression {
+        CompressionMethod::None => block.extend_from_slice(stored),
+        CompressionMethod::Zlib => {
+            ZlibDecoder::new(stored).read_to_end(&mut block)?;
+        }
+        CompressionMethod::Zstd | CompressionMethod::Other(_) => {
+            return Err(Error::UnsupportedCompression(header.record_compression))
+        }
+        CompressionMethod::Auto => unreachable!("headers never decode as auto"),
+    }
+
+    let mut cursor = Cursor::new(&block);
+    let mut records = (0..header.records)
+        .map(|_| TreRecord::read(&mut cursor))
+        .collect::<binrw::BinResult<Vec<_>>>()?;
+
+    patch(
+        records
+            .get_mut(index)
+            .ok_or(FileNotFoundError::Index(index))?,
+    );
+
+    let mut block = Cursor::new(Vec::new());
+    for record in &records {
+        record.write(&mut block)?;
+    }
+
+    let block = match header.record_compression {
+        CompressionMethod::None => block.into_inner(),
+        CompressionMethod::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(block.get_ref())?;
+            encoder.finish()?
+        }
+        CompressionMethod::Zstd | CompressionMethod::Other(_) => {
+            return Err(Error::UnsupportedCompression(header.record_compression))
+        }
+        CompressionMethod::Auto => unreachable!("headers never decode as auto"),
+    };
+
+    header.record_compressed = block.len() as u32;
+    data.splice(start..end, block);
+
+    let mut head = Cursor::new(Vec::new());
+    header.write(&mut head)?;
+    data.splice(..head.get_ref().len(), head.into_inner());
+
+    Ok(())
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        Self(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
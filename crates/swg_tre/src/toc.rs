@@ -0,0 +1,605 @@
+//! Tables of contents which index the entries of many archives at once
+//!
+//! Later releases of the client ship a `.toc` file alongside their archives, listing every file
+//! the archives hold and where its data is. The client reads the table instead of the header of
+//! each archive, and [`TreVfs::from_toc`](crate::vfs::TreVfs::from_toc) does the same.
+//!
+//! ## File Structure
+//!
+//! A TOC file starts with a header, followed by the names of the archives it indexes, a record
+//! block and a name block. All integers are little-endian.
+//!
+//! | Offset (bytes) | Field                  | Description                                          |
+//! |----------------|------------------------|------------------------------------------------------|
+//! | 0x0000         | Magic number           | 4 bytes: " COT", "TOC " stored as a little-endian tag |
+//! | 0x0004         | Version                | 4 bytes: "1000", "0001" stored the same way          |
+//! | 0x0008         | Record Compression     | 1 byte: Compression method for the record block      |
+//! | 0x0009         | Name Compression       | 1 byte: Compression method for the name block        |
+//! | 0x000A         | Unused                 | 2 bytes                                              |
+//! | 0x000C         | Record Count           | 4 bytes: Number of records                           |
+//! | 0x0010         | Record Comp. Size      | 4 bytes: Compressed size of the record block         |
+//! | 0x0014         | Name Comp. Size        | 4 bytes: Compressed size of the name block           |
+//! | 0x0018         | Name Uncomp. Size      | 4 bytes: Uncompressed size of the name block         |
+//! | 0x001C         | Archive Count          | 4 bytes: Number of archives indexed                  |
+//! | 0x0020         | Archive Names Size     | 4 bytes: Size of the archive name block              |
+//!
+//! The archive name block is never compressed and holds the path of each archive, relative to the
+//! TOC file and ending with a null terminator. Each record of the record block has the following
+//! structure:
+//!
+//! | Offset (bytes) | Field                  | Description                                          |
+//! |----------------|------------------------|------------------------------------------------------|
+//! | 0x0000         | Compression            | 1 byte: Compression method for the record data       |
+//! | 0x0001         | Unused                 | 1 byte                                               |
+//! | 0x0002         | Archive                | 2 bytes: Index of the archive holding the data       |
+//! | 0x0004         | CRC32                  | 4 bytes: The checksum from the archive's record      |
+//! | 0x0008         | Name Offset            | 4 bytes: Offset to the name within the name block    |
+//! | 0x000C         | Data Offset            | 4 bytes: Offset to the data within the archive       |
+//! | 0x0010         | Uncompressed Size      | 4 bytes: Size of the data when uncompressed          |
+//! | 0x0014         | Compressed Size        | 4 bytes: Size of the data as stored                  |
+//!
+//! Records are sorted by their checksum, and there's a single record for each file, pointing at
+//! the archive whose copy the client loads. The name block is laid out the same way as an
+//! archive's.
+//!
+//! ```no_run
+//! # fn doit() -> swg_tre::error::Result<()>
+//! # {
+//! use std::fs::File;
+//! use swg_tre::{toc::{TocFile, TocWriter, TocWriterOptions}, TreArchive};
+//!
+//! let mut writer = TocWriter::new(TocWriterOptions::builder().build());
+//! writer.add_archive("bottom.tre", &TreArchive::new(File::open("bottom.tre")?)?)?;
+//! writer.add_archive("patch_01.tre", &TreArchive::new(File::open("patch_01.tre")?)?)?;
+//! writer.finish(File::create("client.toc")?)?;
+//!
+//! let toc = TocFile::new(File::open("client.toc")?)?;
+//! println!("{:?}", toc.by_name("misc/readme.txt"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+};
+
+use binrw::{BinRead, BinWrite};
+use bon::Builder;
+use byteorder::WriteBytesExt;
+use flate2::Compression;
+use tracing::instrument;
+
+use crate::{
+    compression::{compress_if_smaller, CompressionMethod, TreBlockReader, TreBlockWriter},
+    error::{Error, LimitExceededError, OutOfBoundsError, Result, TocError},
+    read::{TreArchive, TreLimits},
+    transform::BlockKind,
+};
+
+/// The size of [`TocHeader`] as stored
+const HEADER_SIZE: u64 = 36;
+
+/// The size of [`TocRecord`] as stored
+const RECORD_SIZE: usize = 24;
+
+/// TOC file header
+#[derive(BinRead, BinWrite, Debug, Default, Copy, Clone, PartialEq)]
+#[brw(magic = b" COT1000", little)]
+pub struct TocHeader {
+    /// The compression type used for compressing the record block
+    #[br(map = |value: u8| CompressionMethod::from(value as u32))]
+    #[bw(map = |method: &CompressionMethod| method.stored().unwrap_or_default() as u8)]
+    pub record_compression: CompressionMethod,
+
+    /// The compression type used for compressing the block of file names
+    #[br(map = |value: u8| CompressionMethod::from(value as u32))]
+    #[bw(map = |method: &CompressionMethod| method.stored().unwrap_or_default() as u8)]
+    #[brw(pad_after = 2)]
+    pub name_compression: CompressionMethod,
+
+    /// The number of records stored in the file
+    pub records: u32,
+
+    /// The size in the file of the compressed record block
+    pub record_compressed: u32,
+
+    /// The size of the name block after compression
+    pub name_compressed: u32,
+
+    /// The size of the name block before compression
+    pub name_uncompressed: u32,
+
+    /// The number of archives the records refer to
+    pub archives: u32,
+
+    /// The size of the block of archive names
+    pub archive_names: u32,
+}
+
+/// TOC file record
+///
+/// Describes where a file's data is stored, copied from the record of the archive holding it
+#[derive(BinRead, BinWrite, Debug, Default, Copy, Clone, PartialEq)]
+#[brw(little)]
+pub struct TocRecord {
+    /// The compression type used to compress this record's data
+    #[br(map = |value: u8| CompressionMethod::from(value as u32))]
+    #[bw(map = |method: &CompressionMethod| method.stored().unwrap_or_default() as u8)]
+    #[brw(pad_after = 1)]
+    pub data_compression: CompressionMethod,
+
+    /// The index of the archive holding the data
+    pub archive: u16,
+
+    /// The checksum from the archive's record, normally of the file's name
+    pub checksum: u32,
+
+    /// The offset from the start of the name block for this record's name
+    pub name_offset: u32,
+
+    /// The offset to the data for this record from the start of its archive
+    pub data_offset: u32,
+
+    /// The size of the data for this record before compression
+    pub data_uncompressed: u32,
+
+    /// The size of this record's data after compression
+    pub data_compressed: u32,
+}
+
+/// A file listed in a table of contents
+#[derive(Debug, Clone, PartialEq)]
+pub struct TocEntry {
+    /// The name of the file
+    pub name: String,
+    /// Where the file's data is stored
+    pub record: TocRecord,
+}
+
+/// A table of contents read into memory
+#[derive(Debug, Clone)]
+pub struct TocFile {
+    header: TocHeader,
+    archives: Vec<String>,
+    entries: Vec<TocEntry>,
+    names: HashMap<String, usize>,
+}
+
+impl TocFile {
+    /// Read a table of contents, with the default [`TreLimits`]
+    pub fn new<R: Read + Seek>(reader: R) -> Result<TocFile> {
+        Self::with_limits(reader, TreLimits::default())
+    }
+
+    /// Read a table of contents, checking its header against the limits before allocating
+    /// anything it declares
+    #[instrument(skip_all, err)]
+    pub fn with_limits<R: Read + Seek>(mut reader: R, limits: TreLimits) -> Result<TocFile> {
+        let length = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        let header = TocHeader::read(&mut reader)?;
+        Self::check_header(&header, &limits, length)?;
+
+        let mut archive_names = vec![0; header.archive_names as usize];
+        reader.read_exact(&mut archive_names)?;
+        let archives = archive_names
+            .split(|b| *b == b'\0')
+            .take(header.archives as usize)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect::<Vec<_>>();
+
+        let record_start = HEADER_SIZE + header.archive_names as u64;
+        let record_size = (header.records as u64)
+            .checked_mul(RECORD_SIZE as u64)
+            .ok_or(LimitExceededError::Records {
+                count: header.records,
+                limit: limits.max_records,
+            })?;
+        // Records are padded, which needs a reader that can seek
+        let mut record_block = Vec::with_capacity(record_size.min(length) as usize);
+        TreBlockReader::new(
+            &mut reader,
+            record_start,
+            header.record_compressed as u64,
+            header.record_compression,
+            BlockKind::Records,
+            None,
+            None,
+        )?
+        .take(record_size)
+        .read_to_end(&mut record_block)?;
+        let mut record_reader = Cursor::new(record_block);
+        let records = (0..header.records)
+            .map(|_| TocRecord::read(&mut record_reader))
+            .collect::<binrw::BinResult<Vec<_>>>()?;
+
+        let mut names = Vec::with_capacity((header.name_uncompressed as u64).min(length) as usize);
+        TreBlockReader::new(
+            &mut reader,
+            record_start + header.record_compressed as u64,
+            header.name_compressed as u64,
+            header.name_compression,
+            BlockKind::Names,
+            None,
+            None,
+        )?
+        .take(header.name_uncompressed as u64)
+        .read_to_end(&mut names)?;
+
+        let entries = records
+            .into_iter()
+            .enumerate()
+            .map(|(index, record)| {
+                if record.archive as usize >= archives.len() {
+                    return Err(TocError::ArchiveIndex {
+                        index,
+                        archive: record.archive,
+                        count: archives.len(),
+                    });
+                }
+
+                let name = names
+                    .get(record.name_offset as usize..)
+                    .and_then(|rest| rest.split(|b| *b == b'\0').next())
+                    .ok_or(TocError::NameOffset {
+                        index,
+                        offset: record.name_offset,
+                    })?;
+                let name = String::from_utf8_lossy(name).into_owned();
+                Ok(TocEntry { name, record })
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let names = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (entry.name.clone(), index))
+            .collect();
+        Ok(TocFile {
+            header,
+            archives,
+            entries,
+            names,
+        })
+    }
+
+    fn check_header(header: &TocHeader, limits: &TreLimits, length: u64) -> Result<()> {
+        if header.records > limits.max_records {
+            return Err(LimitExceededError::Records {
+                count: header.records,
+                limit: limits.max_records,
+            }
+            .into());
+        }
+
+        for size in [header.name_compressed, header.name_uncompressed] {
+            if size > limits.max_name_block_size {
+                return Err(LimitExceededError::NameBlock {
+                    size,
+                    limit: limits.max_name_block_size,
+                }
+                .into());
+            }
+        }
+
+        let record_start = HEADER_SIZE + header.archive_names as u64;
+        if record_start > length {
+            return Err(OutOfBoundsError::ArchiveNameBlock {
+                start: HEADER_SIZE,
+                end: record_start,
+                length,
+            }
+            .into());
+        }
+
+        let record_end = record_start + header.record_compressed as u64;
+        if record_end > length {
+            return Err(OutOfBoundsError::RecordBlock {
+                start: record_start,
+                end: record_end,
+                length,
+            }
+            .into());
+        }
+
+        let name_end = record_end + header.name_compressed as u64;
+        if name_end > length {
+            return Err(OutOfBoundsError::NameBlock {
+                start: record_end,
+                end: name_end,
+                length,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Get the header of the table
+    pub fn header(&self) -> &TocHeader {
+        &self.header
+    }
+
+    /// The paths of the archives the table indexes, relative to the table
+    pub fn archives(&self) -> &[String] {
+        &self.archives
+    }
+
+    /// Every file in the table, in the order they're stored
+    pub fn entries(&self) -> &[TocEntry] {
+        &self.entries
+    }
+
+    /// The number of files in the table
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the table has no files
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Search for a file by name
+    pub fn by_name(&self, name: &str) -> Option<&TocEntry> {
+        self.names.get(name).map(|&index| &self.entries[index])
+    }
+}
+
+/// Options for how the TOC file should be written
+#[derive(Debug, Clone, Builder)]
+pub struct TocWriterOptions {
+    /// The compression method to use for the record block
+    #[builder(default)]
+    pub record_compression: CompressionMethod,
+
+    /// The compression method to use for the name block
+    #[builder(default)]
+    pub name_compression: CompressionMethod,
+
+    /// The Zlib compression level from 0 to 9, trading speed for smaller output
+    #[builder(default = 6)]
+    pub compression_level: u32,
+}
+
+/// Builds a table of contents from archives, from the lowest priority to the highest
+#[derive(Debug)]
+pub struct TocWriter {
+    options: TocWriterOptions,
+    archives: Vec<String>,
+    entries: BTreeMap<String, TocRecord>,
+}
+
+impl TocWriter {
+    /// Create an empty table
+    pub fn new(options: TocWriterOptions) -> TocWriter {
+        TocWriter {
+            options,
+            archives: Vec::new(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Add the entries of an archive, replacing those of archives added before it
+    ///
+    /// `path` is where the archive is found relative to the table once it's written.
+    pub fn add_archive<R: Read + Seek>(
+        &mut self,
+        path: impl Into<String>,
+        tre: &TreArchive<R>,
+    ) -> Result<()> {
+        let archive = u16::try_from(self.archives.len())
+            .map_err(|_| TocError::TooManyArchives(u16::MAX as usize + 1))?;
+        self.archives.push(path.into());
+
+        for (name, record) in tre.file_names().zip(tre.records()) {
+            let record = TocRecord {
+                data_compression: record.data_compression,
+                archive,
+                checksum: record.checksum,
+                name_offset: 0,
+                data_offset: record.data_offset,
+                data_uncompressed: record.data_uncompressed,
+                data_compressed: record.data_compressed,
+            };
+            self.entries.insert(name.to_owned(), record);
+        }
+        Ok(())
+    }
+
+    /// Write the table
+    #[instrument(skip_all, err, fields(records = self.entries.len()))]
+    pub fn finish<W: Write>(self, mut writer: W) -> Result<W> {
+        let level = Compression::new(self.options.compression_level);
+        let mut header = TocHeader {
+            record_compression: self.options.record_compression,
+            name_compression: self.options.name_compression,
+            records: self.entries.len() as u32,
+            archives: self.archives.len() as u32,
+            ..Default::default()
+        };
+
+        let mut entries = self.entries.into_iter().collect::<Vec<_>>();
+        entries.sort_by(|(a, x), (b, y)| (x.checksum, a).cmp(&(y.checksum, b)));
+
+        let mut record_block =
+            TreBlockWriter::new(Cursor::new(Vec::new()), header.record_compression, level)?;
+        let mut name_block =
+            TreBlockWriter::new(Cursor::new(Vec::new()), header.name_compression, level)?;
+        for (name, mut record) in entries {
+            record.name_offset = offset(name_block.total_in())?;
+            record.write(&mut record_block)?;
+            name_block.write_all(name.as_bytes())?;
+            name_block.write_u8(0)?;
+        }
+
+        let mut record_block = record_block.finalize()?.into_inner();
+        if header.record_compression == CompressionMethod::Auto {
+            (header.record_compression, record_block) = compress_if_smaller(record_block, level)?;
+        }
+        header.record_compressed = offset(record_block.len() as u64)?;
+
+        header.name_uncompressed = offset(name_block.total_in())?;
+        let mut name_block = name_block.finalize()?.into_inner();
+        if header.name_compression == CompressionMethod::Auto {
+            (header.name_compression, name_block) = compress_if_smaller(name_block, level)?;
+        }
+        header.name_compressed = offset(name_block.len() as u64)?;
+
+        let mut archive_names = Vec::new();
+        for archive in &self.archives {
+            archive_names.extend_from_slice(archive.as_bytes());
+            archive_names.push(0);
+        }
+        header.archive_names = offset(archive_names.len() as u64)?;
+
+        let mut header_block = Cursor::new(Vec::new());
+        header.write(&mut header_block)?;
+        writer.write_all(&header_block.into_inner())?;
+        writer.write_all(&archive_names)?;
+        writer.write_all(&record_block)?;
+        writer.write_all(&name_block)?;
+        writer.flush()?;
+
+        Ok(writer)
+    }
+}
+
+/// Narrow a size to the 32 bits the format stores
+fn offset(value: u64) -> Result<u32> {
+    u32::try_from(value).map_err(|_| Error::TooLarge(value))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Write};
+
+    use binrw::BinWrite;
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        compression::CompressionMethod,
+        error::{Error, LimitExceededError, OutOfBoundsError, Result, TocError},
+        read::TreArchive,
+        toc::{TocFile, TocHeader, TocWriter, TocWriterOptions},
+        write::{TreWriter, TreWriterOptions},
+    };
+
+    fn archive(entries: &[(&str, &str)]) -> Result<TreArchive<Cursor<Vec<u8>>>> {
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        for (name, contents) in entries {
+            tre.start_file(name, CompressionMethod::Zlib)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+        TreArchive::new(tre.finish()?)
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let bottom = archive(&[("a.txt", "base"), ("b.txt", "only in base")])?;
+        let patch = archive(&[("a.txt", "patched"), ("c.txt", "only in patch")])?;
+
+        for compression in [CompressionMethod::None, CompressionMethod::Auto] {
+            let options = TocWriterOptions::builder()
+                .record_compression(compression)
+                .name_compression(compression)
+                .build();
+            let mut writer = TocWriter::new(options);
+            writer.add_archive("bottom.tre", &bottom)?;
+            writer.add_archive("patch.tre", &patch)?;
+            let toc = TocFile::new(writer.finish(Cursor::new(Vec::new()))?)?;
+
+            assert_eq!(toc.archives(), ["bottom.tre", "patch.tre"]);
+            assert_eq!(toc.len(), 3);
+
+            let checksums = toc.entries().iter().map(|e| e.record.checksum);
+            assert!(checksums
+                .clone()
+                .zip(checksums.skip(1))
+                .all(|(a, b)| a <= b));
+
+            let patched = toc.by_name("a.txt").expect("a.txt is in the table");
+            let record = patch.by_name("a.txt")?.record();
+            assert_eq!(patched.record.archive, 1);
+            assert_eq!(patched.record.data_offset, record.data_offset);
+            assert_eq!(patched.record.data_compressed, record.data_compressed);
+            assert_eq!(patched.record.data_uncompressed, 7);
+            assert_eq!(toc.by_name("b.txt").map(|e| e.record.archive), Some(0));
+            assert!(toc.by_name("d.txt").is_none());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_archive() -> Result<()> {
+        let mut writer = TocWriter::new(
+            TocWriterOptions::builder()
+                .record_compression(CompressionMethod::None)
+                .build(),
+        );
+        writer.add_archive("bottom.tre", &archive(&[("a.txt", "base")])?)?;
+        let mut toc = writer.finish(Cursor::new(Vec::new()))?.into_inner();
+
+        // The archive index of the only record, after the header and "bottom.tre\0"
+        toc[36 + 11 + 2] = 1;
+        assert!(matches!(
+            TocFile::new(Cursor::new(toc)),
+            Err(Error::Toc(TocError::ArchiveIndex { archive: 1, .. }))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_malicious_header() -> Result<()> {
+        let header = |header: TocHeader| -> Result<Cursor<Vec<u8>>> {
+            let mut data = Cursor::new(Vec::new());
+            header.write(&mut data)?;
+            Ok(data)
+        };
+
+        let records = header(TocHeader {
+            records: u32::MAX,
+            ..Default::default()
+        })?;
+        assert_eq!(records.get_ref().len(), 36);
+        assert!(matches!(
+            TocFile::new(records),
+            Err(Error::LimitExceeded(LimitExceededError::Records { .. }))
+        ));
+
+        let names = header(TocHeader {
+            name_uncompressed: u32::MAX,
+            ..Default::default()
+        })?;
+        assert!(matches!(
+            TocFile::new(names),
+            Err(Error::LimitExceeded(LimitExceededError::NameBlock { .. }))
+        ));
+
+        let archives = header(TocHeader {
+            archive_names: u32::MAX,
+            ..Default::default()
+        })?;
+        assert!(matches!(
+            TocFile::new(archives),
+            Err(Error::OutOfBounds(
+                OutOfBoundsError::ArchiveNameBlock { .. }
+            ))
+        ));
+
+        let records = header(TocHeader {
+            records: 1000,
+            record_compressed: 1000 * 24,
+            ..Default::default()
+        })?;
+        assert!(matches!(
+            TocFile::new(records),
+            Err(Error::OutOfBounds(OutOfBoundsError::RecordBlock { .. }))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+//! Hooks for archives whose blocks are obfuscated or encrypted
+//!
+//! Some community clients ship archives whose stored blocks are scrambled on top of the usual
+//! compression. A [`BlockTransform`] set through [`crate::read::TreArchiveOptions::transform`] is
+//! undone on every stored byte before it is decompressed, and one set through
+//! [`crate::write::TreWriterOptions::transform`] is applied to every block once it is compressed.
+//!
+//! Transforms work in place and can't change the length of a block, as the header and records
+//! describe blocks by their stored size. The header and the hash block are never transformed, and
+//! the hashes cover the data as it is stored.
+
+use std::{
+    fmt::Debug,
+    io::{self, Read},
+    sync::Arc,
+};
+
+/// The kind of block a transform is applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    /// The data of a single entry
+    Entry,
+    /// The block of records
+    Records,
+    /// The block of names
+    Names,
+}
+
+/// A reversible, length preserving transform of the stored bytes of a block
+///
+/// Blocks may be read in pieces, so each call is given the offset of the first byte of `data`
+/// within the archive and must transform it independently of any other call.
+pub trait BlockTransform: Debug + Send + Sync {
+    /// Undo the transform, turning stored bytes back into the block as it was compressed
+    fn decode(&self, block: BlockKind, position: u64, data: &mut [u8]);
+
+    /// Apply the transform to a compressed block, turning it into the bytes which are stored
+    fn encode(&self, block: BlockKind, position: u64, data: &mut [u8]);
+}
+
+/// Combines every byte with a repeating key, aligned to the byte's offset within the archive
+///
+/// ```
+/// use swg_tre::transform::{BlockKind, BlockTransform, XorTransform};
+///
+/// let xor = XorTransform::new(*b"key");
+/// let mut data = *b"Hello, World!";
+/// xor.encode(BlockKind::Entry, 36, &mut data);
+/// xor.decode(BlockKind::Entry, 36, &mut data);
+/// assert_eq!(&data, b"Hello, World!");
+/// ```
+#[derive(Debug, Clone)]
+pub struct XorTransform {
+    key: Box<[u8]>,
+}
+
+impl XorTransform {
+    /// Create a transform with the given key, an empty key leaves data as it is
+    pub fn new(key: impl Into<Box<[u8]>>) -> Self {
+        XorTransform { key: key.into() }
+    }
+
+    fn apply(&self, position: u64, data: &mut [u8]) {
+        if self.key.is_empty() {
+            return;
+        }
+
+        let len = self.key.len() as u64;
+        let key = self.key.iter().cycle().skip((position % len) as usize);
+        for (byte, key) in data.iter_mut().zip(key) {
+            *byte ^= key;
+        }
+    }
+}
+
+impl BlockTransform for XorTransform {
+    fn decode(&self, _block: BlockKind, position: u64, data: &mut [u8]) {
+        self.apply(position, data);
+    }
+
+    fn encode(&self, _block: BlockKind, position: u64, data: &mut [u8]) {
+        self.apply(position, data);
+    }
+}
+
+/// Undoes a transform on the stored bytes of a block as they are read
+pub(crate) struct Decoded<R> {
+    inner: R,
+    transform: Option<(Arc<dyn BlockTransform>, BlockKind)>,
+    position: u64,
+}
+
+impl<R: Read> Decoded<R> {
+    /// Wrap a reader positioned at `position` within the archive
+    pub fn new(
+        inner: R,
+        transform: Option<(Arc<dyn BlockTransform>, BlockKind)>,
+        position: u64,
+    ) -> Self {
+        Decoded {
+            inner,
+            transform,
+            position,
+        }
+    }
+}
+
+impl<R: Read> Read for Decoded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some((transform, block)) = &self.transform {
+            transform.decode(*block, self.position, &mut buf[..read]);
+        }
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Cursor, Read, Write},
+        sync::Arc,
+    };
+
+    use crate::{
+        compression::CompressionMethod,
+        error::Result,
+        read::{TreArchive, TreArchiveOptions},
+        transform::XorTransform,
+        write::{TreWriter, TreWriterOptions},
+    };
+
+    #[test]
+    fn xor_round_trip() -> Result<()> {
+        let entries = [
+            (
+                "a.txt",
+                "Hello, World! ".repeat(16),
+                CompressionMethod::Zlib,
+            ),
+            ("b.txt", "stored".to_owned(), CompressionMethod::None),
+        ];
+        let transform = Arc::new(XorTransform::new(*b"secret"));
+
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .transform(transform.clone())
+                .build(),
+        );
+        for (name, contents, compression) in &entries {
+            tre.start_file(name, *compression)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+        let data = tre.finish()?.into_inner();
+
+        assert!(!data.windows(6).any(|w| w == b"stored"));
+        assert!(TreArchive::new(Cursor::new(&data)).is_err());
+
+        let tre = TreArchive::with_options(
+            Cursor::new(&data),
+            TreArchiveOptions::builder().transform(transform).build(),
+        )?;
+        for (name, contents, _) in &entries {
+            let mut actual = String::new();
+            tre.by_name(name)?.read_to_string(&mut actual)?;
+            assert_eq!(&actual, contents);
+        }
+
+        Ok(())
+    }
+}
@@ -29,7 +29,6 @@ pub struct TreHeader {
     pub name_compressed: u32,
 
     /// The size of the name block before compression
-    #[allow(dead_code)]
     pub name_uncompressed: u32,
 }
 
@@ -53,7 +52,7 @@ impl Default for TreHeader {
 #[derive(BinRead, BinWrite, Debug, Default, Copy, Clone, PartialEq)]
 #[brw(little)]
 pub struct TreRecord {
-    /// A [`crc::CRC_32_BZIP2`] checksum of the record's name
+    /// A [`crc::CRC_32_BZIP2`] checksum, normally of the record's name
     pub checksum: u32,
 
     /// The size of the data for this record before compression
@@ -69,7 +68,6 @@ pub struct TreRecord {
     pub data_compressed: u32,
 
     /// The offset from the start of the name block for this record's name
-    #[allow(dead_code)]
     pub name_offset: u32,
 }
 
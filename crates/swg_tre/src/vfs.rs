@@ -0,0 +1,1143 @@
+//! Archives and directories layered into a single tree of files, the way the client loads them
+//!
+//! The client searches a list of archives for each file it loads and uses the copy from the
+//! archive with the highest priority. [`TreVfs`] resolves names the same way, so tools see the
+//! same file the game would.
+//!
+//! Directories of loose files can be layered between archives too, the way the client's
+//! `searchPath` entries are, so a mod being worked on can override the stock archives without
+//! being packed first. [`TreVfs::from_client_dir`] reads the layers and their priorities from the
+//! client's configs.
+//!
+//! Indexing every archive of an installation means opening each of them, so
+//! [`TreVfs::open_cached`] keeps the merged index on disk and reuses it until an archive changes.
+//! Installations which ship a [table of contents](crate::toc) can be opened with
+//! [`TreVfs::from_toc`] instead, which also leaves each archive closed until it's read from.
+//!
+//! ```no_run
+//! # fn doit() -> swg_tre::error::Result<()>
+//! # {
+//! use std::io::Read;
+//! use swg_tre::vfs::TreVfs;
+//!
+//! let vfs = TreVfs::open(["bottom.tre", "patch_01.tre"])?;
+//! let mut contents = String::new();
+//! vfs.by_name("misc/readme.txt")?.read_to_string(&mut contents)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+    ops::Bound,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError},
+    time::UNIX_EPOCH,
+};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use tracing::{debug, instrument, warn};
+use walkdir::WalkDir;
+
+use crate::{
+    cache::EntryCache,
+    config::ClientConfig,
+    error::{Error, FileNotFoundError, Result},
+    read::{TreArchive, TreFile},
+    toc::TocFile,
+};
+
+/// The bytes an index cache starts with, bumped whenever its layout changes
+const INDEX_MAGIC: &[u8; 8] = b"SWGVFSI1";
+
+/// Stored in an index cache in place of the entry index of a file from a directory
+const NO_INDEX: u32 = u32::MAX;
+
+/// A layer of a [`TreVfs`]
+pub enum VfsLayer<R> {
+    /// An archive
+    Archive(TreArchive<R>),
+    /// A directory of loose files, laid out the same way as the entries of an archive
+    Directory(PathBuf),
+}
+
+/// Where the winning copy of a file is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VfsLocation {
+    /// The index of the layer, in the order the layers were given
+    pub layer: usize,
+    /// The index of the entry when the layer is an archive
+    ///
+    /// Files listed by a table of contents are only known by name until their archive is opened,
+    /// so have no index either.
+    pub index: Option<usize>,
+}
+
+/// A file opened from a [`TreVfs`]
+#[allow(clippy::large_enum_variant)]
+pub enum VfsFile<'a, R: Read + Seek> {
+    /// An entry of an archive
+    Archive(TreFile<'a, R>),
+    /// A loose file
+    Loose(File),
+}
+
+impl<R: Read + Seek> Read for VfsFile<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            VfsFile::Archive(file) => file.read(buf),
+            VfsFile::Loose(file) => file.read(buf),
+        }
+    }
+}
+
+/// A file opened from a [`TreVfs`] which can be seeked, for parsers which jump around
+///
+/// Archive entries are decompressed whole when the handle is opened, loose files are read from
+/// disk as they're used.
+#[derive(Debug)]
+pub enum VfsHandle {
+    /// The contents of an archive entry
+    Archive(Cursor<Arc<[u8]>>),
+    /// A loose file
+    Loose(File),
+}
+
+impl Read for VfsHandle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            VfsHandle::Archive(contents) => contents.read(buf),
+            VfsHandle::Loose(file) => file.read(buf),
+        }
+    }
+}
+
+impl Seek for VfsHandle {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            VfsHandle::Archive(contents) => contents.seek(pos),
+            VfsHandle::Loose(file) => file.seek(pos),
+        }
+    }
+}
+
+/// Something found directly within a directory of a [`TreVfs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VfsDirEntry {
+    /// A file, and the layer it's provided by
+    File {
+        /// The name of the file within the directory
+        name: String,
+        /// Where the winning copy of the file is stored
+        location: VfsLocation,
+    },
+    /// A directory, which may hold files from any number of layers
+    Directory {
+        /// The name of the directory within its parent
+        name: String,
+    },
+}
+
+impl VfsDirEntry {
+    /// The name of the entry within its directory
+    pub fn name(&self) -> &str {
+        match self {
+            VfsDirEntry::File { name, .. } | VfsDirEntry::Directory { name } => name,
+        }
+    }
+}
+
+/// One layer's copy of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VfsCopy {
+    /// Where the copy is stored
+    pub location: VfsLocation,
+    /// The size of the copy once decompressed
+    pub size: u64,
+    /// The checksum from the archive's record, loose files have none
+    pub crc32: Option<u32>,
+}
+
+/// A file provided by more than one layer of a [`TreVfs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowedFile {
+    /// The name of the file
+    pub name: String,
+    /// The copy which is loaded, from the highest priority layer
+    pub winner: VfsCopy,
+    /// The copies it overrides, from the highest priority layer to the lowest
+    pub losers: Vec<VfsCopy>,
+}
+
+/// How often a [`TreVfs`]'s cache has had the files asked of it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Reads served from memory
+    pub hits: u64,
+    /// Reads which had to decompress the file
+    pub misses: u64,
+    /// The bytes of decompressed files currently held
+    pub size: u64,
+    /// The most bytes the cache holds at once
+    pub capacity: u64,
+}
+
+/// An ordered set of archives and directories, where layers later in the order take priority
+/// over earlier ones
+pub struct TreVfs<R> {
+    layers: Vec<Layer<R>>,
+    files: BTreeMap<Box<str>, VfsLocation>,
+    open: Option<OpenArchive<R>>,
+    cache: Option<Mutex<EntryCache<Box<str>>>>,
+}
+
+/// Opens the archive of a layer which was indexed from a cache
+type OpenArchive<R> = fn(&Path) -> Result<TreArchive<R>>;
+
+/// The winning archive entry of every file in a cached index
+type CachedFiles = Vec<(Box<str>, VfsLocation)>;
+
+/// A layer as it's held by a [`TreVfs`]
+///
+/// Archives indexed from a cache aren't opened until a file is read from them, so `path` is kept
+/// to open them by.
+enum Layer<R> {
+    Archive {
+        archive: OnceLock<TreArchive<R>>,
+        path: Option<PathBuf>,
+    },
+    Directory(PathBuf),
+}
+
+impl TreVfs<File> {
+    /// Open archives and directories from disk, from the lowest priority to the highest
+    #[instrument(skip_all, err)]
+    pub fn open<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<TreVfs<File>> {
+        let layers = paths
+            .into_iter()
+            .map(|path| match path.as_ref().is_dir() {
+                true => Ok(VfsLayer::Directory(path.as_ref().to_owned())),
+                false => Ok(VfsLayer::Archive(open_archive(path.as_ref())?)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        TreVfs::with_layers(layers)
+    }
+
+    /// Open archives and directories like [`TreVfs::open`], keeping the merged index of the
+    /// archives in the file at `cache`
+    ///
+    /// The cache is used as long as every archive has the same size and modification time it had
+    /// when the cache was written, and archives are then only opened once a file is read from
+    /// them. Otherwise every archive is opened and the cache is rewritten. Directories are
+    /// walked either way, a failure to write the cache is logged rather than returned.
+    #[instrument(skip_all, err, fields(cache = %cache.as_ref().display()))]
+    pub fn open_cached<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+        cache: impl AsRef<Path>,
+    ) -> Result<TreVfs<File>> {
+        let cache = cache.as_ref();
+        let paths = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_owned())
+            .collect::<Vec<_>>();
+        let keys = paths
+            .iter()
+            .map(|path| LayerKey::new(path))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        match read_index(cache, &keys) {
+            Ok(Some(archived)) => {
+                debug!("using the cached index of {} files", archived.len());
+                return TreVfs::from_index(paths, archived);
+            }
+            Ok(None) => debug!("the cached index is missing or stale"),
+            Err(e) => warn!("unable to read the cached index: {}", e),
+        }
+
+        let vfs = TreVfs::open(&paths)?;
+        if let Err(e) = write_index(cache, &keys, &vfs.archived_files()) {
+            warn!("unable to write the cached index: {}", e);
+        }
+        Ok(vfs)
+    }
+
+    /// Open the archives and directories a client installation loads, in the order its configs
+    /// give them
+    ///
+    /// See [`ClientConfig::from_client_dir`].
+    pub fn from_client_dir(dir: impl AsRef<Path>) -> Result<TreVfs<File>> {
+        let dir = dir.as_ref();
+        let config = ClientConfig::from_client_dir(dir)?;
+        TreVfs::open(config.entries().iter().map(|entry| dir.join(&entry.path)))
+    }
+
+    /// Open the archives listed by a table of contents, using the table to find files
+    ///
+    /// Archives are found relative to the table, and are only opened once a file is read from
+    /// them.
+    #[instrument(skip_all, err, fields(path = %path.as_ref().display()))]
+    pub fn from_toc(path: impl AsRef<Path>) -> Result<TreVfs<File>> {
+        let path = path.as_ref();
+        let toc = TocFile::new(BufReader::new(File::open(path)?))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+
+        let layers = toc
+            .archives()
+            .iter()
+            .map(|archive| Layer::Archive {
+                archive: OnceLock::new(),
+                path: Some(dir.join(archive)),
+            })
+            .collect();
+        let files = toc
+            .entries()
+            .iter()
+            .map(|entry| {
+                let layer = entry.record.archive as usize;
+                (
+                    entry.name.as_str().into(),
+                    VfsLocation { layer, index: None },
+                )
+            })
+            .collect();
+
+        Ok(TreVfs {
+            layers,
+            files,
+            open: Some(open_archive),
+            cache: None,
+        })
+    }
+
+    /// Build a VFS from the cached index of its archives, which are left unopened
+    fn from_index(paths: Vec<PathBuf>, archived: CachedFiles) -> Result<TreVfs<File>> {
+        let mut files = archived.into_iter().collect::<BTreeMap<_, _>>();
+        let mut layers = Vec::with_capacity(paths.len());
+        for (layer, path) in paths.into_iter().enumerate() {
+            if !path.is_dir() {
+                let archive = OnceLock::new();
+                let path = Some(path);
+                layers.push(Layer::Archive { archive, path });
+                continue;
+            }
+
+            for name in loose_files(&path)? {
+                // The cache only knows which archive wins, so a directory replaces the file
+                // unless a later archive has it too
+                let location = VfsLocation { layer, index: None };
+                files
+                    .entry(name)
+                    .and_modify(|winner| {
+                        if winner.layer < layer {
+                            *winner = location;
+                        }
+                    })
+                    .or_insert(location);
+            }
+            layers.push(Layer::Directory(path));
+        }
+
+        Ok(TreVfs {
+            layers,
+            files,
+            open: Some(open_archive),
+            cache: None,
+        })
+    }
+}
+
+impl<R: Read + Seek> TreVfs<R> {
+    /// Layer archives which are already open, from the lowest priority to the highest
+    pub fn new(archives: Vec<TreArchive<R>>) -> TreVfs<R> {
+        TreVfs::with_layers(archives.into_iter().map(VfsLayer::Archive).collect())
+            .expect("archives are indexed without touching the disk")
+    }
+
+    /// Layer archives and directories, from the lowest priority to the highest
+    ///
+    /// Directories are walked once up front, files added to them later aren't found and files
+    /// whose paths aren't valid UTF-8 are skipped.
+    pub fn with_layers(layers: Vec<VfsLayer<R>>) -> Result<TreVfs<R>> {
+        let mut files = BTreeMap::new();
+        for (layer, source) in layers.iter().enumerate() {
+            match source {
+                VfsLayer::Archive(tre) => {
+                    for (index, name) in tre.file_names().enumerate() {
+                        let index = Some(index);
+                        files.insert(name.into(), VfsLocation { layer, index });
+                    }
+                }
+                VfsLayer::Directory(root) => {
+                    for name in loose_files(root)? {
+                        let index = None;
+                        files.insert(name, VfsLocation { layer, index });
+                    }
+                }
+            }
+        }
+
+        let layers = layers
+            .into_iter()
+            .map(|layer| match layer {
+                VfsLayer::Archive(tre) => Layer::Archive {
+                    archive: OnceLock::from(tre),
+                    path: None,
+                },
+                VfsLayer::Directory(root) => Layer::Directory(root),
+            })
+            .collect();
+        Ok(TreVfs {
+            layers,
+            files,
+            open: None,
+            cache: None,
+        })
+    }
+
+    /// Keep up to `capacity` bytes of decompressed archive entries in memory, across every layer
+    ///
+    /// Entries are dropped least recently used first, zero disables the cache. Loose files are
+    /// always read from disk.
+    pub fn with_cache_size(mut self, capacity: u64) -> TreVfs<R> {
+        self.cache = (capacity > 0).then(|| Mutex::new(EntryCache::new(capacity)));
+        self
+    }
+
+    /// How the cache has fared so far, for tuning its size
+    ///
+    /// Returns `None` when there's no cache.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.lock_cache().map(|cache| {
+            let (hits, misses) = cache.lookups();
+            CacheStats {
+                hits,
+                misses,
+                size: cache.size(),
+                capacity: cache.capacity(),
+            }
+        })
+    }
+
+    /// The number of layers
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// The archive of a layer, opening it first if it hasn't been yet
+    ///
+    /// Returns `None` when the layer is a directory.
+    pub fn archive(&self, layer: usize) -> Result<Option<&TreArchive<R>>> {
+        let Layer::Archive { archive, path } = &self.layers[layer] else {
+            return Ok(None);
+        };
+        if let Some(tre) = archive.get() {
+            return Ok(Some(tre));
+        }
+
+        let (Some(path), Some(open)) = (path, self.open) else {
+            unreachable!("archives are either open or have a path to open them by");
+        };
+        let tre = open(path)?;
+        Ok(Some(archive.get_or_init(|| tre)))
+    }
+
+    /// The number of distinct files across every layer
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no layer has any files
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The name of every file across every layer, in sorted order
+    pub fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(|name| &**name)
+    }
+
+    /// Whether any layer has a file with this name
+    pub fn exists(&self, name: &str) -> bool {
+        self.files.contains_key(name)
+    }
+
+    /// Find which layer provides a file
+    pub fn locate(&self, name: &str) -> Option<VfsLocation> {
+        self.files.get(name).copied()
+    }
+
+    /// Open a file from the highest priority layer which has it
+    pub fn by_name(&self, name: &str) -> Result<VfsFile<'_, R>> {
+        match self.find(name)? {
+            (Source::Archive(tre), Some(index)) => Ok(VfsFile::Archive(tre.by_index(index)?)),
+            (Source::Directory(root), _) => Ok(VfsFile::Loose(File::open(root.join(name))?)),
+            (Source::Archive(tre), None) => Ok(VfsFile::Archive(tre.by_name(name)?)),
+        }
+    }
+
+    /// Read the whole contents of a file from the highest priority layer which has it
+    ///
+    /// Once [`TreVfs::with_cache_size`] is set, recently read archive entries are served from
+    /// memory.
+    pub fn contents_by_name(&self, name: &str) -> Result<Arc<[u8]>> {
+        if let Some(data) = self.lock_cache().and_then(|mut cache| cache.get(name)) {
+            return Ok(data);
+        }
+
+        let data = match self.find(name)? {
+            (Source::Directory(root), _) => return Ok(std::fs::read(root.join(name))?.into()),
+            (Source::Archive(tre), Some(index)) => tre.contents_by_index(index)?,
+            (Source::Archive(tre), None) => tre.contents_by_name(name)?,
+        };
+        if let Some(mut cache) = self.lock_cache() {
+            cache.insert(name.into(), data.clone());
+        }
+        Ok(data)
+    }
+
+    /// Open a file from the highest priority layer which has it, as a handle which can be seeked
+    /// and doesn't borrow the VFS
+    pub fn seekable_by_name(&self, name: &str) -> Result<VfsHandle> {
+        match self.find(name)? {
+            (Source::Directory(root), _) => Ok(VfsHandle::Loose(File::open(root.join(name))?)),
+            (Source::Archive(_), _) => Ok(VfsHandle::Archive(Cursor::new(
+                self.contents_by_name(name)?,
+            ))),
+        }
+    }
+
+    /// List what's directly within a directory, across every layer
+    ///
+    /// Directories only exist as the prefixes of file names, so any directory with no files
+    /// beneath it lists as empty. `""` lists the top level.
+    pub fn read_dir(&self, dir: &str) -> Vec<VfsDirEntry> {
+        let dir = dir.trim_end_matches('/');
+        let prefix = match dir.is_empty() {
+            true => String::new(),
+            false => format!("{}/", dir),
+        };
+
+        let mut entries = Vec::new();
+        for (name, location) in self
+            .files
+            .range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+        {
+            let Some(rest) = name.strip_prefix(prefix.as_str()) else {
+                break;
+            };
+
+            let entry = match rest.split_once('/') {
+                Some((child, _)) => VfsDirEntry::Directory {
+                    name: child.to_owned(),
+                },
+                None => VfsDirEntry::File {
+                    name: rest.to_owned(),
+                    location: *location,
+                },
+            };
+            // Files beneath the same directory sort next to each other
+            if entries.last() != Some(&entry) {
+                entries.push(entry);
+            }
+        }
+        entries
+    }
+
+    /// Every file which more than one layer provides, with the copy which wins and those it
+    /// overrides, in name order
+    ///
+    /// Every archive is opened and every directory walked again, so loose files added since the
+    /// VFS was built are included.
+    #[instrument(skip_all, err)]
+    pub fn shadowed(&self) -> Result<Vec<ShadowedFile>> {
+        let mut copies = BTreeMap::<Box<str>, Vec<VfsCopy>>::new();
+        for (layer, source) in self.layers.iter().enumerate() {
+            match source {
+                Layer::Archive { .. } => {
+                    let tre = self
+                        .archive(layer)?
+                        .expect("archive layers have an archive");
+                    for (index, (name, record)) in tre.file_names().zip(tre.records()).enumerate() {
+                        copies.entry(name.into()).or_default().push(VfsCopy {
+                            location: VfsLocation {
+                                layer,
+                                index: Some(index),
+                            },
+                            size: record.data_uncompressed as u64,
+                            crc32: Some(record.checksum),
+                        });
+                    }
+                }
+                Layer::Directory(root) => {
+                    for name in loose_files(root)? {
+                        let size = std::fs::metadata(root.join(&*name))?.len();
+                        copies.entry(name).or_default().push(VfsCopy {
+                            location: VfsLocation { layer, index: None },
+                            size,
+                            crc32: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(copies
+            .into_iter()
+            .filter(|(_, copies)| copies.len() > 1)
+            .map(|(name, mut copies)| {
+                let winner = copies.pop().expect("shadowed files have several copies");
+                copies.reverse();
+                ShadowedFile {
+                    name: name.into(),
+                    winner,
+                    losers: copies,
+                }
+            })
+            .collect())
+    }
+
+    fn find(&self, name: &str) -> Result<(Source<'_, R>, Option<usize>)> {
+        let location = self
+            .locate(name)
+            .ok_or_else(|| Error::FileNotFound(FileNotFoundError::Name(name.to_owned())))?;
+        let source = match &self.layers[location.layer] {
+            Layer::Directory(root) => Source::Directory(root),
+            Layer::Archive { .. } => Source::Archive(
+                self.archive(location.layer)?
+                    .expect("archive layers have an archive"),
+            ),
+        };
+        Ok((source, location.index))
+    }
+
+    fn lock_cache(&self) -> Option<MutexGuard<'_, EntryCache<Box<str>>>> {
+        // A panic while holding the lock can't leave the cache inconsistent
+        self.cache
+            .as_ref()
+            .map(|cache| cache.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// The winning archive entry for every file found in an archive, ignoring directories
+    fn archived_files(&self) -> Vec<(&str, VfsLocation)> {
+        let mut files = BTreeMap::new();
+        for (layer, source) in self.layers.iter().enumerate() {
+            let Layer::Archive { archive, .. } = source else {
+                continue;
+            };
+            let tre = archive.get().expect("archives are opened before caching");
+            for (index, name) in tre.file_names().enumerate() {
+                let index = Some(index);
+                files.insert(name, VfsLocation { layer, index });
+            }
+        }
+        files.into_iter().collect()
+    }
+}
+
+/// The layer a file was found in, once its archive has been opened
+enum Source<'a, R> {
+    Archive(&'a TreArchive<R>),
+    Directory(&'a Path),
+}
+
+/// What a cached index remembers about a layer to tell whether it has changed
+#[derive(Debug, PartialEq, Eq)]
+struct LayerKey {
+    path: PathBuf,
+    directory: bool,
+    size: u64,
+    modified: u64,
+}
+
+impl LayerKey {
+    fn new(path: &Path) -> io::Result<LayerKey> {
+        let path = path.canonicalize()?;
+        let metadata = path.metadata()?;
+        if metadata.is_dir() {
+            return Ok(LayerKey {
+                path,
+                directory: true,
+                size: 0,
+                modified: 0,
+            });
+        }
+
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos() as u64);
+        Ok(LayerKey {
+            path,
+            directory: false,
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+fn open_archive(path: &Path) -> Result<TreArchive<File>> {
+    TreArchive::new(File::open(path)?)
+}
+
+/// Read the cached index at `path`, or `None` if there isn't one or it was built from different
+/// layers
+fn read_index(path: &Path, keys: &[LayerKey]) -> io::Result<Option<CachedFiles>> {
+    let mut reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != INDEX_MAGIC {
+        return Ok(None);
+    }
+
+    let layers = reader.read_u32::<LE>()? as usize;
+    let mut cached = Vec::with_capacity(layers);
+    for _ in 0..layers {
+        cached.push(LayerKey {
+            path: read_string(&mut reader)?.into(),
+            directory: reader.read_u8()? != 0,
+            size: reader.read_u64::<LE>()?,
+            modified: reader.read_u64::<LE>()?,
+        });
+    }
+    if cached != keys {
+        return Ok(None);
+    }
+
+    let count = reader.read_u32::<LE>()? as usize;
+    let mut files = Vec::with_capacity(count);
+    for _ in 0..count {
+        let name = read_string(&mut reader)?.into_boxed_str();
+        let layer = reader.read_u32::<LE>()? as usize;
+        let index = match reader.read_u32::<LE>()? {
+            NO_INDEX => None,
+            index => Some(index as usize),
+        };
+        if keys.get(layer).map_or(true, |key| key.directory) || index.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} is cached in layer {} which isn't an archive",
+                    name, layer
+                ),
+            ));
+        }
+        files.push((name, VfsLocation { layer, index }));
+    }
+
+    Ok(Some(files))
+}
+
+/// Write the index of the archives in `keys` to `path`, replacing any index already there
+fn write_index(path: &Path, keys: &[LayerKey], files: &[(&str, VfsLocation)]) -> io::Result<()> {
+    // Written alongside and renamed into place, so a reader never sees half an index
+    let partial = path.with_extension("partial");
+    let mut writer = BufWriter::new(File::create(&partial)?);
+
+    writer.write_all(INDEX_MAGIC)?;
+    writer.write_u32::<LE>(keys.len() as u32)?;
+    for key in keys {
+        let path = key.path.to_str().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "layer path isn't valid UTF-8")
+        })?;
+        write_string(&mut writer, path)?;
+        writer.write_u8(key.directory as u8)?;
+        writer.write_u64::<LE>(key.size)?;
+        writer.write_u64::<LE>(key.modified)?;
+    }
+
+    writer.write_u32::<LE>(files.len() as u32)?;
+    for (name, location) in files {
+        write_string(&mut writer, name)?;
+        writer.write_u32::<LE>(location.layer as u32)?;
+        writer.write_u32::<LE>(location.index.map_or(NO_INDEX, |index| index as u32))?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    std::fs::rename(&partial, path)
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let mut bytes = vec![0; reader.read_u32::<LE>()? as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_string(writer: &mut impl Write, string: &str) -> io::Result<()> {
+    writer.write_u32::<LE>(string.len() as u32)?;
+    writer.write_all(string.as_bytes())
+}
+
+/// The name of every file below `root`, with `/` separators
+fn loose_files(root: &Path) -> Result<Vec<Box<str>>> {
+    let mut names = Vec::new();
+    for entry in WalkDir::new(root).min_depth(1) {
+        let entry = entry.map_err(io::Error::from)?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+
+        let relative = entry
+            .path()
+            .strip_prefix(root)
+            .expect("walked entries are beneath the directory");
+        let Some(components) = relative
+            .components()
+            .map(|component| component.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        names.push(components.join("/").into());
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+    use crate::{
+        compression::CompressionMethod,
+        error::{Error, Result},
+        read::TreArchive,
+        toc::{TocWriter, TocWriterOptions},
+        vfs::{CacheStats, Layer, TreVfs, VfsCopy, VfsDirEntry, VfsLayer, VfsLocation},
+        write::{TreWriter, TreWriterOptions},
+    };
+
+    fn archive(entries: &[(&str, &str)]) -> Result<TreArchive<Cursor<Vec<u8>>>> {
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        for (name, contents) in entries {
+            tre.start_file(name, CompressionMethod::Zlib)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+        TreArchive::new(tre.finish()?)
+    }
+
+    #[test]
+    fn layered_lookup() -> Result<()> {
+        let vfs = TreVfs::new(vec![
+            archive(&[("a.txt", "base"), ("b.txt", "only in base")])?,
+            archive(&[("a.txt", "patched"), ("c.txt", "only in patch")])?,
+        ]);
+
+        assert_eq!(vfs.len(), 3);
+        assert_eq!(
+            vfs.file_names().collect::<Vec<_>>(),
+            ["a.txt", "b.txt", "c.txt"]
+        );
+        assert!(vfs.exists("b.txt"));
+        assert!(!vfs.exists("d.txt"));
+        assert_eq!(
+            vfs.locate("a.txt"),
+            Some(VfsLocation {
+                layer: 1,
+                index: Some(0)
+            })
+        );
+
+        let mut contents = String::new();
+        vfs.by_name("a.txt")?.read_to_string(&mut contents)?;
+        assert_eq!(contents, "patched");
+        assert_eq!(&*vfs.contents_by_name("b.txt")?, b"only in base");
+
+        assert!(matches!(vfs.by_name("d.txt"), Err(Error::FileNotFound(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn loose_directory_overlay() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("swg_tre_vfs_loose_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested"))?;
+        std::fs::write(dir.join("a.txt"), "loose")?;
+        std::fs::write(dir.join("nested/d.txt"), "nested")?;
+
+        let vfs = TreVfs::with_layers(vec![
+            VfsLayer::Archive(archive(&[("a.txt", "base"), ("b.txt", "only in base")])?),
+            VfsLayer::Directory(dir.clone()),
+        ])?;
+
+        assert_eq!(
+            vfs.file_names().collect::<Vec<_>>(),
+            ["a.txt", "b.txt", "nested/d.txt"]
+        );
+        assert_eq!(
+            vfs.locate("a.txt"),
+            Some(VfsLocation {
+                layer: 1,
+                index: None
+            })
+        );
+
+        let mut contents = String::new();
+        vfs.by_name("a.txt")?.read_to_string(&mut contents)?;
+        assert_eq!(contents, "loose");
+        assert_eq!(&*vfs.contents_by_name("nested/d.txt")?, b"nested");
+        assert_eq!(&*vfs.contents_by_name("b.txt")?, b"only in base");
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn from_client_dir() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("swg_tre_vfs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        for (name, contents) in [("bottom.tre", "base"), ("patch.tre", "patched")] {
+            let tre = archive(&[("a.txt", contents)])?;
+            std::fs::write(dir.join(name), tre.into_inner().into_inner())?;
+        }
+        std::fs::write(
+            dir.join("client.cfg"),
+            "[SharedFile]\nsearchTree_00_9=patch.tre\nsearchTree_00_1=bottom.tre\n\
+             searchPath_00_5=loose\n",
+        )?;
+        std::fs::create_dir_all(dir.join("loose"))?;
+        std::fs::write(dir.join("loose/b.txt"), "loose")?;
+
+        let vfs = TreVfs::from_client_dir(&dir)?;
+        assert_eq!(&*vfs.contents_by_name("b.txt")?, b"loose");
+        assert_eq!(vfs.layer_count(), 3);
+        assert_eq!(&*vfs.contents_by_name("a.txt")?, b"patched");
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn cached_index() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("swg_tre_vfs_cache_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("loose"))?;
+        let write = |name: &str, contents: &str| -> Result<()> {
+            let tre = archive(&[("a.txt", contents), (name, "")])?;
+            Ok(std::fs::write(
+                dir.join(name),
+                tre.into_inner().into_inner(),
+            )?)
+        };
+        write("bottom.tre", "base")?;
+        write("patch.tre", "patched")?;
+        std::fs::write(dir.join("loose/b.txt"), "loose")?;
+
+        let paths = [
+            dir.join("bottom.tre"),
+            dir.join("loose"),
+            dir.join("patch.tre"),
+        ];
+        let cache = dir.join("index.cache");
+        let built = TreVfs::open_cached(&paths, &cache)?;
+        assert!(cache.exists());
+
+        let cached = TreVfs::open_cached(&paths, &cache)?;
+        assert!(
+            matches!(&cached.layers[0], Layer::Archive { archive, .. } if archive.get().is_none())
+        );
+        assert_eq!(
+            cached.file_names().collect::<Vec<_>>(),
+            built.file_names().collect::<Vec<_>>()
+        );
+        assert_eq!(cached.locate("a.txt"), built.locate("a.txt"));
+        assert_eq!(&*cached.contents_by_name("a.txt")?, b"patched");
+        assert_eq!(&*cached.contents_by_name("b.txt")?, b"loose");
+        assert!(
+            matches!(&cached.layers[0], Layer::Archive { archive, .. } if archive.get().is_none())
+        );
+
+        write("patch.tre", "changed in a longer patch")?;
+        let rebuilt = TreVfs::open_cached(&paths, &cache)?;
+        assert!(
+            matches!(&rebuilt.layers[0], Layer::Archive { archive, .. } if archive.get().is_some())
+        );
+        assert_eq!(
+            &*rebuilt.contents_by_name("a.txt")?,
+            b"changed in a longer patch"
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn from_toc() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("swg_tre_vfs_toc_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+
+        let mut toc = TocWriter::new(TocWriterOptions::builder().build());
+        for (name, contents) in [("bottom.tre", "base"), ("patch.tre", "patched")] {
+            let tre = archive(&[("a.txt", contents), (name, "")])?;
+            toc.add_archive(name, &tre)?;
+            std::fs::write(dir.join(name), tre.into_inner().into_inner())?;
+        }
+        toc.finish(std::fs::File::create(dir.join("client.toc"))?)?;
+
+        let vfs = TreVfs::from_toc(dir.join("client.toc"))?;
+        assert_eq!(vfs.layer_count(), 2);
+        assert_eq!(
+            vfs.file_names().collect::<Vec<_>>(),
+            ["a.txt", "bottom.tre", "patch.tre"]
+        );
+        assert!(vfs.layers.iter().all(
+            |layer| matches!(layer, Layer::Archive { archive, .. } if archive.get().is_none())
+        ));
+
+        assert_eq!(&*vfs.contents_by_name("a.txt")?, b"patched");
+        assert!(
+            matches!(&vfs.layers[0], Layer::Archive { archive, .. } if archive.get().is_none())
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn seekable_handles() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("swg_tre_vfs_seek_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("b.txt"), "loose file")?;
+
+        let vfs = TreVfs::with_layers(vec![
+            VfsLayer::Archive(archive(&[("a.txt", "archived file")])?),
+            VfsLayer::Directory(dir.clone()),
+        ])?;
+
+        for (name, first) in [("a.txt", b"a"), ("b.txt", b"l")] {
+            let mut handle = vfs.seekable_by_name(name)?;
+            handle.seek(SeekFrom::End(-4))?;
+            let mut contents = String::new();
+            handle.read_to_string(&mut contents)?;
+            assert_eq!(contents, "file");
+
+            handle.rewind()?;
+            let mut start = [0; 1];
+            handle.read_exact(&mut start)?;
+            assert_eq!(&start, first);
+        }
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn read_dir() -> Result<()> {
+        let vfs = TreVfs::new(vec![
+            archive(&[
+                ("misc/a.txt", ""),
+                ("misc/sub/b.txt", ""),
+                ("readme.txt", ""),
+            ])?,
+            archive(&[
+                ("misc/sub/c.txt", ""),
+                ("misc-notes.txt", ""),
+                ("misc/z.txt", ""),
+            ])?,
+        ]);
+
+        let names = |dir| {
+            vfs.read_dir(dir)
+                .iter()
+                .map(|entry| match entry {
+                    VfsDirEntry::File { name, .. } => name.clone(),
+                    VfsDirEntry::Directory { name } => format!("{}/", name),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(""), ["misc-notes.txt", "misc/", "readme.txt"]);
+        assert_eq!(names("misc"), ["a.txt", "sub/", "z.txt"]);
+        assert_eq!(names("misc/sub/"), ["b.txt", "c.txt"]);
+        assert!(names("missing").is_empty());
+
+        assert_eq!(
+            vfs.read_dir("misc/sub")[1],
+            VfsDirEntry::File {
+                name: "c.txt".into(),
+                location: VfsLocation {
+                    layer: 1,
+                    index: Some(0)
+                }
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn shadowed() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("swg_tre_vfs_shadow_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("a.txt"), "loose")?;
+
+        let bottom = archive(&[("a.txt", "base"), ("b.txt", "only in base")])?;
+        let crc = bottom.by_name("a.txt")?.crc32();
+        let vfs = TreVfs::with_layers(vec![
+            VfsLayer::Archive(bottom),
+            VfsLayer::Archive(archive(&[("a.txt", "patched"), ("c.txt", "")])?),
+            VfsLayer::Directory(dir.clone()),
+        ])?;
+
+        let shadowed = vfs.shadowed()?;
+        assert_eq!(shadowed.len(), 1);
+        assert_eq!(shadowed[0].name, "a.txt");
+        assert_eq!(
+            shadowed[0].winner,
+            VfsCopy {
+                location: VfsLocation {
+                    layer: 2,
+                    index: None
+                },
+                size: 5,
+                crc32: None
+            }
+        );
+        assert_eq!(
+            shadowed[0]
+                .losers
+                .iter()
+                .map(|copy| (copy.location.layer, copy.size))
+                .collect::<Vec<_>>(),
+            [(1, 7), (0, 4)]
+        );
+        assert_eq!(shadowed[0].losers[1].crc32, Some(crc));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn entry_cache() -> Result<()> {
+        let vfs = TreVfs::new(vec![
+            archive(&[("a.txt", "base"), ("b.txt", "only in base")])?,
+            archive(&[("a.txt", "patched")])?,
+        ]);
+        assert_eq!(vfs.cache_stats(), None);
+
+        let vfs = vfs.with_cache_size(10);
+        assert_eq!(&*vfs.contents_by_name("a.txt")?, b"patched");
+        assert_eq!(&*vfs.contents_by_name("a.txt")?, b"patched");
+        // Larger than the whole cache, so it's read every time
+        assert_eq!(&*vfs.contents_by_name("b.txt")?, b"only in base");
+        assert_eq!(&*vfs.contents_by_name("b.txt")?, b"only in base");
+
+        assert_eq!(
+            vfs.cache_stats(),
+            Some(CacheStats {
+                hits: 1,
+                misses: 3,
+                size: 7,
+                capacity: 10
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+//! Keeping a [`TreVfs`] in step with the files it's built from
+//!
+//! A [`WatchedVfs`] watches each of its archives and directories, and rebuilds the VFS whenever
+//! one of them changes on disk, so tools stay in sync while a mod is being worked on. Callers can
+//! [`subscribe`](WatchedVfs::subscribe) to hear about each rebuild.
+//!
+//! ```no_run
+//! # fn doit() -> swg_tre::error::Result<()>
+//! # {
+//! use swg_tre::watch::VfsEvent;
+//!
+//! use swg_tre::vfs::TreVfs;
+//!
+//! let watched = TreVfs::watch(["bottom.tre", "workspace"])?;
+//! let events = watched.subscribe();
+//! for event in events {
+//!     if let VfsEvent::Reloaded { .. } = event {
+//!         println!("{} files", watched.vfs().len());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, RwLock, RwLockReadGuard,
+    },
+};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::{instrument, warn};
+
+use crate::{
+    error::{Error, Result},
+    vfs::TreVfs,
+};
+
+/// Something which happened to a watched VFS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VfsEvent {
+    /// Files the VFS is built from changed, and it was rebuilt to match
+    Reloaded {
+        /// The files which changed
+        paths: Vec<PathBuf>,
+    },
+
+    /// Files the VFS is built from changed, but it couldn't be rebuilt and is left as it was
+    ///
+    /// This is expected while an archive is still being written, the VFS is rebuilt again when
+    /// the write finishes.
+    Failed {
+        /// The files which changed
+        paths: Vec<PathBuf>,
+        /// Why the VFS couldn't be rebuilt
+        error: String,
+    },
+}
+
+/// A [`TreVfs`] opened from disk, which is rebuilt whenever its archives or directories change
+pub struct WatchedVfs {
+    state: Arc<State>,
+    _watcher: RecommendedWatcher,
+}
+
+struct State {
+    layers: Vec<PathBuf>,
+    vfs: RwLock<TreVfs<File>>,
+    subscribers: Mutex<Vec<Sender<VfsEvent>>>,
+}
+
+impl WatchedVfs {
+    /// Open archives and directories like [`TreVfs::open`], and start watching them
+    #[instrument(skip_all, err)]
+    pub fn new<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<WatchedVfs> {
+        let layers = paths
+            .into_iter()
+            .map(|path| path.as_ref().canonicalize())
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        let state = Arc::new(State {
+            vfs: RwLock::new(TreVfs::open(&layers)?),
+            layers,
+            subscribers: Mutex::new(Vec::new()),
+        });
+
+        let handler = state.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) => handler.changed(event.paths),
+                Err(e) => warn!("unable to watch layers: {}", e),
+            })
+            .map_err(watch_error)?;
+
+        for layer in &state.layers {
+            // Archives are usually replaced rather than written in place, which a watch on the
+            // file itself would miss, so their directory is watched instead
+            let (path, mode) = match layer.is_dir() {
+                true => (layer.as_path(), RecursiveMode::Recursive),
+                false => (layer.parent().unwrap_or(layer), RecursiveMode::NonRecursive),
+            };
+            watcher.watch(path, mode).map_err(watch_error)?;
+        }
+
+        Ok(WatchedVfs {
+            state,
+            _watcher: watcher,
+        })
+    }
+
+    /// The VFS as of the last rebuild
+    ///
+    /// Rebuilds wait for the guard to be dropped, so it shouldn't be held onto.
+    pub fn vfs(&self) -> RwLockReadGuard<'_, TreVfs<File>> {
+        self.state.vfs.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Receive an event each time the VFS is rebuilt, or fails to be
+    pub fn subscribe(&self) -> Receiver<VfsEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.state
+            .subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(sender);
+        receiver
+    }
+}
+
+impl TreVfs<File> {
+    /// Open archives and directories like [`TreVfs::open`], rebuilding the VFS whenever one of
+    /// them changes, see [`WatchedVfs`]
+    pub fn watch<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<WatchedVfs> {
+        WatchedVfs::new(paths)
+    }
+}
+
+impl State {
+    fn changed(&self, paths: Vec<PathBuf>) {
+        let paths = paths
+            .into_iter()
+            .filter(|path| self.layers.iter().any(|layer| path.starts_with(layer)))
+            .collect::<Vec<_>>();
+        if paths.is_empty() {
+            return;
+        }
+
+        let event = match TreVfs::open(&self.layers) {
+            Ok(vfs) => {
+                *self.vfs.write().unwrap_or_else(|e| e.into_inner()) = vfs;
+                VfsEvent::Reloaded { paths }
+            }
+            Err(e) => VfsEvent::Failed {
+                paths,
+                error: e.to_string(),
+            },
+        };
+
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+fn watch_error(error: notify::Error) -> Error {
+    match error.kind {
+        notify::ErrorKind::Io(e) => e.into(),
+        _ => Error::CustomError(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Cursor, Write},
+        time::Duration,
+    };
+
+    use crate::{
+        compression::CompressionMethod,
+        error::Result,
+        vfs::TreVfs,
+        watch::VfsEvent,
+        write::{TreWriter, TreWriterOptions},
+    };
+
+    #[test]
+    fn reload_on_change() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("swg_tre_watch_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("loose"))?;
+
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        tre.start_file("a.txt", CompressionMethod::Zlib)?;
+        tre.write_all(b"archived")?;
+        std::fs::write(dir.join("base.tre"), tre.finish()?.into_inner())?;
+
+        let watched = TreVfs::watch([dir.join("base.tre"), dir.join("loose")])?;
+        let events = watched.subscribe();
+        assert!(!watched.vfs().exists("b.txt"));
+
+        std::fs::write(dir.join("loose/b.txt"), "loose")?;
+        let event = events
+            .recv_timeout(Duration::from_secs(10))
+            .expect("the change should be seen");
+        assert!(matches!(event, VfsEvent::Reloaded { .. }));
+        assert_eq!(&*watched.vfs().contents_by_name("b.txt")?, b"loose");
+        assert_eq!(&*watched.vfs().contents_by_name("a.txt")?, b"archived");
+
+        drop(watched);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
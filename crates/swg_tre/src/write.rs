@@ -2,21 +2,34 @@
 //!
 
 use binrw::BinWrite;
-use bon::{builder, Builder};
+use bon::Builder;
 use byteorder::WriteBytesExt;
+use flate2::Compression;
 use md5::{Digest, Md5};
+use std::collections::HashMap;
 use std::fmt::Debug;
-use std::io::{self, Cursor, Seek, Write};
-use std::mem;
-use tracing::{instrument, Level};
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{instrument, Level, Span};
+use walkdir::WalkDir;
 
 use super::compression::CompressionMethod;
-use crate::compression::TreBlockWriter;
-use crate::error::Result;
+use crate::compression::{compress_if_smaller, TreBlockWriter};
+use crate::error::{Error, InvalidNameError, Result};
+#[cfg(feature = "signing")]
+use crate::sign::Signer;
+use crate::transform::{BlockKind, BlockTransform};
 use crate::types::{TreHeader, TreRecord};
 
+/// The checksum algorithm used for record checksums, with a larger table as data checksums cover
+/// whole entries
+pub(crate) static CHECKSUM: crc::Crc<u32, crc::Table<16>> =
+    crc::Crc::<u32, crc::Table<16>>::new(&crc::CRC_32_BZIP2);
+
 /// Options for how the TRE file should be written
-#[derive(Debug, Clone, Copy, Builder)]
+#[derive(Debug, Clone, Builder)]
 pub struct TreWriterOptions {
     /// The compression method to use for the record block
     #[builder(default)]
@@ -25,12 +38,224 @@ pub struct TreWriterOptions {
     /// The compression method to use for the name block
     #[builder(default)]
     pub name_compression: CompressionMethod,
+
+    /// The Zlib compression level from 0 to 9, trading speed for smaller output
+    #[builder(default = 6)]
+    pub compression_level: u32,
+
+    /// Whether to append the block of MD5 hashes for each entry's stored data
+    #[builder(default = true)]
+    pub hash_block: bool,
+
+    /// Pad the data of every entry and the start of the record block to a multiple of this many
+    /// bytes
+    ///
+    /// The padding is zeroed and isn't covered by an entry's hash, readers find each entry's data
+    /// through the offset in its record.
+    #[builder(default = 1)]
+    pub alignment: u32,
+
+    /// Which entry names [`TreWriter::start_file`] accepts
+    #[builder(default)]
+    pub name_policy: TreNamePolicy,
+
+    /// What happens when a file is started with a name which has already been written
+    #[builder(default)]
+    pub duplicates: DuplicatePolicy,
+
+    /// The order records are written in
+    #[builder(default)]
+    pub sort_records: SortOrder,
+
+    /// How record checksums are computed for files started with [`TreWriter::start_file`]
+    #[builder(default)]
+    pub checksum: ChecksumPolicy,
+
+    /// A transform to apply to every block once it is compressed
+    ///
+    /// See [`crate::transform`] for archives which need one.
+    pub transform: Option<Arc<dyn BlockTransform>>,
+
+    /// A key to sign the finished archive with, appending the signature after it
+    ///
+    /// See [`crate::sign`] for how the signature is stored.
+    #[cfg(feature = "signing")]
+    pub signing_key: Option<ed25519_dalek::SigningKey>,
+}
+
+/// How the checksum stored in a record is computed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ChecksumPolicy {
+    /// CRC-32/BZIP2 of the entry name, which is what the client looks entries up by
+    #[default]
+    Name,
+
+    /// CRC-32/BZIP2 of the entry's uncompressed data
+    Data,
+
+    /// A value supplied by the caller, such as the checksum of an entry being copied
+    Value(u32),
+}
+
+/// How [`TreWriter::start_file`] handles a name which has already been written
+///
+/// The client resolves names shared by several records unpredictably, so they are rejected by
+/// default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Reject the name with [`InvalidNameError::Duplicate`]
+    #[default]
+    Error,
+
+    /// Replace the earlier entry, keeping its position among the records
+    Replace,
+
+    /// Write another record with the same name
+    Allow,
 }
 
-#[derive(Debug, Clone, Default)]
-struct TreStats {
-    info_offset: u32,
-    name_offset: u32,
+/// The order of the records in a written archive
+///
+/// The data of each entry is laid out in the same order as its record.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SortOrder {
+    /// Keep records in the order their files were started
+    #[default]
+    Insertion,
+
+    /// Sort records by the bytes of their names
+    Name,
+
+    /// Sort records by their checksum, the order the client searches them in
+    Crc,
+}
+
+/// How backslashes in entry names are handled
+///
+/// The client only looks entries up by names with forward slashes, so backslashes from Windows
+/// paths are replaced by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SeparatorPolicy {
+    /// Reject names containing backslashes
+    Reject,
+
+    /// Replace backslashes with forward slashes
+    #[default]
+    Normalize,
+
+    /// Write names as they are, without checking that they are normalized
+    Allow,
+}
+
+/// Rules applied to entry names before they are written
+///
+/// Names are always rejected when they are empty or contain a NUL byte, as neither can be
+/// represented in the name block. Unless separators are allowed as they are, names must also be
+/// relative paths without `.` or `..` components or repeated slashes.
+#[derive(Debug, Clone, Copy, Builder)]
+pub struct TreNamePolicy {
+    /// The maximum length of a name in bytes
+    #[builder(default = 255)]
+    pub max_length: usize,
+
+    /// How backslashes in names are handled
+    #[builder(default)]
+    pub separators: SeparatorPolicy,
+
+    /// Whether names are lowercased, so differently cased source files produce the same names
+    #[builder(default)]
+    pub lowercase: bool,
+}
+
+impl Default for TreNamePolicy {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl TreNamePolicy {
+    /// Check a name against this policy, returning the name that should be written
+    pub fn validate(&self, name: &str) -> std::result::Result<String, InvalidNameError> {
+        if name.is_empty() {
+            return Err(InvalidNameError::Empty);
+        }
+
+        if name.contains('\0') {
+            return Err(InvalidNameError::Nul(name.to_owned()));
+        }
+
+        let name = match self.separators {
+            SeparatorPolicy::Reject if name.contains('\\') => {
+                return Err(InvalidNameError::Separator(name.to_owned()))
+            }
+            SeparatorPolicy::Normalize => name.replace('\\', "/"),
+            _ => name.to_owned(),
+        };
+        let name = if self.lowercase {
+            name.to_lowercase()
+        } else {
+            name
+        };
+
+        if self.separators != SeparatorPolicy::Allow
+            && name
+                .split('/')
+                .any(|component| matches!(component, "" | "." | ".."))
+        {
+            return Err(InvalidNameError::NotNormalized(name));
+        }
+
+        if name.len() > self.max_length {
+            return Err(InvalidNameError::TooLong {
+                length: name.len(),
+                limit: self.max_length,
+                name,
+            });
+        }
+
+        Ok(name)
+    }
+}
+
+/// Options for adding a directory with [`TreWriter::add_dir_recursive`]
+#[derive(Clone, Copy, Builder)]
+pub struct TreDirOptions<'a> {
+    /// The compression method to use for every entry
+    #[builder(default)]
+    pub compression: CompressionMethod,
+
+    /// Extensions of files which are stored as they are, for formats which are already compressed
+    ///
+    /// Extensions are compared without the leading dot and ignoring case.
+    #[builder(default)]
+    pub store_extensions: &'a [&'a str],
+
+    /// Decides which paths are added, given each path relative to the directory
+    ///
+    /// Directories are offered too, returning `false` for one skips everything beneath it.
+    pub filter: Option<&'a dyn Fn(&Path) -> bool>,
+}
+
+impl Default for TreDirOptions<'_> {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Convert a size or offset to the 32 bits the format stores it in
+fn offset(value: u64) -> Result<u32> {
+    u32::try_from(value).map_err(|_| Error::TooLarge(value))
+}
+
+/// The size of the chunks [`TreWriter::add_entry_from_reader`] copies in
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A finished entry waiting to be laid out
+struct PendingEntry {
+    name: String,
+    record: TreRecord,
+    data: Vec<u8>,
+    padding: u32,
 }
 
 /// TRE archive generator
@@ -62,12 +287,24 @@ struct TreStats {
 pub struct TreWriter<W: Write + Seek> {
     inner: W,
     writing_to_file: bool,
-    info_block: TreBlockWriter<Cursor<Vec<u8>>>,
-    data_block: TreBlockWriter<Cursor<Vec<u8>>>,
-    name_block: TreBlockWriter<Cursor<Vec<u8>>>,
-    hash_block: TreBlockWriter<Cursor<Vec<u8>>>,
+    entries: Vec<PendingEntry>,
     current_data_block: Option<TreBlockWriter<Cursor<Vec<u8>>>>,
-    stats: TreStats,
+    current_name: String,
+    level: Compression,
+    name_policy: TreNamePolicy,
+    positions: HashMap<String, usize>,
+    current_position: usize,
+    /// The position the current entry's name resolved to before it was started
+    shadowed_position: Option<usize>,
+    duplicates: DuplicatePolicy,
+    sort_records: SortOrder,
+    alignment: u32,
+    checksum: ChecksumPolicy,
+    data_checksum: Option<crc::Digest<'static, u32, crc::Table<16>>>,
+    write_hashes: bool,
+    transform: Option<Arc<dyn BlockTransform>>,
+    #[cfg(feature = "signing")]
+    signing_key: Option<ed25519_dalek::SigningKey>,
     header: TreHeader,
     record: TreRecord,
 }
@@ -82,12 +319,23 @@ impl<W: Write + Seek> TreWriter<W> {
         TreWriter {
             inner,
             writing_to_file: false,
-            info_block: TreBlockWriter::new(Cursor::new(Vec::new()), options.record_compression),
-            data_block: TreBlockWriter::new(Cursor::new(Vec::new()), CompressionMethod::None),
+            entries: Vec::new(),
             current_data_block: None,
-            name_block: TreBlockWriter::new(Cursor::new(Vec::new()), options.name_compression),
-            hash_block: TreBlockWriter::new(Cursor::new(Vec::new()), CompressionMethod::None),
-            stats: TreStats::default(),
+            current_name: String::new(),
+            level: Compression::new(options.compression_level.min(9)),
+            name_policy: options.name_policy,
+            positions: HashMap::new(),
+            current_position: 0,
+            shadowed_position: None,
+            duplicates: options.duplicates,
+            sort_records: options.sort_records,
+            alignment: options.alignment.max(1),
+            checksum: options.checksum,
+            data_checksum: None,
+            write_hashes: options.hash_block,
+            transform: options.transform,
+            #[cfg(feature = "signing")]
+            signing_key: options.signing_key,
             header: TreHeader {
                 record_compression: options.record_compression,
                 name_compression: options.name_compression,
@@ -103,97 +351,361 @@ impl<W: Write + Seek> TreWriter<W> {
     }
 
     /// Start a new file for with the requested compression.
-    #[instrument(skip(self, name), err)]
+    ///
+    /// The name is checked against [`TreWriterOptions::name_policy`] and
+    /// [`TreWriterOptions::duplicates`] first, a rejected name leaves the writer untouched. So does
+    /// [`CompressionMethod::Zstd`] without the `zstd` feature.
     pub fn start_file(
         &mut self,
         name: impl ToString,
         compression: CompressionMethod,
     ) -> Result<()> {
+        self.start_file_with_checksum(name, compression, self.checksum)
+    }
+
+    /// Start a new file, computing its record checksum with `checksum` instead of
+    /// [`TreWriterOptions::checksum`]
+    #[instrument(skip(self, name), err, fields(name = %name.to_string()))]
+    pub fn start_file_with_checksum(
+        &mut self,
+        name: impl ToString,
+        compression: CompressionMethod,
+        checksum: ChecksumPolicy,
+    ) -> Result<()> {
+        let name = self.name_policy.validate(&name.to_string())?;
+
+        let existing = self.positions.get(&name).copied();
+        if existing.is_some() && self.duplicates == DuplicatePolicy::Error {
+            return Err(InvalidNameError::Duplicate(name).into());
+        }
+
+        let block = TreBlockWriter::new(Cursor::new(Vec::new()), compression, self.level)?;
+
         if self.writing_to_file {
             self.finish_file()?;
         }
 
+        self.current_position = match existing {
+            Some(position) if self.duplicates == DuplicatePolicy::Replace => position,
+            _ => {
+                self.header.records += 1;
+                self.entries.len()
+            }
+        };
+        self.shadowed_position = self.positions.insert(name.clone(), self.current_position);
+        self.current_name = name.clone();
+
         assert!(self.current_data_block.is_none());
 
-        let _ = mem::replace(
-            &mut self.current_data_block,
-            Some(TreBlockWriter::new(Cursor::new(Vec::new()), compression)),
-        );
+        self.current_data_block = Some(block);
 
-        self.header.records += 1;
-        {
-            self.name_block.write_all(name.to_string().as_bytes())?;
-            self.name_block.write_u8(0u8)?;
-        }
+        // Offsets are filled in once every entry is known
+        self.record = TreRecord {
+            data_compression: compression,
+            checksum: match checksum {
+                ChecksumPolicy::Name => CHECKSUM.checksum(name.as_bytes()),
+                ChecksumPolicy::Data => 0,
+                ChecksumPolicy::Value(value) => value,
+            },
+            ..Default::default()
+        };
+        self.data_checksum = (checksum == ChecksumPolicy::Data).then(|| CHECKSUM.digest());
 
-        // Update Record
-        self.record.data_compression = compression;
-        self.record.checksum =
-            crc::Crc::<u32>::new(&crc::CRC_32_BZIP2).checksum(name.to_string().as_bytes());
+        self.writing_to_file = true;
 
-        self.record.data_offset = 36 + self.data_block.total_in() as u32;
-        self.record.name_offset = self.stats.name_offset;
+        Ok(())
+    }
 
-        self.stats.name_offset = self.name_block.total_in() as u32;
+    /// Add a file from disk as a new entry, returning the number of bytes copied
+    #[instrument(skip(self, name, path), err, fields(path = %path.as_ref().display()))]
+    pub fn add_file_from_path(
+        &mut self,
+        name: impl ToString,
+        path: impl AsRef<Path>,
+        compression: CompressionMethod,
+    ) -> Result<u64> {
+        let file = File::open(path.as_ref())?;
+        let record = self.add_entry_from_reader(name, compression, file, None)?;
 
-        self.writing_to_file = true;
+        Ok(record.data_uncompressed as u64)
+    }
 
-        Ok(())
+    /// Add a new entry holding everything `reader` yields, or at most `limit` bytes of it
+    ///
+    /// The entry is finished once the copy is done and its record is returned, as with
+    /// [`TreWriter::finish_file`]. A failed copy abandons the entry with [`TreWriter::abort_file`].
+    #[instrument(skip(self, name, reader), err, fields(name = %name.to_string()))]
+    pub fn add_entry_from_reader(
+        &mut self,
+        name: impl ToString,
+        compression: CompressionMethod,
+        reader: impl Read,
+        limit: Option<u64>,
+    ) -> Result<TreRecord> {
+        self.start_file(name, compression)?;
+
+        let mut reader = reader.take(limit.unwrap_or(u64::MAX));
+        let mut buf = vec![0; COPY_CHUNK_SIZE];
+        loop {
+            let read = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.abort_file();
+                    return Err(e.into());
+                }
+            };
+            if let Err(e) = self.write_all(&buf[..read]) {
+                self.abort_file();
+                return Err(e.into());
+            }
+        }
+
+        self.finish_file()
     }
 
-    #[instrument(skip(self), err)]
-    fn finish_file(&mut self) -> Result<()> {
-        self.stats.info_offset += 24;
+    /// Add every file beneath a directory, returning the number of entries added
+    ///
+    /// Entries are named after their path relative to `dir` with `/` separators, appended to
+    /// `prefix`. Files are added in file name order so the same directory always produces the
+    /// same archive.
+    #[instrument(skip(self, dir, options), err, fields(dir = %dir.as_ref().display()))]
+    pub fn add_dir_recursive(
+        &mut self,
+        dir: impl AsRef<Path>,
+        prefix: &str,
+        options: TreDirOptions,
+    ) -> Result<usize> {
+        let dir = dir.as_ref();
+        let mut added = 0;
+
+        let entries = WalkDir::new(dir)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| match options.filter {
+                Some(filter) => entry.path().strip_prefix(dir).map_or(true, filter),
+                None => true,
+            });
+
+        for entry in entries {
+            let entry = entry.map_err(io::Error::from)?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+
+            let relative = entry
+                .path()
+                .strip_prefix(dir)
+                .expect("walked entries are beneath the directory");
+            let not_unicode = || InvalidNameError::NotUnicode(relative.display().to_string());
+            let components = relative
+                .components()
+                .map(|component| component.as_os_str().to_str().ok_or_else(not_unicode))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            let name = match prefix.trim_end_matches('/') {
+                "" => components.join("/"),
+                prefix => format!("{}/{}", prefix, components.join("/")),
+            };
+
+            let stored = entry.path().extension().is_some_and(|extension| {
+                options
+                    .store_extensions
+                    .iter()
+                    .any(|stored| extension.eq_ignore_ascii_case(stored))
+            });
+            let compression = match stored {
+                true => CompressionMethod::None,
+                false => options.compression,
+            };
+
+            self.add_file_from_path(name, entry.path(), compression)?;
+            added += 1;
+        }
 
+        Ok(added)
+    }
+
+    /// Finish the current file, returning its record
+    ///
+    /// The offsets in the record are only known once [`TreWriter::finish`] lays out the archive.
+    /// Starting another file or finishing the archive does this implicitly.
+    #[instrument(skip(self), err, fields(name = %self.current_name, size, compressed_size))]
+    pub fn finish_file(&mut self) -> Result<TreRecord> {
+        if !self.writing_to_file {
+            return Err(io::Error::other("No file has been started").into());
+        }
         let current_block = self
             .current_data_block
             .take()
             .expect("current data block should always be valid when finishing a file");
 
         let block_total_in = current_block.total_in();
-        let current_block_data = current_block.finalize()?.into_inner();
+        let mut current_block_data = current_block.finalize()?.into_inner();
 
-        self.record.data_uncompressed = block_total_in as u32;
-        self.record.data_compressed = current_block_data.len() as u32;
+        if self.record.data_compression == CompressionMethod::Auto {
+            (self.record.data_compression, current_block_data) =
+                compress_if_smaller(current_block_data, self.level)?;
+        }
 
-        self.record.write(&mut self.info_block)?;
+        if let Some(digest) = self.data_checksum.take() {
+            self.record.checksum = digest.finalize();
+        }
 
-        self.data_block.write_all(&current_block_data)?;
+        self.record.data_uncompressed = offset(block_total_in)?;
+        self.record.data_compressed = offset(current_block_data.len() as u64)?;
+
+        Span::current()
+            .record("size", self.record.data_uncompressed)
+            .record("compressed_size", self.record.data_compressed);
+
+        let entry = PendingEntry {
+            name: std::mem::take(&mut self.current_name),
+            record: self.record,
+            data: current_block_data,
+            padding: 0,
+        };
+        match self.entries.get_mut(self.current_position) {
+            Some(replaced) => *replaced = entry,
+            None => self.entries.push(entry),
+        }
+        self.writing_to_file = false;
 
-        let mut hasher = Md5::new();
-        hasher.update(current_block_data);
+        Ok(self.record)
+    }
 
-        self.hash_block.write_all(&hasher.finalize())?;
-        self.writing_to_file = false;
+    /// Abandon the current file, leaving the archive as it was before it was started
+    ///
+    /// An entry it was going to replace under [`DuplicatePolicy::Replace`] is kept. Does nothing
+    /// when no file is open.
+    #[instrument(skip(self), fields(name = %self.current_name))]
+    pub fn abort_file(&mut self) {
+        if !self.writing_to_file {
+            return;
+        }
 
-        Ok(())
+        let name = std::mem::take(&mut self.current_name);
+        if self.current_position == self.entries.len() {
+            self.header.records -= 1;
+            match self.shadowed_position {
+                Some(position) => self.positions.insert(name, position),
+                None => self.positions.remove(&name),
+            };
+        }
+
+        self.current_data_block = None;
+        self.data_checksum = None;
+        self.record = TreRecord::default();
+        self.writing_to_file = false;
     }
 
     /// Finish the last file and write all other TRE file structures
     ///
     /// This will return the writer, but one should normally not append any data to the end of the file.
-    #[instrument(skip(self), err)]
+    #[instrument(skip(self), err, fields(records = self.header.records, size))]
     pub fn finish(mut self) -> Result<W> {
         if self.writing_to_file {
             self.finish_file()?;
         }
 
-        let data_block = self.data_block.finalize()?.into_inner();
-        self.header.record_start = 36 + data_block.len() as u32;
+        match self.sort_records {
+            SortOrder::Insertion => {}
+            SortOrder::Name => self.entries.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortOrder::Crc => self.entries.sort_by_key(|entry| entry.record.checksum),
+        }
+
+        let mut info_block = TreBlockWriter::new(
+            Cursor::new(Vec::new()),
+            self.header.record_compression,
+            self.level,
+        )?;
+        let mut name_block = TreBlockWriter::new(
+            Cursor::new(Vec::new()),
+            self.header.name_compression,
+            self.level,
+        )?;
+        let mut hash_block = Vec::new();
+        let mut data_size = 0u64;
+
+        let alignment = self.alignment as u64;
+        let padding = |offset: u64| (alignment - offset % alignment) % alignment;
+
+        for entry in &mut self.entries {
+            entry.padding = padding(36 + data_size) as u32;
+            data_size += entry.padding as u64;
+
+            entry.record.data_offset = offset(36 + data_size)?;
+            entry.record.name_offset = offset(name_block.total_in())?;
+            data_size += entry.data.len() as u64;
+
+            entry.record.write(&mut info_block)?;
+            name_block.write_all(entry.name.as_bytes())?;
+            name_block.write_u8(0u8)?;
+
+            if let Some(transform) = &self.transform {
+                let position = entry.record.data_offset as u64;
+                transform.encode(BlockKind::Entry, position, &mut entry.data);
+            }
+            if self.write_hashes {
+                hash_block.extend_from_slice(&Md5::digest(&entry.data));
+            }
+        }
 
-        let info_block = self.info_block.finalize()?.into_inner();
+        let record_padding = padding(36 + data_size);
+        data_size += record_padding;
+        self.header.record_start = offset(36 + data_size)?;
+
+        let mut info_block = info_block.finalize()?.into_inner();
+        if self.header.record_compression == CompressionMethod::Auto {
+            (self.header.record_compression, info_block) =
+                compress_if_smaller(info_block, self.level)?;
+        }
         self.header.record_compressed = info_block.len() as u32;
 
-        self.header.name_uncompressed = self.name_block.total_in() as u32;
-        let name_block = self.name_block.finalize()?.into_inner();
+        self.header.name_uncompressed = offset(name_block.total_in())?;
+        let mut name_block = name_block.finalize()?.into_inner();
+        if self.header.name_compression == CompressionMethod::Auto {
+            (self.header.name_compression, name_block) =
+                compress_if_smaller(name_block, self.level)?;
+        }
         self.header.name_compressed = name_block.len() as u32;
 
-        self.header.write(&mut self.inner)?;
-        self.inner.write_all(&data_block)?;
-        self.inner.write_all(&info_block)?;
-        self.inner.write_all(&name_block)?;
-        self.inner
-            .write_all(&self.hash_block.finalize()?.into_inner())?;
+        if let Some(transform) = &self.transform {
+            let record_start = self.header.record_start as u64;
+            transform.encode(BlockKind::Records, record_start, &mut info_block);
+            let name_start = record_start + info_block.len() as u64;
+            transform.encode(BlockKind::Names, name_start, &mut name_block);
+        }
+
+        let mut header = Cursor::new(Vec::with_capacity(36));
+        self.header.write(&mut header)?;
+
+        #[cfg(feature = "signing")]
+        let mut out = Signer::new(&mut self.inner, self.signing_key.is_some());
+        #[cfg(not(feature = "signing"))]
+        let out = &mut self.inner;
+
+        out.write_all(header.get_ref())?;
+        for entry in &self.entries {
+            out.write_all(&vec![0; entry.padding as usize])?;
+            out.write_all(&entry.data)?;
+        }
+        out.write_all(&vec![0; record_padding as usize])?;
+        out.write_all(&info_block)?;
+        out.write_all(&name_block)?;
+        out.write_all(&hash_block)?;
+
+        #[cfg(feature = "signing")]
+        if let Some(key) = &self.signing_key {
+            out.finish(key)?;
+        }
+
+        Span::current().record(
+            "size",
+            36 + data_size as usize + info_block.len() + name_block.len() + hash_block.len(),
+        );
 
         Ok(self.inner)
     }
@@ -203,15 +715,19 @@ impl<W: Write + Seek> Write for TreWriter<W> {
     #[instrument(skip_all, err, ret(level = Level::TRACE), fields(size=buf.len()) )]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         if !self.writing_to_file {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "No file has been started",
-            ));
+            return Err(io::Error::other("No file has been started"));
         }
-        self.current_data_block
+        let written = self
+            .current_data_block
             .as_mut()
             .expect("current data block should be initialized by the time we write")
-            .write(buf)
+            .write(buf)?;
+
+        if let Some(digest) = &mut self.data_checksum {
+            digest.update(&buf[..written]);
+        }
+
+        Ok(written)
     }
 
     #[instrument(skip(self), err)]
@@ -225,12 +741,16 @@ mod test {
     use pretty_assertions::assert_str_eq;
     use tracing_test::traced_test;
 
-    use crate::error::Result;
+    use crate::error::{Error, InvalidNameError, Result};
     use crate::{
         compression::CompressionMethod,
-        write::{TreWriter, TreWriterOptions},
+        read::{self, TreArchive, TreArchiveOptions},
+        write::{
+            ChecksumPolicy, DuplicatePolicy, SeparatorPolicy, SortOrder, TreDirOptions,
+            TreNamePolicy, TreWriter, TreWriterOptions,
+        },
     };
-    use std::io::{Cursor, Write};
+    use std::io::{Cursor, Read, Write};
 
     #[traced_test]
     #[test]
@@ -772,4 +1292,403 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn tre_uncompressed_with_data_without_hashes_write() -> Result<()> {
+        let file_data = [
+            0x48, 0x65, 0x6C, 0x6C, 0x6F, 0x20, 0x57, 0x6F, 0x72, 0x6C, 0x64,
+        ];
+
+        #[rustfmt::skip]
+        let expected = [
+            // Header
+            0x45, 0x45, 0x52, 0x54, 0x35, 0x30, 0x30, 0x30, 
+            0x01, 0x00, 0x00, 0x00, 
+            0x2F, 0x00, 0x00, 0x00, 
+            0x00, 0x00, 0x00, 0x00,
+            0x18, 0x00, 0x00, 0x00, 
+            0x00, 0x00, 0x00, 0x00,
+            0x0A, 0x00, 0x00, 0x00, 
+            0x0A, 0x00, 0x00, 0x00,
+            // Data
+            0x48, 0x65, 0x6C, 0x6C, 0x6F, 0x20, 0x57, 0x6F, 0x72, 0x6C, 0x64,
+            // Records
+            0xAA, 0x30, 0x7E, 0x52,
+            0x0B, 0x00, 0x00, 0x00, 
+            0x24, 0x00, 0x00, 0x00, 
+            0x00, 0x00, 0x00, 0x00, 
+            0x0B, 0x00, 0x00, 0x00, 
+            0x00, 0x00, 0x00, 0x00,
+            // Names
+            0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x2E, 0x74, 0x78, 0x74, 0x00,
+        ];
+
+        let mut writer = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .record_compression(CompressionMethod::None)
+                .name_compression(CompressionMethod::None)
+                .hash_block(false)
+                .build(),
+        );
+        writer.start_file("hello.txt", CompressionMethod::None)?;
+        writer.write_all(&file_data)?;
+
+        let result = writer.finish()?;
+        assert_eq!(result.get_ref().len(), expected.len());
+        assert_str_eq!(
+            format!("{:02X?}", *result.get_ref()),
+            format!("{:02X?}", expected)
+        );
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[test]
+    fn tre_invalid_names() -> Result<()> {
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .name_policy(
+                    TreNamePolicy::builder()
+                        .max_length(16)
+                        .separators(SeparatorPolicy::Reject)
+                        .build(),
+                )
+                .build(),
+        );
+
+        for (name, expected) in [
+            ("", "Empty"),
+            ("a\0b", "Nul"),
+            ("a\\b", "Separator"),
+            ("/a", "NotNormalized"),
+            ("a//b", "NotNormalized"),
+            ("a/../b", "NotNormalized"),
+            ("a/b/", "NotNormalized"),
+            ("abcdefghijklmnopq", "TooLong"),
+        ] {
+            match tre.start_file(name, CompressionMethod::None) {
+                Err(Error::InvalidName(e)) => assert!(format!("{e:?}").starts_with(expected)),
+                other => panic!("{name:?} was not rejected: {other:?}"),
+            }
+        }
+        assert!(!tre.is_writing_file());
+
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        tre.start_file("a\\b.txt", CompressionMethod::None)?;
+
+        let tre = TreArchive::new(tre.finish()?)?;
+        assert_eq!(tre.file_names().collect::<Vec<_>>(), ["a/b.txt"]);
+
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .name_policy(TreNamePolicy::builder().lowercase(true).build())
+                .build(),
+        );
+        tre.start_file("A\\B.txt", CompressionMethod::None)?;
+
+        let tre = TreArchive::new(tre.finish()?)?;
+        assert_eq!(tre.file_names().collect::<Vec<_>>(), ["a/b.txt"]);
+        assert_eq!(
+            tre.by_index(0)?.crc32(),
+            crc::Crc::<u32>::new(&crc::CRC_32_BZIP2).checksum(b"a/b.txt")
+        );
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[test]
+    fn tre_add_dir_recursive() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("swg_tre_dir_{}", std::process::id()));
+        for (path, contents) in [
+            ("b.TXT", "second"),
+            ("a/one.txt", "first"),
+            ("skip/hidden.txt", "hidden"),
+        ] {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, contents)?;
+        }
+
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        let added = tre.add_dir_recursive(
+            &dir,
+            "data/",
+            TreDirOptions::builder()
+                .compression(CompressionMethod::Zlib)
+                .store_extensions(&["txt"])
+                .filter(&|path| !path.starts_with("skip"))
+                .build(),
+        );
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(added?, 2);
+
+        let tre = TreArchive::new(tre.finish()?)?;
+        assert_eq!(
+            tre.file_names().collect::<Vec<_>>(),
+            ["data/a/one.txt", "data/b.TXT"]
+        );
+
+        let mut contents = String::new();
+        let mut file = tre.by_name("data/a/one.txt")?;
+        assert_eq!(file.compression_method(), CompressionMethod::None);
+        file.read_to_string(&mut contents)?;
+        assert_eq!(contents, "first");
+        assert_eq!(
+            tre.by_name("data/b.TXT")?.compression_method(),
+            CompressionMethod::None
+        );
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[test]
+    fn tre_sorted_records() -> Result<()> {
+        let names = ["b.txt", "c/a.txt", "a.txt"];
+
+        for (order, expected) in [
+            (SortOrder::Insertion, ["b.txt", "c/a.txt", "a.txt"]),
+            (SortOrder::Name, ["a.txt", "b.txt", "c/a.txt"]),
+        ] {
+            let mut tre = TreWriter::new(
+                Cursor::new(Vec::new()),
+                TreWriterOptions::builder().sort_records(order).build(),
+            );
+            for name in names {
+                tre.start_file(name, CompressionMethod::None)?;
+                tre.write_all(name.as_bytes())?;
+            }
+
+            let tre = TreArchive::new(tre.finish()?)?;
+            assert_eq!(tre.file_names().collect::<Vec<_>>(), expected);
+            for name in names {
+                let mut contents = String::new();
+                tre.by_name(name)?.read_to_string(&mut contents)?;
+                assert_eq!(contents, name);
+            }
+        }
+
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .sort_records(SortOrder::Crc)
+                .build(),
+        );
+        for name in names {
+            tre.start_file(name, CompressionMethod::Zlib)?;
+            tre.write_all(name.as_bytes())?;
+        }
+
+        let tre = TreArchive::new(tre.finish()?)?;
+        let checksums = (0..tre.len())
+            .map(|i| Ok(tre.by_index(i)?.crc32()))
+            .collect::<Result<Vec<_>>>()?;
+        assert!(checksums.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[test]
+    fn tre_checksum_policy() -> Result<()> {
+        let crc = crc::Crc::<u32>::new(&crc::CRC_32_BZIP2);
+
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .checksum(ChecksumPolicy::Data)
+                .build(),
+        );
+        tre.start_file("data.txt", CompressionMethod::Zlib)?;
+        tre.write_all(b"Hello, ")?;
+        tre.write_all(b"World!")?;
+        tre.start_file_with_checksum("name.txt", CompressionMethod::None, ChecksumPolicy::Name)?;
+        tre.start_file_with_checksum(
+            "value.txt",
+            CompressionMethod::None,
+            ChecksumPolicy::Value(0xDEADBEEF),
+        )?;
+
+        let tre = TreArchive::new(tre.finish()?)?;
+        assert_eq!(
+            tre.by_name("data.txt")?.crc32(),
+            crc.checksum(b"Hello, World!")
+        );
+        assert_eq!(tre.by_name("name.txt")?.crc32(), crc.checksum(b"name.txt"));
+        assert_eq!(tre.by_name("value.txt")?.crc32(), 0xDEADBEEF);
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[test]
+    fn tre_add_entry_from_reader() -> Result<()> {
+        let contents = "Hello, World! ".repeat(8192);
+
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        let record = tre.add_entry_from_reader(
+            "all.txt",
+            CompressionMethod::Zlib,
+            contents.as_bytes(),
+            None,
+        )?;
+        assert!(!tre.is_writing_file());
+        assert_eq!(record.data_compression, CompressionMethod::Zlib);
+        assert_eq!(record.data_uncompressed as usize, contents.len());
+        assert!(record.data_compressed < record.data_uncompressed);
+
+        let record = tre.add_entry_from_reader(
+            "part.txt",
+            CompressionMethod::None,
+            contents.as_bytes(),
+            Some(13),
+        )?;
+        assert_eq!(record.data_uncompressed, 13);
+        assert_eq!(record.data_compressed, 13);
+
+        let tre = TreArchive::new(tre.finish()?)?;
+        let mut actual = String::new();
+        tre.by_name("all.txt")?.read_to_string(&mut actual)?;
+        assert_str_eq!(actual, contents);
+
+        actual.clear();
+        tre.by_name("part.txt")?.read_to_string(&mut actual)?;
+        assert_str_eq!(actual, "Hello, World!");
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[test]
+    fn tre_aligned_write() -> Result<()> {
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder().alignment(16).build(),
+        );
+        for (name, contents) in [("a.txt", "first"), ("b.txt", "second entry")] {
+            tre.start_file(name, CompressionMethod::None)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+
+        let tre = TreArchive::new(tre.finish()?)?;
+        assert!(tre.has_hash_block());
+        for (name, contents) in [("a.txt", "first"), ("b.txt", "second entry")] {
+            let mut file = tre.by_name(name)?;
+            assert_eq!(file.data_start() % 16, 0);
+
+            let mut actual = String::new();
+            file.read_to_string(&mut actual)?;
+            assert_eq!(actual, contents);
+        }
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[test]
+    fn tre_duplicate_names() -> Result<()> {
+        let write = |duplicates| -> Result<Vec<u8>> {
+            let mut tre = TreWriter::new(
+                Cursor::new(Vec::new()),
+                TreWriterOptions::builder().duplicates(duplicates).build(),
+            );
+            for (name, contents) in [("a.txt", "first"), ("b.txt", "other"), ("a.txt", "second")] {
+                tre.start_file(name, CompressionMethod::None)?;
+                tre.write_all(contents.as_bytes())?;
+            }
+            Ok(tre.finish()?.into_inner())
+        };
+
+        assert!(matches!(
+            write(DuplicatePolicy::Error),
+            Err(Error::InvalidName(InvalidNameError::Duplicate(name))) if name == "a.txt"
+        ));
+
+        let tre = TreArchive::new(Cursor::new(write(DuplicatePolicy::Replace)?))?;
+        assert_eq!(tre.file_names().collect::<Vec<_>>(), ["a.txt", "b.txt"]);
+        let mut actual = String::new();
+        tre.by_name("a.txt")?.read_to_string(&mut actual)?;
+        assert_eq!(actual, "second");
+
+        let tre = TreArchive::with_options(
+            Cursor::new(write(DuplicatePolicy::Allow)?),
+            TreArchiveOptions::builder()
+                .duplicates(read::DuplicatePolicy::KeepAll)
+                .build(),
+        )?;
+        assert_eq!(tre.len(), 3);
+        assert_eq!(tre.duplicates().len(), 1);
+
+        Ok(())
+    }
+
+    #[traced_test]
+    #[test]
+    fn tre_abort_file() -> Result<()> {
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .duplicates(DuplicatePolicy::Replace)
+                .build(),
+        );
+        assert!(tre.finish_file().is_err());
+
+        tre.start_file("a.txt", CompressionMethod::None)?;
+        tre.write_all(b"first")?;
+        let record = tre.finish_file()?;
+        assert_eq!(record.data_uncompressed, 5);
+        assert!(!tre.is_writing_file());
+
+        tre.start_file("a.txt", CompressionMethod::None)?;
+        tre.write_all(b"replacement")?;
+        tre.abort_file();
+        assert!(!tre.is_writing_file());
+
+        tre.start_file("b.txt", CompressionMethod::None)?;
+        tre.write_all(b"abandoned")?;
+        tre.abort_file();
+        tre.abort_file();
+
+        let tre = TreArchive::new(tre.finish()?)?;
+        assert_eq!(tre.file_names().collect::<Vec<_>>(), ["a.txt"]);
+        let mut actual = String::new();
+        tre.by_name("a.txt")?.read_to_string(&mut actual)?;
+        assert_eq!(actual, "first");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn tre_zstd_write() -> Result<()> {
+        let mut tre = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .record_compression(CompressionMethod::Zstd)
+                .name_compression(CompressionMethod::Zstd)
+                .build(),
+        );
+        let contents = "Hello, World! ".repeat(64);
+        tre.start_file("a.txt", CompressionMethod::Zstd)?;
+        tre.write_all(contents.as_bytes())?;
+
+        let tre = TreArchive::new(tre.finish()?)?;
+        assert_eq!(tre.get_record_compression(), CompressionMethod::Zstd);
+        assert_eq!(tre.get_name_compression(), CompressionMethod::Zstd);
+
+        let file = tre.by_name("a.txt")?;
+        assert_eq!(file.compression_method(), CompressionMethod::Zstd);
+        assert!(file.compressed_size() < file.size());
+
+        let mut actual = String::new();
+        tre.by_name("a.txt")?.read_to_string(&mut actual)?;
+        assert_eq!(actual, contents);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,132 @@
+//! Conversion between TRE archives and standard zip files
+//!
+//! Entry names carry over as zip paths and back. Entries compressed with Zlib become deflated zip
+//! entries, which use the same compression without the Zlib wrapper, and stored entries stay
+//! stored.
+
+use std::{
+    io::{self, Read, Seek, Write},
+    path::Component,
+};
+use tracing::{instrument, Span};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::{
+    compression::CompressionMethod,
+    error::{InvalidNameError, Result},
+    read::TreArchive,
+    write::TreWriter,
+};
+
+/// Write every entry of an archive to a zip file, returning the writer
+#[instrument(skip_all, err, fields(entries = archive.len()))]
+pub fn to_zip<R: Read + Seek, W: Write + Seek>(archive: &TreArchive<R>, writer: W) -> Result<W> {
+    let mut zip = ZipWriter::new(writer);
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let method = match file.compression_method() {
+            CompressionMethod::None => zip::CompressionMethod::Stored,
+            _ => zip::CompressionMethod::Deflated,
+        };
+
+        let options = SimpleFileOptions::default()
+            .compression_method(method)
+            .large_file(file.size() > u32::MAX as u64);
+        zip.start_file(file.name(), options)?;
+        io::copy(&mut file, &mut zip)?;
+    }
+
+    Ok(zip.finish()?)
+}
+
+impl<W: Write + Seek> TreWriter<W> {
+    /// Add every file of a zip archive, returning the number of entries added
+    ///
+    /// Entries keep their path within the zip as their name and are written with `compression`,
+    /// whatever the zip used. Directories are skipped and paths which would escape the archive are
+    /// rejected with [`InvalidNameError::NotNormalized`].
+    #[instrument(skip_all, err, fields(entries, added))]
+    pub fn add_zip<R: Read + Seek>(
+        &mut self,
+        reader: R,
+        compression: CompressionMethod,
+    ) -> Result<usize> {
+        let mut zip = ZipArchive::new(reader)?;
+        Span::current().record("entries", zip.len());
+
+        let mut added = 0;
+        for index in 0..zip.len() {
+            let mut file = zip.by_index(index)?;
+            if file.is_dir() {
+                continue;
+            }
+
+            let not_normalized = || InvalidNameError::NotNormalized(file.name().to_owned());
+            let path = file.enclosed_name().ok_or_else(not_normalized)?;
+            let name = path
+                .components()
+                .map(|component| match component {
+                    Component::Normal(part) => part.to_str().ok_or_else(not_normalized),
+                    _ => Err(not_normalized()),
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?
+                .join("/");
+
+            self.add_entry_from_reader(name, compression, &mut file, None)?;
+            added += 1;
+        }
+
+        Span::current().record("added", added);
+
+        Ok(added)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Read, Write};
+
+    use crate::{
+        compression::CompressionMethod,
+        error::Result,
+        read::TreArchive,
+        write::{TreWriter, TreWriterOptions},
+        zip::to_zip,
+    };
+
+    #[test]
+    fn zip_round_trip() -> Result<()> {
+        let entries = [
+            ("a.txt", "first", CompressionMethod::Zlib),
+            ("dir/b.txt", "second", CompressionMethod::None),
+        ];
+
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        for (name, contents, compression) in entries {
+            tre.start_file(name, compression)?;
+            tre.write_all(contents.as_bytes())?;
+        }
+        let tre = TreArchive::new(tre.finish()?)?;
+
+        let zip = to_zip(&tre, Cursor::new(Vec::new()))?;
+        let mut archive = ::zip::ZipArchive::new(Cursor::new(zip.get_ref().as_slice()))?;
+        assert_eq!(archive.len(), 2);
+        assert_eq!(
+            archive.by_name("a.txt")?.compression(),
+            ::zip::CompressionMethod::Deflated
+        );
+
+        let mut tre = TreWriter::new(Cursor::new(Vec::new()), TreWriterOptions::builder().build());
+        assert_eq!(tre.add_zip(zip, CompressionMethod::Auto)?, 2);
+        let tre = TreArchive::new(tre.finish()?)?;
+
+        for (name, contents, _) in entries {
+            let mut actual = String::new();
+            tre.by_name(name)?.read_to_string(&mut actual)?;
+            assert_eq!(actual, contents);
+        }
+
+        Ok(())
+    }
+}
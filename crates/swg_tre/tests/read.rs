@@ -25,7 +25,7 @@ fn validate_tre(path: &PathBuf) -> Result<(), Error> {
         .collect::<Vec<_>>();
 
     let mut r = File::open(path)?;
-    let mut tre = TreArchive::new(&mut r)?;
+    let tre = TreArchive::new(&mut r)?;
     assert_eq!(tre.len(), expected_files.len());
 
     let count = tre.len();
@@ -59,10 +59,9 @@ fn validate_tre_parsing() -> Result<(), Error> {
         .map(|dir_entry| dir_entry.path())
         .filter(|e| e.is_file())
         .filter(|path| {
-            path.file_name().map_or(false, |name| {
-                name.to_str().map_or(false, |f| {
-                    f.ends_with(".tre") && !f.ends_with(".generated.tre")
-                })
+            path.file_name().is_some_and(|name| {
+                name.to_str()
+                    .is_some_and(|f| f.ends_with(".tre") && !f.ends_with(".generated.tre"))
             })
         });
 
@@ -0,0 +1,482 @@
+use std::{
+    borrow::Cow,
+    io::{Cursor, Read, Write},
+};
+use swg_tre::{
+    compression::{BlockDecoder, DecoderRegistry},
+    error::{DecompressionBombError, Error, FileNotFoundError, LayoutError, MetadataError, Result},
+    read::{TreArchiveOptions, TreLimits},
+    testing::{CompressionMix, Corruption, EntryContent, SyntheticArchive},
+    transform::BlockKind,
+    write::TreWriterOptions,
+    CompressionMethod, TreArchive, TreWriter,
+};
+use tracing_test::traced_test;
+
+fn validate(synthetic: &SyntheticArchive) -> Result<()> {
+    let tre = TreArchive::new(Cursor::new(synthetic.generate()?))?;
+    assert_eq!(tre.len(), synthetic.entries);
+
+    for i in 0..synthetic.entries {
+        let mut file = tre.by_index(i)?;
+        assert_eq!(file.name(), synthetic.entry_name(i));
+        assert_eq!(file.compression_method(), synthetic.compression.method(i));
+
+        let mut actual = Vec::new();
+        file.read_to_end(&mut actual)?;
+        assert_eq!(actual, synthetic.entry_data(i));
+    }
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn synthetic_round_trip() -> Result<()> {
+    for compression in [
+        CompressionMix::None,
+        CompressionMix::Zlib,
+        CompressionMix::Alternating,
+    ] {
+        for content in [EntryContent::Text, EntryContent::Random] {
+            for block_compression in [CompressionMethod::None, CompressionMethod::Zlib] {
+                validate(
+                    &SyntheticArchive::builder()
+                        .entries(50)
+                        .compression(compression)
+                        .content(content)
+                        .record_compression(block_compression)
+                        .name_compression(block_compression)
+                        .build(),
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn synthetic_auto_compression() -> Result<()> {
+    for (content, expected) in [
+        (EntryContent::Text, CompressionMethod::Zlib),
+        (EntryContent::Random, CompressionMethod::None),
+    ] {
+        let synthetic = SyntheticArchive::builder().content(content).build();
+
+        let mut writer = TreWriter::new(
+            Cursor::new(Vec::new()),
+            TreWriterOptions::builder()
+                .record_compression(CompressionMethod::Auto)
+                .name_compression(CompressionMethod::Auto)
+                .build(),
+        );
+        for i in 0..synthetic.entries {
+            writer.start_file(synthetic.entry_name(i), CompressionMethod::Auto)?;
+            writer.write_all(&synthetic.entry_data(i))?;
+        }
+
+        let tre = TreArchive::new(Cursor::new(writer.finish()?.into_inner()))?;
+        assert_eq!(tre.get_name_compression(), CompressionMethod::Zlib);
+
+        for i in 0..synthetic.entries {
+            let mut file = tre.by_index(i)?;
+            assert_eq!(file.compression_method(), expected);
+
+            let mut actual = Vec::new();
+            file.read_to_end(&mut actual)?;
+            assert_eq!(actual, synthetic.entry_data(i));
+        }
+    }
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn synthetic_corruptions() -> Result<()> {
+    let corruptions = [
+        Corruption::Truncate { len: 20 },
+        Corruption::RecordCount(1000),
+        Corruption::EntryOffset {
+            index: 3,
+            offset: u32::MAX,
+        },
+    ];
+
+    for corruption in corruptions {
+        let synthetic = SyntheticArchive::builder()
+            .entries(10)
+            .corruptions(vec![corruption.clone()])
+            .build();
+
+        let result = TreArchive::new(Cursor::new(synthetic.generate()?));
+        assert!(
+            matches!(result, Err(Error::InvalidArchive | Error::OutOfBounds(_))),
+            "{corruption:?} was not detected"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn synthetic_unpatchable_records() -> Result<()> {
+    let out_of_range = SyntheticArchive::builder()
+        .entries(4)
+        .corruptions(vec![Corruption::EntrySize { index: 4, size: 0 }])
+        .build();
+    assert!(matches!(
+        out_of_range.generate(),
+        Err(Error::FileNotFound(FileNotFoundError::Index(4)))
+    ));
+
+    let zstd = SyntheticArchive::builder()
+        .entries(4)
+        .record_compression(CompressionMethod::Zstd)
+        .corruptions(vec![Corruption::EntryOffset {
+            index: 0,
+            offset: 0,
+        }])
+        .build();
+    assert!(matches!(
+        zstd.generate(),
+        Err(Error::UnsupportedCompression(CompressionMethod::Zstd))
+    ));
+
+    let truncated = SyntheticArchive::builder()
+        .entries(4)
+        .corruptions(vec![
+            Corruption::Truncate { len: 4 },
+            Corruption::RecordCount(1),
+        ])
+        .build();
+    assert_eq!(truncated.generate()?.len(), 4);
+
+    Ok(())
+}
+
+/// Stores entries reversed, as a stand in for a community codec
+#[derive(Debug)]
+struct Reversed;
+
+impl BlockDecoder for Reversed {
+    fn decode(&self, _block: BlockKind, stored: &mut dyn Read) -> std::io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        stored.read_to_end(&mut data)?;
+        data.reverse();
+        Ok(data)
+    }
+}
+
+#[traced_test]
+#[test]
+fn synthetic_registered_decoder() -> Result<()> {
+    let reversed = CompressionMethod::Other(0x52455645);
+    let synthetic = SyntheticArchive::builder()
+        .entries(4)
+        .compression(CompressionMix::None)
+        .corruptions(vec![Corruption::EntryCompression {
+            index: 2,
+            compression: reversed,
+        }])
+        .build();
+    let data = synthetic.generate()?;
+
+    let result = TreArchive::new(Cursor::new(&data));
+    assert!(matches!(
+        result,
+        Err(Error::Metadata(MetadataError::UnknownCompression {
+            index: 2,
+            name,
+            value: 0x52455645
+        })) if name == synthetic.entry_name(2)
+    ));
+
+    let (tre, skipped) = TreArchive::new_lossy(Cursor::new(&data))?;
+    assert_eq!(tre.len(), 3);
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(
+        skipped[0].name.as_deref(),
+        Some(synthetic.entry_name(2).as_str())
+    );
+
+    let tre = TreArchive::with_options(
+        Cursor::new(&data),
+        TreArchiveOptions::builder()
+            .decoders(DecoderRegistry::new().register(0x52455645, Reversed))
+            .build(),
+    )?;
+    let mut file = tre.by_index(2)?;
+    assert_eq!(file.compression_method(), reversed);
+
+    let mut actual = Vec::new();
+    file.read_to_end(&mut actual)?;
+    actual.reverse();
+    assert_eq!(actual, synthetic.entry_data(2));
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn synthetic_decompression_bomb() -> Result<()> {
+    let synthetic = SyntheticArchive::builder()
+        .entries(4)
+        .min_entry_size(4096)
+        .corruptions(vec![Corruption::EntrySize { index: 1, size: 16 }])
+        .build();
+    let tre = TreArchive::new(Cursor::new(synthetic.generate()?))?;
+
+    // The record understates the size of the entry, so reading it stops at the declared size
+    assert!(tre.by_index(1)?.read_to_end(&mut Vec::new()).is_err());
+    assert!(matches!(
+        tre.contents_by_index(1),
+        Err(Error::DecompressionBomb(DecompressionBombError::Size {
+            size: 16
+        }))
+    ));
+    assert_eq!(*tre.contents_by_index(0)?, synthetic.entry_data(0));
+
+    let options = TreArchiveOptions::builder()
+        .limits(TreLimits::builder().max_compression_ratio(2).build())
+        .build();
+    let tre = TreArchive::with_options(Cursor::new(synthetic.generate()?), options)?;
+    assert!(matches!(
+        tre.by_index(0),
+        Err(Error::DecompressionBomb(DecompressionBombError::Ratio {
+            limit: 2,
+            ..
+        }))
+    ));
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn synthetic_strict_layout() -> Result<()> {
+    let strict = || TreArchiveOptions::builder().strict(true).build();
+    let synthetic = SyntheticArchive::builder().entries(10).build();
+
+    let data = synthetic.generate()?;
+    assert_eq!(
+        TreArchive::with_options(Cursor::new(&data), strict())?.len(),
+        10
+    );
+
+    let mut trailing = data.clone();
+    trailing.extend_from_slice(b"trailing");
+    assert!(TreArchive::new(Cursor::new(&trailing)).is_ok());
+    assert!(matches!(
+        TreArchive::with_options(Cursor::new(&trailing), strict()),
+        Err(Error::Layout(LayoutError::Length { end, length }))
+            if end == data.len() as u64 && length == trailing.len() as u64
+    ));
+
+    let record_start = u32::from_le_bytes(data[12..16].try_into().unwrap());
+    for (offset, expected) in [(0, "header"), (record_start, "metadata")] {
+        let corrupted = SyntheticArchive::builder()
+            .entries(10)
+            .corruptions(vec![Corruption::EntryOffset { index: 3, offset }])
+            .build()
+            .generate()?;
+
+        match (
+            expected,
+            TreArchive::with_options(Cursor::new(&corrupted), strict()),
+        ) {
+            ("header", Err(Error::Layout(LayoutError::Header { index: 3, .. }))) => {}
+            ("metadata", Err(Error::Layout(LayoutError::Metadata { index: 3, .. }))) => {}
+            (_, result) => panic!("expected a {} overlap, found {:?}", expected, result.err()),
+        }
+
+        let (tre, skipped) = TreArchive::with_options_lossy(Cursor::new(&corrupted), strict())?;
+        assert_eq!(tre.len(), 9);
+        assert_eq!(skipped[0].index, 3);
+    }
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn synthetic_lossy_skips_bad_entries() -> Result<()> {
+    let synthetic = SyntheticArchive::builder()
+        .entries(10)
+        .corruptions(vec![Corruption::EntryOffset {
+            index: 3,
+            offset: u32::MAX,
+        }])
+        .build();
+
+    let (tre, skipped) = TreArchive::new_lossy(Cursor::new(synthetic.generate()?))?;
+    assert_eq!(tre.len(), 9);
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].index, 3);
+    assert_eq!(
+        skipped[0].name.as_deref(),
+        Some(synthetic.entry_name(3).as_str())
+    );
+    assert!(matches!(skipped[0].error, Error::OutOfBounds(_)));
+
+    let mut actual = Vec::new();
+    tre.by_name(&synthetic.entry_name(4))?
+        .read_to_end(&mut actual)?;
+    assert_eq!(actual, synthetic.entry_data(4));
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn synthetic_lossy_truncated_names() -> Result<()> {
+    let synthetic = SyntheticArchive::builder()
+        .entries(10)
+        .record_compression(CompressionMethod::None)
+        .name_compression(CompressionMethod::None)
+        .build();
+
+    // Cut off the hash block and the last few names
+    let mut data = synthetic.generate()?;
+    data.truncate(data.len() - 10 * 16 - 60);
+
+    let (tre, skipped) = TreArchive::new_lossy(Cursor::new(data))?;
+    assert!(!tre.is_empty());
+    assert_eq!(tre.len() + skipped.len(), 10);
+    assert!(skipped
+        .iter()
+        .all(|s| matches!(s.error, Error::Metadata(MetadataError::Name(_)))));
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn synthetic_par_entries() -> Result<()> {
+    use rayon::prelude::*;
+
+    let synthetic = SyntheticArchive::builder()
+        .entries(64)
+        .compression(CompressionMix::Alternating)
+        .build();
+    let data = synthetic.generate()?;
+    let tre = TreArchive::new(Cursor::new(data.as_slice()))?;
+
+    tre.par_entries().enumerate().try_for_each(|(i, entry)| {
+        let mut entry = entry?;
+        assert_eq!(entry.name(), synthetic.entry_name(i));
+
+        let mut actual = Vec::new();
+        entry.read_to_end(&mut actual)?;
+        assert_eq!(actual, synthetic.entry_data(i));
+
+        Ok::<_, Error>(())
+    })
+}
+
+#[traced_test]
+#[test]
+fn synthetic_concurrent_by_index() -> Result<()> {
+    let synthetic = SyntheticArchive::builder()
+        .entries(64)
+        .compression(CompressionMix::Alternating)
+        .build();
+    let tre = TreArchive::new(Cursor::new(synthetic.generate()?))?;
+
+    // Two handles can be interleaved, each keeping its own position
+    let mut first = tre.by_index(0)?;
+    let mut second = tre.by_index(1)?;
+    let (mut first_data, mut second_data) = (Vec::new(), Vec::new());
+    let mut buffer = [0u8; 7];
+    loop {
+        let first_read = first.read(&mut buffer)?;
+        first_data.extend_from_slice(&buffer[..first_read]);
+        let second_read = second.read(&mut buffer)?;
+        second_data.extend_from_slice(&buffer[..second_read]);
+        if first_read == 0 && second_read == 0 {
+            break;
+        }
+    }
+    assert_eq!(first_data, synthetic.entry_data(0));
+    assert_eq!(second_data, synthetic.entry_data(1));
+
+    std::thread::scope(|scope| {
+        let handles = (0..4)
+            .map(|thread| {
+                let (tre, synthetic) = (&tre, &synthetic);
+                scope.spawn(move || {
+                    for i in (0..synthetic.entries).map(|i| (i + thread * 16) % synthetic.entries) {
+                        let mut actual = Vec::new();
+                        tre.by_index(i)?.read_to_end(&mut actual)?;
+                        assert_eq!(actual, synthetic.entry_data(i));
+                    }
+                    Ok::<_, Error>(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().expect("reader thread panicked"))
+    })
+}
+
+#[traced_test]
+#[test]
+fn synthetic_cached_contents() -> Result<()> {
+    let synthetic = SyntheticArchive::builder().entries(16).build();
+    let tre = TreArchive::with_options(
+        Cursor::new(synthetic.generate()?),
+        TreArchiveOptions::builder()
+            .cache_size(synthetic.max_entry_size as u64 * 2)
+            .build(),
+    )?;
+
+    for i in (0..synthetic.entries).chain(0..synthetic.entries) {
+        assert_eq!(*tre.contents_by_index(i)?, synthetic.entry_data(i));
+    }
+
+    let first = tre.contents_by_name(&synthetic.entry_name(3))?;
+    let second = tre.contents_by_name(&synthetic.entry_name(3))?;
+    assert!(std::sync::Arc::ptr_eq(&first, &second));
+    assert!(tre.cached_size() <= synthetic.max_entry_size as u64 * 2);
+
+    Ok(())
+}
+
+#[traced_test]
+#[test]
+fn synthetic_from_bytes() -> Result<()> {
+    let synthetic = SyntheticArchive::builder()
+        .entries(16)
+        .compression(CompressionMix::Alternating)
+        .build();
+    let data = synthetic.generate()?;
+    let tre = TreArchive::from_bytes(&data)?;
+
+    for i in 0..synthetic.entries {
+        let bytes = tre.bytes_by_index(i)?;
+        assert_eq!(*bytes, synthetic.entry_data(i));
+
+        match synthetic.compression.method(i) {
+            CompressionMethod::None => {
+                assert!(matches!(bytes, Cow::Borrowed(_)));
+                assert!(data.as_ptr_range().contains(&bytes.as_ptr()));
+            }
+            _ => assert!(matches!(bytes, Cow::Owned(_))),
+        }
+    }
+
+    assert_eq!(
+        *tre.bytes_by_name(&synthetic.entry_name(3))?,
+        synthetic.entry_data(3)
+    );
+    assert_eq!(
+        tre.stored_by_index(1)?.len() as u64,
+        tre.by_index(1)?.compressed_size()
+    );
+
+    Ok(())
+}
@@ -13,7 +13,7 @@ use tracing_test::traced_test;
 #[instrument(skip_all, fields(file=%path.file_name().unwrap().to_string_lossy()))]
 fn validate_tre_merge(path: &Path) -> Result<()> {
     let input_file = File::open(path).into_diagnostic()?;
-    let mut tre_input = TreArchive::new(&input_file)?;
+    let tre_input = TreArchive::new(&input_file)?;
 
     let parent_dir = &path
         .parent()
@@ -65,7 +65,7 @@ fn validate_tre_merge(path: &Path) -> Result<()> {
     // Rewind so we can read from the generated data
     actual.rewind().into_diagnostic()?;
 
-    let mut tre_output = TreArchive::new(actual)?;
+    let tre_output = TreArchive::new(actual)?;
 
     assert_eq!(tre_input.len(), tre_output.len());
     assert_eq!(
@@ -105,10 +105,9 @@ fn merge_tre() -> Result<()> {
         .map(|dir_entry| dir_entry.path())
         .filter(|e| e.is_file())
         .filter(|path| {
-            path.file_name().map_or(false, |name| {
-                name.to_str().map_or(false, |f| {
-                    f.ends_with(".tre") && !f.ends_with(".generated.tre")
-                })
+            path.file_name().is_some_and(|name| {
+                name.to_str()
+                    .is_some_and(|f| f.ends_with(".tre") && !f.ends_with(".generated.tre"))
             })
         });
 